@@ -21,6 +21,17 @@ pub enum Block<'a> {
 }
 
 impl<'a> Block<'a> {
+    /// See [`Document::into_owned`](struct.Document.html#method.into_owned).
+    pub fn into_owned(self) -> Block<'static> {
+        match self {
+            Block::Content(content) => {
+                Block::Content(content.into_iter().map(Inline::into_owned).collect())
+            }
+            Block::Literal(literal) => Block::Literal(literal.into_owned()),
+            Block::Tag(tag) => Block::Tag(tag.into_owned()),
+        }
+    }
+
     pub fn as_content(&self) -> Option<&Vec<Inline<'a>>> {
         if let Block::Content(content) = self {
             Some(content)
@@ -37,6 +48,31 @@ impl<'a> Block<'a> {
         }
     }
 
+    /// ```rust
+    /// # use prosidy_ast::{Block, Literal, Text};
+    /// let code = Literal::from(Text::from("fn main() {}\n"));
+    /// let mut block = Block::Literal(code.clone());
+    /// assert_eq!(Some(&code), block.as_literal());
+    /// *block.as_mut_literal().unwrap() = Literal::default();
+    /// assert_eq!(Some(&Literal::default()), block.as_literal());
+    /// assert_eq!(None, Block::Content(vec![]).as_literal());
+    /// ```
+    pub fn as_literal(&self) -> Option<&Literal<'a>> {
+        if let Block::Literal(literal) = self {
+            Some(literal)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_mut_literal(&mut self) -> Option<&mut Literal<'a>> {
+        if let Block::Literal(literal) = self {
+            Some(literal)
+        } else {
+            None
+        }
+    }
+
     pub fn as_tag(&self) -> Option<&BlockTag<'a>> {
         if let Block::Tag(tag) = self {
             Some(tag)
@@ -6,19 +6,25 @@
 use serde::{Deserialize, Serialize};
 
 use crate::block::Block;
-use crate::types::PropSet;
+use crate::inline::Inline;
+use crate::node::Node;
+use crate::print::{self, PrintError, PrintOpts};
+use crate::tag::{self, BlockTag, TagRef};
+use crate::types::{DocumentProps, Key, PropSet, Text};
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 /// The abstract syntax-tree of a Prosidy document.
 pub struct Document<'a> {
     #[serde(borrow)]
-    props: PropSet<'a>,
+    props: DocumentProps<'a>,
     #[serde(borrow)]
     content: Vec<Block<'a>>,
 }
 
 impl<'a> Document<'a> {
+    /// Builds a document with `props` as its header.
     pub fn new(props: PropSet<'a>, content: Vec<Block<'a>>) -> Self {
+        let props = DocumentProps::from_props(props);
         Document { props, content }
     }
 
@@ -30,11 +36,245 @@ impl<'a> Document<'a> {
         &mut self.content
     }
 
-    pub fn props(&self) -> &PropSet<'a> {
+    pub fn props(&self) -> &DocumentProps<'a> {
         &self.props
     }
 
-    pub fn props_mut(&mut self) -> &mut PropSet<'a> {
+    pub fn props_mut(&mut self) -> &mut DocumentProps<'a> {
         &mut self.props
     }
+
+    /// Returns the document's title, if its header declares one.
+    ///
+    /// The title is kept in the header alongside the other settings, so that it's written out
+    /// exactly once, as the `title` setting, by every format.
+    pub fn title(&self) -> Option<Text<'a>> {
+        self.props.title()
+    }
+
+    /// Sets the document's title, returning the previous one.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::{Document, PropSet, Text};
+    /// let mut doc = Document::new(PropSet::new(), vec![]);
+    /// assert_eq!(None, doc.set_title("Hello!"));
+    /// assert_eq!(Some(Text::from("Hello!")), doc.title());
+    /// assert_eq!(Some(Text::from("Hello!")), doc.props().title());
+    /// ```
+    pub fn set_title<V: Into<Text<'a>>>(&mut self, title: V) -> Option<Text<'a>> {
+        self.props.set_title(title)
+    }
+
+    /// Writes the document as Prosidy source, in a canonical style. Parsing the output gives
+    /// back the same document, except that a property with an empty value comes back as a flag.
+    ///
+    /// Documents which can't be written as Prosidy, like one with a literal outside of a block
+    /// tag or a key containing whitespace, produce an error. The document's [`Display`] output is
+    /// the same, with the default [`PrintOpts`].
+    ///
+    /// [`Display`]: std::fmt::Display
+    ///
+    /// ```rust
+    /// # use prosidy_ast::{props, BlockTag, Document, PrintOpts, Text};
+    /// let tag = BlockTag::new("h1", props! {}, vec![vec![Text::from("#1").into()].into()]);
+    /// let doc = Document::new(props! { title = "Hi" }, vec![tag.into()]);
+    /// assert_eq!("title: Hi\n---\n\n#-h1{\\#1}\n", doc.print(&PrintOpts::default()).unwrap());
+    /// assert_eq!(doc.print(&PrintOpts::default()).unwrap(), doc.to_string());
+    /// ```
+    pub fn print(&self, opts: &PrintOpts) -> Result<String, PrintError> {
+        print::print_document(self, opts)
+    }
+
+    /// Finds the first block tag with the given name among the document's top-level blocks.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::{BlockTag, Document, PropSet};
+    /// let h1 = BlockTag::new("h1", PropSet::new(), vec![]);
+    /// let section = BlockTag::new("section", PropSet::new(), vec![h1.clone().into()]);
+    /// let doc = Document::new(PropSet::new(), vec![section.into(), h1.into()]);
+    /// assert!(doc.find_block_tag("h1").is_some());
+    /// assert_eq!(1, doc.find_block_tags("h1").count());
+    /// assert_eq!(2, doc.find_block_tags_recursive("h1").count());
+    /// ```
+    pub fn find_block_tag<K: Into<Key>>(&self, name: K) -> Option<&BlockTag<'a>> {
+        self.find_block_tags(name).next()
+    }
+
+    /// Iterates over the block tags with the given name among the document's top-level blocks.
+    pub fn find_block_tags<'r, K: Into<Key>>(
+        &'r self,
+        name: K,
+    ) -> impl Iterator<Item = &'r BlockTag<'a>> {
+        tag::child_tags(&self.content, name.into())
+    }
+
+    /// Iterates over the block tags with the given name at any depth, in depth-first pre-order.
+    pub fn find_block_tags_recursive<'r, K: Into<Key>>(
+        &'r self,
+        name: K,
+    ) -> impl Iterator<Item = &'r BlockTag<'a>> {
+        tag::descendant_tags(&self.content, name.into())
+    }
+
+    /// Iterates over the block and inline tags with the given name at any depth, in depth-first
+    /// pre-order: a tag comes before the tags inside it, and otherwise tags come in the order they
+    /// appear in the source.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::{Block, BlockTag, Document, InlineTag, PropSet, TagRef, Text};
+    /// let img = |src| InlineTag::builder("img").setting("src", src).build();
+    /// let para = Block::Content(vec![img("a.png").into(), img("b.png").into()]);
+    /// let figure = BlockTag::new("img", PropSet::new(), vec![para]);
+    /// let doc = Document::new(PropSet::new(), vec![figure.into()]);
+    /// let srcs: Vec<_> = doc
+    ///     .tags("img")
+    ///     .filter_map(TagRef::as_inline)
+    ///     .map(|tag| tag.props().lookup(prosidy_ast::Key::new("src")).unwrap())
+    ///     .collect();
+    /// assert_eq!(vec![Text::from("a.png"), Text::from("b.png")], srcs);
+    /// assert_eq!(3, doc.tags("img").count());
+    /// ```
+    pub fn tags<'r, K: Into<Key>>(&'r self, name: K) -> impl Iterator<Item = TagRef<'r, 'a>> {
+        let name = name.into();
+        Node::from(self)
+            .descendants()
+            .filter_map(|node| match node {
+                Node::Block(Block::Tag(tag)) => Some(TagRef::Block(tag)),
+                Node::Inline(Inline::Tag(tag)) => Some(TagRef::Inline(tag)),
+                _ => None,
+            })
+            .filter(move |tag| *tag.name() == name)
+    }
+
+    /// Concatenates the visible text of the document, with tags stripped.
+    ///
+    /// - Text runs are copied as-is, and the contents of tags (but not their properties) are
+    ///   included in place.
+    /// - Each soft break becomes a single space.
+    /// - Each paragraph of inline content, and each literal block, is separated from the one
+    ///   before it by a blank line (`"\n\n"`). Block tags don't add separators of their own.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::{Block, BlockTag, Document, Inline, Literal, PropSet, Text};
+    /// let first = Block::Content(vec![
+    ///     Text::from("one").into(),
+    ///     Inline::SoftBreak,
+    ///     Text::from("two").into(),
+    /// ]);
+    /// let second = Block::Literal(Literal::from(Text::from("three")));
+    /// let section = BlockTag::new("section", PropSet::new(), vec![first, second]);
+    /// let doc = Document::new(PropSet::new(), vec![section.into()]);
+    /// assert_eq!("one two\n\nthree", doc.plain_text());
+    /// ```
+    pub fn plain_text(&self) -> String {
+        collect_text(std::iter::once(Node::from(self)), true)
+    }
+
+    /// Counts the words in the document's text, excluding literal blocks.
+    ///
+    /// A word is a run of characters between whitespace, so hyphenated words like `well-known`
+    /// count once. Since CJK scripts don't separate words with spaces, each CJK ideograph, kana,
+    /// or hangul syllable is counted as a word of its own. Words may span tags, so `un#em{usual}`
+    /// is a single word.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::{Block, Document, Inline, InlineTag, Literal, PropSet, Text};
+    /// let para = Block::Content(vec![
+    ///     Text::from("A well-known ").into(),
+    ///     InlineTag::new("em", PropSet::new(), vec![Text::from("un").into()]).into(),
+    ///     Text::from("usual").into(),
+    ///     Inline::SoftBreak,
+    ///     Text::from("文書").into(),
+    /// ]);
+    /// let code = Block::Literal(Literal::from(Text::from("fn main() {}")));
+    /// let doc = Document::new(PropSet::new(), vec![para, code]);
+    /// assert_eq!(5, doc.word_count());
+    /// assert_eq!(8, doc.word_count_with_literals());
+    /// ```
+    pub fn word_count(&self) -> usize {
+        count_words(&collect_text(std::iter::once(Node::from(self)), false))
+    }
+
+    /// Counts the words in the document's text, including literal blocks. See
+    /// [`Document::word_count`] for how words are counted.
+    pub fn word_count_with_literals(&self) -> usize {
+        count_words(&collect_text(std::iter::once(Node::from(self)), true))
+    }
+
+    /// Converts all borrowed text in the document into owned text, detaching it from the source
+    /// it was parsed from.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::{Block, Document, Inline, PropSet, Text};
+    /// let doc: Document<'static> = {
+    ///     let source = String::from("hello");
+    ///     let content = vec![Block::Content(vec![Inline::Text(Text::from(source.as_str()))])];
+    ///     Document::new(PropSet::new(), content).into_owned()
+    /// };
+    /// assert_eq!(Some(Text::from("hello")), doc.content()[0].as_content().unwrap()[0].as_text());
+    /// ```
+    pub fn into_owned(self) -> Document<'static> {
+        Document {
+            props: self.props.into_owned(),
+            content: self.content.into_iter().map(Block::into_owned).collect(),
+        }
+    }
+}
+
+/// Concatenates the text beneath each of `roots`, as described by [`Document::plain_text`].
+pub(crate) fn collect_text<'r, 'a: 'r, I>(roots: I, literals: bool) -> String
+where
+    I: IntoIterator<Item = Node<'r, 'a>>,
+{
+    let mut buf = String::new();
+    for node in roots.into_iter().flat_map(Node::descendants) {
+        match node {
+            Node::Block(Block::Content(_)) => paragraph_break(&mut buf),
+            Node::Block(Block::Literal(literal)) if literals => {
+                paragraph_break(&mut buf);
+                buf.push_str(literal);
+            }
+            Node::Block(Block::Literal(_)) => {}
+            Node::Inline(Inline::Text(text)) => buf.push_str(text),
+            Node::Inline(Inline::SoftBreak) => buf.push(' '),
+            Node::Document(_) | Node::Block(Block::Tag(_)) | Node::Inline(Inline::Tag(_)) => {}
+        }
+    }
+    buf
+}
+
+fn paragraph_break(buf: &mut String) {
+    if !buf.is_empty() {
+        buf.push_str("\n\n");
+    }
+}
+
+fn count_words(text: &str) -> usize {
+    let mut count = 0;
+    let mut in_word = false;
+    for ch in text.chars() {
+        if ch.is_whitespace() {
+            in_word = false;
+        } else if is_cjk(ch) {
+            count += 1;
+            in_word = false;
+        } else if !in_word {
+            count += 1;
+            in_word = true;
+        }
+    }
+    count
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{1100}'..='\u{11FF}'
+            | '\u{3040}'..='\u{30FF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{AC00}'..='\u{D7AF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{20000}'..='\u{2FA1F}'
+    )
 }
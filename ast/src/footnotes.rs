@@ -0,0 +1,145 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::mem;
+
+use crate::block::Block;
+use crate::document::Document;
+use crate::inline::Inline;
+use crate::tag::{BlockTag, InlineTag};
+use crate::types::{Key, PropSet, Text};
+use crate::visit::{walk_inline_mut, VisitMut};
+
+/// Moves every `#note{...}` inline tag to the end of the document as a numbered footnote.
+///
+/// Notes are numbered from 1 in the order they appear, at any depth. Each one is replaced by a
+/// reference marker, `#sup[id='fnref-N']{#a[href='#fn-N']{N}}`, and its content is moved to a
+/// `footnote` block tag with `id='fn-N'`, which ends with a link back to the marker. The
+/// footnotes are appended, in order, inside a single `footnotes` block tag. A note within a note
+/// is numbered after the note containing it.
+///
+/// Returns the number of footnotes collected. Documents without notes are left untouched.
+///
+/// ```rust
+/// # use prosidy_ast::{collect_footnotes, Block, Document, Inline, InlineTag, PropSet, Text};
+/// let note = InlineTag::new("note", PropSet::new(), vec![Text::from("A source.").into()]);
+/// let para = Block::Content(vec![Text::from("A claim.").into(), note.into()]);
+/// let mut doc = Document::new(PropSet::new(), vec![para]);
+/// assert_eq!(1, collect_footnotes(&mut doc));
+/// let footnotes = doc.find_block_tag("footnotes").unwrap();
+/// assert_eq!("A source. ↩", footnotes.plain_text());
+/// ```
+pub fn collect_footnotes(doc: &mut Document) -> usize {
+    let mut notes = Footnotes {
+        note: Key::new(TAG_NOTE),
+        bodies: Vec::new(),
+    };
+    notes.visit_document_mut(doc);
+    let count = notes.bodies.len();
+    if count > 0 {
+        let footnotes = notes
+            .bodies
+            .into_iter()
+            .enumerate()
+            .map(|(index, body)| footnote(index + 1, body).into())
+            .collect();
+        let footnotes = BlockTag::new(TAG_FOOTNOTES, PropSet::new(), footnotes);
+        doc.content_mut().push(footnotes.into());
+    }
+    count
+}
+
+struct Footnotes<'a> {
+    note: Key,
+    bodies: Vec<Vec<Inline<'a>>>,
+}
+
+impl<'a> VisitMut<'a> for Footnotes<'a> {
+    fn visit_inline_mut(&mut self, inline: &mut Inline<'a>) {
+        let mut body = match &mut *inline {
+            Inline::Tag(tag) if tag.name_is(&self.note) => mem::take(tag.content_mut()),
+            _ => return walk_inline_mut(self, inline),
+        };
+        // The number is claimed before visiting the note's content, so notes nested inside it
+        // come after it.
+        let number = self.bodies.len() + 1;
+        self.bodies.push(Vec::new());
+        *inline = marker(number);
+        for child in body.iter_mut() {
+            self.visit_inline_mut(child);
+        }
+        self.bodies[number - 1] = body;
+    }
+}
+
+/// The reference left in place of a note.
+fn marker<'a>(number: usize) -> Inline<'a> {
+    let link = InlineTag::builder("a")
+        .setting("href", format!("#fn-{}", number))
+        .child(Text::from(number.to_string()))
+        .build();
+    InlineTag::builder("sup")
+        .setting("id", format!("fnref-{}", number))
+        .child(link)
+        .build()
+        .into()
+}
+
+/// The footnote holding a note's content, with a link back to its marker.
+fn footnote(number: usize, mut body: Vec<Inline>) -> BlockTag {
+    let back = InlineTag::builder("a")
+        .setting("href", format!("#fnref-{}", number))
+        .child(Text::from("↩"))
+        .build();
+    body.push(Text::from(" ").into());
+    body.push(back.into());
+    BlockTag::builder(TAG_FOOTNOTE)
+        .setting("id", format!("fn-{}", number))
+        .child(Block::Content(body))
+        .build()
+}
+
+const TAG_FOOTNOTE: &str = "footnote";
+const TAG_FOOTNOTES: &str = "footnotes";
+const TAG_NOTE: &str = "note";
+
+#[test]
+fn nested_footnotes() {
+    let note = |content: Vec<Inline<'static>>| -> Inline<'static> {
+        InlineTag::new(TAG_NOTE, PropSet::new(), content).into()
+    };
+    let inner = note(vec![Text::from("inner").into()]);
+    let outer = note(vec![Text::from("outer").into(), inner]);
+    let emphasis = InlineTag::new("em", PropSet::new(), vec![outer]);
+    let section = BlockTag::new(
+        "section",
+        PropSet::new(),
+        vec![Block::Content(vec![
+            emphasis.into(),
+            note(vec![Text::from("last").into()]),
+        ])],
+    );
+    let mut doc = Document::new(PropSet::new(), vec![section.into()]);
+    assert_eq!(3, collect_footnotes(&mut doc));
+    let note_key = Key::new(TAG_NOTE);
+    assert!(!crate::Node::from(&doc)
+        .descendants()
+        .any(|node| match node {
+            crate::Node::Inline(Inline::Tag(tag)) => tag.name_is(&note_key),
+            _ => false,
+        }));
+    let ids: Vec<_> = doc
+        .find_block_tags_recursive(TAG_FOOTNOTE)
+        .map(|footnote| footnote.props().lookup(Key::new("id")).unwrap())
+        .collect();
+    let expected: Vec<Text> = vec!["fn-1".into(), "fn-2".into(), "fn-3".into()];
+    assert_eq!(expected, ids);
+    let bodies: Vec<_> = doc
+        .find_block_tags_recursive(TAG_FOOTNOTE)
+        .map(BlockTag::plain_text)
+        .collect();
+    assert_eq!(vec!["outer2 ↩", "inner ↩", "last ↩"], bodies);
+    assert_eq!("13", doc.content()[0].as_tag().unwrap().plain_text());
+}
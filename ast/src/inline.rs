@@ -19,6 +19,24 @@ pub enum Inline<'a> {
 }
 
 impl<'a> Inline<'a> {
+    /// See [`Document::into_owned`](struct.Document.html#method.into_owned).
+    pub fn into_owned(self) -> Inline<'static> {
+        match self {
+            Inline::SoftBreak => Inline::SoftBreak,
+            Inline::Tag(tag) => Inline::Tag(tag.into_owned()),
+            Inline::Text(text) => Inline::Text(text.into_owned()),
+        }
+    }
+
+    /// ```rust
+    /// # use prosidy_ast::{Inline, Text};
+    /// assert!(Inline::SoftBreak.as_soft_break());
+    /// assert!(!Inline::from(Text::from(" ")).as_soft_break());
+    /// ```
+    pub fn as_soft_break(&self) -> bool {
+        matches!(self, Inline::SoftBreak)
+    }
+
     pub fn as_tag(&self) -> Option<&InlineTag<'a>> {
         if let Inline::Tag(tag) = self {
             Some(tag)
@@ -8,20 +8,30 @@ extern crate derive_more;
 
 pub use block::Block;
 pub use document::Document;
+pub use footnotes::collect_footnotes;
 pub use inline::Inline;
-pub use literal::Literal;
+pub use literal::{dedent_literals, Literal};
 pub use node::Node;
-pub use tag::{BlockTag, InlineTag, Tag};
-pub use types::{Key, PropSet, Text};
+pub use print::{PrintError, PrintOpts};
+pub use tag::{BlockTag, InlineTag, Tag, TagBuilder, TagRef};
+pub use toc::{table_of_contents, TocEntry};
+#[cfg(feature = "interning")]
+pub use types::{AtomicKeySet, KeySet};
+pub use types::{DocumentProps, Key, ParseBoolError, PropSet, PropSetIntoIter, Text, TextMut};
 
 mod block;
 mod document;
+mod footnotes;
 mod inline;
 mod literal;
 mod node;
+mod print;
 mod tag;
+mod toc;
 mod types;
 
+pub mod visit;
+
 #[macro_export]
 macro_rules! props {
     ($($key:ident $(= $val:expr)?),* $(,)?) => {{
@@ -63,3 +73,47 @@ fn test_serde() {
     let expected = Document::deserialize(&mut serde).unwrap();
     assert_eq!(actual, expected);
 }
+
+#[test]
+fn test_serialize_sorted() {
+    let mut forward = PropSet::new();
+    forward.set("a");
+    forward.set("b");
+    forward.put("x", "1");
+    forward.put("y", "2");
+    let mut reverse = PropSet::new();
+    reverse.put("y", "2");
+    reverse.put("x", "1");
+    reverse.set("b");
+    reverse.set("a");
+    let first = serde_json::to_vec(&forward).unwrap();
+    let second = serde_json::to_vec(&forward).unwrap();
+    assert_eq!(first, second);
+    assert_eq!(first, serde_json::to_vec(&reverse).unwrap());
+    assert_eq!(
+        r#"{"properties":["a","b"],"settings":{"x":"1","y":"2"}}"#,
+        String::from_utf8(first).unwrap()
+    );
+}
+
+#[test]
+fn test_deserialize_key_interned() {
+    use std::collections::HashMap;
+    let first: Key = serde_json::from_slice(String::from(r#""shared""#).as_bytes()).unwrap();
+    let second: Key = serde_json::from_slice(String::from(r#""shared""#).as_bytes()).unwrap();
+    assert_eq!(first, second);
+    let mut map = HashMap::new();
+    map.insert(first, 1);
+    assert_eq!(Some(&1), map.get(&second));
+}
+
+#[cfg(not(feature = "interning"))]
+#[test]
+fn test_keys_without_interning() {
+    use std::collections::HashSet;
+    let key = Key::new("shared");
+    assert_eq!(key, Key::uninterned("shared"));
+    assert_ne!(key, Key::new("other"));
+    let keys: HashSet<Key> = vec![key].into_iter().collect();
+    assert!(keys.contains(&Key::uninterned("shared")));
+}
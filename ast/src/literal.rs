@@ -3,8 +3,93 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use crate::block::Block;
+use crate::document::Document;
 use crate::types::Text;
+use crate::visit::{walk_block_mut, VisitMut};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Default, Eq, Deserialize, Deref, From, PartialEq, Serialize)]
 pub struct Literal<'a>(#[serde(borrow)] Text<'a>);
+
+impl<'a> Literal<'a> {
+    /// See [`Document::into_owned`](struct.Document.html#method.into_owned).
+    #[inline]
+    pub fn into_owned(self) -> Literal<'static> {
+        Literal(self.0.into_owned())
+    }
+
+    /// Removes the indentation common to every line of the literal which isn't blank, returning
+    /// whether there was any.
+    ///
+    /// Indentation is the run of spaces and tabs starting a line, and only the prefix shared
+    /// exactly by every line is removed: a tab is never treated as some number of spaces, so a
+    /// literal mixing lines indented by a tab with lines indented by spaces is left alone. Blank
+    /// lines don't count towards the common prefix, and lose whatever part of it they have.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::{Literal, Text};
+    /// let mut literal = Literal::from(Text::from("    if x {\n        y();\n\n    }\n"));
+    /// assert!(literal.dedent());
+    /// assert_eq!(Literal::from(Text::from("if x {\n    y();\n\n}\n")), literal);
+    /// let mut mixed = Literal::from(Text::from("\tone\n    two\n"));
+    /// assert!(!mixed.dedent());
+    /// ```
+    pub fn dedent(&mut self) -> bool {
+        let mut prefix: Option<&str> = None;
+        for line in self.0.lines().filter(|line| !line.trim().is_empty()) {
+            let indent = &line[..line.len() - line.trim_start_matches(is_indent).len()];
+            let common = match prefix {
+                Some(prefix) => prefix
+                    .bytes()
+                    .zip(indent.bytes())
+                    .take_while(|(a, b)| a == b)
+                    .count(),
+                None => indent.len(),
+            };
+            prefix = Some(&indent[..common]);
+        }
+        let prefix = match prefix {
+            Some(prefix) if !prefix.is_empty() => prefix,
+            _ => return false,
+        };
+        let mut dedented = String::with_capacity(self.0.len());
+        for line in self.0.split_inclusive('\n') {
+            match line.strip_prefix(prefix) {
+                Some(rest) => dedented.push_str(rest),
+                None => dedented.push_str(line.trim_start_matches(is_indent)),
+            }
+        }
+        self.0 = Text::from(dedented);
+        true
+    }
+}
+
+/// Removes the common indentation from every literal block in the document, at any depth. See
+/// [`Literal::dedent`] for how indentation is found.
+///
+/// Returns the number of literals which were changed.
+pub fn dedent_literals(doc: &mut Document) -> usize {
+    let mut dedent = Dedent(0);
+    dedent.visit_document_mut(doc);
+    dedent.0
+}
+
+struct Dedent(usize);
+
+impl<'a> VisitMut<'a> for Dedent {
+    fn visit_block_mut(&mut self, block: &mut Block<'a>) {
+        match block {
+            Block::Literal(literal) => {
+                if literal.dedent() {
+                    self.0 += 1;
+                }
+            }
+            block => walk_block_mut(self, block),
+        }
+    }
+}
+
+fn is_indent(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
@@ -15,6 +15,38 @@ pub enum Node<'r, 'a> {
 }
 
 impl<'r, 'a> Node<'r, 'a> {
+    /// Iterates over this node and every node beneath it, in depth-first pre-order: each node is
+    /// yielded before its children, and children are yielded in document order.
+    ///
+    /// The walk keeps its own worklist rather than recursing, so deeply nested documents can't
+    /// overflow the stack.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::{Block, BlockTag, Document, Inline, Node, PropSet, Text};
+    /// let para = Block::Content(vec![Inline::Text(Text::from("hi")), Inline::SoftBreak]);
+    /// let section = BlockTag::new("section", PropSet::new(), vec![para]);
+    /// let doc = Document::new(PropSet::new(), vec![section.into()]);
+    /// let kinds: Vec<&str> = Node::from(&doc)
+    ///     .descendants()
+    ///     .map(|node| match node {
+    ///         Node::Document(_) => "document",
+    ///         Node::Block(Block::Tag(_)) => "tag",
+    ///         Node::Block(_) => "block",
+    ///         Node::Inline(Inline::Text(_)) => "text",
+    ///         Node::Inline(_) => "inline",
+    ///     })
+    ///     .collect();
+    /// assert_eq!(kinds, vec!["document", "tag", "block", "text", "inline"]);
+    /// ```
+    pub fn descendants(self) -> impl Iterator<Item = Node<'r, 'a>> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            node.push_children(|child| stack.push(child));
+            Some(node)
+        })
+    }
+
     pub fn push_children<F>(self, mut f: F)
     where
         F: FnMut(Node<'r, 'a>),
@@ -0,0 +1,533 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Writes the AST back out as Prosidy source.
+//!
+//! These rules mirror the grammar in the `prosidy-parse` crate, which this crate can't depend on:
+//! any change to what the grammar reserves or skips has to be made here too.
+
+use std::fmt::{self, Display, Formatter};
+use std::slice;
+
+use crate::block::Block;
+use crate::document::Document;
+use crate::inline::Inline;
+use crate::tag::{BlockTag, InlineTag};
+use crate::types::{Key, PropSet, Text};
+
+/// Settings for [`Document::print`].
+#[derive(Clone, Debug)]
+pub struct PrintOpts {
+    /// How many spaces to indent the content of block tags by.
+    pub indent: usize,
+    /// Reflows paragraphs to fit within this many characters, where they can be broken. Without
+    /// it, paragraphs keep their line breaks.
+    pub width: Option<usize>,
+}
+
+impl Default for PrintOpts {
+    fn default() -> Self {
+        PrintOpts {
+            indent: 4,
+            width: None,
+        }
+    }
+}
+
+/// The error returned when a document can't be written as Prosidy source, like one with a
+/// literal outside of a block tag or a key containing whitespace.
+#[derive(Clone, Debug, Display, Eq, PartialEq)]
+#[display(fmt = "{}", _0)]
+pub struct PrintError(String);
+
+impl std::error::Error for PrintError {}
+
+type Result<T> = std::result::Result<T, PrintError>;
+
+pub(crate) fn print_document(doc: &Document, opts: &PrintOpts) -> Result<String> {
+    let mut printer = Printer::new(opts);
+    printer.header(doc.props())?;
+    if !doc.content().is_empty() {
+        printer.out.push('\n');
+        printer.blocks(doc.content(), 0)?;
+    }
+    Ok(printer.out)
+}
+
+/// Writes a document as Prosidy source, with the default [`PrintOpts`]. See [`Document::print`].
+///
+/// A document which can't be written as Prosidy is written in its `Debug` form instead, so that
+/// `to_string` never panics; call [`Document::print`] to find out why.
+impl<'a> Display for Document<'a> {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        write_or_debug(fmt, self, print_document(self, &PrintOpts::default()))
+    }
+}
+
+/// Writes a block as it would appear at the top level of a document's body, ending in a line feed,
+/// or in its `Debug` form if it can't be written as Prosidy.
+impl<'a> Display for Block<'a> {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        let opts = PrintOpts::default();
+        let mut printer = Printer::new(&opts);
+        let printed = printer
+            .blocks(slice::from_ref(self), 0)
+            .map(|_| printer.out);
+        write_or_debug(fmt, self, printed)
+    }
+}
+
+impl<'a> Display for BlockTag<'a> {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        let opts = PrintOpts::default();
+        let mut printer = Printer::new(&opts);
+        let printed = printer.block_tag(self, 0).map(|_| printer.out);
+        write_or_debug(fmt, self, printed)
+    }
+}
+
+/// Writes an inline node as it would appear within a paragraph, without a trailing line feed, or
+/// in its `Debug` form if it can't be written as Prosidy.
+impl<'a> Display for Inline<'a> {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        let opts = PrintOpts::default();
+        let mut printer = Printer::new(&opts);
+        let mut tokens = Vec::new();
+        let printed = printer.inline(self, None, &mut tokens).map(|_| {
+            printer.layout(&tokens, 0);
+            printer.out.pop();
+            printer.out
+        });
+        write_or_debug(fmt, self, printed)
+    }
+}
+
+impl<'a> Display for InlineTag<'a> {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        let opts = PrintOpts::default();
+        let mut printer = Printer::new(&opts);
+        let mut tokens = Vec::new();
+        let printed = printer.inline_tag(self, None, &mut tokens).map(|_| {
+            printer.layout(&tokens, 0);
+            printer.out.pop();
+            printer.out
+        });
+        write_or_debug(fmt, self, printed)
+    }
+}
+
+/// Writes the source `printed` for `node`, or the node's `Debug` form if it couldn't be printed.
+/// `Display` may only fail when the formatter does, or `to_string` would panic.
+fn write_or_debug<T: fmt::Debug>(
+    fmt: &mut Formatter,
+    node: &T,
+    printed: Result<String>,
+) -> fmt::Result {
+    match printed {
+        Ok(source) => fmt.write_str(&source),
+        Err(_) => write!(fmt, "{:?}", node),
+    }
+}
+
+struct Printer<'o> {
+    out: String,
+    opts: &'o PrintOpts,
+}
+
+/// A piece of a paragraph, as laid out onto lines.
+#[derive(Debug)]
+enum Token {
+    /// Escaped text without breakable whitespace.
+    Text(String),
+    /// A run of spaces and tabs, where the line may be broken when reflowing.
+    Gap(String),
+    /// The start of a tag, up to and including its opening brace.
+    Open(String),
+    Close,
+    SoftBreak,
+}
+
+impl<'o> Printer<'o> {
+    fn new(opts: &'o PrintOpts) -> Self {
+        Printer {
+            out: String::new(),
+            opts,
+        }
+    }
+
+    fn header(&mut self, props: &PropSet) -> Result<()> {
+        for (key, value) in sorted(props) {
+            self.out.push_str(check_key(key)?);
+            if let Some(value) = value.filter(|value| !value.is_empty()) {
+                self.out.push_str(": ");
+                // Whitespace after the separator or after an escape is skipped by the parser.
+                let mut skipped = true;
+                for ch in value.chars() {
+                    if skipped && ch.is_whitespace() {
+                        escape_code_point(&mut self.out, ch);
+                    } else {
+                        skipped = escape(&mut self.out, ch);
+                    }
+                }
+            }
+            self.out.push('\n');
+        }
+        self.out.push_str("---\n");
+        Ok(())
+    }
+
+    fn blocks(&mut self, blocks: &[Block], depth: usize) -> Result<()> {
+        for (index, block) in blocks.iter().enumerate() {
+            if index > 0 {
+                self.out.push('\n');
+            }
+            match block {
+                Block::Content(inlines) => {
+                    let mut tokens = Vec::new();
+                    self.paragraph(inlines, &mut tokens)?;
+                    self.layout(&tokens, depth);
+                }
+                Block::Literal(_) => {
+                    return Err(unprintable(
+                        "a literal must be the only content of a block tag",
+                    ))
+                }
+                Block::Tag(tag) => self.block_tag(tag, depth)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn block_tag(&mut self, tag: &BlockTag, depth: usize) -> Result<()> {
+        let head = format!("{}{}", check_key(tag.name())?, props(tag.props())?);
+        match tag.content() {
+            [] => {
+                self.indent(depth);
+                self.out.push_str("#-");
+                self.out.push_str(&head);
+                self.out.push('\n');
+            }
+            [Block::Content(inlines)] => {
+                let mut tokens = vec![Token::Open(format!("#-{}{{", head))];
+                self.paragraph(inlines, &mut tokens)?;
+                tokens.push(Token::Close);
+                self.layout(&tokens, depth);
+            }
+            [Block::Literal(literal)] => {
+                let text: &str = literal;
+                if !(text.is_empty() || text.ends_with('\n')) || text.contains('\r') {
+                    return Err(unprintable("a literal must end with a line feed"));
+                }
+                let label = literal_label(text);
+                self.indent(depth);
+                self.out.push_str(&format!("#={}:{}\n", head, label));
+                self.out.push_str(text);
+                self.indent(depth);
+                self.out.push_str(&format!("#:{}\n", label));
+            }
+            blocks => {
+                self.indent(depth);
+                self.out.push_str(&format!("#-{}:\n", head));
+                self.blocks(blocks, depth + 1)?;
+                self.indent(depth);
+                self.out.push_str("#:\n");
+            }
+        }
+        Ok(())
+    }
+
+    fn paragraph(&self, inlines: &[Inline], tokens: &mut Vec<Token>) -> Result<()> {
+        let soft_break = |inline: Option<&Inline>| matches!(inline, Some(Inline::SoftBreak));
+        if inlines.is_empty() || soft_break(inlines.first()) || soft_break(inlines.last()) {
+            return Err(unprintable(
+                "a paragraph can't be empty or start or end with a break",
+            ));
+        }
+        for (index, inline) in inlines.iter().enumerate() {
+            let next = inlines.get(index + 1);
+            if inline.as_soft_break() && soft_break(next) {
+                return Err(unprintable("a paragraph can't contain consecutive breaks"));
+            }
+            self.inline(inline, next, tokens)?;
+        }
+        Ok(())
+    }
+
+    fn inline(
+        &self,
+        inline: &Inline,
+        next: Option<&Inline>,
+        tokens: &mut Vec<Token>,
+    ) -> Result<()> {
+        match inline {
+            Inline::SoftBreak if self.opts.width.is_some() => push_gap(tokens, " "),
+            Inline::SoftBreak => tokens.push(Token::SoftBreak),
+            Inline::Tag(tag) => self.inline_tag(tag, next, tokens)?,
+            Inline::Text(text) => {
+                let mut rest = text.as_str();
+                while let Some(ch) = rest.chars().next() {
+                    let end = rest.find(|c| is_gap(c) != is_gap(ch)).unwrap_or(rest.len());
+                    if is_gap(ch) {
+                        push_gap(tokens, &rest[..end]);
+                    } else {
+                        let mut escaped = String::with_capacity(end);
+                        rest[..end].chars().for_each(|ch| {
+                            escape(&mut escaped, ch);
+                        });
+                        tokens.push(Token::Text(escaped));
+                    }
+                    rest = &rest[end..];
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn inline_tag(
+        &self,
+        tag: &InlineTag,
+        next: Option<&Inline>,
+        tokens: &mut Vec<Token>,
+    ) -> Result<()> {
+        let name = check_key(tag.name())?;
+        if name.starts_with('+') {
+            return Err(unprintable(format!(
+                "inline tag {:?} can't start with '+'",
+                name
+            )));
+        }
+        let head = format!("#{}{}", name, props(tag.props())?);
+        if !tag.content().is_empty() {
+            tokens.push(Token::Open(head + "{"));
+            self.paragraph(tag.content(), tokens)?;
+            tokens.push(Token::Close);
+            return Ok(());
+        }
+        // Without a body, text right after the tag could be read as more of its name or props.
+        let ambiguous = match next {
+            Some(Inline::Text(text)) if tag.props().is_empty() => text
+                .as_str()
+                .chars()
+                .next()
+                .is_some_and(|ch| ch == '[' || !is_key_reserved(ch)),
+            _ => false,
+        };
+        tokens.push(Token::Text(if ambiguous { head + "{}" } else { head }));
+        Ok(())
+    }
+
+    /// Writes a paragraph's tokens starting on a new line, followed by a line feed.
+    fn layout(&mut self, tokens: &[Token], depth: usize) {
+        let indent = depth * self.opts.indent;
+        self.indent(depth);
+        let mut column = indent;
+        let mut line_start = true;
+        for (index, token) in tokens.iter().enumerate() {
+            let text = match token {
+                Token::Text(text) | Token::Open(text) => text.as_str(),
+                Token::Close => "}",
+                Token::Gap(gap) => {
+                    let next = unit_width(&tokens[index + 1..]);
+                    let overflows = self
+                        .opts
+                        .width
+                        .is_some_and(|width| column + gap.chars().count() + next > width);
+                    if overflows && is_breakable(tokens, index) {
+                        self.out.push('\n');
+                        self.indent(depth);
+                        column = indent;
+                        line_start = true;
+                        continue;
+                    }
+                    gap.as_str()
+                }
+                Token::SoftBreak => {
+                    self.out.push('\n');
+                    self.indent(depth);
+                    column = indent;
+                    line_start = true;
+                    continue;
+                }
+            };
+            let mut chars = text.chars();
+            match chars.next() {
+                // The parser skips whitespace at the start of a line.
+                Some(ch) if line_start && ch.is_whitespace() => {
+                    escape_code_point(&mut self.out, ch);
+                    self.out.push_str(chars.as_str());
+                }
+                _ => self.out.push_str(text),
+            }
+            column += text.chars().count();
+            line_start = false;
+        }
+        self.out.push('\n');
+    }
+
+    fn indent(&mut self, depth: usize) {
+        let width = depth * self.opts.indent;
+        self.out.push_str(&" ".repeat(width));
+    }
+}
+
+/// Whether the line can be broken at the gap at `index`. A tag's content can't start or end with
+/// a break, so gaps just inside its braces are kept.
+fn is_breakable(tokens: &[Token], index: usize) -> bool {
+    let before = index.checked_sub(1).and_then(|before| tokens.get(before));
+    matches!(before, Some(Token::Text(_)) | Some(Token::Close))
+        && matches!(
+            tokens.get(index + 1),
+            Some(Token::Text(_)) | Some(Token::Open(_))
+        )
+}
+
+/// The width of the tokens up to the next place the line could be broken.
+fn unit_width(tokens: &[Token]) -> usize {
+    tokens
+        .iter()
+        .map_while(|token| match token {
+            Token::Text(text) | Token::Open(text) => Some(text.chars().count()),
+            Token::Close => Some(1),
+            Token::Gap(_) | Token::SoftBreak => None,
+        })
+        .sum()
+}
+
+fn push_gap(tokens: &mut Vec<Token>, gap: &str) {
+    match tokens.last_mut() {
+        Some(Token::Gap(previous)) => previous.push_str(gap),
+        _ => tokens.push(Token::Gap(gap.to_string())),
+    }
+}
+
+fn is_gap(ch: char) -> bool {
+    ch == ' ' || ch == '\t'
+}
+
+/// Escapes a character of text, returning whether it needed an escape.
+fn escape(out: &mut String, ch: char) -> bool {
+    let escaped = match ch {
+        '\\' => "\\\\",
+        '#' => "\\#",
+        '{' => "\\{",
+        '}' => "\\}",
+        '\n' => "\\n",
+        '\r' => {
+            escape_code_point(out, ch);
+            return true;
+        }
+        _ => {
+            out.push(ch);
+            return false;
+        }
+    };
+    out.push_str(escaped);
+    true
+}
+
+fn escape_code_point(out: &mut String, ch: char) {
+    out.push_str(&format!("\\u{{{:X}}}", ch as u32));
+}
+
+/// Formats properties as they're written after a tag's name, sorted by key.
+fn props(props: &PropSet) -> Result<String> {
+    if props.is_empty() {
+        return Ok(String::new());
+    }
+    let mut out = String::from("[");
+    for (index, (key, value)) in sorted(props).into_iter().enumerate() {
+        if index > 0 {
+            out.push_str(", ");
+        }
+        out.push_str(check_key(key)?);
+        if let Some(value) = value.filter(|value| !value.is_empty()) {
+            out.push('=');
+            quote(&mut out, key, value.as_str())?;
+        }
+    }
+    out.push(']');
+    Ok(out)
+}
+
+/// Quotes a property value, with whichever quote needs fewer escapes.
+///
+/// At the start of a value and right after an escape, the parser skips whitespace and reads `##`
+/// as the start of a comment, and it reads carriage returns as line feeds. None of those can be
+/// escaped, so values which would change are unprintable, unless the other quote avoids the
+/// escape.
+fn quote(out: &mut String, key: &Key, value: &str) -> Result<()> {
+    let is_skipped = |rest: &str| rest.starts_with(char::is_whitespace) || rest.starts_with("##");
+    let skips = |quote: char| {
+        value
+            .match_indices([quote, '\\'])
+            .any(|(index, escaped)| is_skipped(&value[index + escaped.len()..]))
+    };
+    let (first, second) = if value.matches('\'').count() > value.matches('"').count() {
+        ('"', '\'')
+    } else {
+        ('\'', '"')
+    };
+    let quote = if skips(first) { second } else { first };
+    if is_skipped(value) || value.contains('\r') || skips(quote) {
+        return Err(unprintable(format!(
+            "the value of {:?} can't be quoted as it is",
+            key.as_str()
+        )));
+    }
+    out.push(quote);
+    for ch in value.chars() {
+        if ch == quote || ch == '\\' {
+            out.push('\\');
+        }
+        out.push(ch);
+    }
+    out.push(quote);
+    Ok(())
+}
+
+fn sorted<'p, 'a>(props: &'p PropSet<'a>) -> Vec<(&'p Key, Option<Text<'a>>)> {
+    let mut sorted: Vec<_> = props.iter().collect();
+    sorted.sort_by(|(left, _), (right, _)| left.as_str().cmp(right.as_str()));
+    sorted
+}
+
+/// Picks a label for a literal's delimiters which doesn't appear within it.
+fn literal_label(text: &str) -> String {
+    if !text.contains("#:") {
+        return String::new();
+    }
+    let mut label = String::from("end");
+    let mut suffix = 1;
+    while text.contains(&format!("#:{}", label)) {
+        suffix += 1;
+        label = format!("end{}", suffix);
+    }
+    label
+}
+
+fn check_key(key: &Key) -> Result<&str> {
+    let name = key.as_str();
+    let mut chars = name.chars();
+    let valid = match chars.next() {
+        Some(first) => {
+            !(is_key_reserved(first) || first == '-' || ('1'..='9').contains(&first))
+                && chars.all(|ch| !is_key_reserved(ch))
+        }
+        None => false,
+    };
+    if valid {
+        Ok(name)
+    } else {
+        Err(unprintable(format!("{:?} isn't a valid key", name)))
+    }
+}
+
+fn is_key_reserved(ch: char) -> bool {
+    ch.is_whitespace() || "\\#{}[]:=\",".contains(ch)
+}
+
+fn unprintable<S: Into<String>>(message: S) -> PrintError {
+    PrintError(message.into())
+}
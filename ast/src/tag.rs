@@ -8,8 +8,11 @@ use std::ops::{Deref, DerefMut};
 use serde::{Deserialize, Serialize};
 
 use crate::block::Block;
+use crate::document;
 use crate::inline::Inline;
-use crate::types::{Key, PropSet};
+use crate::node::Node;
+use crate::types::{Key, PropSet, Text};
+use crate::visit::TagContent;
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 /// A named marker for a region of a document.
@@ -33,6 +36,69 @@ impl<'a, T> Tag<'a, T> {
             content,
         }
     }
+
+    /// Starts building a tag with the given name.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::{Block, BlockTag, Inline, Key, PropSet, Text};
+    /// let tag = BlockTag::builder("section")
+    ///     .prop("numbered")
+    ///     .setting("id", String::from("intro"))
+    ///     .child(Block::Content(vec![Text::from("hello").into()]))
+    ///     .build();
+    /// assert!(tag.name_is(&Key::new("section")));
+    /// assert!(tag.props().is_set(Key::new("numbered")));
+    /// assert_eq!(Some(Text::from("intro")), tag.props().lookup(Key::new("id")));
+    /// assert_eq!(1, tag.content().len());
+    /// ```
+    #[inline]
+    pub fn builder<K: Into<Key>>(name: K) -> TagBuilder<'a, T> {
+        TagBuilder(Tag::new(name, PropSet::new(), Vec::new()))
+    }
+}
+
+/// A builder for [`Tag`](struct.Tag.html)s, created by
+/// [`Tag::builder`](struct.Tag.html#method.builder).
+#[derive(Clone, Debug)]
+pub struct TagBuilder<'a, T>(Tag<'a, T>);
+
+impl<'a, T> TagBuilder<'a, T> {
+    /// Sets a property on the tag.
+    #[inline]
+    pub fn prop<K: Into<Key>>(mut self, key: K) -> Self {
+        self.0.props.set(key);
+        self
+    }
+
+    /// Sets a setting on the tag, replacing any previous value for the key.
+    #[inline]
+    pub fn setting<K: Into<Key>, V: Into<Text<'a>>>(mut self, key: K, value: V) -> Self {
+        self.0.props.put(key, value);
+        self
+    }
+
+    /// Appends a child to the tag's content.
+    #[inline]
+    pub fn child<C: Into<T>>(mut self, child: C) -> Self {
+        self.0.content.push(child.into());
+        self
+    }
+
+    /// Appends each of the children to the tag's content.
+    #[inline]
+    pub fn children<I>(mut self, children: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<T>,
+    {
+        self.0.content.extend(children.into_iter().map(Into::into));
+        self
+    }
+
+    #[inline]
+    pub fn build(self) -> Tag<'a, T> {
+        self.0
+    }
 }
 
 impl<'a, T> Tag<'a, T> {
@@ -41,6 +107,39 @@ impl<'a, T> Tag<'a, T> {
         &self.name
     }
 
+    /// Checks whether this tag has the given name.
+    ///
+    /// Since [`Key`]s are interned, this is a pointer comparison. When checking many tags against
+    /// the same name, intern the name once outside of the loop rather than once per tag:
+    ///
+    /// ```rust
+    /// # use prosidy_ast::{BlockTag, Key, PropSet};
+    /// let tags = vec![
+    ///     BlockTag::new("h1", PropSet::new(), vec![]),
+    ///     BlockTag::new("p", PropSet::new(), vec![]),
+    ///     BlockTag::new("h1", PropSet::new(), vec![]),
+    /// ];
+    /// let h1 = Key::new("h1");
+    /// assert_eq!(2, tags.iter().filter(|tag| tag.name_is(&h1)).count());
+    /// ```
+    #[inline]
+    pub fn name_is(&self, name: &Key) -> bool {
+        self.name == *name
+    }
+
+    /// Checks whether this tag's name is any of the given names.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::{InlineTag, Key, PropSet};
+    /// let allowed = [Key::new("em"), Key::new("strong")];
+    /// assert!(InlineTag::new("em", PropSet::new(), vec![]).names_in(&allowed));
+    /// assert!(!InlineTag::new("code", PropSet::new(), vec![]).names_in(&allowed));
+    /// ```
+    #[inline]
+    pub fn names_in(&self, names: &[Key]) -> bool {
+        names.contains(&self.name)
+    }
+
     #[inline]
     pub fn set_name<K: Into<Key>>(&mut self, name: K) {
         self.name = name.into();
@@ -67,6 +166,143 @@ impl<'a, T> Tag<'a, T> {
     }
 }
 
+impl<'a, T: TagContent<'a>> Tag<'a, T> {
+    /// Finds the first tag with the given name among this tag's direct children.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::{BlockTag, PropSet};
+    /// let inner = BlockTag::new("h1", PropSet::new(), vec![]);
+    /// let middle = BlockTag::new("section", PropSet::new(), vec![inner.into()]);
+    /// let outer = BlockTag::new("h1", PropSet::new(), vec![]);
+    /// let root = BlockTag::new("article", PropSet::new(), vec![middle.into(), outer.into()]);
+    /// assert!(root.find_tag("h1").unwrap().is_empty());
+    /// assert_eq!(1, root.find_tags("h1").count());
+    /// assert_eq!(2, root.find_tags_recursive("h1").count());
+    /// assert!(root.find_tag("table").is_none());
+    /// ```
+    pub fn find_tag<K: Into<Key>>(&self, name: K) -> Option<&Tag<'a, T>> {
+        self.find_tags(name).next()
+    }
+
+    /// Iterates over the tags with the given name among this tag's direct children.
+    pub fn find_tags<'r, K: Into<Key>>(&'r self, name: K) -> impl Iterator<Item = &'r Tag<'a, T>> {
+        child_tags(&self.content, name.into())
+    }
+
+    /// Iterates over the tags with the given name at any depth beneath this tag, in depth-first
+    /// pre-order. Only tags of the same kind are searched, so a block tag's search does not
+    /// descend into inline tags within its paragraphs.
+    pub fn find_tags_recursive<'r, K: Into<Key>>(
+        &'r self,
+        name: K,
+    ) -> impl Iterator<Item = &'r Tag<'a, T>> {
+        descendant_tags(&self.content, name.into())
+    }
+}
+
+/// Iterates over the tags named `name` within `content`.
+pub(crate) fn child_tags<'r, 'a: 'r, T: TagContent<'a>>(
+    content: &'r [T],
+    name: Key,
+) -> impl Iterator<Item = &'r Tag<'a, T>> {
+    content
+        .iter()
+        .filter_map(T::as_tag)
+        .filter(move |tag| tag.name_is(&name))
+}
+
+/// Iterates over the tags named `name` within `content` and, recursively, within their content.
+pub(crate) fn descendant_tags<'r, 'a: 'r, T: TagContent<'a>>(
+    content: &'r [T],
+    name: Key,
+) -> impl Iterator<Item = &'r Tag<'a, T>> {
+    let mut stack: Vec<&Tag<'a, T>> = content.iter().rev().filter_map(T::as_tag).collect();
+    std::iter::from_fn(move || loop {
+        let tag = stack.pop()?;
+        stack.extend(tag.content.iter().rev().filter_map(T::as_tag));
+        if tag.name_is(&name) {
+            return Some(tag);
+        }
+    })
+}
+
+/// A tag of either kind, as found by [`Document::tags`](struct.Document.html#method.tags).
+#[derive(Copy, Clone, Debug, From)]
+pub enum TagRef<'r, 'a> {
+    Block(&'r BlockTag<'a>),
+    Inline(&'r InlineTag<'a>),
+}
+
+impl<'r, 'a> TagRef<'r, 'a> {
+    pub fn name(self) -> &'r Key {
+        match self {
+            TagRef::Block(tag) => tag.name(),
+            TagRef::Inline(tag) => tag.name(),
+        }
+    }
+
+    pub fn props(self) -> &'r PropSet<'a> {
+        match self {
+            TagRef::Block(tag) => tag.props(),
+            TagRef::Inline(tag) => tag.props(),
+        }
+    }
+
+    pub fn as_block(self) -> Option<&'r BlockTag<'a>> {
+        if let TagRef::Block(tag) = self {
+            Some(tag)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_inline(self) -> Option<&'r InlineTag<'a>> {
+        if let TagRef::Inline(tag) = self {
+            Some(tag)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> BlockTag<'a> {
+    /// Concatenates the visible text beneath this tag, following the same rules as
+    /// [`Document::plain_text`](struct.Document.html#method.plain_text).
+    ///
+    /// ```rust
+    /// # use prosidy_ast::{Block, BlockTag, Inline, InlineTag, PropSet, Text};
+    /// let para = Block::Content(vec![
+    ///     Text::from("Getting ").into(),
+    ///     InlineTag::new("em", PropSet::new(), vec![Text::from("started").into()]).into(),
+    /// ]);
+    /// let heading = BlockTag::new("h1", PropSet::new(), vec![para]);
+    /// assert_eq!("Getting started", heading.plain_text());
+    /// ```
+    pub fn plain_text(&self) -> String {
+        document::collect_text(self.content.iter().map(Node::from), true)
+    }
+
+    /// See [`Document::into_owned`](struct.Document.html#method.into_owned).
+    pub fn into_owned(self) -> BlockTag<'static> {
+        Tag {
+            name: self.name,
+            props: self.props.into_owned(),
+            content: self.content.into_iter().map(Block::into_owned).collect(),
+        }
+    }
+}
+
+impl<'a> InlineTag<'a> {
+    /// See [`Document::into_owned`](struct.Document.html#method.into_owned).
+    pub fn into_owned(self) -> InlineTag<'static> {
+        Tag {
+            name: self.name,
+            props: self.props.into_owned(),
+            content: self.content.into_iter().map(Inline::into_owned).collect(),
+        }
+    }
+}
+
 impl<'a, T> Deref for Tag<'a, T> {
     type Target = [T];
 
@@ -0,0 +1,129 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use serde::Serialize;
+
+use crate::block::Block;
+use crate::document::Document;
+use crate::node::Node;
+use crate::types::Key;
+
+/// A heading in a table of contents, along with the headings beneath it.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct TocEntry {
+    /// The heading's level, starting from 1.
+    pub level: usize,
+    /// The heading's text, with tags stripped.
+    pub title: String,
+    /// The heading's `id` setting, if it has one.
+    pub id: Option<String>,
+    pub children: Vec<TocEntry>,
+}
+
+/// Builds a table of contents from the block tags named in `heading_names`, at any depth.
+///
+/// A heading's level is one more than the position of its name in `heading_names`, so passing
+/// `&["h1", "h2", "h3"]` puts `h1` at level 1. Each heading is nested beneath the nearest heading
+/// before it with a lower level. Skipped levels aren't filled in: an `h3` directly after an `h1`
+/// becomes a child of the `h1`, but keeps level 3. Likewise, headings before the first one of the
+/// top level are placed at the top of the table.
+///
+/// ```rust
+/// # use prosidy_ast::{table_of_contents, Block, BlockTag, Document, Inline, PropSet, Text};
+/// let heading = |name: &str, text: &str| -> Block {
+///     let content = Block::Content(vec![Inline::from(Text::from(text.to_string()))]);
+///     BlockTag::new(name, PropSet::new(), vec![content]).into()
+/// };
+/// let doc = Document::new(
+///     PropSet::new(),
+///     vec![heading("h1", "Intro"), heading("h2", "Goals"), heading("h1", "Usage")],
+/// );
+/// let toc = table_of_contents(&doc, &["h1", "h2"]);
+/// assert_eq!(2, toc.len());
+/// assert_eq!("Goals", toc[0].children[0].title);
+/// assert_eq!(2, toc[0].children[0].level);
+/// assert!(toc[1].children.is_empty());
+/// ```
+pub fn table_of_contents(doc: &Document, heading_names: &[&str]) -> Vec<TocEntry> {
+    let names: Vec<Key> = heading_names.iter().copied().map(Key::new).collect();
+    let id = Key::new("id");
+    let mut toc = Vec::new();
+    // The entries still open for children, outermost first.
+    let mut open: Vec<TocEntry> = Vec::new();
+    for node in Node::from(doc).descendants() {
+        let tag = match node {
+            Node::Block(Block::Tag(tag)) => tag,
+            _ => continue,
+        };
+        let level = match names.iter().position(|name| tag.name_is(name)) {
+            Some(index) => index + 1,
+            None => continue,
+        };
+        let entry = TocEntry {
+            level,
+            title: tag.plain_text(),
+            id: tag.props().lookup_str(&id).map(str::to_string),
+            children: Vec::new(),
+        };
+        close_until(&mut open, &mut toc, level);
+        open.push(entry);
+    }
+    close_until(&mut open, &mut toc, 0);
+    toc
+}
+
+/// Closes the open entries with a level of at least `level`, attaching each to its parent.
+fn close_until(open: &mut Vec<TocEntry>, toc: &mut Vec<TocEntry>, level: usize) {
+    while open.last().map(|entry| entry.level) >= Some(level.max(1)) {
+        let entry = open.pop().unwrap();
+        match open.last_mut() {
+            Some(parent) => parent.children.push(entry),
+            None => toc.push(entry),
+        }
+    }
+}
+
+#[test]
+fn skipped_levels() {
+    use crate::{BlockTag, Inline, PropSet, Text};
+
+    let heading = |name: &str, text: &'static str| -> Block {
+        let content = Block::Content(vec![Inline::from(Text::from(text))]);
+        BlockTag::new(name, PropSet::new(), vec![content]).into()
+    };
+    let mut props = PropSet::new();
+    props.put("id", "setup");
+    let section = BlockTag::new(
+        "section",
+        PropSet::new(),
+        vec![
+            heading("h3", "Details"),
+            BlockTag::new(
+                "h2",
+                props,
+                vec![Block::Content(vec![Text::from("Setup").into()])],
+            )
+            .into(),
+        ],
+    );
+    let doc = Document::new(
+        PropSet::new(),
+        vec![
+            heading("h2", "Preface"),
+            heading("h1", "Intro"),
+            section.into(),
+            heading("h1", "End"),
+        ],
+    );
+    let toc = table_of_contents(&doc, &["h1", "h2", "h3"]);
+    let titles = |entries: &[TocEntry]| -> Vec<String> {
+        entries.iter().map(|entry| entry.title.clone()).collect()
+    };
+    assert_eq!(vec!["Preface", "Intro", "End"], titles(&toc));
+    assert_eq!(vec!["Details", "Setup"], titles(&toc[1].children));
+    assert_eq!(3, toc[1].children[0].level);
+    assert_eq!(Some("setup".to_string()), toc[1].children[1].id);
+    assert!(toc[2].children.is_empty());
+}
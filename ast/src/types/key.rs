@@ -7,10 +7,13 @@ use std::borrow::{Borrow, Cow};
 use std::fmt::{self, Debug, Display, Formatter};
 use std::hash::{Hash, Hasher};
 use std::ops::Deref;
-use std::sync::{Arc, RwLock, Weak};
+use std::sync::Arc;
+#[cfg(feature = "interning")]
+use std::sync::{RwLock, Weak};
 
 use serde::de::{Deserialize, Deserializer};
 use serde::ser::{Serialize, Serializer};
+#[cfg(feature = "interning")]
 use weak_table::WeakHashSet;
 
 use super::text::Text;
@@ -33,15 +36,70 @@ use super::text::Text;
 /// ```
 ///
 /// Keys incur a small creation cost for much faster equality and hashing operations.
+///
+/// Interning relies on a global set behind a lock, which is provided by the `interning` feature,
+/// on by default. Without it, [`Key::new`] creates [uninterned](Key::uninterned) keys, and keys
+/// are compared and hashed by their strings: creating a key never takes a lock, but comparing
+/// two costs as much as comparing their strings. [`KeySet`] and [`AtomicKeySet`] are only
+/// available with the feature.
 #[derive(Clone, Debug)]
 pub struct Key(Arc<str>);
 
 impl Key {
+    #[cfg(feature = "interning")]
     #[inline]
     pub fn new(s: &str) -> Key {
         GLOBAL_KEY_SET.intern(s)
     }
 
+    #[cfg(not(feature = "interning"))]
+    #[inline]
+    pub fn new(s: &str) -> Key {
+        Key::uninterned(s)
+    }
+
+    /// Creates a key interned in the given set rather than the global one.
+    ///
+    /// Keys are only equal to keys interned in the same set: a key created with `new_in` is never
+    /// equal to one created with [`Key::new`], or with a different set, even if their strings
+    /// match. Interned strings are released once every key referencing them is dropped.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::{AtomicKeySet, Key};
+    /// let set = AtomicKeySet::new();
+    /// let key_1 = Key::new_in(&set, "foo");
+    /// let key_2 = Key::new_in(&set, "foo");
+    /// assert_eq!(key_1, key_2);
+    /// assert_ne!(key_1, Key::new("foo"));
+    /// assert_ne!(key_1, Key::new_in(&AtomicKeySet::new(), "foo"));
+    /// ```
+    #[cfg(feature = "interning")]
+    #[inline]
+    pub fn new_in(set: &AtomicKeySet, s: &str) -> Key {
+        set.intern(s)
+    }
+
+    /// Creates a key from the lowercase form of `s`, so that names differing only in case, like
+    /// `DIV` and `div`, give equal keys.
+    ///
+    /// Only the lowercase string is kept: it's what [`Key::as_str`] returns and what the key
+    /// serializes as, and the key is equal to one created with [`Key::new`] from that string.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::Key;
+    /// assert_eq!(Key::new_folded("DIV"), Key::new_folded("div"));
+    /// assert_eq!(Key::new("straße"), Key::new_folded("STRAßE"));
+    /// assert_eq!("div", Key::new_folded("Div").as_str());
+    /// assert_ne!(Key::new("DIV"), Key::new_folded("DIV"));
+    /// ```
+    pub fn new_folded(s: &str) -> Key {
+        if s.chars().any(char::is_uppercase) {
+            Key::new(&s.to_lowercase())
+        } else {
+            Key::new(s)
+        }
+    }
+
     #[inline]
     pub fn uninterned(s: &str) -> Key {
         Key(Arc::from(s))
@@ -52,6 +110,23 @@ impl Key {
         &self.0
     }
 
+    /// Compares the strings of two keys, rather than their pointers. Unlike `==`, this holds for
+    /// keys created from the same string in different [`KeySet`]s, or with [`Key::uninterned`].
+    /// Without the `interning` feature, it's the same as `==`.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::Key;
+    /// let interned = Key::new("foo");
+    /// let uninterned = Key::uninterned("foo");
+    /// # #[cfg(feature = "interning")]
+    /// assert_ne!(interned, uninterned);
+    /// assert!(interned.same_str(&uninterned));
+    /// ```
+    #[inline]
+    pub fn same_str(&self, other: &Key) -> bool {
+        self == other || self.as_str() == other.as_str()
+    }
+
     #[inline]
     pub fn arc(&self) -> Arc<str> {
         Arc::clone(&self.0)
@@ -90,10 +165,16 @@ impl Display for Key {
 impl Eq for Key {}
 
 impl Hash for Key {
+    #[cfg(feature = "interning")]
     fn hash<H: Hasher>(&self, hasher: &mut H) {
         let ptr: *const u8 = self.0.as_ptr();
         hasher.write_usize(ptr as usize)
     }
+
+    #[cfg(not(feature = "interning"))]
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        self.as_str().hash(hasher)
+    }
 }
 
 impl<'a> From<&'a str> for Key {
@@ -115,12 +196,22 @@ impl<'a> From<Text<'a>> for Key {
 }
 
 impl PartialEq for Key {
+    #[cfg(feature = "interning")]
     #[inline]
     fn eq(&self, other: &Key) -> bool {
         Arc::ptr_eq(&self.0, &other.0)
     }
+
+    #[cfg(not(feature = "interning"))]
+    #[inline]
+    fn eq(&self, other: &Key) -> bool {
+        Arc::ptr_eq(&self.0, &other.0) || self.as_str() == other.as_str()
+    }
 }
 
+/// Keys are deserialized with [`Key::new`]. With the `interning` feature that's into the global key
+/// set, so that equal strings deserialize to equal keys regardless of which buffer they were read
+/// from; without it, keys compare by their strings anyway.
 impl<'a> Deserialize<'a> for Key {
     fn deserialize<D: Deserializer<'a>>(de: D) -> Result<Key, D::Error> {
         let s = Cow::<'a, str>::deserialize(de)?;
@@ -130,18 +221,27 @@ impl<'a> Deserialize<'a> for Key {
 
 impl Serialize for Key {
     fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
-        ser.serialize_str(&self)
+        ser.serialize_str(self)
     }
 }
 
 /// A context for creating interned Keys.
 ///
-/// For a set sharable across threads, see [`AtomicKeySet`].
+/// For a set sharable across threads, see [`AtomicKeySet`]. Keys interned in one set are never
+/// equal to keys interned in another; see [`Key::new_in`].
+#[cfg(feature = "interning")]
 #[derive(Clone, Default)]
-struct KeySet(WeakHashSet<Weak<str>>);
+pub struct KeySet(WeakHashSet<Weak<str>>);
 
+#[cfg(feature = "interning")]
 impl KeySet {
-    fn intern(&mut self, key: &str) -> Key {
+    #[inline]
+    pub fn new() -> Self {
+        KeySet::default()
+    }
+
+    /// Returns the key for a string, interning it if it hasn't been already.
+    pub fn intern(&mut self, key: &str) -> Key {
         self.get(key).unwrap_or_else(|| {
             let arc = Arc::from(key);
             self.0.insert(Arc::clone(&arc));
@@ -149,11 +249,13 @@ impl KeySet {
         })
     }
 
-    fn get(&self, key: &str) -> Option<Key> {
+    /// Returns the key for a string, if it has already been interned.
+    pub fn get(&self, key: &str) -> Option<Key> {
         self.0.get(key).map(Key)
     }
 }
 
+#[cfg(feature = "interning")]
 impl Debug for KeySet {
     fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
         fmt.debug_struct("KeySet").finish()
@@ -162,30 +264,43 @@ impl Debug for KeySet {
 
 /// A context, sharable across threads, for creating interned Keys.
 ///
-/// If thread safety is not required, [`KeySet`] should perform better.
+/// If thread safety is not required, [`KeySet`] should perform better. [`Key::new`] interns into a
+/// global `AtomicKeySet`; create another to intern keys in a narrower scope, such as a single
+/// request.
+#[cfg(feature = "interning")]
 #[derive(Clone, Default)]
-struct AtomicKeySet(Arc<RwLock<KeySet>>);
+pub struct AtomicKeySet(Arc<RwLock<KeySet>>);
 
+#[cfg(feature = "interning")]
 impl AtomicKeySet {
-    fn intern(&self, key: &str) -> Key {
+    #[inline]
+    pub fn new() -> Self {
+        AtomicKeySet::default()
+    }
+
+    /// Returns the key for a string, interning it if it hasn't been already.
+    pub fn intern(&self, key: &str) -> Key {
         self.get(key).unwrap_or_else(|| {
             let mut guard = self.0.write().unwrap_or_else(|x| x.into_inner());
             guard.intern(key)
         })
     }
 
-    fn get(&self, key: &str) -> Option<Key> {
+    /// Returns the key for a string, if it has already been interned.
+    pub fn get(&self, key: &str) -> Option<Key> {
         let guard = self.0.read().unwrap_or_else(|x| x.into_inner());
         guard.get(key)
     }
 }
 
+#[cfg(feature = "interning")]
 impl Debug for AtomicKeySet {
     fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
         fmt.debug_struct("AtomicKeySet").finish()
     }
 }
 
+#[cfg(feature = "interning")]
 lazy_static::lazy_static! {
     static ref GLOBAL_KEY_SET: AtomicKeySet = AtomicKeySet::default();
 }
@@ -4,8 +4,10 @@
  */
 
 pub use key::Key;
-pub use props::PropSet;
-pub use text::Text;
+#[cfg(feature = "interning")]
+pub use key::{AtomicKeySet, KeySet};
+pub use props::{DocumentProps, ParseBoolError, PropSet, PropSetIntoIter};
+pub use text::{Text, TextMut};
 
 mod key;
 mod props;
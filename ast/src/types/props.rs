@@ -6,7 +6,13 @@
 use std::borrow::Borrow;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
+use std::iter::FromIterator;
+use std::num::ParseIntError;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
 
+use indexmap::{IndexMap, IndexSet};
+use serde::ser::{SerializeStruct, Serializer};
 use serde::{Deserialize, Serialize};
 
 use super::key::Key;
@@ -16,11 +22,17 @@ use super::text::Text;
 ///
 /// `PropSet`s consist of both valued _settings_ (e.g. `foo = 'bar'`) and boolean _properties_
 /// (e.g.  `'baz'`).
-#[derive(Clone, Default, Deserialize, Eq, PartialEq, Serialize)]
+///
+/// Settings and properties each remember the order in which their keys were first inserted, and
+/// are iterated in that order. Equality does not depend on order.
+///
+/// When serialized, settings and properties are sorted by key so that the output is reproducible
+/// regardless of insertion order.
+#[derive(Clone, Default, Deserialize, Eq, PartialEq)]
 pub struct PropSet<'a> {
-    properties: HashSet<Key>,
+    properties: IndexSet<Key>,
     #[serde(borrow)]
-    settings: HashMap<Key, Text<'a>>,
+    settings: IndexMap<Key, Text<'a>>,
 }
 
 impl<'a> PropSet<'a> {
@@ -32,8 +44,8 @@ impl<'a> PropSet<'a> {
     #[inline]
     pub fn with_capacity(cap: usize) -> Self {
         PropSet {
-            properties: HashSet::with_capacity(cap),
-            settings: HashMap::with_capacity(cap),
+            properties: IndexSet::with_capacity(cap),
+            settings: IndexMap::with_capacity(cap),
         }
     }
 
@@ -48,7 +60,7 @@ impl<'a> PropSet<'a> {
     }
 
     /// Sets a keyed value, called a "setting", in the PropSet. Returns the previous value if the
-    /// key already existed.
+    /// key already existed, in which case the setting keeps its original position.
     ///
     /// ```rust
     /// # use prosidy_ast::{Key, Text, PropSet};
@@ -82,7 +94,7 @@ impl<'a> PropSet<'a> {
     /// ```
     #[inline]
     pub fn delete<K: Borrow<Key>>(&mut self, key: K) -> Option<Text<'a>> {
-        self.settings.remove(key.borrow())
+        self.settings.shift_remove(key.borrow())
     }
 
     /// Retrieves the setting associated with a key from the PropSet.
@@ -102,6 +114,82 @@ impl<'a> PropSet<'a> {
         self.settings.get(key.borrow()).cloned()
     }
 
+    /// Retrieves the setting associated with a key from the PropSet, borrowing its value rather
+    /// than cloning it like [`PropSet::lookup`].
+    /// ```rust
+    /// # use prosidy_ast::{Key, PropSet};
+    /// let mut props = PropSet::new();
+    /// props.put("lang", "en");
+    /// assert_eq!(Some("en"), props.lookup_str(Key::new("lang")));
+    /// assert_eq!(None, props.lookup_str(Key::new("title")));
+    /// ```
+    #[inline]
+    pub fn lookup_str<K: Borrow<Key>>(&self, key: K) -> Option<&str> {
+        self.settings.get(key.borrow()).map(Text::as_str)
+    }
+
+    /// Retrieves a setting from the PropSet and parses it with [`FromStr`]. Whitespace around the
+    /// value is trimmed before parsing.
+    /// ```rust
+    /// # use prosidy_ast::{Key, PropSet};
+    /// let mut props = PropSet::new();
+    /// props.put("width", " 80 ");
+    /// props.put("ratio", "wide");
+    /// assert_eq!(Some(Ok(80)), props.lookup_parsed::<u32, _>(Key::new("width")));
+    /// assert!(props.lookup_parsed::<f64, _>(Key::new("ratio")).unwrap().is_err());
+    /// assert!(props.lookup_parsed::<u32, _>(Key::new("height")).is_none());
+    /// ```
+    pub fn lookup_parsed<T, K>(&self, key: K) -> Option<Result<T, T::Err>>
+    where
+        T: FromStr,
+        K: Borrow<Key>,
+    {
+        self.settings
+            .get(key.borrow())
+            .map(|value| value.as_str().trim().parse())
+    }
+
+    /// Retrieves a setting from the PropSet as a boolean. `true`, `yes`, `on`, and `1` are truthy;
+    /// `false`, `no`, `off`, and `0` are falsy. Matching ignores case and surrounding whitespace.
+    /// A blank value is treated the same as a missing one.
+    /// ```rust
+    /// # use prosidy_ast::{Key, PropSet};
+    /// let mut props = PropSet::new();
+    /// props.put("draft", "Yes");
+    /// props.put("toc", "off");
+    /// props.put("empty", "  ");
+    /// props.put("maybe", "perhaps");
+    /// assert_eq!(Some(Ok(true)), props.lookup_bool(Key::new("draft")));
+    /// assert_eq!(Some(Ok(false)), props.lookup_bool(Key::new("toc")));
+    /// assert_eq!(None, props.lookup_bool(Key::new("empty")));
+    /// assert!(props.lookup_bool(Key::new("maybe")).unwrap().is_err());
+    /// ```
+    pub fn lookup_bool<K: Borrow<Key>>(&self, key: K) -> Option<Result<bool, ParseBoolError>> {
+        let value = self.settings.get(key.borrow())?.as_str().trim();
+        if value.is_empty() {
+            return None;
+        }
+        let lower = value.to_ascii_lowercase();
+        Some(match lower.as_str() {
+            "true" | "yes" | "on" | "1" => Ok(true),
+            "false" | "no" | "off" | "0" => Ok(false),
+            _ => Err(ParseBoolError(value.to_owned())),
+        })
+    }
+
+    /// Retrieves a setting from the PropSet as a signed integer. Whitespace around the value is
+    /// trimmed before parsing.
+    /// ```rust
+    /// # use prosidy_ast::{Key, PropSet};
+    /// let mut props = PropSet::new();
+    /// props.put("depth", "-2");
+    /// assert_eq!(Some(Ok(-2)), props.lookup_int(Key::new("depth")));
+    /// ```
+    #[inline]
+    pub fn lookup_int<K: Borrow<Key>>(&self, key: K) -> Option<Result<i64, ParseIntError>> {
+        self.lookup_parsed(key)
+    }
+
     /// Sets a propperty on the PropSet which does not take a value. The namespaces for settings
     /// and properties does not overlap.
     /// ```rust
@@ -129,7 +217,7 @@ impl<'a> PropSet<'a> {
     /// ```
     #[inline]
     pub fn unset<K: Borrow<Key>>(&mut self, key: K) -> bool {
-        self.properties.remove(key.borrow())
+        self.properties.shift_remove(key.borrow())
     }
 
     /// Checks whether or not a the PropSet contains a property.
@@ -146,8 +234,7 @@ impl<'a> PropSet<'a> {
         self.properties.contains(key.borrow())
     }
 
-    /// Iterates over every setting in the PropSet. The order of yielded settings is not
-    /// guarenteed.
+    /// Iterates over every setting in the PropSet, in the order they were inserted.
     /// ```rust
     /// # use prosidy_ast::{Key, PropSet, Text};
     /// let mut props = PropSet::new();
@@ -165,14 +252,35 @@ impl<'a> PropSet<'a> {
     ///     assert_eq!(v.len(), k.as_str().len() + "-value".len());
     /// }
     /// assert_eq!(count, 3);
+    /// props.delete(&foo);
+    /// props.put(foo.clone(), Text::from("foo-value"));
+    /// let order: Vec<&Key> = props.settings().map(|(k, _)| k).collect();
+    /// assert_eq!(order, vec![&bar, &baz, &foo]);
     /// ```
     #[inline]
     pub fn settings<'r>(&'r self) -> impl 'r + Iterator<Item = (&'r Key, Text<'a>)> {
         self.settings.iter().map(|(k, v)| (k, v.clone()))
     }
 
-    /// Iterates over every property in the PropSet. The order of yielded properties is not
-    /// guarenteed.
+    /// Iterates over every setting in the PropSet by reference, in the order they were inserted.
+    /// Unlike [`PropSet::settings`], values aren't cloned.
+    /// ```rust
+    /// # use prosidy_ast::{Key, PropSet, Text};
+    /// let mut props = PropSet::new();
+    /// props.put("foo", "1");
+    /// props.put("bar", "2");
+    /// let pairs: Vec<(&str, &str)> = props
+    ///     .settings_ref()
+    ///     .map(|(k, v)| (k.as_str(), v.as_str()))
+    ///     .collect();
+    /// assert_eq!(vec![("foo", "1"), ("bar", "2")], pairs);
+    /// ```
+    #[inline]
+    pub fn settings_ref<'r>(&'r self) -> impl 'r + Iterator<Item = (&'r Key, &'r Text<'a>)> {
+        self.settings.iter()
+    }
+
+    /// Iterates over every property in the PropSet, in the order they were inserted.
     /// ```rust
     /// # use prosidy_ast::{Key, PropSet};
     /// let mut props = PropSet::new();
@@ -182,16 +290,16 @@ impl<'a> PropSet<'a> {
     /// props.set(foo.clone());
     /// props.set(bar.clone());
     /// props.set(baz.clone());
-    /// for prop in props.properties() {
-    ///     assert!(*prop == foo || *prop == bar || *prop == baz)
-    /// }
+    /// let order: Vec<&Key> = props.properties().collect();
+    /// assert_eq!(order, vec![&foo, &bar, &baz]);
     /// ```
     #[inline]
     pub fn properties<'r>(&'r self) -> impl 'r + Iterator<Item = &'r Key> {
         self.properties.iter()
     }
 
-    /// Iterates over every property and every setting in the PropSet. No order is guarenteed.
+    /// Iterates over every property and every setting in the PropSet. Settings are yielded first,
+    /// followed by properties, each in the order they were inserted.
     /// ```rust
     /// # use prosidy_ast::{Key, PropSet, Text};
     /// let mut props = PropSet::new();
@@ -215,6 +323,223 @@ impl<'a> PropSet<'a> {
             .map(|(k, v)| (k, Some(v)))
             .chain(self.properties().map(|k| (k, None)))
     }
+
+    /// Removes every setting and property for which `f` returns `false`. As with
+    /// [`PropSet::iter`], settings are passed with `Some` value and properties with `None`.
+    /// ```rust
+    /// # use prosidy_ast::{Key, PropSet, Text};
+    /// let mut props = PropSet::new();
+    /// props.put("title", "Hello!");
+    /// props.put("_editor", "alex");
+    /// props.set("_draft");
+    /// props.set("toc");
+    /// props.retain(|key, _| !key.as_str().starts_with('_'));
+    /// assert_eq!(2, props.len());
+    /// assert_eq!(Some(Text::from("Hello!")), props.lookup(Key::new("title")));
+    /// assert!(props.is_set(Key::new("toc")));
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Key, Option<&Text<'a>>) -> bool,
+    {
+        self.settings.retain(|key, value| f(key, Some(value)));
+        self.properties.retain(|key| f(key, None));
+    }
+
+    /// Converts every borrowed setting into an owned one, detaching the PropSet from its source.
+    pub fn into_owned(self) -> PropSet<'static> {
+        PropSet {
+            properties: self.properties,
+            settings: self
+                .settings
+                .into_iter()
+                .map(|(key, value)| (key, value.into_owned()))
+                .collect(),
+        }
+    }
+
+    /// Returns the properties and settings of this set which aren't in `other`, in the order of
+    /// this set.
+    ///
+    /// A setting only counts as being in both sets if its value is the same in each, so a
+    /// setting whose value differs is part of the difference, with this set's value. A property
+    /// and a setting are never the same, even with the same key.
+    /// ```rust
+    /// # use prosidy_ast::{Key, PropSet, Text};
+    /// let mut old = PropSet::new();
+    /// old.put("title", "Draft");
+    /// old.put("lang", "en");
+    /// old.set("draft");
+    /// let mut new = PropSet::new();
+    /// new.put("title", "Final");
+    /// new.put("lang", "en");
+    /// let changed = old.difference(&new);
+    /// assert_eq!(Some(Text::from("Draft")), changed.lookup(Key::new("title")));
+    /// assert!(changed.is_set(Key::new("draft")));
+    /// assert_eq!(2, changed.len());
+    /// ```
+    pub fn difference(&self, other: &PropSet) -> PropSet<'a> {
+        self.filter_entries(|key, value| !other.has_entry(key, value))
+    }
+
+    /// Returns the properties and settings which are in both this set and `other`, in the order
+    /// of this set. As with [`PropSet::difference`], a setting is only in both if its value
+    /// matches.
+    /// ```rust
+    /// # use prosidy_ast::{Key, PropSet, Text};
+    /// let mut old = PropSet::new();
+    /// old.put("title", "Draft");
+    /// old.put("lang", "en");
+    /// let mut new = PropSet::new();
+    /// new.put("title", "Final");
+    /// new.put("lang", "en");
+    /// let same = old.intersection(&new);
+    /// assert_eq!(Some(Text::from("en")), same.lookup(Key::new("lang")));
+    /// assert_eq!(1, same.len());
+    /// ```
+    pub fn intersection(&self, other: &PropSet) -> PropSet<'a> {
+        self.filter_entries(|key, value| other.has_entry(key, value))
+    }
+
+    fn filter_entries<F>(&self, mut f: F) -> PropSet<'a>
+    where
+        F: FnMut(&Key, Option<&Text<'a>>) -> bool,
+    {
+        let mut props = self.clone();
+        props.retain(|key, value| f(key, value));
+        props
+    }
+
+    fn has_entry(&self, key: &Key, value: Option<&Text>) -> bool {
+        match value {
+            Some(value) => self.settings.get(key).map(Text::as_str) == Some(value.as_str()),
+            None => self.properties.contains(key),
+        }
+    }
+
+    /// Folds another PropSet into this one. Settings in `other` take precedence, overwriting any
+    /// setting in `self` with the same key; properties from both sets are kept.
+    /// ```rust
+    /// # use prosidy_ast::{Key, PropSet, Text};
+    /// let mut props = PropSet::new();
+    /// props.put("a", "2");
+    /// props.set("b");
+    /// let mut overlay = PropSet::new();
+    /// overlay.put("a", "1");
+    /// props.merge(overlay);
+    /// assert_eq!(Some(Text::from("1")), props.lookup(Key::new("a")));
+    /// assert!(props.is_set(Key::new("b")));
+    /// assert_eq!(2, props.len());
+    /// ```
+    pub fn merge(&mut self, other: PropSet<'a>) {
+        self.settings.extend(other.settings);
+        self.properties.extend(other.properties);
+    }
+}
+
+/// Collects a PropSet from entries shaped like those of [`PropSet::iter`]. See the [`Extend`]
+/// impl for how entries are inserted.
+/// ```rust
+/// # use prosidy_ast::{Key, PropSet, Text};
+/// let props: PropSet = vec![
+///     (Key::new("lang"), Some(Text::from("en"))),
+///     (Key::new("draft"), None),
+/// ]
+/// .into_iter()
+/// .collect();
+/// assert_eq!(Some(Text::from("en")), props.lookup(Key::new("lang")));
+/// assert!(props.is_set(Key::new("draft")));
+/// let round_trip: PropSet = props.clone().into_iter().collect();
+/// assert_eq!(props, round_trip);
+/// ```
+impl<'a> FromIterator<(Key, Option<Text<'a>>)> for PropSet<'a> {
+    fn from_iter<I: IntoIterator<Item = (Key, Option<Text<'a>>)>>(iter: I) -> Self {
+        let mut props = PropSet::new();
+        props.extend(iter);
+        props
+    }
+}
+
+impl<'a> IntoIterator for PropSet<'a> {
+    type Item = (Key, Option<Text<'a>>);
+    type IntoIter = PropSetIntoIter<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        PropSetIntoIter {
+            settings: self.settings.into_iter(),
+            properties: self.properties.into_iter(),
+        }
+    }
+}
+
+/// An owning iterator over the entries of a [`PropSet`]. Entries are yielded in the same order
+/// as [`PropSet::iter`].
+pub struct PropSetIntoIter<'a> {
+    settings: indexmap::map::IntoIter<Key, Text<'a>>,
+    properties: indexmap::set::IntoIter<Key>,
+}
+
+impl<'a> Iterator for PropSetIntoIter<'a> {
+    type Item = (Key, Option<Text<'a>>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.settings.next() {
+            Some((key, value)) => Some((key, Some(value))),
+            None => self.properties.next().map(|key| (key, None)),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.settings.len() + self.properties.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a> ExactSizeIterator for PropSetIntoIter<'a> {}
+
+/// Entries are interpreted as in [`PropSet::iter`]: `Some` values are put as settings, and
+/// `None` values are set as properties. Later settings overwrite earlier ones.
+/// ```rust
+/// # use prosidy_ast::{Key, PropSet, Text};
+/// let mut props = PropSet::new();
+/// props.extend(vec![
+///     (Key::new("a"), Some(Text::from("1"))),
+///     (Key::new("b"), None),
+/// ]);
+/// assert_eq!(Some(Text::from("1")), props.lookup(Key::new("a")));
+/// assert!(props.is_set(Key::new("b")));
+/// ```
+impl<'a> Extend<(Key, Option<Text<'a>>)> for PropSet<'a> {
+    fn extend<I: IntoIterator<Item = (Key, Option<Text<'a>>)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            match value {
+                Some(value) => {
+                    self.put(key, value);
+                }
+                None => self.set(key),
+            }
+        }
+    }
+}
+
+impl<'a> Serialize for PropSet<'a> {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        struct Settings<'r, 'a>(Vec<(&'r Key, &'r Text<'a>)>);
+        impl<'r, 'a> Serialize for Settings<'r, 'a> {
+            fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+                ser.collect_map(self.0.iter().cloned())
+            }
+        }
+        let mut properties: Vec<&Key> = self.properties.iter().collect();
+        properties.sort_unstable_by_key(|key| key.as_str());
+        let mut settings: Vec<_> = self.settings.iter().collect();
+        settings.sort_unstable_by_key(|(key, _)| key.as_str());
+        let mut state = ser.serialize_struct("PropSet", 2)?;
+        state.serialize_field("properties", &properties)?;
+        state.serialize_field("settings", &Settings(settings))?;
+        state.end()
+    }
 }
 
 impl<'a> Debug for PropSet<'a> {
@@ -237,11 +562,131 @@ impl<'a> Debug for PropSet<'a> {
     }
 }
 
+/// The error returned by [`PropSet::lookup_bool`] when a setting is not a recognized boolean.
+#[derive(Clone, Debug, Display, Eq, PartialEq)]
+#[display(fmt = "invalid boolean value {:?}", _0)]
+pub struct ParseBoolError(String);
+
+impl std::error::Error for ParseBoolError {}
+
+/// The [`PropSet`] declared in the header of a document.
+///
+/// Header properties are written with a different syntax than tag properties (values are unquoted
+/// and run until the end of the line), and carry document-level metadata such as the `title`. The
+/// separate type keeps a header's properties from being confused with a tag's by accident; it
+/// doesn't check that the contents were actually written as a header.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct DocumentProps<'a>(#[serde(borrow)] PropSet<'a>);
+
+impl<'a> DocumentProps<'a> {
+    const TITLE: &'static str = "title";
+
+    #[inline]
+    pub fn new() -> Self {
+        DocumentProps::default()
+    }
+
+    /// Treats a property set as a document's header, as [`Document::new`](crate::Document::new)
+    /// does for a document built by hand. The properties are taken as-is.
+    #[inline]
+    pub(crate) fn from_props(props: PropSet<'a>) -> Self {
+        DocumentProps(props)
+    }
+
+    /// Returns the document's title, if the header declares one.
+    /// ```rust
+    /// # use prosidy_ast::{DocumentProps, Text};
+    /// let mut header = DocumentProps::new();
+    /// header.put("title", "Hello!");
+    /// assert_eq!(Some(Text::from("Hello!")), header.title());
+    /// assert_eq!(None, DocumentProps::new().title());
+    /// ```
+    #[inline]
+    pub fn title(&self) -> Option<Text<'a>> {
+        self.0.lookup(Key::new(DocumentProps::TITLE))
+    }
+
+    /// Sets the document's title, returning the previous one.
+    /// ```rust
+    /// # use prosidy_ast::{DocumentProps, Text};
+    /// let mut header = DocumentProps::new();
+    /// assert_eq!(None, header.set_title("Hello!"));
+    /// assert_eq!(Some(Text::from("Hello!")), header.set_title("Goodbye!"));
+    /// assert_eq!(Some(Text::from("Goodbye!")), header.title());
+    /// ```
+    #[inline]
+    pub fn set_title<V: Into<Text<'a>>>(&mut self, title: V) -> Option<Text<'a>> {
+        self.0.put(Key::new(DocumentProps::TITLE), title)
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> PropSet<'a> {
+        self.0
+    }
+
+    /// See [`PropSet::into_owned`].
+    #[inline]
+    pub fn into_owned(self) -> DocumentProps<'static> {
+        DocumentProps(self.0.into_owned())
+    }
+}
+
+impl<'a> Deref for DocumentProps<'a> {
+    type Target = PropSet<'a>;
+
+    #[inline]
+    fn deref(&self) -> &PropSet<'a> {
+        &self.0
+    }
+}
+
+impl<'a> DerefMut for DocumentProps<'a> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut PropSet<'a> {
+        &mut self.0
+    }
+}
+
 impl<'a> From<(HashSet<Key>, HashMap<Key, Text<'a>>)> for PropSet<'a> {
     fn from(pair: (HashSet<Key>, HashMap<Key, Text<'a>>)) -> Self {
         PropSet {
-            properties: pair.0,
-            settings: pair.1,
+            properties: pair.0.into_iter().collect(),
+            settings: pair.1.into_iter().collect(),
         }
     }
 }
+
+#[test]
+fn test_difference_and_intersection() {
+    let props = |settings: &[(&str, &'static str)], properties: &[&str]| {
+        let mut props = PropSet::new();
+        for (key, value) in settings {
+            props.put(*key, *value);
+        }
+        for key in properties {
+            props.set(*key);
+        }
+        props
+    };
+    // Properties only.
+    let left = props(&[], &["a", "b"]);
+    let right = props(&[], &["b", "c"]);
+    assert_eq!(props(&[], &["a"]), left.difference(&right));
+    assert_eq!(props(&[], &["b"]), left.intersection(&right));
+    // Settings only: `y` differs, so it's only in the difference.
+    let left = props(&[("x", "1"), ("y", "2")], &[]);
+    let right = props(&[("x", "1"), ("y", "3")], &[]);
+    assert_eq!(props(&[("y", "2")], &[]), left.difference(&right));
+    assert_eq!(props(&[("x", "1")], &[]), left.intersection(&right));
+    // Mixed: a property never matches a setting with the same key.
+    let left = props(&[("k", "v"), ("both", "1")], &["flag", "both"]);
+    let right = props(&[("flag", "on"), ("both", "1")], &["k"]);
+    assert_eq!(
+        props(&[("k", "v")], &["flag", "both"]),
+        left.difference(&right)
+    );
+    assert_eq!(props(&[("both", "1")], &[]), left.intersection(&right));
+    assert!(left.difference(&left).is_empty());
+    assert_eq!(left, left.intersection(&left));
+}
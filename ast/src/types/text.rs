@@ -6,7 +6,8 @@
 use std::borrow::{Borrow, Cow};
 use std::fmt::{self, Debug, Display, Formatter};
 use std::iter::FromIterator;
-use std::ops::Deref;
+use std::mem;
+use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
 
 use serde::de::{Deserialize, Deserializer, Error as DeError, Visitor};
@@ -38,11 +39,140 @@ impl<'a> Text<'a> {
         }
     }
 
+    /// Gets a mutable buffer over the text's contents, similar to [`Cow::to_mut`].
+    ///
+    /// The owned variant is backed by an `Arc<str>`, which can't change length even when it's
+    /// unique, so the contents are always copied into a `String` for editing, and written back
+    /// into a fresh `Text::Owned` when the returned [`TextMut`] is dropped. Other clones sharing
+    /// the original `Arc` are unaffected. Batch edits through a single `TextMut` to avoid paying
+    /// for the copy more than once, and prefer [`Text::make_mut`] for edits which keep the length
+    /// the same.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::Text;
+    /// let original = Text::from("Hello, World");
+    /// let mut text = original.clone();
+    /// text.to_mut().make_ascii_lowercase();
+    /// text.to_mut().push('!');
+    /// assert_eq!(text, Text::from("hello, world!"));
+    /// assert!(text.owned());
+    /// assert_eq!(original, Text::from("Hello, World"));
+    /// ```
     #[inline]
-    pub fn as_str(&self) -> &'a str {
+    pub fn to_mut(&mut self) -> TextMut<'_, 'a> {
+        let buf = String::from(self.as_str());
+        TextMut { text: self, buf }
+    }
+
+    /// Gets a mutable reference to the text's contents, for edits which keep its length.
+    ///
+    /// Like [`Arc::make_mut`], owned text whose `Arc` isn't shared with any clone is edited in
+    /// place, through [`Arc::get_mut`]. Borrowed or shared text is first copied into a new `Arc`,
+    /// leaving other clones unaffected.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::Text;
+    /// let original = Text::from("Hello");
+    /// let mut text = original.clone();
+    /// text.make_mut().make_ascii_uppercase();
+    /// assert_eq!(text, Text::from("HELLO"));
+    /// assert_eq!(original, Text::from("Hello"));
+    ///
+    /// let mut unique = Text::from(String::from("hi"));
+    /// let before = unique.as_str().as_ptr();
+    /// unique.make_mut().make_ascii_uppercase();
+    /// assert_eq!(before, unique.as_str().as_ptr());
+    /// ```
+    pub fn make_mut(&mut self) -> &mut str {
+        let unique = match self {
+            Text::Owned(arc) => Arc::get_mut(arc).is_some(),
+            Text::Borrowed(_) => false,
+        };
+        if !unique {
+            *self = Text::Owned(Arc::from(self.as_str()));
+        }
+        match self {
+            Text::Owned(arc) => Arc::get_mut(arc).expect("the Arc was just made unique"),
+            Text::Borrowed(_) => unreachable!("borrowed text was just made owned"),
+        }
+    }
+
+    /// Returns the text with leading and trailing whitespace removed.
+    ///
+    /// Borrowed text stays borrowed, pointing into the same source. Owned text shares its
+    /// existing `Arc` when there is nothing to trim, and otherwise copies the trimmed slice into a
+    /// new one.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::Text;
+    /// let trimmed = Text::from("  hi  ").trim();
+    /// assert_eq!(trimmed, Text::from("hi"));
+    /// assert!(trimmed.borrowed());
+    /// assert!(Text::from(String::from(" hi ")).trim().owned());
+    /// ```
+    #[inline]
+    pub fn trim(&self) -> Text<'a> {
+        self.slice(str::trim)
+    }
+
+    /// Returns the text with leading whitespace removed. See [`Text::trim`] for how the result
+    /// is backed.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::Text;
+    /// assert_eq!(Text::from("  hi  ").trim_start(), Text::from("hi  "));
+    /// ```
+    #[inline]
+    pub fn trim_start(&self) -> Text<'a> {
+        self.slice(str::trim_start)
+    }
+
+    /// Returns the text with trailing whitespace removed. See [`Text::trim`] for how the result
+    /// is backed.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::Text;
+    /// assert_eq!(Text::from("  hi  ").trim_end(), Text::from("  hi"));
+    /// ```
+    #[inline]
+    pub fn trim_end(&self) -> Text<'a> {
+        self.slice(str::trim_end)
+    }
+
+    fn slice<F: FnOnce(&str) -> &str>(&self, f: F) -> Text<'a> {
+        match *self {
+            Text::Borrowed(s) => Text::Borrowed(f(s)),
+            Text::Owned(ref arc) => {
+                let sliced = f(arc);
+                if sliced.len() == arc.len() {
+                    Text::Owned(arc.clone())
+                } else {
+                    Text::Owned(Arc::from(sliced))
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
         match *self {
             Text::Borrowed(s) => s,
-            Text::Owned(ref s) => unsafe { &*(s.deref() as *const str) },
+            Text::Owned(ref s) => s,
+        }
+    }
+
+    /// Returns the text for the full lifetime `'a` if it's borrowed, or `None` if it's owned.
+    ///
+    /// ```rust
+    /// # use prosidy_ast::Text;
+    /// assert_eq!(Some("hi"), Text::from("hi").as_borrowed());
+    /// assert_eq!(None, Text::from(String::from("hi")).as_borrowed());
+    /// ```
+    #[inline]
+    pub fn as_borrowed(&self) -> Option<&'a str> {
+        match *self {
+            Text::Borrowed(s) => Some(s),
+            Text::Owned(_) => None,
         }
     }
 
@@ -204,6 +334,35 @@ impl<'a, 'b> PartialEq<Text<'b>> for Text<'a> {
 
 impl<'a> Serialize for Text<'a> {
     fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
-        ser.serialize_str(&self)
+        ser.serialize_str(self)
+    }
+}
+
+/// A mutable view over the contents of a [`Text`], returned by [`Text::to_mut`]. Changes are
+/// written back to the `Text` when this is dropped.
+pub struct TextMut<'t, 'a> {
+    text: &'t mut Text<'a>,
+    buf: String,
+}
+
+impl<'t, 'a> Deref for TextMut<'t, 'a> {
+    type Target = String;
+
+    #[inline]
+    fn deref(&self) -> &String {
+        &self.buf
+    }
+}
+
+impl<'t, 'a> DerefMut for TextMut<'t, 'a> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut String {
+        &mut self.buf
+    }
+}
+
+impl<'t, 'a> Drop for TextMut<'t, 'a> {
+    fn drop(&mut self) {
+        *self.text = Text::Owned(mem::take(&mut self.buf).into());
     }
 }
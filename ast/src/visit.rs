@@ -0,0 +1,134 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use crate::block::Block;
+use crate::document::Document;
+use crate::inline::Inline;
+use crate::tag::Tag;
+
+/// Rewrites a document in place.
+///
+/// Every method defaults to recursing into the node's children via the matching `walk_*`
+/// function, so implementors only override the cases they care about. An override that should
+/// still reach nested nodes must call the `walk_*` function itself.
+///
+/// ```rust
+/// # use prosidy_ast::{Block, BlockTag, Document, Inline, InlineTag, Key, PropSet, Tag, Text};
+/// # use prosidy_ast::visit::{walk_tag_mut, TagContent, VisitMut};
+/// struct RenameEm(Key, Key);
+///
+/// impl<'a> VisitMut<'a> for RenameEm {
+///     fn visit_tag_mut<T: TagContent<'a>>(&mut self, tag: &mut Tag<'a, T>) {
+///         if tag.name_is(&self.0) {
+///             tag.set_name(self.1.clone());
+///         }
+///         walk_tag_mut(self, tag);
+///     }
+/// }
+///
+/// let em = InlineTag::new("em", PropSet::new(), vec![Inline::from(Text::from("hi"))]);
+/// let strong = InlineTag::new("strong", PropSet::new(), vec![em.into()]);
+/// let para = Block::Content(vec![Text::from("say ").into(), strong.into()]);
+/// let section = BlockTag::new("section", PropSet::new(), vec![para]);
+/// let mut doc = Document::new(PropSet::new(), vec![section.into()]);
+///
+/// RenameEm(Key::new("em"), Key::new("i")).visit_document_mut(&mut doc);
+///
+/// let i = InlineTag::new("i", PropSet::new(), vec![Inline::from(Text::from("hi"))]);
+/// let strong = InlineTag::new("strong", PropSet::new(), vec![i.into()]);
+/// let para = Block::Content(vec![Text::from("say ").into(), strong.into()]);
+/// let section = BlockTag::new("section", PropSet::new(), vec![para]);
+/// assert_eq!(doc, Document::new(PropSet::new(), vec![section.into()]));
+/// ```
+pub trait VisitMut<'a> {
+    fn visit_document_mut(&mut self, doc: &mut Document<'a>) {
+        walk_document_mut(self, doc)
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Block<'a>) {
+        walk_block_mut(self, block)
+    }
+
+    fn visit_inline_mut(&mut self, inline: &mut Inline<'a>) {
+        walk_inline_mut(self, inline)
+    }
+
+    /// Called for both block and inline tags.
+    fn visit_tag_mut<T: TagContent<'a>>(&mut self, tag: &mut Tag<'a, T>) {
+        walk_tag_mut(self, tag)
+    }
+}
+
+/// The types which may appear in the content of a [`Tag`]: [`Block`] and [`Inline`].
+pub trait TagContent<'a>: Sized {
+    /// Passes `self` to the visitor method for its type.
+    fn accept_mut<V: VisitMut<'a> + ?Sized>(&mut self, visitor: &mut V);
+
+    /// Returns the nested tag, if this is one.
+    fn as_tag(&self) -> Option<&Tag<'a, Self>>;
+}
+
+impl<'a> TagContent<'a> for Block<'a> {
+    #[inline]
+    fn accept_mut<V: VisitMut<'a> + ?Sized>(&mut self, visitor: &mut V) {
+        visitor.visit_block_mut(self)
+    }
+
+    #[inline]
+    fn as_tag(&self) -> Option<&Tag<'a, Self>> {
+        Block::as_tag(self)
+    }
+}
+
+impl<'a> TagContent<'a> for Inline<'a> {
+    #[inline]
+    fn accept_mut<V: VisitMut<'a> + ?Sized>(&mut self, visitor: &mut V) {
+        visitor.visit_inline_mut(self)
+    }
+
+    #[inline]
+    fn as_tag(&self) -> Option<&Tag<'a, Self>> {
+        Inline::as_tag(self)
+    }
+}
+
+/// Visits each of the document's top-level blocks.
+pub fn walk_document_mut<'a, V: VisitMut<'a> + ?Sized>(visitor: &mut V, doc: &mut Document<'a>) {
+    for block in doc.content_mut() {
+        visitor.visit_block_mut(block);
+    }
+}
+
+/// Visits the inline content or tag within a block. Literals have no children.
+pub fn walk_block_mut<'a, V: VisitMut<'a> + ?Sized>(visitor: &mut V, block: &mut Block<'a>) {
+    match block {
+        Block::Content(content) => {
+            for inline in content {
+                visitor.visit_inline_mut(inline);
+            }
+        }
+        Block::Tag(tag) => visitor.visit_tag_mut(tag),
+        Block::Literal(_) => {}
+    }
+}
+
+/// Visits the tag within an inline, if there is one.
+pub fn walk_inline_mut<'a, V: VisitMut<'a> + ?Sized>(visitor: &mut V, inline: &mut Inline<'a>) {
+    match inline {
+        Inline::Tag(tag) => visitor.visit_tag_mut(tag),
+        Inline::SoftBreak | Inline::Text(_) => {}
+    }
+}
+
+/// Visits each child in the tag's content.
+pub fn walk_tag_mut<'a, V, T>(visitor: &mut V, tag: &mut Tag<'a, T>)
+where
+    V: VisitMut<'a> + ?Sized,
+    T: TagContent<'a>,
+{
+    for child in tag.content_mut() {
+        child.accept_mut(visitor);
+    }
+}
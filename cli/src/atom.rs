@@ -0,0 +1,203 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::io::Write;
+use std::path::Path;
+use std::time::SystemTime;
+
+use anyhow::Result;
+use prosidy::xml::quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use prosidy::xml::quick_xml::{Result as XMLResult, Writer};
+use prosidy::xml::{XMLConfig, XML};
+use prosidy::Key;
+
+use crate::manifest::Manifest;
+use crate::timestamp::{format_rfc3339, parse_rfc3339};
+
+/// An Atom feed, built from the headers in a [`Manifest`].
+///
+/// Every document with a `title`, `date`, and `author` setting becomes an entry, newest first,
+/// linked to its page as [`page_url`] describes.
+/// The feed takes its own title from `index.pro`, falling back to the base URL.
+#[derive(Debug)]
+pub struct Feed {
+    id: String,
+    title: String,
+    updated: String,
+    entries: Vec<FeedEntry>,
+}
+
+#[derive(Debug)]
+struct FeedEntry {
+    url: String,
+    title: String,
+    author: String,
+    updated: String,
+    timestamp: (i64, u32),
+}
+
+impl Feed {
+    const ATOM_URI: &'static str = "http://www.w3.org/2005/Atom";
+    const INDEX: &'static str = "index.pro";
+
+    /// Builds a feed from a manifest, linking each entry to its page under `base_url`.
+    pub fn new(manifest: &Manifest, base_url: &str) -> Result<Feed> {
+        let base_url = base_url.trim_end_matches('/');
+        let (title, author, date) = (Key::new("title"), Key::new("author"), Key::new("date"));
+        let mut entries = Vec::new();
+        let mut feed_title = None;
+        for (path, entry) in manifest.iter() {
+            let props = entry.props();
+            if path == Path::new(Feed::INDEX) {
+                feed_title = props.title().map(|title| title.as_str().to_string());
+            }
+            let (title, author, date) = match (
+                props.lookup(&title),
+                props.lookup(&author),
+                props.lookup(&date),
+            ) {
+                (Some(title), Some(author), Some(date)) => (title, author, date),
+                _ => {
+                    log::debug!("leaving {:?} out of the feed", path);
+                    continue;
+                }
+            };
+            let date = date.trim();
+            let timestamp = parse_rfc3339(date.as_str()).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{:?} has a malformed date {:?}; expected an RFC 3339 timestamp such as \
+                     2020-01-31T09:30:00Z",
+                    path,
+                    date.as_str(),
+                )
+            })?;
+            entries.push(FeedEntry {
+                url: page_url(base_url, path),
+                title: title.as_str().to_string(),
+                author: author.as_str().to_string(),
+                updated: date.as_str().to_string(),
+                timestamp,
+            });
+        }
+        entries.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then(a.url.cmp(&b.url)));
+        let updated = match entries.first() {
+            Some(entry) => entry.updated.clone(),
+            None => format_rfc3339(SystemTime::now()),
+        };
+        Ok(Feed {
+            id: base_url.to_string(),
+            title: feed_title.unwrap_or_else(|| base_url.to_string()),
+            updated,
+            entries,
+        })
+    }
+}
+
+impl XML for Feed {
+    fn to_events<F>(&self, _config: &XMLConfig, emit: &mut F) -> XMLResult<()>
+    where
+        F: for<'a> FnMut(Event<'a>) -> XMLResult<()>,
+    {
+        let mut start = BytesStart::borrowed_name(b"feed");
+        start.push_attribute(("xmlns", Feed::ATOM_URI));
+        emit(Event::Start(start))?;
+        text_element(emit, "id", &self.id)?;
+        text_element(emit, "title", &self.title)?;
+        text_element(emit, "updated", &self.updated)?;
+        link_element(emit, &self.id)?;
+        for entry in self.entries.iter() {
+            emit(Event::Start(BytesStart::borrowed_name(b"entry")))?;
+            text_element(emit, "id", &entry.url)?;
+            text_element(emit, "title", &entry.title)?;
+            text_element(emit, "updated", &entry.updated)?;
+            emit(Event::Start(BytesStart::borrowed_name(b"author")))?;
+            text_element(emit, "name", &entry.author)?;
+            emit(Event::End(BytesEnd::borrowed(b"author")))?;
+            link_element(emit, &entry.url)?;
+            emit(Event::End(BytesEnd::borrowed(b"entry")))?;
+        }
+        emit(Event::End(BytesEnd::borrowed(b"feed")))
+    }
+}
+
+/// Writes a feed as an indented Atom document.
+pub fn write_feed<W: Write>(writer: W, feed: &Feed) -> Result<()> {
+    let mut writer = Writer::new_with_indent(writer, b' ', 2);
+    let decl = BytesDecl::new(b"1.0", Some(b"UTF-8"), None);
+    writer.write_event(Event::Decl(decl))?;
+    feed.to_events(&XMLConfig::default(), &mut |event| {
+        writer.write_event(event).map(|_| ())
+    })?;
+    writer.into_inner().write_all(b"\n")?;
+    Ok(())
+}
+
+/// The URL of the page published from the document at `path`, which is relative to the manifest's
+/// directory: the same path under `base_url` (which has no trailing slash), with its extension
+/// rewritten to `.html`.
+pub(crate) fn page_url(base_url: &str, path: &Path) -> String {
+    let page = path.with_extension("html");
+    let components: Vec<_> = page
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect();
+    format!("{}/{}", base_url, components.join("/"))
+}
+
+pub(crate) fn text_element<F>(emit: &mut F, name: &str, text: &str) -> XMLResult<()>
+where
+    F: for<'a> FnMut(Event<'a>) -> XMLResult<()>,
+{
+    emit(Event::Start(BytesStart::borrowed_name(name.as_bytes())))?;
+    emit(Event::Text(BytesText::from_plain_str(text)))?;
+    emit(Event::End(BytesEnd::borrowed(name.as_bytes())))
+}
+
+fn link_element<F>(emit: &mut F, href: &str) -> XMLResult<()>
+where
+    F: for<'a> FnMut(Event<'a>) -> XMLResult<()>,
+{
+    let mut link = BytesStart::borrowed_name(b"link");
+    link.push_attribute(("href", href));
+    emit(Event::Empty(link))
+}
+
+#[test]
+fn feed_from_manifest() {
+    let root = std::env::temp_dir().join(format!("prosidy-atom-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let write = |name: &str, header: &str| std::fs::write(root.join(name), header).unwrap();
+    write("index.pro", "title: Posts & Notes\n---\n");
+    write(
+        "old.pro",
+        "title: Old\nauthor: A\ndate: 2019-05-01T00:00:00Z\n---\n",
+    );
+    write(
+        "new.pro",
+        "title: New\nauthor: B\ndate: 2020-01-01T12:00:00+02:00\n---\n",
+    );
+    write("undated.pro", "title: Undated\nauthor: C\n---\n");
+    let manifest = Manifest::read(&root, false, false, &[]).unwrap();
+    let feed = Feed::new(&manifest, "https://example.com/blog/");
+    write("bad.pro", "title: Bad\nauthor: D\ndate: yesterday\n---\n");
+    let bad_manifest = Manifest::read(&root, false, false, &[]).unwrap();
+    let bad_feed = Feed::new(&bad_manifest, "https://example.com/blog/");
+    std::fs::remove_dir_all(&root).unwrap();
+
+    let mut out = Vec::new();
+    write_feed(&mut out, &feed.unwrap()).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("<title>Posts &amp; Notes</title>"));
+    assert!(out.contains("<updated>2020-01-01T12:00:00+02:00</updated>"));
+    assert!(out.contains("<link href=\"https://example.com/blog/new.html\"/>"));
+    assert!(!out.contains("Undated"));
+    let (new, old) = (
+        out.find("<title>New").unwrap(),
+        out.find("<title>Old").unwrap(),
+    );
+    assert!(new < old);
+    let err = bad_feed.unwrap_err().to_string();
+    assert!(err.contains("\"bad.pro\" has a malformed date \"yesterday\""));
+}
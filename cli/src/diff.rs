@@ -0,0 +1,419 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter, Write};
+
+use prosidy::xml::quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+use prosidy::xml::{quick_xml::Result as XMLResult, XMLConfig, XML};
+use prosidy::{Block, Document, Inline, Key, Node, PropSet};
+use serde::Serialize;
+
+/// The structural differences between two documents.
+///
+/// Each change is found at a path like `content[2].tag(em).props.ru`: `content[i]` is the `i`th
+/// child of a node, `tag(name)` names the tag found there, and `props.key` is one of its
+/// properties. Indices count children in the old document, except for added nodes, which count
+/// them in the new one.
+#[derive(Debug, Default, Serialize)]
+pub struct Diff {
+    changes: Vec<Change>,
+}
+
+#[derive(Debug, Eq, PartialEq, Serialize)]
+struct Change {
+    path: String,
+    kind: ChangeKind,
+    /// The node's source, or the property's value. Properties without a value have neither.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl ChangeKind {
+    fn name(self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Changed => "changed",
+        }
+    }
+}
+
+impl Diff {
+    const TAG_DIFF: &'static str = "diff";
+    const TAG_CHANGE: &'static str = "change";
+    const TAG_OLD: &'static str = "old";
+    const TAG_NEW: &'static str = "new";
+    const ATTR_KIND: &'static str = "kind";
+    const ATTR_PATH: &'static str = "path";
+
+    /// Compares two documents: their headers, and then their content.
+    ///
+    /// Children are matched up by the longest run of them left unchanged. Between those, children
+    /// of the same kind, or tags of the same name, are compared with each other; any left over
+    /// were removed or added.
+    pub fn new(old: &Document, new: &Document) -> Self {
+        let mut diff = Diff::default();
+        diff.props("", old.props(), new.props());
+        diff.children("", Node::from(old), Node::from(new));
+        diff
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    fn props(&mut self, base: &str, old: &PropSet, new: &PropSet) {
+        let keys: BTreeSet<&str> = old
+            .properties()
+            .chain(new.properties())
+            .chain(old.settings_ref().map(|(key, _)| key))
+            .chain(new.settings_ref().map(|(key, _)| key))
+            .map(Key::as_str)
+            .collect();
+        for key in keys {
+            let key = Key::new(key);
+            let path = join(base, &format!("props.{}", key));
+            match (prop(old, &key), prop(new, &key)) {
+                (None, Some(new)) => self.push(path, ChangeKind::Added, None, new),
+                (Some(old), None) => self.push(path, ChangeKind::Removed, old, None),
+                (Some(old), Some(new)) if old != new => {
+                    self.push(path, ChangeKind::Changed, old, new)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn children(&mut self, base: &str, old: Node, new: Node) {
+        let (old, new) = (children(old), children(new));
+        let mut unchanged = lcs(&old, &new, same);
+        unchanged.push((old.len(), new.len()));
+        let (mut i, mut j) = (0, 0);
+        for (next_i, next_j) in unchanged {
+            let (gap_old, gap_new) = (&old[i..next_i], &new[j..next_j]);
+            let mut paired = lcs(gap_old, gap_new, |a, b| shape(*a) == shape(*b));
+            paired.push((gap_old.len(), gap_new.len()));
+            let (mut a, mut b) = (0, 0);
+            for (next_a, next_b) in paired {
+                for (k, node) in gap_old[a..next_a].iter().enumerate() {
+                    let path = child_path(base, i + a + k, *node);
+                    self.push(path, ChangeKind::Removed, Some(source(*node)), None);
+                }
+                for (k, node) in gap_new[b..next_b].iter().enumerate() {
+                    let path = child_path(base, j + b + k, *node);
+                    self.push(path, ChangeKind::Added, None, Some(source(*node)));
+                }
+                if next_a < gap_old.len() {
+                    let (old_node, new_node) = (gap_old[next_a], gap_new[next_b]);
+                    let path = child_path(base, i + next_a, old_node);
+                    self.node(&path, old_node, new_node);
+                }
+                a = next_a + 1;
+                b = next_b + 1;
+            }
+            i = next_i + 1;
+            j = next_j + 1;
+        }
+    }
+
+    /// Compares two nodes of the same shape, which are known to differ.
+    fn node(&mut self, path: &str, old: Node, new: Node) {
+        let props = match (old, new) {
+            (Node::Block(Block::Tag(old)), Node::Block(Block::Tag(new))) => {
+                Some((old.props(), new.props()))
+            }
+            (Node::Inline(Inline::Tag(old)), Node::Inline(Inline::Tag(new))) => {
+                Some((old.props(), new.props()))
+            }
+            (Node::Block(Block::Content(_)), Node::Block(Block::Content(_))) => None,
+            _ => {
+                let (old, new) = (Some(source(old)), Some(source(new)));
+                return self.push(path.to_string(), ChangeKind::Changed, old, new);
+            }
+        };
+        if let Some((old, new)) = props {
+            self.props(path, old, new);
+        }
+        self.children(path, old, new);
+    }
+
+    fn push(&mut self, path: String, kind: ChangeKind, old: Option<String>, new: Option<String>) {
+        self.changes.push(Change {
+            path,
+            kind,
+            old,
+            new,
+        });
+    }
+}
+
+/// Whether a property is present, and if so, its value.
+fn prop(props: &PropSet, key: &Key) -> Option<Option<String>> {
+    match props.lookup(key) {
+        Some(value) => Some(Some(value.as_str().to_string())),
+        None if props.is_set(key) => Some(None),
+        None => None,
+    }
+}
+
+fn children<'r, 'a>(node: Node<'r, 'a>) -> Vec<Node<'r, 'a>> {
+    let mut children = Vec::new();
+    node.push_children(|child| children.push(child));
+    children.reverse();
+    children
+}
+
+fn same(old: &Node, new: &Node) -> bool {
+    match (*old, *new) {
+        (Node::Block(old), Node::Block(new)) => old == new,
+        (Node::Inline(old), Node::Inline(new)) => old == new,
+        _ => false,
+    }
+}
+
+/// What a node is, ignoring its content: only nodes of the same shape are compared in detail.
+#[derive(Eq, PartialEq)]
+enum Shape<'r> {
+    Paragraph,
+    Literal,
+    Text,
+    SoftBreak,
+    Tag(&'r Key),
+    Other,
+}
+
+fn shape<'r>(node: Node<'r, '_>) -> Shape<'r> {
+    match node {
+        Node::Block(Block::Content(_)) => Shape::Paragraph,
+        Node::Block(Block::Literal(_)) => Shape::Literal,
+        Node::Block(Block::Tag(tag)) => Shape::Tag(tag.name()),
+        Node::Inline(Inline::Text(_)) => Shape::Text,
+        Node::Inline(Inline::SoftBreak) => Shape::SoftBreak,
+        Node::Inline(Inline::Tag(tag)) => Shape::Tag(tag.name()),
+        Node::Document(_) => Shape::Other,
+    }
+}
+
+fn child_path(base: &str, index: usize, node: Node) -> String {
+    let mut path = join(base, &format!("content[{}]", index));
+    match node {
+        Node::Block(Block::Tag(tag)) => write!(path, ".tag({})", tag.name()),
+        Node::Inline(Inline::Tag(tag)) => write!(path, ".tag({})", tag.name()),
+        _ => Ok(()),
+    }
+    .expect("writing to a string can't fail");
+    path
+}
+
+fn join(base: &str, segment: &str) -> String {
+    if base.is_empty() {
+        segment.to_string()
+    } else {
+        format!("{}.{}", base, segment)
+    }
+}
+
+/// A node as Prosidy source, or its debug form if it can't be printed.
+fn source(node: Node) -> String {
+    let mut source = String::new();
+    let printed = match node {
+        Node::Block(block) => write!(source, "{}", block),
+        Node::Inline(Inline::Text(text)) => return text.as_str().to_string(),
+        Node::Inline(inline) => write!(source, "{}", inline),
+        Node::Document(doc) => write!(source, "{}", doc),
+    };
+    match printed {
+        Ok(()) => source.trim_end_matches(['\n', '\r']).to_string(),
+        Err(_) => format!("{:?}", node),
+    }
+}
+
+/// The indices of a longest common subsequence of `old` and `new`, as pairs in increasing order.
+fn lcs<T, F>(old: &[T], new: &[T], eq: F) -> Vec<(usize, usize)>
+where
+    F: Fn(&T, &T) -> bool,
+{
+    let (n, m) = (old.len(), new.len());
+    let mut table = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            table[i][j] = if eq(&old[i], &new[j]) {
+                table[i + 1][j + 1] + 1
+            } else {
+                table[i + 1][j].max(table[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut pairs = Vec::with_capacity(table[0][0]);
+    while i < n && j < m {
+        if eq(&old[i], &new[j]) {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if table[i + 1][j] >= table[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// One line per change: `+` for additions, `-` for removals, and `~` for changes.
+impl Display for Diff {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        for change in self.changes.iter() {
+            let sign = match change.kind {
+                ChangeKind::Added => '+',
+                ChangeKind::Removed => '-',
+                ChangeKind::Changed => '~',
+            };
+            write!(fmt, "{} {}", sign, change.path)?;
+            match (&change.old, &change.new) {
+                (Some(old), Some(new)) => write!(fmt, ": {:?} -> {:?}", old, new)?,
+                (Some(value), None) | (None, Some(value)) => write!(fmt, ": {:?}", value)?,
+                (None, None) => {}
+            }
+            writeln!(fmt)?;
+        }
+        Ok(())
+    }
+}
+
+impl XML for Diff {
+    fn to_events<F>(&self, config: &XMLConfig, emit: &mut F) -> XMLResult<()>
+    where
+        F: for<'a> FnMut(Event<'a>) -> XMLResult<()>,
+    {
+        let diff = config.name(Diff::TAG_DIFF);
+        let change_tag = config.name(Diff::TAG_CHANGE);
+        let (old_tag, new_tag) = (config.name(Diff::TAG_OLD), config.name(Diff::TAG_NEW));
+        let (attr_kind, attr_path) = (config.name(Diff::ATTR_KIND), config.name(Diff::ATTR_PATH));
+        emit(Event::Start(BytesStart::borrowed_name(diff.as_bytes())))?;
+        for change in self.changes.iter() {
+            let mut start = BytesStart::borrowed_name(change_tag.as_bytes());
+            start.push_attribute((attr_kind.as_str(), change.kind.name()));
+            start.push_attribute((attr_path.as_str(), change.path.as_str()));
+            emit(Event::Start(start))?;
+            for (tag, value) in [(&old_tag, &change.old), (&new_tag, &change.new)].iter() {
+                if let Some(value) = value {
+                    emit(Event::Start(BytesStart::borrowed_name(tag.as_bytes())))?;
+                    emit(Event::Text(BytesText::from_plain_str(value)))?;
+                    emit(Event::End(BytesEnd::borrowed(tag.as_bytes())))?;
+                }
+            }
+            emit(Event::End(BytesEnd::borrowed(change_tag.as_bytes())))?;
+        }
+        emit(Event::End(BytesEnd::borrowed(diff.as_bytes())))
+    }
+}
+
+#[cfg(test)]
+fn diff(old: &str, new: &str) -> Vec<(String, ChangeKind, Option<String>, Option<String>)> {
+    let old = prosidy::parse::parse_document(old).unwrap();
+    let new = prosidy::parse::parse_document(new).unwrap();
+    let diff = Diff::new(&old, &new);
+    let changes = diff.changes.into_iter();
+    changes
+        .map(|change| (change.path, change.kind, change.old, change.new))
+        .collect()
+}
+
+#[test]
+fn diff_insertion() {
+    let old = "---\nOne.\n\nThree.\n";
+    let new = "---\nOne.\n\n#-note:\nTwo.\n#:\n\nThree.\n";
+    assert_eq!(
+        vec![(
+            "content[1].tag(note)".to_string(),
+            ChangeKind::Added,
+            None,
+            Some("#-note{Two.}".to_string())
+        )],
+        diff(old, new)
+    );
+    assert_eq!(
+        vec![(
+            "content[0].content[2].tag(b)".to_string(),
+            ChangeKind::Added,
+            None,
+            Some("#b".to_string())
+        )],
+        diff("---\nOne #em{two}\n", "---\nOne #em{two}#b\n")
+    );
+}
+
+#[test]
+fn diff_deletion() {
+    let old = "title: Old\ndraft\n---\nOne.\n\nTwo.\n\nThree.\n";
+    let new = "title: Old\n---\nOne.\n\nThree.\n";
+    assert_eq!(
+        vec![
+            ("props.draft".to_string(), ChangeKind::Removed, None, None),
+            (
+                "content[1]".to_string(),
+                ChangeKind::Removed,
+                Some("Two.".to_string()),
+                None
+            ),
+        ],
+        diff(old, new)
+    );
+}
+
+#[test]
+fn diff_prop_change() {
+    let old = "title: Old\n---\n#-section:\nSay #em[ru='a']{hi}.\n#:\n";
+    let new = "title: New\n---\n#-section:\nSay #em[ru='b']{hi}.\n#:\n";
+    assert_eq!(
+        vec![
+            (
+                "props.title".to_string(),
+                ChangeKind::Changed,
+                Some("Old".to_string()),
+                Some("New".to_string())
+            ),
+            (
+                "content[0].tag(section).content[0].content[1].tag(em).props.ru".to_string(),
+                ChangeKind::Changed,
+                Some("a".to_string()),
+                Some("b".to_string())
+            ),
+        ],
+        diff(old, new)
+    );
+    let same = "---\n#-section[id='x']:\nSame.\n#:\n";
+    assert!(Diff::new(
+        &prosidy::parse::parse_document(same).unwrap(),
+        &prosidy::parse::parse_document(same).unwrap()
+    )
+    .is_empty());
+}
+
+#[test]
+fn diff_display() {
+    let old = prosidy::parse::parse_document("a: 1\n---\nOne.\n").unwrap();
+    let new = prosidy::parse::parse_document("a: 2\nb\n---\nTwo.\n").unwrap();
+    assert_eq!(
+        "~ props.a: \"1\" -> \"2\"\n+ props.b\n~ content[0].content[0]: \"One.\" -> \"Two.\"\n",
+        Diff::new(&old, &new).to_string()
+    );
+}
@@ -7,11 +7,15 @@ use std::io::Write;
 
 use anyhow::Result;
 use clap::{App, Arg, ArgMatches};
-use prosidy::xml::quick_xml::{events::BytesDecl, events::BytesText, events::Event};
-use prosidy::xml::{self, XML};
+use prosidy::render::{self, Stylesheet};
+use prosidy::xml::{InvalidChars, SoftBreak, XMLConfig, PROSIDY_NAMESPACE, XML};
+use prosidy::Document;
 use serde::Serialize;
 
+pub use prosidy::render::FormatOpts;
+
 use crate::args::{AppExt, FromArgs};
+use crate::jsonld::Article;
 
 #[derive(Clone, Debug)]
 pub struct Format {
@@ -23,6 +27,36 @@ impl Format {
     pub fn write<S: Serialize + XML, W: Write>(&self, writer: W, value: &S) -> Result<()> {
         self.kind.write(&self.opts, writer, value)
     }
+
+    /// Writes a document, which unlike other values can also be written as JSON-LD.
+    pub fn write_document<W: Write>(&self, writer: W, doc: &Document) -> Result<()> {
+        self.kind.write_document(&self.opts, writer, doc)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        self.kind.extension()
+    }
+
+    /// Whether this format can only describe a document, as JSON-LD does.
+    pub fn is_document_only(&self) -> bool {
+        self.kind == FormatKind::JsonLd
+    }
+
+    pub fn opts(&self) -> &FormatOpts {
+        &self.opts
+    }
+
+    /// Registers the same arguments as [`FromArgs::register_args`], but for commands writing
+    /// documents, which may also be written as JSON-LD.
+    pub fn register_document_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        app.arg(format_arg(DOCUMENT_FORMATS))
+            .register::<FormatOpts>()
+    }
+
+    /// Whether `--format` was given, rather than left to default to JSON.
+    pub fn is_given(matches: &ArgMatches) -> bool {
+        matches.occurrences_of(ARG_FORMAT) > 0
+    }
 }
 
 impl FromArgs for Format {
@@ -37,10 +71,14 @@ impl FromArgs for Format {
     }
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum FormatKind {
     CBOR,
+    HTML,
     JSON,
+    JsonLd,
+    Markdown,
     XML,
 }
 
@@ -51,10 +89,72 @@ impl FormatKind {
         writer: W,
         value: &S,
     ) -> Result<()> {
+        let kind = match self {
+            FormatKind::JsonLd => {
+                anyhow::bail!("JSON-LD can only be written from a document")
+            }
+            FormatKind::CBOR => render::FormatKind::CBOR,
+            FormatKind::HTML => render::FormatKind::HTML,
+            FormatKind::JSON => render::FormatKind::JSON,
+            FormatKind::Markdown => render::FormatKind::Markdown,
+            FormatKind::XML => render::FormatKind::XML,
+        };
+        kind.write(opts, writer, value)?;
+        Ok(())
+    }
+
+    /// Writes a document. JSON-LD describes only its header, as an [`Article`].
+    pub fn write_document<W: Write>(
+        self,
+        opts: &FormatOpts,
+        writer: W,
+        doc: &Document,
+    ) -> Result<()> {
+        match self {
+            FormatKind::JsonLd => {
+                opts.write_json(writer, &Article::new(doc.props()))?;
+                Ok(())
+            }
+            _ => self.write(opts, writer, doc),
+        }
+    }
+
+    /// Reads a document back from its AST as written in this format. Only CBOR, JSON, and XML
+    /// keep all of a document, so no other format can be read. XML elements are expected to be
+    /// named as `opts` describes.
+    pub fn read_document(self, opts: &FormatOpts, bytes: &[u8]) -> Result<Document<'static>> {
+        let doc = match self {
+            FormatKind::CBOR => serde_cbor::from_slice::<Document>(bytes)?.into_owned(),
+            FormatKind::JSON => serde_json::from_slice::<Document>(bytes)?.into_owned(),
+            FormatKind::XML => prosidy::xml::from_reader_with(bytes, &opts.xml_config)?,
+            _ => anyhow::bail!("Only CBOR, JSON, and XML documents can be read"),
+        };
+        Ok(doc)
+    }
+
+    /// Looks up a format by the name given for it on the command line, such as `json`.
+    pub fn from_name(name: &str) -> Option<FormatKind> {
+        let kind = match name {
+            ARG_FORMAT_CBOR => FormatKind::CBOR,
+            ARG_FORMAT_HTML => FormatKind::HTML,
+            ARG_FORMAT_JSON => FormatKind::JSON,
+            ARG_FORMAT_JSONLD => FormatKind::JsonLd,
+            ARG_FORMAT_MARKDOWN => FormatKind::Markdown,
+            ARG_FORMAT_XML => FormatKind::XML,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    /// The file extension conventionally used for this format.
+    pub fn extension(self) -> &'static str {
         match self {
-            FormatKind::CBOR => opts.write_cbor(writer, value),
-            FormatKind::JSON => opts.write_json(writer, value),
-            FormatKind::XML => opts.write_xml(writer, value),
+            FormatKind::CBOR => ARG_FORMAT_CBOR,
+            FormatKind::HTML => ARG_FORMAT_HTML,
+            FormatKind::JSON => ARG_FORMAT_JSON,
+            FormatKind::JsonLd => ARG_FORMAT_JSONLD,
+            FormatKind::Markdown => "md",
+            FormatKind::XML => ARG_FORMAT_XML,
         }
     }
 
@@ -62,7 +162,10 @@ impl FormatKind {
     pub fn media_type(self) -> &'static mime::Mime {
         match self {
             FormatKind::CBOR => &crate::mediatype::APPLICATION_CBOR,
+            FormatKind::HTML => &mime::TEXT_HTML,
             FormatKind::JSON => &mime::APPLICATION_JSON,
+            FormatKind::JsonLd => &crate::mediatype::APPLICATION_LD_JSON,
+            FormatKind::Markdown => &crate::mediatype::TEXT_MARKDOWN,
             FormatKind::XML => &mime::TEXT_XML,
         }
     }
@@ -70,91 +173,41 @@ impl FormatKind {
 
 impl FromArgs for FormatKind {
     fn register_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let arg = Arg::with_name(ARG_FORMAT)
-            .help("Selects the output format of the parsed AST")
-            .long("format")
-            .short("f")
-            .default_value_if(ARG_JSON_PRETTY, None, ARG_FORMAT_JSON)
-            .default_value_if(ARG_XSLT, None, ARG_FORMAT_XML)
-            .default_value_if(ARG_XMLNS, None, ARG_FORMAT_XML)
-            .default_value(ARG_FORMAT_JSON)
-            .takes_value(true)
-            .possible_values(&[ARG_FORMAT_CBOR, ARG_FORMAT_JSON, ARG_FORMAT_XML]);
-        app.arg(arg)
+        app.arg(format_arg(FORMATS))
     }
 
     fn parse_args(matches: &ArgMatches) -> Result<Self> {
-        let fmt = match matches.value_of(ARG_FORMAT) {
-            Some(ARG_FORMAT_CBOR) => FormatKind::CBOR,
-            Some(ARG_FORMAT_JSON) => FormatKind::JSON,
-            Some(ARG_FORMAT_XML) => FormatKind::XML,
-            Some(format) => anyhow::bail!("Unknown format name {:?}", format),
+        match matches.value_of(ARG_FORMAT) {
+            Some(format) => FormatKind::from_name(format)
+                .ok_or_else(|| anyhow::anyhow!("Unknown format name {:?}", format)),
             None => anyhow::bail!("No format name provided"),
-        };
-        Ok(fmt)
-    }
-}
-
-#[derive(Clone, Debug)]
-pub struct FormatOpts {
-    json_pretty: bool,
-    xml_namespace: Option<String>,
-    xml_stylesheets: Vec<String>,
-}
-
-impl FormatOpts {
-    pub fn write_cbor<S: Serialize, W: Write>(&self, writer: W, value: &S) -> Result<()> {
-        serde_cbor::to_writer(writer, value)?;
-        Ok(())
-    }
-
-    pub fn write_json<S: Serialize, W: Write>(&self, mut writer: W, value: &S) -> Result<()> {
-        if self.json_pretty {
-            serde_json::to_writer_pretty(&mut writer, value)?;
-        } else {
-            serde_json::to_writer(&mut writer, value)?;
         }
-        writer.write_all(b"\n")?;
-        Ok(())
     }
+}
 
-    pub fn write_xml<S: XML, W: Write>(&self, writer: W, value: &S) -> Result<()> {
-        let mut writer = xml::quick_xml::Writer::new(writer);
-        // first, write the XML declaration
-        let decl = BytesDecl::new(b"1.0", Some(b"UTF-8"), None);
-        writer.write_event(Event::Decl(decl))?;
-        // next, write all of the stylesheet instructions as pre-processor events
-        for stylesheet in self.xml_stylesheets.iter() {
-            let contents = format!(r#"xml-stylesheet type="text/xsl" href="{}""#, stylesheet,);
-            let event = BytesText::from_escaped_str(&contents);
-            writer.write_event(Event::PI(event))?;
-        }
-        // now, create a callback hook for writing events into the writer.
-        let mut first = true;
-        let mut handle = |mut event: Event| {
-            if first {
-                first = false;
-                let start = match event {
-                    Event::Start(ref mut start) => start,
-                    Event::Empty(ref mut empty) => empty,
-                    _ => panic!("The first emitted XML event was not a tag"),
-                };
-                if let Some(ref ns) = self.xml_namespace {
-                    start.push_attribute(("xmlns", ns.as_str()));
-                }
-                start.push_attribute(("xmlns:prosidy", PROSIDY_URI));
-            }
-            writer.write_event(event).map(|_| ())
-        };
-        value.to_events(&mut handle)?;
-        writer.write_event(Event::Eof)?;
-        writer.into_inner().write_all(b"\n")?;
-        Ok(())
-    }
+/// The `--format` argument, choosing between the `formats` named.
+fn format_arg(formats: &'static [&'static str]) -> Arg<'static, 'static> {
+    Arg::with_name(ARG_FORMAT)
+        .help("Selects the output format of the parsed AST")
+        .long("format")
+        .short("f")
+        .default_value_if(ARG_CBOR_CANONICAL, None, ARG_FORMAT_CBOR)
+        .default_value_if(ARG_JSON_PRETTY, None, ARG_FORMAT_JSON)
+        .default_value_if(ARG_XML_INDENT, None, ARG_FORMAT_XML)
+        .default_value_if(ARG_XSLT, None, ARG_FORMAT_XML)
+        .default_value_if(ARG_CSS, None, ARG_FORMAT_XML)
+        .default_value_if(ARG_XMLNS, None, ARG_FORMAT_XML)
+        .default_value_if(ARG_XML_PREFIX, None, ARG_FORMAT_XML)
+        .default_value(ARG_FORMAT_JSON)
+        .takes_value(true)
+        .possible_values(formats)
 }
 
 impl FromArgs for FormatOpts {
     fn register_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let cbor_canonical = Arg::with_name(ARG_CBOR_CANONICAL)
+            .help("Writes canonical CBOR, which is identical for identical documents")
+            .long("cbor-canonical");
         let json_pretty = Arg::with_name(ARG_JSON_PRETTY)
             .help("Pretty prints JSON output")
             .long("pretty")
@@ -164,6 +217,31 @@ impl FromArgs for FormatOpts {
             .long("xmlns")
             .short("N")
             .value_name("NAMESPACE URI");
+        let xml_prefix = Arg::with_name(ARG_XML_PREFIX)
+            .help("Name Prosidy's own XML elements with PREFIX rather than 'prosidy'")
+            .long("xml-prefix")
+            .value_name("PREFIX");
+        let xml_invalid_chars = Arg::with_name(ARG_XML_INVALID_CHARS)
+            .help("Remove or replace characters XML doesn't allow, or fail on them")
+            .long("xml-invalid-chars")
+            .possible_values(&[
+                INVALID_CHARS_ERROR,
+                INVALID_CHARS_REMOVE,
+                INVALID_CHARS_REPLACE,
+            ])
+            .default_value(INVALID_CHARS_REMOVE);
+        let xml_softbreak = Arg::with_name(ARG_XML_SOFTBREAK)
+            .help("Write soft breaks in XML as an element, a line feed, or a space")
+            .long("xml-softbreak")
+            .possible_values(&[SOFTBREAK_ELEMENT, SOFTBREAK_NEWLINE, SOFTBREAK_SPACE])
+            .default_value(SOFTBREAK_ELEMENT);
+        let xml_indent = Arg::with_name(ARG_XML_INDENT)
+            .help("Indents XML output by WIDTH spaces per level, defaulting to 2")
+            .long("xml-indent")
+            .value_name("WIDTH")
+            .require_equals(true)
+            .min_values(0)
+            .max_values(1);
         let xslt = Arg::with_name(ARG_XSLT)
             .help("Attach one or more XSLT stylesheets to the XML output")
             .long("xslt")
@@ -171,33 +249,163 @@ impl FromArgs for FormatOpts {
             .value_name("STYLESHEET")
             .number_of_values(1)
             .multiple(true);
-        app.arg(json_pretty).arg(xslt).arg(xmlns)
+        let css = Arg::with_name(ARG_CSS)
+            .help("Attach one or more CSS stylesheets to the XML output")
+            .long("css")
+            .value_name("STYLESHEET")
+            .number_of_values(1)
+            .multiple(true);
+        app.arg(cbor_canonical)
+            .arg(css)
+            .arg(json_pretty)
+            .arg(xml_indent)
+            .arg(xml_invalid_chars)
+            .arg(xml_prefix)
+            .arg(xml_softbreak)
+            .arg(xslt)
+            .arg(xmlns)
     }
 
     fn parse_args(matches: &ArgMatches) -> Result<Self> {
+        let cbor_canonical = matches.is_present(ARG_CBOR_CANONICAL);
         let json_pretty = matches.is_present(ARG_JSON_PRETTY);
-        let xml_stylesheets = matches
-            .values_of(ARG_XSLT)
+        let mut xml_stylesheets: Vec<_> = indexed(matches, ARG_XSLT, Stylesheet::xslt)
+            .chain(indexed(matches, ARG_CSS, Stylesheet::css))
+            .collect();
+        xml_stylesheets.sort_by_key(|(index, _)| *index);
+        let xml_stylesheets = xml_stylesheets
             .into_iter()
-            .flatten()
-            .map(String::from)
+            .map(|(_, stylesheet)| stylesheet)
             .collect();
+        let xml_indent = if matches.is_present(ARG_XML_INDENT) {
+            let width = matches.value_of(ARG_XML_INDENT).unwrap_or("2");
+            Some(width.parse()?)
+        } else {
+            None
+        };
         let xml_namespace = matches.value_of(ARG_XMLNS).map(String::from);
+        let xml_config = match matches.value_of(ARG_XML_PREFIX) {
+            Some(prefix) => {
+                let valid = !prefix.is_empty() && !prefix.contains(':');
+                anyhow::ensure!(valid, "--xml-prefix must be non-empty and without a colon");
+                XMLConfig::new(prefix, PROSIDY_NAMESPACE)
+            }
+            None => XMLConfig::default(),
+        };
+        let softbreak = match matches.value_of(ARG_XML_SOFTBREAK) {
+            Some(SOFTBREAK_NEWLINE) => SoftBreak::Newline,
+            Some(SOFTBREAK_SPACE) => SoftBreak::Space,
+            _ => SoftBreak::Element,
+        };
+        let invalid_chars = match matches.value_of(ARG_XML_INVALID_CHARS) {
+            Some(INVALID_CHARS_ERROR) => InvalidChars::Error,
+            Some(INVALID_CHARS_REPLACE) => InvalidChars::Replace,
+            _ => InvalidChars::Remove,
+        };
+        let xml_config = xml_config
+            .with_softbreak(softbreak)
+            .with_invalid_chars(invalid_chars);
         Ok(FormatOpts {
+            cbor_canonical,
             json_pretty,
+            xml_config,
+            xml_indent,
             xml_stylesheets,
             xml_namespace,
         })
     }
 }
 
+/// Reads each value of the argument `name`, paired with where it was given on the command line so
+/// that the values of several arguments can be put back in order.
+fn indexed<'m, T: 'm>(
+    matches: &'m ArgMatches,
+    name: &str,
+    read: fn(&'m str) -> T,
+) -> impl Iterator<Item = (usize, T)> + 'm {
+    let indices = matches.indices_of(name).into_iter().flatten();
+    let values = matches.values_of(name).into_iter().flatten();
+    indices.zip(values.map(read))
+}
+
 const ARG_FORMAT: &str = "format";
 const ARG_FORMAT_CBOR: &str = "cbor";
+const ARG_FORMAT_HTML: &str = "html";
 const ARG_FORMAT_JSON: &str = "json";
+const ARG_FORMAT_JSONLD: &str = "jsonld";
+const ARG_FORMAT_MARKDOWN: &str = "markdown";
 const ARG_FORMAT_XML: &str = "xml";
 
+/// Every format but JSON-LD, which can only describe a document.
+const FORMATS: &[&str] = &[
+    ARG_FORMAT_CBOR,
+    ARG_FORMAT_HTML,
+    ARG_FORMAT_JSON,
+    ARG_FORMAT_MARKDOWN,
+    ARG_FORMAT_XML,
+];
+const DOCUMENT_FORMATS: &[&str] = &[
+    ARG_FORMAT_CBOR,
+    ARG_FORMAT_HTML,
+    ARG_FORMAT_JSON,
+    ARG_FORMAT_JSONLD,
+    ARG_FORMAT_MARKDOWN,
+    ARG_FORMAT_XML,
+];
+
+const ARG_CBOR_CANONICAL: &str = "cbor-canonical";
+const ARG_CSS: &str = "css";
 const ARG_JSON_PRETTY: &str = "json-pretty-print";
+const ARG_XML_INDENT: &str = "xml-indent";
+const ARG_XML_INVALID_CHARS: &str = "xml-invalid-chars";
+const ARG_XML_PREFIX: &str = "xml-prefix";
+const ARG_XML_SOFTBREAK: &str = "xml-softbreak";
 const ARG_XMLNS: &str = "xmlns";
 const ARG_XSLT: &str = "xslt";
 
-const PROSIDY_URI: &str = "https://prosidy.org/schema/prosidy.xsd";
+const INVALID_CHARS_ERROR: &str = "error";
+const INVALID_CHARS_REMOVE: &str = "remove";
+const INVALID_CHARS_REPLACE: &str = "replace";
+
+const SOFTBREAK_ELEMENT: &str = "element";
+const SOFTBREAK_NEWLINE: &str = "newline";
+const SOFTBREAK_SPACE: &str = "space";
+
+#[test]
+fn read_written_documents() {
+    let source = "title: Both ways\n---\n#-section[id='a']:\nSome #em{text}\nhere.\n#:\n";
+    let doc = prosidy::parse::parse_document(source).unwrap();
+    for &kind in &[FormatKind::CBOR, FormatKind::JSON, FormatKind::XML] {
+        let opts = FormatOpts::default();
+        let mut output = Vec::new();
+        kind.write_document(&opts, &mut output, &doc).unwrap();
+        let read = kind.read_document(&opts, &output).unwrap();
+        assert_eq!(doc, read, "{:?}", kind);
+    }
+    assert!(FormatKind::HTML
+        .read_document(&FormatOpts::default(), b"<html></html>")
+        .is_err());
+}
+
+#[test]
+fn json_ld_only_for_documents() {
+    let args = ["test", "--format", "jsonld"];
+    let values = App::new("test").register::<Format>();
+    assert!(values.get_matches_from_safe(args).is_err());
+    let documents = Format::register_document_args(App::new("test"));
+    let matches = documents.get_matches_from_safe(args).unwrap();
+    assert!(Format::parse_args(&matches).unwrap().is_document_only());
+}
+
+#[test]
+fn xml_stylesheets_in_order() {
+    let app = App::new("test").register::<FormatOpts>();
+    let args = ["test", "--css", "a.css", "-s", "b.xsl", "--css", "c.css"];
+    let opts = FormatOpts::parse_args(&app.get_matches_from(args)).unwrap();
+    let expected = vec![
+        Stylesheet::css("a.css"),
+        Stylesheet::xslt("b.xsl"),
+        Stylesheet::css("c.css"),
+    ];
+    assert_eq!(expected, opts.xml_stylesheets);
+}
@@ -8,3 +8,4 @@ pub use sync::{Input, Output};
 
 mod opts;
 mod sync;
+pub mod tree;
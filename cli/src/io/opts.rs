@@ -3,12 +3,12 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use clap::{App, Arg, ArgGroup, ArgMatches};
 
-use super::sync::{Input, Output};
+use super::{Input, Output};
 use crate::args::FromArgs;
 
 #[derive(Debug)]
@@ -49,13 +49,22 @@ impl FromArgs for IOOpts {
 }
 
 impl IOOpts {
-    pub fn input(&self) -> Result<Input> {
+    pub fn input(&self) -> Result<Input<'_>> {
         Input::new(self.input.as_ref())
     }
 
+    /// The input file, or `None` when reading from standard input.
+    pub fn input_path(&self) -> Option<&Path> {
+        self.input.as_deref()
+    }
+
     pub fn output(&self) -> Result<Output> {
         Output::new(self.output.as_ref())
     }
+
+    pub fn output_path(&self) -> Option<&Path> {
+        self.output.as_deref()
+    }
 }
 
 const ARG_INPUT: &str = "input-path";
@@ -45,6 +45,13 @@ impl<'a> Input<'a> {
         Ok(buf)
     }
 
+    /// Reads the whole input without decoding it, for binary formats.
+    pub fn bytes(&mut self) -> Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(self.filesize()?.unwrap_or(1024));
+        self.read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
     pub fn filesize(&self) -> Result<Option<usize>> {
         if let Input::File(path, file) = self {
             let metadata = file.metadata().with_context(|| {
@@ -70,13 +77,13 @@ impl<'a> Read for Input<'a> {
 }
 
 #[derive(Debug)]
-pub enum Output<'a> {
+pub enum Output {
     StdIO(Stdout),
-    File(&'a Path, File),
+    File(File),
 }
 
-impl<'a> Output<'a> {
-    pub fn new<P: AsRef<Path>>(opt_path: Option<&'a P>) -> Result<Self> {
+impl Output {
+    pub fn new<P: AsRef<Path>>(opt_path: Option<&P>) -> Result<Self> {
         opt_path
             .map(Output::open)
             .unwrap_or_else(|| Ok(Output::stdio()))
@@ -86,30 +93,30 @@ impl<'a> Output<'a> {
         Output::StdIO(io::stdout())
     }
 
-    pub fn open<P: AsRef<Path>>(path: &'a P) -> Result<Self> {
-        let path: &'a Path = path.as_ref();
+    pub fn open<P: AsRef<Path>>(path: &P) -> Result<Self> {
+        let path = path.as_ref();
         let file = File::create(path).with_context(|| {
             format! {
                 "failed to open {:?} in write mode",
                 path,
             }
         })?;
-        Ok(Output::File(path, file))
+        Ok(Output::File(file))
     }
 }
 
-impl<'a> Write for Output<'a> {
+impl Write for Output {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         match self {
             Output::StdIO(stdout) => stdout.write(buf),
-            Output::File(_, file) => file.write(buf),
+            Output::File(file) => file.write(buf),
         }
     }
 
     fn flush(&mut self) -> io::Result<()> {
         match self {
             Output::StdIO(stdout) => stdout.flush(),
-            Output::File(_, file) => file.flush(),
+            Output::File(file) => file.flush(),
         }
     }
 }
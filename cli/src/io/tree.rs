@@ -0,0 +1,52 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Lists every file under `root`, relative to it and in sorted order.
+///
+/// Symlinked directories aren't descended into, which keeps a link to a parent from looping
+/// forever. `skip` is left out if it's inside `root`, so that compiling a tree into a directory
+/// within it doesn't pick up earlier output.
+pub fn files(root: &Path, skip: &Path) -> Result<Vec<PathBuf>> {
+    let skip = skip.canonicalize().ok();
+    let mut files = Vec::new();
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(dir) = dirs.pop() {
+        let full = root.join(&dir);
+        let entries =
+            fs::read_dir(&full).with_context(|| format!("failed to read directory {:?}", full))?;
+        for entry in entries {
+            let entry = entry?;
+            let path = dir.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                if skip.is_none() || entry.path().canonicalize().ok() != skip {
+                    dirs.push(path);
+                }
+            } else if entry.path().is_file() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[test]
+fn list_tree() {
+    let root = std::env::temp_dir().join(format!("prosidy-tree-{}", std::process::id()));
+    fs::create_dir_all(root.join("a/b")).unwrap();
+    fs::create_dir_all(root.join("out")).unwrap();
+    for file in &["z.pro", "a/x.pro", "a/b/y.txt", "out/old.json"] {
+        fs::write(root.join(file), "").unwrap();
+    }
+    let files = files(&root, &root.join("out")).unwrap();
+    fs::remove_dir_all(&root).unwrap();
+    let expected: Vec<PathBuf> = vec!["a/b/y.txt".into(), "a/x.pro".into(), "z.pro".into()];
+    assert_eq!(expected, files);
+}
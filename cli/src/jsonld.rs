@@ -0,0 +1,100 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use prosidy::{Key, PropSet};
+use serde::Serialize;
+
+/// A schema.org [`Article`](https://schema.org/Article) describing a document, written as
+/// JSON-LD so that search engines can pick up its structured data.
+///
+/// Each field comes from a header setting:
+///
+/// | Header property | `Article` field                          |
+/// |-----------------|------------------------------------------|
+/// | `title`         | `headline`                               |
+/// | `author`        | `author`, as a `Person` with that `name` |
+/// | `date`          | `datePublished`                          |
+/// | `description`   | `description`                            |
+///
+/// Values are copied as they are, less surrounding whitespace, so `date` should already be an
+/// ISO 8601 date or timestamp. Other properties have no counterpart in schema.org and are left
+/// out.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Article {
+    #[serde(rename = "@context")]
+    context: &'static str,
+    #[serde(rename = "@type")]
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    headline: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author: Option<Person>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date_published: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Person {
+    #[serde(rename = "@type")]
+    kind: &'static str,
+    name: String,
+}
+
+impl Article {
+    const SCHEMA_ORG: &'static str = "https://schema.org";
+
+    /// Builds an article from a document's header.
+    pub fn new(props: &PropSet) -> Self {
+        let setting = |key: &str| {
+            props
+                .lookup_str(Key::new(key))
+                .map(|value| value.trim().to_string())
+        };
+        Article {
+            context: Article::SCHEMA_ORG,
+            kind: "Article",
+            headline: setting(PROP_TITLE),
+            author: setting(PROP_AUTHOR).map(|name| Person {
+                kind: "Person",
+                name,
+            }),
+            date_published: setting(PROP_DATE),
+            description: setting(PROP_DESCRIPTION),
+        }
+    }
+}
+
+const PROP_AUTHOR: &str = "author";
+const PROP_DATE: &str = "date";
+const PROP_DESCRIPTION: &str = "description";
+const PROP_TITLE: &str = "title";
+
+#[test]
+fn article_from_header() {
+    let source = "title: Notes\nauthor: A. Writer\ndate: 2020-01-31\n\
+                  description: Some notes.\nlang: en\ndraft\n---\n";
+    let doc = prosidy::parse::parse_document(source).unwrap();
+    let article = serde_json::to_value(Article::new(doc.props())).unwrap();
+    assert_eq!(
+        serde_json::json!({
+            "@context": "https://schema.org",
+            "@type": "Article",
+            "headline": "Notes",
+            "author": { "@type": "Person", "name": "A. Writer" },
+            "datePublished": "2020-01-31",
+            "description": "Some notes.",
+        }),
+        article
+    );
+    let empty = prosidy::parse::parse_document("---\n").unwrap();
+    let article = serde_json::to_value(Article::new(empty.props())).unwrap();
+    assert_eq!(
+        serde_json::json!({ "@context": "https://schema.org", "@type": "Article" }),
+        article
+    );
+}
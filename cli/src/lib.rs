@@ -3,12 +3,15 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{value_t, App, AppSettings, Arg, ArgMatches, SubCommand};
 use log::LevelFilter;
+use prosidy::{Document, Key};
 
 use self::args::{AppExt, FromArgs};
 
@@ -49,6 +52,7 @@ impl Opts {
             .filter_module("prosidy_ast", self.log_level)
             .filter_module("prosidy_cli", self.log_level)
             .filter_module("prosidy_parse", self.log_level)
+            .filter_module("prosidy_xml", self.log_level)
             .try_init();
         log::debug!("Initialized logger with level {:?}", self.log_level);
         log::debug!("Options: {:?}", self);
@@ -77,33 +81,57 @@ impl FromArgs for Opts {
 
 #[derive(Debug)]
 enum Mode {
+    Check(Check),
     Compile(Compile),
     Completions(Completions),
+    Convert(Convert),
+    Diff(Diff),
+    Fmt(Fmt),
     Manifest(Manifest),
     #[cfg(feature = "server")]
     Serve(serve::ServeOpts),
+    Schema(Schema),
+    Stats(Stats),
+    Validate(Validate),
 }
 
 impl Mode {
+    const CHECK: &'static str = "check";
     const COMPILE: &'static str = "compile";
     const COMPLETIONS: &'static str = "generate-completions";
+    const CONVERT: &'static str = "convert";
+    const DIFF: &'static str = "diff";
+    const FMT: &'static str = "fmt";
     const MANIFEST: &'static str = "manifest";
     #[cfg(feature = "server")]
     const SERVE: &'static str = "serve";
+    const SCHEMA: &'static str = "schema";
+    const STATS: &'static str = "stats";
+    const VALIDATE: &'static str = "validate";
 
     fn run(self, app: App) -> Result<()> {
         match self {
+            Mode::Check(check) => check.run(),
             Mode::Compile(compile) => compile.run(),
             Mode::Completions(complete) => complete.run(app),
+            Mode::Convert(convert) => convert.run(),
+            Mode::Diff(diff) => diff.run(),
+            Mode::Fmt(fmt) => fmt.run(),
             Mode::Manifest(manifest) => manifest.run(),
             #[cfg(feature = "server")]
             Mode::Serve(serve) => serve.run(),
+            Mode::Schema(schema) => schema.run(),
+            Mode::Stats(stats) => stats.run(),
+            Mode::Validate(validate) => validate.run(),
         }
     }
 }
 
 impl FromArgs for Mode {
     fn register_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let check = SubCommand::with_name(Mode::CHECK)
+            .about("Parse a Prosidy document and report any warnings")
+            .register::<Check>();
         let compile = SubCommand::with_name(Mode::COMPILE)
             .about("Parse a Prosidy document into an AST")
             .register::<Compile>();
@@ -111,9 +139,27 @@ impl FromArgs for Mode {
             .about("Generate completions for the Prosidy CLI tool")
             .setting(AppSettings::Hidden)
             .register::<Completions>();
+        let convert = SubCommand::with_name(Mode::CONVERT)
+            .about("Read a document's AST from CBOR, JSON, or XML, and write it in another format")
+            .register::<Convert>();
+        let diff = SubCommand::with_name(Mode::DIFF)
+            .about("Compare two Prosidy documents, reporting the blocks and properties that changed")
+            .register::<Diff>();
+        let fmt = SubCommand::with_name(Mode::FMT)
+            .about("Rewrite a Prosidy document in a canonical style")
+            .register::<Fmt>();
         let manifest = SubCommand::with_name(Mode::MANIFEST)
             .about("Parse the metadata of a document or directory of documents")
             .register::<Manifest>();
+        let schema = SubCommand::with_name(Mode::SCHEMA)
+            .about("Print an XML schema describing the documents written as XML")
+            .register::<Schema>();
+        let stats = SubCommand::with_name(Mode::STATS)
+            .about("Count the words and paragraphs in a document")
+            .register::<Stats>();
+        let validate = SubCommand::with_name(Mode::VALIDATE)
+            .about("Check a document or directory of documents for structural problems")
+            .register::<Validate>();
         #[cfg(feature = "server")]
         let mut app = app;
         #[cfg(feature = "server")]
@@ -124,14 +170,25 @@ impl FromArgs for Mode {
                     .register::<serve::ServeOpts>(),
             );
         }
-        app.subcommand(compile)
+        app.subcommand(check)
+            .subcommand(compile)
             .subcommand(generate_completions)
+            .subcommand(convert)
+            .subcommand(diff)
+            .subcommand(fmt)
             .subcommand(manifest)
+            .subcommand(schema)
+            .subcommand(stats)
+            .subcommand(validate)
     }
 
     fn parse_args(matches: &ArgMatches) -> Result<Self> {
         let (sub, sub_matches) = matches.subcommand();
         match sub {
+            Mode::CHECK => {
+                let check = Check::parse_args(sub_matches.unwrap())?;
+                Ok(Mode::Check(check))
+            }
             Mode::COMPILE => {
                 let compile = Compile::parse_args(sub_matches.unwrap())?;
                 Ok(Mode::Compile(compile))
@@ -140,6 +197,18 @@ impl FromArgs for Mode {
                 let completions = Completions::parse_args(sub_matches.unwrap())?;
                 Ok(Mode::Completions(completions))
             }
+            Mode::CONVERT => {
+                let convert = Convert::parse_args(sub_matches.unwrap())?;
+                Ok(Mode::Convert(convert))
+            }
+            Mode::DIFF => {
+                let diff = Diff::parse_args(sub_matches.unwrap())?;
+                Ok(Mode::Diff(diff))
+            }
+            Mode::FMT => {
+                let fmt = Fmt::parse_args(sub_matches.unwrap())?;
+                Ok(Mode::Fmt(fmt))
+            }
             Mode::MANIFEST => {
                 let manifest = Manifest::parse_args(sub_matches.unwrap())?;
                 Ok(Mode::Manifest(manifest))
@@ -149,6 +218,18 @@ impl FromArgs for Mode {
                 let serve = serve::ServeOpts::parse_args(sub_matches.unwrap())?;
                 Ok(Mode::Serve(serve))
             }
+            Mode::SCHEMA => {
+                let schema = Schema::parse_args(sub_matches.unwrap())?;
+                Ok(Mode::Schema(schema))
+            }
+            Mode::STATS => {
+                let stats = Stats::parse_args(sub_matches.unwrap())?;
+                Ok(Mode::Stats(stats))
+            }
+            Mode::VALIDATE => {
+                let validate = Validate::parse_args(sub_matches.unwrap())?;
+                Ok(Mode::Validate(validate))
+            }
             _ => {
                 anyhow::bail!("unknown subcommand {:?}", sub);
             }
@@ -156,35 +237,316 @@ impl FromArgs for Mode {
     }
 }
 
+#[derive(Debug)]
+struct Check {
+    fail_on_warning: bool,
+    io: io::IOOpts,
+    vocabulary: Option<Vec<Key>>,
+}
+
+impl Check {
+    const FAIL_ON_WARNING: &'static str = "fail-on-warning";
+    const VOCABULARY: &'static str = "vocab";
+
+    fn run(self) -> Result<()> {
+        let source = self.io.input()?.contents()?;
+        let mut ctx = parse_context(&self.vocabulary);
+        prosidy::parse::parse_document_in(&source, &mut ctx)
+            .map_err(|error| anyhow::anyhow!(error.report(&source)))?;
+        let mut output = self.io.output()?;
+        for warning in ctx.warnings() {
+            writeln!(output, "warning: {}", warning)?;
+        }
+        let count = ctx.warnings().len();
+        if self.fail_on_warning && count > 0 {
+            anyhow::bail!("found {} warning(s)", count);
+        }
+        Ok(())
+    }
+}
+
+impl FromArgs for Check {
+    fn register_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let fail_on_warning = Arg::with_name(Check::FAIL_ON_WARNING)
+            .help("Exit with an error if the document produces any warnings")
+            .long("fail-on-warning")
+            .short("W");
+        app.arg(fail_on_warning)
+            .arg(vocabulary_arg(Check::VOCABULARY))
+            .register::<io::IOOpts>()
+    }
+
+    fn parse_args(matches: &ArgMatches) -> Result<Self> {
+        let fail_on_warning = matches.is_present(Check::FAIL_ON_WARNING);
+        let io = io::IOOpts::parse_args(matches)?;
+        let vocabulary = read_vocabulary(matches.value_of(Check::VOCABULARY))?;
+        Ok(Check {
+            fail_on_warning,
+            io,
+            vocabulary,
+        })
+    }
+}
+
+/// The `--vocab FILE` argument shared by the subcommands which report parse warnings.
+fn vocabulary_arg(name: &'static str) -> Arg<'static, 'static> {
+    Arg::with_name(name)
+        .help("Warn about tags not named in FILE, which lists one tag name per line")
+        .long("vocab")
+        .value_name("FILE")
+}
+
+fn read_vocabulary(path: Option<&str>) -> Result<Option<Vec<Key>>> {
+    let path = match path {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let list = fs::read_to_string(path)
+        .with_context(|| format!("failed to read vocabulary from {:?}", path))?;
+    let names = list.lines().map(str::trim).filter(|name| !name.is_empty());
+    Ok(Some(names.map(Key::new).collect()))
+}
+
+fn parse_context(vocabulary: &Option<Vec<Key>>) -> prosidy::parse::Context {
+    let ctx = prosidy::parse::Context::new();
+    match vocabulary {
+        Some(names) => ctx.with_vocabulary(names.iter().cloned()),
+        None => ctx,
+    }
+}
+
 #[derive(Debug)]
 struct Compile {
+    fail_on_warning: bool,
     format: fmt::Format,
     io: io::IOOpts,
+    copy_other: bool,
+    dedent_literals: bool,
+    footnotes: bool,
+    resolve_includes: bool,
+    split_at: Option<String>,
+    vocabulary: Option<Vec<Key>>,
+    watch: bool,
 }
 
 impl Compile {
+    const COPY_OTHER: &'static str = "copy-other";
+    const DEDENT_LITERALS: &'static str = "dedent-literals";
+    const FAIL_ON_WARNING: &'static str = "fail-on-warning";
+    const FOOTNOTES: &'static str = "footnotes";
+    const RESOLVE_INCLUDES: &'static str = "resolve-includes";
+    const SPLIT_AT: &'static str = "split-at";
+    const VOCABULARY: &'static str = "vocab";
+    const WATCH: &'static str = "watch";
+
     fn run(self) -> Result<()> {
+        if self.watch {
+            self.run_watch()
+        } else {
+            self.compile()
+        }
+    }
+
+    /// Recompiles the input whenever it changes. Errors are reported, but don't stop the loop.
+    fn run_watch(self) -> Result<()> {
+        let input = self.io.input_path().ok_or_else(|| {
+            anyhow::anyhow!("--{} requires an input file, not --stdin", Compile::WATCH)
+        })?;
+        anyhow::ensure!(
+            self.io.output_path().is_some(),
+            "--{} requires an output path",
+            Compile::WATCH
+        );
+        let mut last = watch::modified(input);
+        loop {
+            let start = Instant::now();
+            match self.compile() {
+                Ok(()) => log::info!("compiled {:?} in {:?}", input, start.elapsed()),
+                Err(error) => eprintln!("{}", error),
+            }
+            log::debug!("waiting for changes to {:?}", input);
+            last = watch::wait_for_change(input, last);
+        }
+    }
+
+    fn compile(&self) -> Result<()> {
+        if let Some(dir) = self.io.input_path().filter(|path| path.is_dir()) {
+            return self.compile_tree(dir);
+        }
         log::debug!("reading source");
         let source = self.io.input()?.contents()?;
-        log::debug!("parsing source into Document");
-        let doc = prosidy::parse::parse_document(&source)?;
+        // Includes read from standard input are relative to the working directory.
+        let base_dir = self
+            .io
+            .input_path()
+            .and_then(Path::parent)
+            .unwrap_or_else(|| Path::new(""));
+        let doc = self.parse(&source, base_dir)?;
+        if let Some(ref tag_name) = self.split_at {
+            let dir = self.io.output_path().ok_or_else(|| {
+                anyhow::anyhow!("--{} requires an output directory", Compile::SPLIT_AT)
+            })?;
+            log::debug!("splitting document at {:?} tags", tag_name);
+            let split = split::Split::new(doc, tag_name, self.format.extension());
+            return split.write(dir, &self.format);
+        }
         log::debug!("opening output");
         let output = self.io.output()?;
         log::debug!("rendering document to output");
-        self.format.write(output, &doc)?;
+        self.format.write_document(output, &doc)?;
         Ok(())
     }
+
+    /// Compiles each Prosidy file under `input` to the same relative path in the output
+    /// directory. A file which fails is reported, and doesn't stop the rest from compiling.
+    fn compile_tree(&self, input: &Path) -> Result<()> {
+        let output = self.io.output_path().ok_or_else(|| {
+            anyhow::anyhow!("compiling a directory requires an output directory")
+        })?;
+        anyhow::ensure!(
+            self.split_at.is_none(),
+            "--{} can't be used when compiling a directory",
+            Compile::SPLIT_AT
+        );
+        let mut failed = 0;
+        let mut total = 0;
+        for path in io::tree::files(input, output)? {
+            let source = input.join(&path);
+            let result = if path.extension() == Some("pro".as_ref()) {
+                let dest = output.join(&path).with_extension(self.format.extension());
+                log::debug!("compiling {:?} to {:?}", source, dest);
+                self.compile_file(&source, &dest)
+            } else if self.copy_other {
+                let dest = output.join(&path);
+                log::debug!("copying {:?} to {:?}", source, dest);
+                create_parent(&dest).and_then(|_| {
+                    fs::copy(&source, &dest)
+                        .with_context(|| format!("failed to copy to {:?}", dest))?;
+                    Ok(())
+                })
+            } else {
+                continue;
+            };
+            total += 1;
+            if let Err(error) = result {
+                eprintln!("{}: {}", source.display(), error);
+                failed += 1;
+            }
+        }
+        if failed > 0 {
+            anyhow::bail!("{} of {} file(s) failed", failed, total);
+        }
+        Ok(())
+    }
+
+    fn compile_file(&self, source_path: &Path, dest: &Path) -> Result<()> {
+        let source = io::Input::open(&source_path)?.contents()?;
+        let base_dir = source_path.parent().unwrap_or_else(|| Path::new(""));
+        let doc = self.parse(&source, base_dir)?;
+        create_parent(dest)?;
+        self.format.write_document(io::Output::open(&dest)?, &doc)
+    }
+
+    fn parse<'s>(&self, source: &'s str, base_dir: &Path) -> Result<Document<'s>> {
+        log::debug!("parsing source into Document");
+        let mut ctx = parse_context(&self.vocabulary);
+        let mut doc = prosidy::parse::parse_document_in(source, &mut ctx)
+            .map_err(|error| anyhow::anyhow!(error.report(source)))?;
+        if self.fail_on_warning && !ctx.warnings().is_empty() {
+            anyhow::bail!("parsing produced {} warning(s)", ctx.warnings().len());
+        }
+        if self.resolve_includes {
+            log::debug!("resolving includes relative to {:?}", base_dir);
+            doc = prosidy::parse::resolve_includes(doc, base_dir)?;
+        }
+        if self.dedent_literals {
+            let count = prosidy::dedent_literals(&mut doc);
+            log::debug!("dedented {} literal(s)", count);
+        }
+        if self.footnotes {
+            let count = prosidy::collect_footnotes(&mut doc);
+            log::debug!("collected {} footnote(s)", count);
+        }
+        Ok(doc)
+    }
+}
+
+fn create_parent(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create directory {:?}", parent))?;
+    }
+    Ok(())
 }
 
 impl FromArgs for Compile {
     fn register_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        app.register::<fmt::Format>().register::<io::IOOpts>()
+        let copy_other = Arg::with_name(Compile::COPY_OTHER)
+            .help("When compiling a directory, copy files which aren't Prosidy documents as-is")
+            .long("copy-other");
+        let fail_on_warning = Arg::with_name(Compile::FAIL_ON_WARNING)
+            .help("Exit with an error if parsing the document produces any warnings")
+            .long("fail-on-warning")
+            .short("W");
+        let dedent_literals = Arg::with_name(Compile::DEDENT_LITERALS)
+            .help("Remove the indentation shared by every line of each literal block")
+            .long("dedent-literals");
+        let footnotes = Arg::with_name(Compile::FOOTNOTES)
+            .help("Move #note tags to a numbered list of footnotes at the end of the document")
+            .long("footnotes");
+        let resolve_includes = Arg::with_name(Compile::RESOLVE_INCLUDES)
+            .help("Replace each #-include{PATH} tag with the content of the document at PATH")
+            .long("resolve-includes");
+        let split_at = Arg::with_name(Compile::SPLIT_AT)
+            .help("Write a file per top-level occurrence of TAG into the output directory")
+            .long("split-at")
+            .value_name("TAG");
+        let watch = Arg::with_name(Compile::WATCH)
+            .help("Compile again whenever the input file changes")
+            .long("watch")
+            .short("w")
+            .conflicts_with("stdin");
+        let app = app.args(&[
+            copy_other,
+            dedent_literals,
+            fail_on_warning,
+            footnotes,
+            resolve_includes,
+            split_at,
+            vocabulary_arg(Compile::VOCABULARY),
+            watch,
+        ]);
+        fmt::Format::register_document_args(app).register::<io::IOOpts>()
     }
 
     fn parse_args(matches: &ArgMatches) -> Result<Self> {
+        let copy_other = matches.is_present(Compile::COPY_OTHER);
+        let dedent_literals = matches.is_present(Compile::DEDENT_LITERALS);
+        let fail_on_warning = matches.is_present(Compile::FAIL_ON_WARNING);
+        let footnotes = matches.is_present(Compile::FOOTNOTES);
         let format = fmt::Format::parse_args(matches)?;
         let io = io::IOOpts::parse_args(matches)?;
-        Ok(Compile { format, io })
+        let resolve_includes = matches.is_present(Compile::RESOLVE_INCLUDES);
+        let split_at = matches.value_of(Compile::SPLIT_AT).map(String::from);
+        anyhow::ensure!(
+            split_at.is_none() || !format.is_document_only(),
+            "--{} can't write its table of contents as JSON-LD",
+            Compile::SPLIT_AT
+        );
+        let vocabulary = read_vocabulary(matches.value_of(Compile::VOCABULARY))?;
+        let watch = matches.is_present(Compile::WATCH);
+        Ok(Compile {
+            copy_other,
+            dedent_literals,
+            fail_on_warning,
+            footnotes,
+            format,
+            io,
+            resolve_includes,
+            split_at,
+            vocabulary,
+            watch,
+        })
     }
 }
 
@@ -217,20 +579,97 @@ impl FromArgs for Completions {
     }
 }
 
+#[derive(Debug)]
+struct Convert {
+    from: fmt::FormatKind,
+    format: fmt::Format,
+    io: io::IOOpts,
+}
+
+impl Convert {
+    const FROM: &'static str = "from";
+
+    fn run(self) -> Result<()> {
+        let bytes = self.io.input()?.bytes()?;
+        let doc = self.from.read_document(self.format.opts(), &bytes)?;
+        self.format.write_document(self.io.output()?, &doc)
+    }
+}
+
+impl FromArgs for Convert {
+    fn register_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let from = Arg::with_name(Convert::FROM)
+            .help("The format the input AST is written in")
+            .long("from")
+            .value_name("FORMAT")
+            .possible_values(&["cbor", "json", "xml"])
+            .required(true);
+        fmt::Format::register_document_args(app.arg(from)).register::<io::IOOpts>()
+    }
+
+    fn parse_args(matches: &ArgMatches) -> Result<Self> {
+        let from = matches
+            .value_of(Convert::FROM)
+            .and_then(fmt::FormatKind::from_name)
+            .context("No input format provided")?;
+        let format = fmt::Format::parse_args(matches)?;
+        let io = io::IOOpts::parse_args(matches)?;
+        Ok(Convert { from, format, io })
+    }
+}
+
 #[derive(Debug)]
 struct Manifest {
     path: PathBuf,
     format: fmt::Format,
+    recursive: bool,
+    filters: Vec<manifest::Filter>,
+    base_url: Option<String>,
+    feed: Option<Feed>,
+}
+
+/// A feed which can be written from a manifest, in place of the manifest itself.
+#[derive(Copy, Clone, Debug)]
+enum Feed {
+    Atom,
+    Sitemap,
 }
 
 impl Manifest {
     const PATH: &'static str = "manifest-path";
+    const RECURSIVE: &'static str = "recursive";
+    const WHERE: &'static str = "where";
+    const WHERE_NOT: &'static str = "where-not";
+    const BASE_URL: &'static str = "base-url";
+    const FEED: &'static str = "feed";
+    const FEED_ATOM: &'static str = "atom";
+    const FEED_SITEMAP: &'static str = "sitemap";
 
     pub fn run(self) -> Result<()> {
-        let manifest = manifest::Manifest::read(&self.path, true)?;
+        let manifest =
+            manifest::Manifest::read(&self.path, true, self.recursive, &self.filters)?;
+        for (path, reason) in manifest.warnings() {
+            eprintln!("warning: skipped {}: {}", path.display(), reason);
+        }
         let stdout = std::io::stdout();
         let lock = stdout.lock();
-        self.format.write(lock, &manifest)
+        match self.feed {
+            Some(Feed::Atom) => {
+                let feed = atom::Feed::new(&manifest, self.base_url()?)?;
+                atom::write_feed(lock, &feed)
+            }
+            Some(Feed::Sitemap) => {
+                let sitemap = sitemap::Sitemap::new(&manifest, self.base_url()?)?;
+                sitemap::write_sitemap(lock, &sitemap)
+            }
+            None => self.format.write(lock, &manifest),
+        }
+    }
+
+    fn base_url(&self) -> Result<&str> {
+        self.base_url
+            .as_deref()
+            .context("Atom feeds and sitemaps need a --base-url to link their entries to")
     }
 }
 
@@ -240,23 +679,393 @@ impl FromArgs for Manifest {
             .help("The directory to parse metadata from.")
             .value_name("DIR")
             .required(true);
-        app.arg(path).register::<fmt::Format>()
+        let recursive = Arg::with_name(Manifest::RECURSIVE)
+            .help("Include documents in subdirectories, keyed by their relative path")
+            .long("recursive")
+            .short("r");
+        let where_ = Arg::with_name(Manifest::WHERE)
+            .help("Only include documents with this property, or with this setting and value")
+            .long("where")
+            .value_name("PROP[=VALUE]")
+            .multiple(true)
+            .number_of_values(1);
+        let where_not = Arg::with_name(Manifest::WHERE_NOT)
+            .help("Exclude documents with this property, or with this setting and value")
+            .long("where-not")
+            .value_name("PROP[=VALUE]")
+            .multiple(true)
+            .number_of_values(1);
+        let base_url = Arg::with_name(Manifest::BASE_URL)
+            .help("The URL documents are published under, for links in Atom feeds and sitemaps")
+            .long("base-url")
+            .value_name("URL");
+        let feed = Arg::with_name(Manifest::FEED)
+            .help("Write an Atom feed or a sitemap of the documents, rather than the manifest")
+            .long("feed")
+            .value_name("FEED")
+            .possible_values(&[Manifest::FEED_ATOM, Manifest::FEED_SITEMAP]);
+        app.arg(path)
+            .arg(recursive)
+            .arg(where_)
+            .arg(where_not)
+            .arg(base_url)
+            .arg(feed)
+            .register::<fmt::Format>()
     }
 
     fn parse_args(matches: &ArgMatches) -> Result<Self> {
         let format = fmt::Format::parse_args(matches)?;
         let path = value_t!(matches, Manifest::PATH, PathBuf)?;
-        Ok(Manifest { format, path })
+        let recursive = matches.is_present(Manifest::RECURSIVE);
+        let includes = matches.values_of(Manifest::WHERE).into_iter().flatten();
+        let excludes = matches.values_of(Manifest::WHERE_NOT).into_iter().flatten();
+        let filters = includes
+            .map(manifest::Filter::include)
+            .chain(excludes.map(manifest::Filter::exclude))
+            .collect();
+        let base_url = matches.value_of(Manifest::BASE_URL).map(String::from);
+        let feed = match matches.value_of(Manifest::FEED) {
+            Some(Manifest::FEED_ATOM) => Some(Feed::Atom),
+            Some(Manifest::FEED_SITEMAP) => Some(Feed::Sitemap),
+            _ => None,
+        };
+        anyhow::ensure!(
+            feed.is_none() || !fmt::Format::is_given(matches),
+            "--{} can't be combined with --format",
+            Manifest::FEED
+        );
+        Ok(Manifest {
+            format,
+            path,
+            recursive,
+            filters,
+            base_url,
+            feed,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Fmt {
+    check: bool,
+    io: io::IOOpts,
+    opts: prosidy::parse::PrintOpts,
+}
+
+impl Fmt {
+    const CHECK: &'static str = "check";
+    const WIDTH: &'static str = "width";
+
+    fn run(self) -> Result<()> {
+        let source = self.io.input()?.contents()?;
+        let doc = prosidy::parse::parse_document(&source)?;
+        if source.lines().any(|line| line.trim_start().starts_with("##")) {
+            log::warn!("The document has comments, which formatting will remove");
+        }
+        let formatted = prosidy::parse::print_document(&doc, &self.opts)?;
+        if self.check {
+            anyhow::ensure!(formatted == source, "The document is not formatted");
+            return Ok(());
+        }
+        self.io.output()?.write_all(formatted.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl FromArgs for Fmt {
+    fn register_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let check = Arg::with_name(Fmt::CHECK)
+            .help("Fail if the document isn't already formatted, rather than writing it")
+            .long("check");
+        let width = Arg::with_name(Fmt::WIDTH)
+            .help("Reflow paragraphs to fit within N characters")
+            .long("width")
+            .value_name("N");
+        app.arg(check).arg(width).register::<io::IOOpts>()
+    }
+
+    fn parse_args(matches: &ArgMatches) -> Result<Self> {
+        let check = matches.is_present(Fmt::CHECK);
+        let width = if matches.is_present(Fmt::WIDTH) {
+            Some(value_t!(matches, Fmt::WIDTH, usize)?)
+        } else {
+            None
+        };
+        let io = io::IOOpts::parse_args(matches)?;
+        Ok(Fmt {
+            check,
+            io,
+            opts: prosidy::parse::PrintOpts {
+                width,
+                ..Default::default()
+            },
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Diff {
+    old: PathBuf,
+    new: PathBuf,
+    /// The format to write the changes in, or `None` to list them one per line.
+    format: Option<fmt::Format>,
+}
+
+impl Diff {
+    const OLD: &'static str = "old-path";
+    const NEW: &'static str = "new-path";
+
+    fn run(self) -> Result<()> {
+        let old = io::Input::open(&self.old)?.contents()?;
+        let new = io::Input::open(&self.new)?.contents()?;
+        let old = prosidy::parse::parse_document(&old)
+            .map_err(|error| anyhow::anyhow!("{}: {}", self.old.display(), error.report(&old)))?;
+        let new = prosidy::parse::parse_document(&new)
+            .map_err(|error| anyhow::anyhow!("{}: {}", self.new.display(), error.report(&new)))?;
+        let diff = diff::Diff::new(&old, &new);
+        let stdout = std::io::stdout();
+        match self.format {
+            Some(format) => format.write(stdout.lock(), &diff)?,
+            None => write!(stdout.lock(), "{}", diff)?,
+        }
+        anyhow::ensure!(diff.is_empty(), "found {} change(s)", diff.len());
+        Ok(())
+    }
+}
+
+impl FromArgs for Diff {
+    fn register_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let old = Arg::with_name(Diff::OLD)
+            .help("The document to compare against")
+            .value_name("OLD")
+            .required(true);
+        let new = Arg::with_name(Diff::NEW)
+            .help("The document to compare with it")
+            .value_name("NEW")
+            .required(true);
+        app.arg(old).arg(new).register::<fmt::Format>()
+    }
+
+    fn parse_args(matches: &ArgMatches) -> Result<Self> {
+        let old = matches.value_of(Diff::OLD).map(PathBuf::from);
+        let new = matches.value_of(Diff::NEW).map(PathBuf::from);
+        let format = if fmt::Format::is_given(matches) {
+            Some(fmt::Format::parse_args(matches)?)
+        } else {
+            None
+        };
+        Ok(Diff {
+            old: old.context("Missing old document path")?,
+            new: new.context("Missing new document path")?,
+            format,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Schema {
+    opts: fmt::FormatOpts,
+}
+
+impl Schema {
+    fn run(self) -> Result<()> {
+        let schema = prosidy::xml::schema(
+            &self.opts.xml_config,
+            self.opts.xml_namespace.as_deref(),
+        );
+        std::io::stdout().write_all(schema.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl FromArgs for Schema {
+    fn register_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        app.register::<fmt::FormatOpts>()
+    }
+
+    fn parse_args(matches: &ArgMatches) -> Result<Self> {
+        let opts = fmt::FormatOpts::parse_args(matches)?;
+        Ok(Schema { opts })
+    }
+}
+
+#[derive(Debug)]
+struct Stats {
+    format: fmt::Format,
+    include_literals: bool,
+    io: io::IOOpts,
+}
+
+impl Stats {
+    const INCLUDE_LITERALS: &'static str = "include-literals";
+
+    fn run(self) -> Result<()> {
+        let source = self.io.input()?.contents()?;
+        let doc = prosidy::parse::parse_document(&source)?;
+        let stats = stats::Stats::new(&doc, self.include_literals);
+        let output = self.io.output()?;
+        self.format.write(output, &stats)
+    }
+}
+
+impl FromArgs for Stats {
+    fn register_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let include_literals = Arg::with_name(Stats::INCLUDE_LITERALS)
+            .help("Count words inside literal blocks")
+            .long("include-literals");
+        app.arg(include_literals)
+            .register::<fmt::Format>()
+            .register::<io::IOOpts>()
+    }
+
+    fn parse_args(matches: &ArgMatches) -> Result<Self> {
+        let format = fmt::Format::parse_args(matches)?;
+        let include_literals = matches.is_present(Stats::INCLUDE_LITERALS);
+        let io = io::IOOpts::parse_args(matches)?;
+        Ok(Stats {
+            format,
+            include_literals,
+            io,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Validate {
+    format: fmt::Format,
+    io: io::IOOpts,
+    rules: validate::Rules,
+}
+
+impl Validate {
+    const ALLOW_DUPLICATE_IDS: &'static str = "allow-duplicate-ids";
+    const ALLOWED_TAGS: &'static str = "allowed-tags";
+    const REQUIRE_CONTENT: &'static str = "require-content";
+    const REQUIRE_PROP: &'static str = "require-prop";
+
+    fn run(self) -> Result<()> {
+        let mut report = validate::Report::default();
+        match self.io.input_path().filter(|path| path.is_dir()) {
+            Some(dir) => {
+                for path in io::tree::files(dir, dir)? {
+                    if path.extension() == Some("pro".as_ref()) {
+                        let path = dir.join(path);
+                        let source = io::Input::open(&path)?.contents()?;
+                        report.check_source(Some(&path), &source, &self.rules);
+                    }
+                }
+            }
+            None => {
+                let source = self.io.input()?.contents()?;
+                report.check_source(self.io.input_path(), &source, &self.rules);
+            }
+        }
+        self.format.write(self.io.output()?, &report)?;
+        anyhow::ensure!(report.is_empty(), "found {} problem(s)", report.len());
+        Ok(())
+    }
+}
+
+impl FromArgs for Validate {
+    fn register_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let allow_duplicate_ids = Arg::with_name(Validate::ALLOW_DUPLICATE_IDS)
+            .help("Don't report id settings used more than once in a document")
+            .long("allow-duplicate-ids");
+        let allowed_tags = Arg::with_name(Validate::ALLOWED_TAGS)
+            .help("Report tags not named in FILE, which lists one tag name per line")
+            .long("allowed-tags")
+            .value_name("FILE");
+        let require_content = Arg::with_name(Validate::REQUIRE_CONTENT)
+            .help("Report TAG tags without any content")
+            .long("require-content")
+            .value_name("TAG")
+            .number_of_values(1)
+            .multiple(true);
+        let require_prop = Arg::with_name(Validate::REQUIRE_PROP)
+            .help("Report documents without the header property KEY")
+            .long("require-prop")
+            .value_name("KEY")
+            .number_of_values(1)
+            .multiple(true);
+        app.arg(allow_duplicate_ids)
+            .arg(allowed_tags)
+            .arg(require_content)
+            .arg(require_prop)
+            .register::<fmt::Format>()
+            .register::<io::IOOpts>()
+    }
+
+    fn parse_args(matches: &ArgMatches) -> Result<Self> {
+        let format = fmt::Format::parse_args(matches)?;
+        let io = io::IOOpts::parse_args(matches)?;
+        let allowed_tags = match matches.value_of(Validate::ALLOWED_TAGS) {
+            Some(path) => {
+                let list = fs::read_to_string(path)
+                    .with_context(|| format!("failed to read allowed tags from {:?}", path))?;
+                let names = list.lines().map(str::trim).filter(|name| !name.is_empty());
+                Some(names.map(String::from).collect())
+            }
+            None => None,
+        };
+        let values = |name| -> Vec<String> {
+            let values = matches.values_of(name).into_iter().flatten();
+            values.map(String::from).collect()
+        };
+        let rules = validate::Rules {
+            allowed_tags,
+            required_props: values(Validate::REQUIRE_PROP),
+            require_content: values(Validate::REQUIRE_CONTENT),
+            unique_ids: !matches.is_present(Validate::ALLOW_DUPLICATE_IDS),
+        };
+        Ok(Validate { format, io, rules })
     }
 }
 
 mod args;
+mod atom;
+mod diff;
 mod fmt;
 mod io;
+mod jsonld;
 mod manifest;
+mod sitemap;
+mod split;
+mod stats;
+mod timestamp;
+mod validate;
+mod watch;
 
 #[cfg(feature = "server")]
 mod mediatype;
 
 #[cfg(feature = "server")]
 mod serve;
+
+#[test]
+fn compile_tree() {
+    let root = std::env::temp_dir().join(format!("prosidy-compile-tree-{}", std::process::id()));
+    let (input, output) = (root.join("content"), root.join("dist"));
+    fs::create_dir_all(input.join("sub")).unwrap();
+    fs::write(input.join("index.pro"), "title: Home\n---\nHello\n").unwrap();
+    fs::write(input.join("sub/page.pro"), "---\nPage\n").unwrap();
+    fs::write(input.join("sub/broken.pro"), "---\n#oops{\n").unwrap();
+    fs::write(input.join("style.css"), "p {}").unwrap();
+    let app = App::new("test").register::<Compile>();
+    let matches = app.get_matches_from(vec![
+        "test".as_ref(),
+        input.as_os_str(),
+        "-o".as_ref(),
+        output.as_os_str(),
+        "-f".as_ref(),
+        "json".as_ref(),
+    ]);
+    let result = Compile::parse_args(&matches).unwrap().compile();
+    let index = fs::read_to_string(output.join("index.json")).unwrap();
+    let page_exists = output.join("sub/page.json").is_file();
+    let css_exists = output.join("style.css").exists();
+    fs::remove_dir_all(&root).unwrap();
+    assert_eq!("1 of 3 file(s) failed", result.unwrap_err().to_string());
+    assert!(index.contains("Home"));
+    assert!(page_exists);
+    assert!(!css_exists, "other files are only copied with --copy-other");
+}
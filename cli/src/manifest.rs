@@ -3,17 +3,19 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
 use std::fs::{self, FileType};
 use std::path::{Path, PathBuf};
-use std::pin::Pin;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use anyhow::Result;
 use prosidy::parse::parse_meta;
 use prosidy::xml::quick_xml::events::{BytesEnd, BytesStart, Event};
-use prosidy::xml::{quick_xml::Result as XMLResult, XML};
-use prosidy::{PropSet, Text};
+use prosidy::xml::{insert_props, quick_xml::Result as XMLResult, XMLConfig, XML};
+use prosidy::{DocumentProps, Key};
+use rayon::prelude::*;
 use serde::ser::Serializer;
 use serde::Serialize;
 
@@ -22,42 +24,60 @@ use futures::prelude::*;
 #[cfg(feature = "server")]
 use tokio_fs as tfs;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, PartialEq)]
 /// A collection of [`PropSet`] nodes extracted from the headers of each Prosidy file in a directory.
-pub struct Manifest(HashMap<PathBuf, Entry>);
+///
+/// Files which can't be read are skipped, just as those which don't parse are, and are listed in
+/// its [`warnings`](Manifest::warnings) instead, so that one bad file doesn't keep the rest of a
+/// directory from being read.
+pub struct Manifest {
+    entries: HashMap<PathBuf, Entry>,
+    /// The files, or directories, which couldn't be read, and why.
+    warnings: Vec<(PathBuf, String)>,
+}
 
 impl Manifest {
-    const TAG_MANIFEST: &'static str = "prosidy:manifest";
-    const TAG_ITEM: &'static str = "prosidy:item";
-    const ATTR_PATH: &'static str = "prosidy:path";
+    const TAG_MANIFEST: &'static str = "manifest";
+    const TAG_ITEM: &'static str = "item";
+    const ATTR_PATH: &'static str = "path";
 
-    pub fn read<P: AsRef<Path>>(path: P, follow_symlinks: bool) -> Result<Manifest> {
+    /// Reads the header of each Prosidy file in a directory, and in its subdirectories too if
+    /// `recursive` is set. Entries are keyed by their path relative to the directory, and only
+    /// those matching every filter are kept.
+    pub fn read<P: AsRef<Path>>(
+        path: P,
+        follow_symlinks: bool,
+        recursive: bool,
+        filters: &[Filter],
+    ) -> Result<Manifest> {
         let root_path: Arc<Path> = Arc::from(path.as_ref().canonicalize()?);
         anyhow::ensure!(
             root_path.is_dir(),
             "Manifests can only be read from a directory"
         );
         log::info!("reading manifest from {:?}", root_path);
-        let dir_entries = fs::read_dir(Arc::clone(&root_path))?;
-        let map: Result<HashMap<PathBuf, Entry>> = dir_entries
-            .map(|dir_entry| {
-                let dir_entry = dir_entry.map_err(anyhow::Error::from)?;
-                let file_type = dir_entry.file_type()?;
-                let path = dir_entry.path();
-                if let Some(resolved) = entry_path(file_type, path, follow_symlinks)? {
-                    if let Some(entry) = Entry::try_read(&resolved)? {
-                        let rel_path = resolved
-                            .strip_prefix(&root_path)
-                            .expect("all paths to be a child of the root path")
-                            .to_path_buf();
-                        return Ok(Some((rel_path, entry)));
-                    }
-                }
-                Ok(None) as Result<Option<_>>
+        let mut manifest = Manifest::default();
+        // Listing the directory is cheap; reading each file's header is what's worth spreading
+        // across threads.
+        let paths = entry_paths(&root_path, follow_symlinks, recursive, &mut manifest)?;
+        let read: Vec<_> = paths
+            .into_par_iter()
+            .map(|path| match Entry::try_read(&path) {
+                Ok(entry) => Ok(entry
+                    .filter(|entry| filters.iter().all(|filter| filter.matches(entry)))
+                    .map(|entry| (path, entry))),
+                Err(error) => Err((path, error)),
             })
-            .flat_map(Result::transpose)
             .collect();
-        Ok(Manifest(map?))
+        for result in read {
+            match result {
+                Ok(Some((path, entry))) => manifest.insert(&root_path, &path, entry),
+                Ok(None) => {}
+                Err((path, error)) => manifest.warn(&root_path, &path, error),
+            }
+        }
+        manifest.summarize();
+        Ok(manifest)
     }
 
     #[cfg(feature = "server")]
@@ -68,106 +88,174 @@ impl Manifest {
             "Manifests can only be read from a directory"
         );
         log::info!("reading manifest from {:?}", root_path);
-        let dir_entries = tfs::read_dir(Arc::clone(&root_path)).await?;
-        let map = dir_entries
-            .try_filter_map(|dir_entry| {
-                async move {
-                    let file_type = dir_entry.file_type().await?;
-                    let path = dir_entry.path();
-                    entry_path(file_type, path, follow_symlinks)
+        let mut manifest = Manifest::default();
+        let mut dir_entries = tfs::read_dir(Arc::clone(&root_path)).await?;
+        while let Some(dir_entry) = dir_entries.next().await {
+            let dir_entry = match dir_entry {
+                Ok(dir_entry) => dir_entry,
+                Err(error) => {
+                    manifest.warn(&root_path, &root_path, error);
+                    continue;
                 }
-            })
-            .map_err(anyhow::Error::from)
-            .try_filter_map(|path| {
-                let root_path = Arc::clone(&root_path);
-                async move {
-                    let opt_entry = Entry::try_read_async(&path).await?;
-                    Ok(opt_entry.map(|entry| {
-                        let rel_path = path
-                            .strip_prefix(root_path)
-                            .expect("all paths to be a child of the root path")
-                            .to_path_buf();
-                        (rel_path, entry)
-                    }))
+            };
+            let path = dir_entry.path();
+            let read = async {
+                let file_type = dir_entry.file_type().await?;
+                match entry_path(file_type, dir_entry.path(), follow_symlinks)? {
+                    Some(path) => Entry::try_read_async(&path).await,
+                    None => Ok(None),
                 }
-            })
-            .try_collect()
-            .await?;
-        Ok(Manifest(map))
+            };
+            match read.await {
+                Ok(Some(entry)) => manifest.insert(&root_path, &path, entry),
+                Ok(None) => {}
+                Err(error) => manifest.warn(&root_path, &path, error),
+            }
+        }
+        manifest.summarize();
+        Ok(manifest)
+    }
+
+    /// The files, or directories, which were skipped because they couldn't be read, along with
+    /// why. Paths are relative to the manifest's directory, which is itself `.`.
+    pub fn warnings(&self) -> &[(PathBuf, String)] {
+        &self.warnings
+    }
+
+    /// Iterates over each entry, along with its path relative to the manifest's directory.
+    pub fn iter(&self) -> impl Iterator<Item = (&Path, &Entry)> {
+        self.entries
+            .iter()
+            .map(|(path, entry)| (path.as_path(), entry))
+    }
+
+    /// Checks for an entry by its path relative to the manifest's directory.
+    #[cfg(feature = "server")]
+    pub fn contains<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.entries.contains_key(path.as_ref())
+    }
+
+    fn insert(&mut self, root_path: &Path, path: &Path, entry: Entry) {
+        let rel_path = path
+            .strip_prefix(root_path)
+            .expect("all paths to be a child of the root path")
+            .to_path_buf();
+        self.entries.insert(rel_path, entry);
+    }
+
+    /// Records that `path`, beneath `root_path`, was skipped. Only the summary is logged as a
+    /// warning, since the warnings themselves are left to the manifest's reader to report.
+    fn warn<E: Display>(&mut self, root_path: &Path, path: &Path, error: E) {
+        log::debug!("Failed to read {:?}: {}", path, error);
+        let rel_path = match path.strip_prefix(root_path) {
+            Ok(rel_path) if rel_path.as_os_str().is_empty() => Path::new("."),
+            Ok(rel_path) => rel_path,
+            Err(_) => path,
+        };
+        self.warnings
+            .push((rel_path.to_path_buf(), error.to_string()));
+    }
+
+    fn summarize(&self) {
+        if !self.warnings.is_empty() {
+            log::warn!(
+                "Skipped {} file(s) which couldn't be read while reading the manifest",
+                self.warnings.len()
+            );
+        }
     }
 }
 
 impl XML for Manifest {
-    fn to_events<F>(&self, emit: &mut F) -> XMLResult<()>
+    fn to_events<F>(&self, config: &XMLConfig, emit: &mut F) -> XMLResult<()>
     where
         F: for<'a> FnMut(Event<'a>) -> XMLResult<()>,
     {
-        let start = BytesStart::borrowed_name(Manifest::TAG_MANIFEST.as_bytes());
+        let start = BytesStart::owned_name(config.name(Manifest::TAG_MANIFEST));
         emit(Event::Start(start))?;
-        for (path, entry) in self.0.iter() {
-            let mut start = BytesStart::borrowed_name(Manifest::TAG_ITEM.as_bytes());
+        for (path, entry) in self.entries.iter() {
+            let mut start = BytesStart::owned_name(config.name(Manifest::TAG_ITEM));
             let path_str = path.to_string_lossy();
-            start.push_attribute((Manifest::ATTR_PATH, path_str.as_ref()));
-            for (name, opt_val) in entry.props.iter() {
-                let val = opt_val.unwrap_or(Text::EMPTY);
-                start.push_attribute((name.as_str(), val.as_str()));
-            }
+            start.push_attribute((config.name(Manifest::ATTR_PATH).as_str(), path_str.as_ref()));
+            insert_props(&mut start, &entry.props, config)?;
             emit(Event::Empty(start))?;
         }
-        let end = BytesEnd::borrowed(Manifest::TAG_MANIFEST.as_bytes());
+        let end = BytesEnd::owned(config.name(Manifest::TAG_MANIFEST).into_bytes());
         emit(Event::End(end))
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Entry {
-    source: Pin<Arc<str>>,
-    props: PropSet<'static>,
+    props: DocumentProps<'static>,
+    modified: Option<SystemTime>,
 }
 
 impl Entry {
+    pub fn props(&self) -> &DocumentProps<'static> {
+        &self.props
+    }
+
+    /// When the file was last modified, if the platform records it.
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
     pub fn try_read<P: AsRef<Path>>(path: P) -> Result<Option<Entry>> {
         let path = path.as_ref();
         log::info!("reading the header of {:?}", path);
-        // Reads the filepath into a string, then wraps it in an atomic reference counter & pins
-        // it. This lets us treat `parsed` as owned by an Entry via coercing it's lifetime.
-        let string = fs::read_to_string(path)?;
-        let source = Pin::new(Arc::from(string));
-        // Read metadata from the source string. It will be returned with the anonymous lifetime
-        // which can't be kept past this functions end, so we'll transmute it into the correct
-        // lifetime.
-        let parsed: PropSet<'_> = match parse_meta(&source) {
-            Ok(parsed) => parsed,
-            Err(err) => {
-                log::warn!("Failed to parse {:?} as a Prosidy file: {}", path, err);
-                return Ok(None);
-            }
+        let source = match Entry::decode(path, fs::read(path)?)? {
+            Some(source) => source,
+            None => return Ok(None),
         };
-        let props: PropSet<'static> = unsafe { std::mem::transmute(parsed) };
-        Ok(Some(Entry { source, props }))
+        let modified = fs::metadata(path).and_then(|meta| meta.modified()).ok();
+        Ok(Entry::from_source(path, &source, modified))
     }
 
     #[cfg(feature = "server")]
     pub async fn try_read_async<P: AsRef<Path>>(path: P) -> Result<Option<Entry>> {
         let path = path.as_ref();
         log::info!("reading the header of {:?}", path);
-        // Reads the filepath into a string, then wraps it in an atomic reference counter & pins
-        // it. This lets us treat `parsed` as owned by an Entry via coercing it's lifetime.
-        let bytes = tfs::read(path).await?;
-        let string = String::from_utf8(bytes)?;
-        let source = Pin::new(Arc::from(string));
-        // Read metadata from the source string. It will be returned with the anonymous lifetime
-        // which can't be kept past this functions end, so we'll transmute it into the correct
-        // lifetime.
-        let parsed: PropSet<'_> = match parse_meta(&source) {
-            Ok(parsed) => parsed,
+        let source = match Entry::decode(path, tfs::read(path).await?)? {
+            Some(source) => source,
+            None => return Ok(None),
+        };
+        let modified = tfs::metadata(path).await.and_then(|meta| meta.modified()).ok();
+        Ok(Entry::from_source(path, &source, modified))
+    }
+
+    /// Decodes a file's contents as UTF-8. Any other file which isn't, like an image, is no more a
+    /// Prosidy document than one which doesn't parse, so only a `.pro` file is an error.
+    fn decode(path: &Path, bytes: Vec<u8>) -> Result<Option<String>> {
+        match String::from_utf8(bytes) {
+            Ok(source) => Ok(Some(source)),
+            Err(err) if path.extension() == Some("pro".as_ref()) => Err(err.into()),
+            Err(_) => {
+                log::debug!("Skipping {:?}, which isn't UTF-8 text", path);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Reads metadata from the source string, copying it out so the entry outlives the source.
+    fn from_source(path: &Path, source: &str, modified: Option<SystemTime>) -> Option<Entry> {
+        match parse_meta(source) {
+            Ok(parsed) => Some(Entry {
+                props: parsed.into_owned(),
+                modified,
+            }),
             Err(err) => {
                 log::warn!("Failed to parse {:?} as a Prosidy file: {}", path, err);
-                return Ok(None);
+                None
             }
-        };
-        let props: PropSet<'static> = unsafe { std::mem::transmute(parsed) };
-        Ok(Some(Entry { source, props }))
+        }
+    }
+}
+
+/// Only the entries are serialized, as a map from their paths to their headers.
+impl Serialize for Manifest {
+    fn serialize<S: Serializer>(&self, ser: S) -> std::result::Result<S::Ok, S::Error> {
+        self.entries.serialize(ser)
     }
 }
 
@@ -177,6 +265,102 @@ impl Serialize for Entry {
     }
 }
 
+/// A condition on a document's header, deciding whether it's included in a manifest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filter {
+    key: Key,
+    value: Option<String>,
+    include: bool,
+}
+
+impl Filter {
+    /// Keeps entries whose header has the property `key`, or with `key=value`, the setting `key`
+    /// equal to `value`.
+    pub fn include(spec: &str) -> Filter {
+        Filter::parse(spec, true)
+    }
+
+    /// Keeps entries which would not be kept by [`Filter::include`].
+    pub fn exclude(spec: &str) -> Filter {
+        Filter::parse(spec, false)
+    }
+
+    fn parse(spec: &str, include: bool) -> Filter {
+        let mut parts = spec.splitn(2, '=');
+        let key = Key::new(parts.next().unwrap_or_default());
+        let value = parts.next().map(String::from);
+        Filter {
+            key,
+            value,
+            include,
+        }
+    }
+
+    fn matches(&self, entry: &Entry) -> bool {
+        let found = match self.value {
+            Some(ref value) => {
+                entry.props.lookup_str(&self.key) == Some(value.as_str())
+            }
+            None => entry.props.is_set(&self.key) || entry.props.lookup(&self.key).is_some(),
+        };
+        found == self.include
+    }
+}
+
+/// Lists the files under `root` which may hold a manifest entry. Anything beneath it which can't
+/// be listed is skipped, with a warning added to `manifest`.
+///
+/// When recursing, each directory is only read once, since following symlinks may otherwise
+/// lead in circles.
+fn entry_paths(
+    root: &Path,
+    follow_symlinks: bool,
+    recursive: bool,
+    manifest: &mut Manifest,
+) -> Result<Vec<PathBuf>> {
+    let mut visited = HashSet::new();
+    visited.insert(root.to_path_buf());
+    let mut dirs = vec![root.to_path_buf()];
+    let mut paths = Vec::new();
+    while let Some(dir) = dirs.pop() {
+        let dir_entries = match fs::read_dir(&dir) {
+            Ok(dir_entries) => dir_entries,
+            Err(error) if dir.as_path() == root => return Err(error.into()),
+            Err(error) => {
+                manifest.warn(root, &dir, error);
+                continue;
+            }
+        };
+        for dir_entry in dir_entries {
+            let dir_entry = match dir_entry {
+                Ok(dir_entry) => dir_entry,
+                Err(error) => {
+                    manifest.warn(root, &dir, error);
+                    continue;
+                }
+            };
+            let path = dir_entry.path();
+            let listed = dir_entry.file_type().and_then(|file_type| {
+                let is_dir = file_type.is_dir()
+                    || (follow_symlinks && file_type.is_symlink() && path.is_dir());
+                if !is_dir {
+                    return entry_path(file_type, path.clone(), follow_symlinks);
+                }
+                if recursive && visited.insert(path.canonicalize()?) {
+                    dirs.push(path.clone());
+                }
+                Ok(None)
+            });
+            match listed {
+                Ok(Some(path)) => paths.push(path),
+                Ok(None) => {}
+                Err(error) => manifest.warn(root, &path, error),
+            }
+        }
+    }
+    Ok(paths)
+}
+
 fn entry_path(
     file_type: FileType,
     path: PathBuf,
@@ -195,3 +379,119 @@ fn entry_path(
     };
     Ok(path)
 }
+
+#[test]
+fn read_many_files() {
+    let root = std::env::temp_dir().join(format!("prosidy-manifest-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    for i in 0..200 {
+        let source = format!("title: Document {}\nindex: {}\n---\nHello\n", i, i);
+        fs::write(root.join(format!("doc{}.pro", i)), source).unwrap();
+    }
+    fs::write(root.join("not-prosidy.pro"), "no header here").unwrap();
+    let manifest = Manifest::read(&root, false, false, &[]).unwrap();
+    let mut sequential = HashMap::new();
+    for entry in fs::read_dir(&root).unwrap() {
+        let path = entry.unwrap().path();
+        if let Some(entry) = Entry::try_read(&path).unwrap() {
+            sequential.insert(path.strip_prefix(&root).unwrap().to_path_buf(), entry);
+        }
+    }
+    fs::remove_dir_all(&root).unwrap();
+    assert_eq!(200, manifest.entries.len());
+    let sequential = Manifest {
+        entries: sequential,
+        warnings: Vec::new(),
+    };
+    assert_eq!(sequential, manifest);
+}
+
+#[test]
+fn read_recursive() {
+    let root = std::env::temp_dir().join(format!("prosidy-manifest-rec-{}", std::process::id()));
+    fs::create_dir_all(root.join("blog/2020")).unwrap();
+    fs::write(root.join("index.pro"), "title: Home\n---\n").unwrap();
+    fs::write(root.join("blog/2020/post.pro"), "title: Post\n---\n").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&root, root.join("blog/loop")).unwrap();
+    let flat = Manifest::read(&root, true, false, &[]).unwrap();
+    let nested = Manifest::read(&root, true, true, &[]).unwrap();
+    fs::remove_dir_all(&root).unwrap();
+    let mut flat_paths: Vec<_> = flat.entries.keys().cloned().collect();
+    flat_paths.sort();
+    let mut nested_paths: Vec<_> = nested.entries.keys().cloned().collect();
+    nested_paths.sort();
+    assert_eq!(vec![PathBuf::from("index.pro")], flat_paths);
+    assert_eq!(
+        vec![PathBuf::from("blog/2020/post.pro"), PathBuf::from("index.pro")],
+        nested_paths
+    );
+}
+
+#[test]
+fn read_filtered() {
+    let root = std::env::temp_dir().join(format!("prosidy-manifest-filter-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("draft.pro"), "draft\nlang: en\n---\n").unwrap();
+    fs::write(root.join("french.pro"), "published\nlang: fr\n---\n").unwrap();
+    fs::write(root.join("post.pro"), "published\nlang: en\n---\n").unwrap();
+    let read = |filters: &[Filter]| {
+        let manifest = Manifest::read(&root, false, false, filters).unwrap();
+        let mut paths: Vec<_> = manifest.entries.keys().cloned().collect();
+        paths.sort();
+        paths
+    };
+    let drafts = read(&[Filter::include("draft")]);
+    let english = read(&[Filter::include("lang=en"), Filter::exclude("draft")]);
+    let all = read(&[]);
+    fs::remove_dir_all(&root).unwrap();
+    assert_eq!(vec![PathBuf::from("draft.pro")], drafts);
+    assert_eq!(vec![PathBuf::from("post.pro")], english);
+    assert_eq!(3, all.len());
+}
+
+#[test]
+fn read_past_unreadable_files() {
+    let root = std::env::temp_dir().join(format!("prosidy-manifest-bad-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("good.pro"), "title: Good\n---\n").unwrap();
+    fs::write(root.join("binary.pro"), b"title: \xff\xfe\n---\n").unwrap();
+    fs::write(root.join("logo.png"), b"\x89PNG\r\n\x1a\n\xff\xfe").unwrap();
+    // Permissions don't stop the superuser, so the locked file may well be read.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let locked = root.join("locked.pro");
+        fs::write(&locked, "title: Locked\n---\n").unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+    }
+    let manifest = Manifest::read(&root, false, false, &[]);
+    fs::remove_dir_all(&root).unwrap();
+    let manifest = manifest.unwrap();
+    assert!(manifest.entries.contains_key(Path::new("good.pro")));
+    assert!(!manifest.entries.contains_key(Path::new("binary.pro")));
+    // Skipped paths are relative to the manifest's directory, like its entries.
+    let skipped: Vec<_> = manifest.warnings().iter().map(|(path, _)| path).collect();
+    assert!(
+        skipped.contains(&&PathBuf::from("binary.pro")),
+        "{:?}",
+        skipped
+    );
+    assert!(
+        !skipped.contains(&&PathBuf::from("logo.png")),
+        "{:?}",
+        skipped
+    );
+    assert_eq!(3, manifest.entries.len() + manifest.warnings.len());
+}
+
+#[test]
+fn entry_outlives_source() {
+    let entry = {
+        let source = String::from("title: Kept\n---\n");
+        Entry::from_source(Path::new("kept.pro"), &source, None).unwrap()
+    };
+    let title = entry.props().title().unwrap();
+    assert_eq!(prosidy::Text::from("Kept"), title);
+    assert!(title.owned());
+}
@@ -3,6 +3,7 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use std::collections::HashMap;
 use std::ops::Deref;
 use std::path::Path;
 
@@ -12,51 +13,112 @@ use phf::{phf_map, Map};
 
 use StaticMime::*;
 
-pub fn infer_media_type<P: AsRef<Path>>(path: P) -> &'static Mime {
-    let path = path.as_ref();
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .and_then(|ext| MEDIA_TYPES.get(ext))
-        .map(|mime| mime.deref())
+/// Infers the media type of a file from its extension, looking in `overrides` before the
+/// built-in types.
+pub fn infer_media_type<P: AsRef<Path>>(overrides: &HashMap<String, Mime>, path: P) -> &Mime {
+    let ext = match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => ext,
+        None => return DEFAULT_MEDIA_TYPE,
+    };
+    overrides
+        .get(ext)
+        .or_else(|| MEDIA_TYPES.get(ext).map(|mime| mime.deref()))
         .unwrap_or(DEFAULT_MEDIA_TYPE)
 }
 
 const DEFAULT_MEDIA_TYPE: &Mime = &mime::APPLICATION_OCTET_STREAM;
 
 static MEDIA_TYPES: Map<&'static str, StaticMime> = phf_map! {
-    "css"   => Static(mime::TEXT_CSS),
-    "gif"   => Static(mime::IMAGE_GIF),
-    "html"  => Static(mime::TEXT_HTML),
-    "jpeg"  => Static(mime::IMAGE_JPEG),
-    "jpg"   => Static(mime::IMAGE_JPEG),
-    "js"    => Static(mime::APPLICATION_JAVASCRIPT),
-    "json"  => Static(mime::APPLICATION_JSON),
-    "pdf"   => Static(mime::APPLICATION_PDF),
-    "png"   => Static(mime::IMAGE_PNG),
-    "svg"   => Static(mime::IMAGE_SVG),
-    "txt"   => Static(mime::TEXT_PLAIN),
-    "woff"  => Static(mime::FONT_WOFF),
-    "woff2" => Static(mime::FONT_WOFF2),
-    "xml"   => Static(mime::TEXT_XML),
-    "xsd"   => Static(mime::TEXT_XML),
+    "atom"   => Lazy(&APPLICATION_ATOM),
+    "avif"   => Lazy(&IMAGE_AVIF),
+    "css"    => Static(mime::TEXT_CSS),
+    "gif"    => Static(mime::IMAGE_GIF),
+    "html"   => Static(mime::TEXT_HTML),
+    "ico"    => Lazy(&IMAGE_ICON),
+    "jpeg"   => Static(mime::IMAGE_JPEG),
+    "jpg"    => Static(mime::IMAGE_JPEG),
+    "js"     => Static(mime::APPLICATION_JAVASCRIPT),
+    "json"   => Static(mime::APPLICATION_JSON),
+    "jsonld" => Lazy(&APPLICATION_LD_JSON),
+    "mjs"    => Static(mime::APPLICATION_JAVASCRIPT),
+    "otf"    => Lazy(&FONT_OTF),
+    "pdf"    => Static(mime::APPLICATION_PDF),
+    "png"    => Static(mime::IMAGE_PNG),
+    "svg"    => Static(mime::IMAGE_SVG),
+    "ttf"    => Lazy(&FONT_TTF),
+    "txt"    => Static(mime::TEXT_PLAIN),
+    "wasm"   => Lazy(&APPLICATION_WASM),
+    "webp"   => Lazy(&IMAGE_WEBP),
+    "woff"   => Static(mime::FONT_WOFF),
+    "woff2"  => Static(mime::FONT_WOFF2),
+    "xml"    => Static(mime::TEXT_XML),
+    "xsd"    => Static(mime::TEXT_XML),
 
-    "cbor"  => Lazy(&APPLICATION_CBOR),
-    "xsl"   => Lazy(&APPLICATION_XSLT),
-    "xslt"  => Lazy(&APPLICATION_XSLT),
+    "cbor"   => Lazy(&APPLICATION_CBOR),
+    "md"     => Lazy(&TEXT_MARKDOWN),
+    "xsl"    => Lazy(&APPLICATION_XSLT),
+    "xslt"   => Lazy(&APPLICATION_XSLT),
 };
 
 lazy_static! {
+    pub static ref APPLICATION_ATOM: Mime = {
+        "application/atom+xml"
+            .parse::<Mime>()
+            .expect("Failed to instantiate media type")
+    };
     pub static ref APPLICATION_CBOR: Mime = {
         "application/cbor"
             .parse::<Mime>()
             .expect("Failed to instantiate media type")
     };
+    pub static ref APPLICATION_LD_JSON: Mime = {
+        "application/ld+json"
+            .parse::<Mime>()
+            .expect("Failed to instantiate media type")
+    };
+    pub static ref APPLICATION_WASM: Mime = {
+        "application/wasm"
+            .parse::<Mime>()
+            .expect("Failed to instantiate media type")
+    };
     pub static ref APPLICATION_XSLT: Mime = {
         "application/xslt+xml"
             .parse::<Mime>()
             .expect("Failed to instantiate media type")
     };
-    pub static ref CBOR: Name<'static> = { APPLICATION_CBOR.subtype() };
+    pub static ref FONT_OTF: Mime = {
+        "font/otf"
+            .parse::<Mime>()
+            .expect("Failed to instantiate media type")
+    };
+    pub static ref FONT_TTF: Mime = {
+        "font/ttf"
+            .parse::<Mime>()
+            .expect("Failed to instantiate media type")
+    };
+    pub static ref IMAGE_AVIF: Mime = {
+        "image/avif"
+            .parse::<Mime>()
+            .expect("Failed to instantiate media type")
+    };
+    pub static ref IMAGE_ICON: Mime = {
+        "image/vnd.microsoft.icon"
+            .parse::<Mime>()
+            .expect("Failed to instantiate media type")
+    };
+    pub static ref IMAGE_WEBP: Mime = {
+        "image/webp"
+            .parse::<Mime>()
+            .expect("Failed to instantiate media type")
+    };
+    pub static ref TEXT_MARKDOWN: Mime = {
+        "text/markdown"
+            .parse::<Mime>()
+            .expect("Failed to instantiate media type")
+    };
+    pub static ref CBOR: Name<'static> = APPLICATION_CBOR.subtype();
+    pub static ref LD: Name<'static> = APPLICATION_LD_JSON.subtype();
+    pub static ref MARKDOWN: Name<'static> = TEXT_MARKDOWN.subtype();
 }
 
 enum StaticMime {
@@ -69,7 +131,19 @@ impl Deref for StaticMime {
     fn deref(&self) -> &Mime {
         match self {
             Static(mime) => mime,
-            Lazy(lazy) => &lazy,
+            Lazy(lazy) => lazy,
         }
     }
 }
+
+#[test]
+fn media_type_overrides() {
+    let mut overrides = HashMap::new();
+    overrides.insert("js".to_string(), "text/javascript".parse().unwrap());
+    overrides.insert("glb".to_string(), "model/gltf-binary".parse().unwrap());
+    let infer = |path| infer_media_type(&overrides, path).as_ref().to_string();
+    assert_eq!("text/javascript", infer("app.js"));
+    assert_eq!("model/gltf-binary", infer("scene.glb"));
+    assert_eq!("image/webp", infer("photo.webp"));
+    assert_eq!("application/octet-stream", infer("README"));
+}
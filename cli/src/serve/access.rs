@@ -0,0 +1,149 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::io::Write;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use hyper::{header, Body, Method, Response, StatusCode, Uri};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+/// How each request is logged once it's been answered.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LogFormat {
+    /// A line per field, through the logger at the `info` level, or `error` if handling failed.
+    Pretty,
+    /// A JSON object per request, on a line of its own on standard output, whatever the log level.
+    Json,
+}
+
+/// A request, and how it was answered.
+#[derive(Debug)]
+pub struct Access<'r> {
+    pub remote_addr: SocketAddr,
+    pub method: &'r Method,
+    pub uri: &'r Uri,
+    pub status: StatusCode,
+    pub duration: Duration,
+    /// The length of the response body, if it's known up front.
+    pub bytes: Option<u64>,
+    pub error: Option<&'r anyhow::Error>,
+}
+
+impl<'r> Access<'r> {
+    /// Records the response to a request. Its body has to be measured before it's sent, so the
+    /// length comes from the `Content-Length` header, or is zero for a `HEAD` request.
+    pub fn new(
+        remote_addr: SocketAddr,
+        method: &'r Method,
+        uri: &'r Uri,
+        duration: Duration,
+        response: &Response<Body>,
+        error: Option<&'r anyhow::Error>,
+    ) -> Self {
+        let bytes = if method == Method::HEAD {
+            Some(0)
+        } else {
+            response
+                .headers()
+                .get(header::CONTENT_LENGTH)
+                .and_then(|len| len.to_str().ok()?.parse().ok())
+        };
+        Access {
+            remote_addr,
+            method,
+            uri,
+            status: response.status(),
+            duration,
+            bytes,
+            error,
+        }
+    }
+
+    pub fn log(&self, format: LogFormat) {
+        match format {
+            LogFormat::Pretty => self.log_pretty(),
+            LogFormat::Json => {
+                let mut line = match serde_json::to_vec(self) {
+                    Ok(line) => line,
+                    Err(error) => return log::error!("failed to encode an access log: {}", error),
+                };
+                line.push(b'\n');
+                if let Err(error) = std::io::stdout().write_all(&line) {
+                    log::error!("failed to write an access log: {}", error);
+                }
+            }
+        }
+    }
+
+    fn log_pretty(&self) {
+        match self.error {
+            None => log::info!(
+                "addr={addr:?}\nmethod={method:?}\nuri={uri:?}\nduration={dur:?}\nstatus={status:}",
+                addr = self.remote_addr,
+                method = self.method,
+                uri = self.uri,
+                dur = self.duration,
+                status = self.status,
+            ),
+            Some(error) => log::error!(
+                "addr={addr:?}\nmethod={method:?}\nuri={uri:?}\nduration={dur:?}\nerror={err:}",
+                addr = self.remote_addr,
+                method = self.method,
+                uri = self.uri,
+                dur = self.duration,
+                err = error,
+            ),
+        }
+    }
+}
+
+impl<'r> Serialize for Access<'r> {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        let len = if self.error.is_some() { 7 } else { 6 };
+        let mut ser = ser.serialize_struct("Access", len)?;
+        ser.serialize_field("remote_addr", &self.remote_addr.to_string())?;
+        ser.serialize_field("method", self.method.as_str())?;
+        ser.serialize_field("uri", &self.uri.to_string())?;
+        ser.serialize_field("status", &self.status.as_u16())?;
+        ser.serialize_field("duration_ms", &(self.duration.as_secs_f64() * 1000.0))?;
+        ser.serialize_field("bytes", &self.bytes)?;
+        if let Some(error) = self.error {
+            ser.serialize_field("error", &error.to_string())?;
+        }
+        ser.end()
+    }
+}
+
+#[test]
+fn access_json() {
+    let method = Method::GET;
+    let uri: Uri = "/doc.pro?json".parse().unwrap();
+    let response = Response::builder()
+        .status(404)
+        .header(header::CONTENT_LENGTH, 42)
+        .body(Body::empty())
+        .unwrap();
+    let addr = SocketAddr::from(([127, 0, 0, 1], 50123));
+    let duration = Duration::from_micros(1500);
+    let access = Access::new(addr, &method, &uri, duration, &response, None);
+    assert_eq!(
+        serde_json::json!({
+            "remote_addr": "127.0.0.1:50123",
+            "method": "GET",
+            "uri": "/doc.pro?json",
+            "status": 404,
+            "duration_ms": 1.5,
+            "bytes": 42,
+        }),
+        serde_json::to_value(&access).unwrap()
+    );
+    let error = anyhow::anyhow!("disk on fire");
+    let head = Method::HEAD;
+    let access = Access::new(addr, &head, &uri, duration, &response, Some(&error));
+    let value = serde_json::to_value(&access).unwrap();
+    assert_eq!(0, value["bytes"]);
+    assert_eq!("disk on fire", value["error"]);
+}
@@ -3,22 +3,39 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use std::time::SystemTime;
+
 use hyper::http::header::{self, HeaderValue};
 use hyper::http::response::Builder;
 use hyper::{Body, Request, Response, StatusCode};
 use sha2::{Digest, Sha256};
 
 use super::opts::CacheOpts;
+use crate::timestamp::{format_http_date, parse_http_date, unix_seconds};
 
+/// Validates a request against the `data` a response is derived from, and against the time that
+/// data was last modified, if it's known. Answers with `304 Not Modified` when the client's copy
+/// is still fresh.
 pub fn handle_caching(
     request: &Request<Body>,
     opts: &CacheOpts,
     builder: &mut Builder,
     data: &[u8],
+    modified: Option<SystemTime>,
 ) -> Result<(), anyhow::Result<Response<Body>>> {
     let mut hash = [0; 43];
     hash_bytes_b64(&mut hash, data);
-    check_etag(request, &hash)?;
+    // An entity tag is the stronger validator, so the date is only checked without one.
+    if request.headers().contains_key(header::IF_NONE_MATCH) {
+        check_etag(request, &hash)?;
+    } else if let Some(modified) = modified {
+        check_modified_since(request, modified)?;
+    }
+    if let Some(modified) = modified {
+        let last_modified =
+            HeaderValue::from_str(&format_http_date(modified)).map_err(|e| Err(e.into()))?;
+        builder.header(header::LAST_MODIFIED, last_modified);
+    }
     let etag = HeaderValue::from_bytes(&hash).map_err(|e| Err(e.into()))?;
     let cache_control = if opts.validate {
         HeaderValue::from_str(&format!("max-age={}, no-cache", opts.max_age))
@@ -35,22 +52,42 @@ pub fn handle_caching(
 fn check_etag(request: &Request<Body>, hash: &[u8]) -> Result<(), anyhow::Result<Response<Body>>> {
     if let Some(prev_hash) = request.headers().get(header::IF_NONE_MATCH) {
         if prev_hash.as_bytes() == hash {
-            return Err(Response::builder()
-                .status(StatusCode::NOT_MODIFIED)
-                .body(Body::default())
-                .map_err(anyhow::Error::from));
+            return Err(not_modified());
         }
     }
     Ok(())
 }
 
+fn check_modified_since(
+    request: &Request<Body>,
+    modified: SystemTime,
+) -> Result<(), anyhow::Result<Response<Body>>> {
+    let since = request
+        .headers()
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|since| since.to_str().ok())
+        .and_then(parse_http_date);
+    match since {
+        // HTTP dates are only precise to the second.
+        Some(since) if unix_seconds(modified) <= since => Err(not_modified()),
+        _ => Ok(()),
+    }
+}
+
+fn not_modified() -> anyhow::Result<Response<Body>> {
+    Response::builder()
+        .status(StatusCode::NOT_MODIFIED)
+        .body(Body::default())
+        .map_err(anyhow::Error::from)
+}
+
 fn hash_bytes_b64(buf: &mut [u8; 43], data: &[u8]) {
     let mut digest = Sha256::new();
     digest.input(env!("CARGO_PKG_NAME"));
     digest.input(b"\0");
     digest.input(env!("CARGO_PKG_VERSION"));
     digest.input(b"\0");
-    digest.input(&data);
+    digest.input(data);
     let hash = digest.result();
     base64::encode_config_slice(&hash, base64::STANDARD_NO_PAD, buf);
 }
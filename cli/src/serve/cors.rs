@@ -0,0 +1,62 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use hyper::header::{self, HeaderValue};
+use hyper::{Body, Request, Response, StatusCode};
+
+/// Allows requests from any origin, when given as one of the allowed origins.
+const ANY_ORIGIN: &str = "*";
+
+/// Picks the `Access-Control-Allow-Origin` value for a request, if its origin is allowed.
+pub fn allowed_origin(allowed: &[String], request: &Request<Body>) -> Option<HeaderValue> {
+    if allowed.iter().any(|origin| origin == ANY_ORIGIN) {
+        return Some(HeaderValue::from_static(ANY_ORIGIN));
+    }
+    let origin = request.headers().get(header::ORIGIN)?;
+    if allowed
+        .iter()
+        .any(|allowed| allowed.as_bytes() == origin.as_bytes())
+    {
+        Some(origin.clone())
+    } else {
+        None
+    }
+}
+
+/// Answers a preflight `OPTIONS` request. The CORS headers are left off when the origin isn't
+/// allowed, which is what tells the browser to refuse the real request.
+pub fn preflight(
+    request: &Request<Body>,
+    origin: Option<HeaderValue>,
+) -> hyper::http::Result<Response<Body>> {
+    let mut response = Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .body(Body::empty())?;
+    if let Some(origin) = origin {
+        let headers = response.headers_mut();
+        let methods = HeaderValue::from_static(ALLOWED_METHODS);
+        headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, methods);
+        if let Some(requested) = request
+            .headers()
+            .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+        {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, requested.clone());
+        }
+        allow(&mut response, origin);
+    }
+    Ok(response)
+}
+
+/// Lets the browser share a response with the given origin.
+pub fn allow(response: &mut Response<Body>, origin: HeaderValue) {
+    let headers = response.headers_mut();
+    // Responses vary by origin unless every origin gets the same answer.
+    if origin != ANY_ORIGIN {
+        headers.append(header::VARY, HeaderValue::from_static("Origin"));
+    }
+    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+}
+
+const ALLOWED_METHODS: &str = "GET, HEAD, OPTIONS";
@@ -0,0 +1,291 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! A small gzip encoder, for compressing responses.
+//!
+//! The whole input is written as a single DEFLATE block using the fixed Huffman codes, with
+//! matches found through hash chains. That's a worse ratio than a full encoder, but markup is
+//! repetitive enough that it still pays off well.
+
+/// Compresses `data` into the gzip format.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut bits = BitWriter::with_capacity(data.len() / 2 + 32);
+    bits.out.extend_from_slice(&GZIP_HEADER);
+    // BFINAL = 1, BTYPE = 01 (fixed Huffman codes)
+    bits.write_bits(1, 1);
+    bits.write_bits(1, 2);
+    deflate(&mut bits, data);
+    write_literal(&mut bits, END_OF_BLOCK);
+    let mut out = bits.finish();
+    out.extend_from_slice(&crc32(data).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+fn deflate(bits: &mut BitWriter, data: &[u8]) {
+    let mut head = vec![NONE; HASH_SIZE];
+    let mut prev = vec![NONE; WINDOW_SIZE];
+    let mut pos = 0;
+    while pos < data.len() {
+        let (length, distance) = longest_match(data, pos, &head, &prev);
+        let advance = if length >= MIN_MATCH {
+            write_match(bits, length, distance);
+            length
+        } else {
+            write_literal(bits, u16::from(data[pos]));
+            1
+        };
+        for p in pos..pos + advance {
+            if p + MIN_MATCH <= data.len() {
+                let hash = hash(&data[p..]);
+                prev[p % WINDOW_SIZE] = head[hash];
+                head[hash] = p;
+            }
+        }
+        pos += advance;
+    }
+}
+
+fn longest_match(data: &[u8], pos: usize, head: &[usize], prev: &[usize]) -> (usize, usize) {
+    if pos + MIN_MATCH > data.len() {
+        return (0, 0);
+    }
+    let max_length = MAX_MATCH.min(data.len() - pos);
+    let (mut best_length, mut best_distance) = (0, 0);
+    let mut candidate = head[hash(&data[pos..])];
+    let mut chain = 0;
+    while candidate != NONE && pos - candidate <= WINDOW_SIZE && chain < MAX_CHAIN {
+        let length = data[candidate..]
+            .iter()
+            .zip(&data[pos..pos + max_length])
+            .take_while(|(a, b)| a == b)
+            .count();
+        if length > best_length {
+            best_length = length;
+            best_distance = pos - candidate;
+            if length == max_length {
+                break;
+            }
+        }
+        let next = prev[candidate % WINDOW_SIZE];
+        // Entries in `prev` are overwritten as the window slides, so stop once the chain stops
+        // moving backwards.
+        if next == NONE || next >= candidate {
+            break;
+        }
+        candidate = next;
+        chain += 1;
+    }
+    (best_length, best_distance)
+}
+
+fn write_literal(bits: &mut BitWriter, value: u16) {
+    let (code, len) = match value {
+        0..=143 => (0b0011_0000 + value, 8),
+        144..=255 => (0b1_1001_0000 + value - 144, 9),
+        256..=279 => (value - 256, 7),
+        _ => (0b1100_0000 + value - 280, 8),
+    };
+    bits.write_code(code, len);
+}
+
+fn write_match(bits: &mut BitWriter, length: usize, distance: usize) {
+    let index = LENGTH_BASE.iter().rposition(|&base| base <= length).unwrap();
+    write_literal(bits, 257 + index as u16);
+    bits.write_bits((length - LENGTH_BASE[index]) as u32, LENGTH_EXTRA[index]);
+    let index = DISTANCE_BASE.iter().rposition(|&base| base <= distance).unwrap();
+    bits.write_code(index as u16, 5);
+    bits.write_bits((distance - DISTANCE_BASE[index]) as u32, DISTANCE_EXTRA[index]);
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let value = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+    (value.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 == 1 {
+                0xEDB8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+        }
+        *entry = c;
+    }
+    !data.iter().fold(!0u32, |crc, &byte| {
+        table[((crc ^ u32::from(byte)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Packs values into bytes, least significant bit first, as DEFLATE requires.
+struct BitWriter {
+    out: Vec<u8>,
+    acc: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    fn with_capacity(capacity: usize) -> Self {
+        BitWriter {
+            out: Vec::with_capacity(capacity),
+            acc: 0,
+            count: 0,
+        }
+    }
+
+    fn write_bits(&mut self, value: u32, count: u32) {
+        self.acc |= value << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Huffman codes are packed starting from their most significant bit.
+    fn write_code(&mut self, code: u16, len: u32) {
+        let reversed = u32::from(code.reverse_bits()) >> (16 - len);
+        self.write_bits(reversed, len);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.acc as u8);
+        }
+        self.out
+    }
+}
+
+const GZIP_HEADER: [u8; 10] = [0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+
+const END_OF_BLOCK: u16 = 256;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 64;
+const WINDOW_SIZE: usize = 32768;
+const HASH_BITS: u32 = 15;
+const HASH_SIZE: usize = 1 << HASH_BITS;
+const NONE: usize = usize::MAX;
+
+const LENGTH_BASE: [usize; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u32; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [usize; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u32; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+#[test]
+fn crc32_check_value() {
+    assert_eq!(0xCBF4_3926, crc32(b"123456789"));
+}
+
+#[test]
+fn compress_repetitive_input() {
+    let data = "<prosidy:paragraph>Hello, world!</prosidy:paragraph>".repeat(100);
+    let out = compress(data.as_bytes());
+    assert_eq!(&GZIP_HEADER[..], &out[..10]);
+    assert!(out.len() < data.len() / 10);
+    let trailer = &out[out.len() - 8..];
+    assert_eq!(&crc32(data.as_bytes()).to_le_bytes()[..], &trailer[..4]);
+    assert_eq!(&(data.len() as u32).to_le_bytes()[..], &trailer[4..]);
+}
+
+#[test]
+fn compress_round_trip() {
+    // xorshift64, so the "random" input is the same on every run.
+    let mut state = 0x2545_F491_4F6C_DD1D_u64;
+    let random: Vec<u8> = (0..100_000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect();
+    let markup = "<prosidy:paragraph>Hello, world!</prosidy:paragraph>".repeat(1000);
+    let run = vec![b'a'; 70_000];
+    for data in &[&b""[..], markup.as_bytes(), &run, &random] {
+        assert_eq!(*data, &inflate(&compress(data))[..]);
+    }
+}
+
+/// Decompresses the output of [`compress`], checking its trailer along the way. Only a single
+/// block using the fixed Huffman codes is supported, since that's all the encoder writes.
+#[cfg(test)]
+fn inflate(gzip: &[u8]) -> Vec<u8> {
+    struct BitReader<'a> {
+        data: &'a [u8],
+        pos: usize,
+    }
+
+    impl BitReader<'_> {
+        fn bit(&mut self) -> u32 {
+            let bit = self.data[self.pos / 8] >> (self.pos % 8) & 1;
+            self.pos += 1;
+            u32::from(bit)
+        }
+
+        fn read_bits(&mut self, count: u32) -> u32 {
+            (0..count).fold(0, |acc, i| acc | self.bit() << i)
+        }
+
+        fn read_code(&mut self, len: u32) -> u32 {
+            (0..len).fold(0, |acc, _| acc << 1 | self.bit())
+        }
+    }
+
+    assert_eq!(&GZIP_HEADER[..], &gzip[..10]);
+    let mut bits = BitReader {
+        data: &gzip[10..gzip.len() - 8],
+        pos: 0,
+    };
+    assert_eq!((1, 1), (bits.read_bits(1), bits.read_bits(2)));
+    let mut out = Vec::new();
+    loop {
+        let code = bits.read_code(7);
+        let symbol = if code < 0b001_1000 {
+            code + 256
+        } else {
+            match code << 1 | bits.bit() {
+                code @ 0b0011_0000..=0b1011_1111 => code - 0b0011_0000,
+                code @ 0b1100_0000..=0b1100_0111 => code - 0b1100_0000 + 280,
+                code => (code << 1 | bits.bit()) - 0b1_1001_0000 + 144,
+            }
+        };
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => break,
+            _ => {
+                let index = (symbol - 257) as usize;
+                let length = LENGTH_BASE[index] + bits.read_bits(LENGTH_EXTRA[index]) as usize;
+                let index = bits.read_code(5) as usize;
+                let distance =
+                    DISTANCE_BASE[index] + bits.read_bits(DISTANCE_EXTRA[index]) as usize;
+                let start = out.len() - distance;
+                for i in start..start + length {
+                    out.push(out[i]);
+                }
+            }
+        }
+    }
+    let trailer = &gzip[gzip.len() - 8..];
+    assert_eq!(&crc32(&out).to_le_bytes()[..], &trailer[..4]);
+    assert_eq!(&(out.len() as u32).to_le_bytes()[..], &trailer[4..]);
+    out
+}
@@ -3,6 +3,12 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use hyper::header::{self, HeaderValue};
+use hyper::{Body, Response};
+
+use super::opts::ServeOpts;
+use crate::mediatype::infer_media_type;
+
 macro_rules! http_error {
     ($($name:ident : $code:expr),* $(,)?) => {
         $(
@@ -12,7 +18,8 @@ macro_rules! http_error {
                 ));
                 hyper::Response::builder()
                     .status($code)
-                    .header(hyper::header::CONTENT_TYPE, mime::TEXT_XML.type_().as_str())
+                    .header(hyper::header::CONTENT_TYPE, mime::TEXT_XML.as_ref())
+                    .header(hyper::header::CONTENT_LENGTH, body.len())
                     .body(hyper::Body::from(body))
         })*
     }
@@ -23,5 +30,35 @@ http_error! {
     forbidden: 403,
     not_found: 404,
     menthod_not_allowed: 405,
+    not_acceptable: 406,
+    range_not_satisfiable: 416,
     internal_server_error: 500,
+    service_unavailable: 503,
+    gateway_timeout: 504,
+}
+
+/// Replaces the body of an error response with the page configured for its status, if there is
+/// one. Other headers, such as `Content-Range` or CORS headers, are kept.
+///
+/// If the page can't be read, the built-in body is sent instead.
+pub async fn with_error_page(opts: &ServeOpts, response: Response<Body>) -> Response<Body> {
+    let path = match opts.error_pages.get(&response.status().as_u16()) {
+        Some(path) => path,
+        None => return response,
+    };
+    let page = match tokio::fs::read(path).await {
+        Ok(page) => page,
+        Err(err) => {
+            log::warn!("failed to read the error page {:?}: {}", path, err);
+            return response;
+        }
+    };
+    let (mut parts, _) = response.into_parts();
+    let mime = infer_media_type(&opts.media_types, path);
+    if let Ok(content_type) = HeaderValue::from_str(mime.as_ref()) {
+        parts.headers.insert(header::CONTENT_TYPE, content_type);
+    }
+    parts.headers.remove(header::CONTENT_ENCODING);
+    parts.headers.insert(header::CONTENT_LENGTH, page.len().into());
+    Response::from_parts(parts, page.into())
 }
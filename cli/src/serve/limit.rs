@@ -0,0 +1,60 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::sync::Arc;
+
+use tokio_sync::semaphore::{Permit, Semaphore};
+
+/// Bounds how many requests are handled at once.
+pub struct Limit {
+    max: usize,
+    semaphore: Semaphore,
+}
+
+impl Limit {
+    pub fn new(max: usize) -> Limit {
+        Limit {
+            max,
+            semaphore: Semaphore::new(max),
+        }
+    }
+
+    /// Claims a slot for a request, or returns `None` if every slot is taken. The slot is freed
+    /// once it's dropped, which needn't be on the thread that claimed it.
+    pub fn try_acquire(self: &Arc<Self>) -> Option<Slot> {
+        let mut permit = Permit::new();
+        match permit.try_acquire(&self.semaphore) {
+            Ok(()) => Some(Slot {
+                limit: Arc::clone(self),
+                permit,
+            }),
+            Err(_) => {
+                log::warn!("turning a request away; all {} slots are in use", self.max);
+                None
+            }
+        }
+    }
+}
+
+pub struct Slot {
+    limit: Arc<Limit>,
+    permit: Permit,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.permit.release(&self.limit.semaphore);
+    }
+}
+
+#[test]
+fn slots_are_released() {
+    let limit = Arc::new(Limit::new(1));
+    let slot = limit.try_acquire();
+    assert!(slot.is_some());
+    assert!(limit.try_acquire().is_none());
+    drop(slot);
+    assert!(limit.try_acquire().is_some());
+}
@@ -0,0 +1,83 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::path::Path;
+
+use anyhow::Result;
+use futures::prelude::*;
+use prosidy::xml::quick_xml::events::{BytesEnd, BytesStart, Event};
+use prosidy::xml::{quick_xml::Result as XMLResult, XMLConfig, XML};
+use serde::Serialize;
+use tokio_fs as tfs;
+
+use crate::manifest::Manifest;
+
+/// The contents of a directory, served in place of an index file.
+///
+/// Prosidy documents are listed with their header properties via a [`Manifest`]; every other
+/// entry is listed by name alone, with a trailing slash on directories.
+#[derive(Debug, Serialize)]
+pub struct Listing {
+    documents: Manifest,
+    files: Vec<String>,
+}
+
+impl Listing {
+    const TAG_LISTING: &'static str = "listing";
+    const TAG_FILE: &'static str = "file";
+    const ATTR_PATH: &'static str = "path";
+
+    /// Lists a directory. Symlinks are skipped unless `follow_symlinks` is set.
+    pub async fn read(path: &Path, follow_symlinks: bool) -> Result<Listing> {
+        let documents = Manifest::read_async(path, follow_symlinks).await?;
+        let mut files: Vec<String> = tfs::read_dir(path.to_path_buf())
+            .await?
+            .map_err(anyhow::Error::from)
+            .try_filter_map(|dir_entry| {
+                let documents = &documents;
+                async move {
+                    let file_type = dir_entry.file_type().await?;
+                    let file_name = dir_entry.file_name();
+                    let mut name = file_name.to_string_lossy().into_owned();
+                    let is_dir = if file_type.is_symlink() {
+                        if !follow_symlinks {
+                            return Ok(None);
+                        }
+                        dir_entry.path().is_dir()
+                    } else {
+                        file_type.is_dir()
+                    };
+                    if is_dir {
+                        name.push('/');
+                    } else if documents.contains(&file_name) {
+                        return Ok(None);
+                    }
+                    Ok(Some(name))
+                }
+            })
+            .try_collect()
+            .await?;
+        files.sort();
+        Ok(Listing { documents, files })
+    }
+}
+
+impl XML for Listing {
+    fn to_events<F>(&self, config: &XMLConfig, emit: &mut F) -> XMLResult<()>
+    where
+        F: for<'a> FnMut(Event<'a>) -> XMLResult<()>,
+    {
+        let start = BytesStart::owned_name(config.name(Listing::TAG_LISTING));
+        emit(Event::Start(start))?;
+        self.documents.to_events(config, emit)?;
+        for file in self.files.iter() {
+            let mut start = BytesStart::owned_name(config.name(Listing::TAG_FILE));
+            start.push_attribute((config.name(Listing::ATTR_PATH).as_str(), file.as_str()));
+            emit(Event::Empty(start))?;
+        }
+        let end = BytesEnd::owned(config.name(Listing::TAG_LISTING).into_bytes());
+        emit(Event::End(end))
+    }
+}
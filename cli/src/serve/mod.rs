@@ -3,9 +3,19 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+// Handlers short-circuit by returning a complete `Response` in the error position.
+#![allow(clippy::result_large_err)]
+
 pub use opts::ServeOpts;
 
+mod access;
 mod cache;
+mod cors;
+mod gzip;
 mod http_error;
+mod limit;
+mod listing;
 mod opts;
+mod range;
+mod reload;
 mod server;
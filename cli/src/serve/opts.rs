@@ -3,23 +3,39 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use std::collections::HashMap;
 use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{value_t, App, Arg, ArgMatches};
+use mime::Mime;
 
+use super::access::LogFormat;
 use crate::args::{AppExt, FromArgs};
-use crate::fmt::FormatOpts;
+use crate::fmt::{FormatKind, FormatOpts};
 
 #[derive(Debug)]
 pub struct ServeOpts {
     pub cache_opts: Option<CacheOpts>,
+    pub compress: bool,
+    pub cors_origins: Vec<String>,
+    pub default_format: FormatKind,
+    pub error_pages: HashMap<u16, PathBuf>,
     pub listen_address: IpAddr,
     pub listen_port: u16,
+    pub max_connections: Option<usize>,
+    /// Media types for file extensions, taking precedence over the built-in ones.
+    pub media_types: HashMap<String, Mime>,
     pub follow_symlinks: bool,
     pub format: FormatOpts,
+    pub list_directories: bool,
+    pub log_format: LogFormat,
     pub root_path: PathBuf,
+    pub strict_accept: bool,
+    pub timeout: Option<Duration>,
+    pub watch: bool,
 }
 
 impl ServeOpts {
@@ -46,6 +62,14 @@ impl FromArgs for ServeOpts {
             .short("P")
             .value_name("PORT")
             .default_value("7080");
+        let max_connections = Arg::with_name(ARG_MAX_CONNECTIONS)
+            .help("Handle at most N requests at once, turning away any more with a 503")
+            .long("max-connections")
+            .value_name("N");
+        let timeout = Arg::with_name(ARG_TIMEOUT)
+            .help("Give up on requests taking longer than SECONDS, responding with a 504")
+            .long("timeout")
+            .value_name("SECONDS");
         let root_path = Arg::with_name(ARG_ROOT_PATH)
             .help("Serve files from this path")
             .value_name("ROOT DIR")
@@ -55,29 +79,181 @@ impl FromArgs for ServeOpts {
             .long("follow")
             .short("F")
             .takes_value(false);
-        app.args(&[address, port, follow_symlinks, root_path])
-            .register::<FormatOpts>()
-            .register::<Option<CacheOpts>>()
+        let list = Arg::with_name(ARG_LIST)
+            .help("List the contents of directories without an index file")
+            .long("list")
+            .short("L")
+            .takes_value(false);
+        let compress = Arg::with_name(ARG_COMPRESS)
+            .help("Compress text responses with gzip, for clients which accept it")
+            .long("compress")
+            .takes_value(false);
+        let cors = Arg::with_name(ARG_CORS)
+            .help("Allow cross-origin requests from ORIGIN, or from any origin with '*'")
+            .long("cors")
+            .value_name("ORIGIN")
+            .number_of_values(1)
+            .multiple(true);
+        let default_format = Arg::with_name(ARG_DEFAULT_FORMAT)
+            .help("The format of documents served to requests which don't ask for one")
+            .long("default-format")
+            .value_name("FORMAT")
+            .default_value("xml")
+            .possible_values(&["cbor", "html", "json", "markdown", "xml"]);
+        let error_page = Arg::with_name(ARG_ERROR_PAGE)
+            .help(
+                "Serve the file at PATH in place of the built-in page for errors with status CODE",
+            )
+            .long("error-page")
+            .value_name("CODE=PATH")
+            .number_of_values(1)
+            .multiple(true);
+        let log_format = Arg::with_name(ARG_LOG_FORMAT)
+            .help("Log requests as pretty text through the logger, or as JSON lines on stdout")
+            .long("log-format")
+            .possible_values(&[LOG_FORMAT_PRETTY, LOG_FORMAT_JSON])
+            .default_value(LOG_FORMAT_PRETTY);
+        let mime = Arg::with_name(ARG_MIME)
+            .help("Serve files ending in .EXT as TYPE, overriding the built-in media types")
+            .long("mime")
+            .value_name("EXT=TYPE")
+            .number_of_values(1)
+            .multiple(true);
+        let strict_accept = Arg::with_name(ARG_STRICT_ACCEPT)
+            .help("Respond with a 406 when the Accept header rules out every format, not with XML")
+            .long("strict-accept")
+            .takes_value(false);
+        let watch = Arg::with_name(ARG_WATCH)
+            .help("Reload pages served as HTML when files under the root path change")
+            .long("watch")
+            .short("w")
+            .takes_value(false);
+        app.args(&[
+            address,
+            port,
+            compress,
+            cors,
+            default_format,
+            error_page,
+            follow_symlinks,
+            list,
+            log_format,
+            max_connections,
+            mime,
+            strict_accept,
+            timeout,
+            watch,
+            root_path,
+        ])
+        .register::<FormatOpts>()
+        .register::<Option<CacheOpts>>()
     }
 
     fn parse_args(matches: &ArgMatches) -> Result<Self> {
         let listen_address = value_t!(matches, ARG_ADDRESS, IpAddr)?;
         let listen_port = value_t!(matches, ARG_PORT, u16)?;
+        let max_connections = if matches.is_present(ARG_MAX_CONNECTIONS) {
+            let max = value_t!(matches, ARG_MAX_CONNECTIONS, usize)?;
+            anyhow::ensure!(max > 0, "--max-connections must be at least 1");
+            Some(max)
+        } else {
+            None
+        };
         let root_path = value_t!(matches, ARG_ROOT_PATH, PathBuf)?.canonicalize()?;
         let format = FormatOpts::parse_args(matches)?;
         let follow_symlinks = matches.is_present(ARG_FOLLOW_SYMLINKS);
+        let list_directories = matches.is_present(ARG_LIST);
+        let log_format = match matches.value_of(ARG_LOG_FORMAT) {
+            Some(LOG_FORMAT_JSON) => LogFormat::Json,
+            _ => LogFormat::Pretty,
+        };
+        let strict_accept = matches.is_present(ARG_STRICT_ACCEPT);
+        let watch = matches.is_present(ARG_WATCH);
+        let timeout = if matches.is_present(ARG_TIMEOUT) {
+            let seconds = value_t!(matches, ARG_TIMEOUT, f64)?;
+            anyhow::ensure!(
+                seconds > 0.0,
+                "--timeout must be a positive number of seconds"
+            );
+            Some(Duration::from_secs_f64(seconds))
+        } else {
+            None
+        };
         let cache_opts = Option::parse_args(matches)?;
+        let compress = matches.is_present(ARG_COMPRESS);
+        let cors_origins = matches
+            .values_of(ARG_CORS)
+            .into_iter()
+            .flatten()
+            .map(String::from)
+            .collect();
+        let default_format = matches
+            .value_of(ARG_DEFAULT_FORMAT)
+            .and_then(FormatKind::from_name)
+            .unwrap_or(FormatKind::XML);
+        let error_pages = matches
+            .values_of(ARG_ERROR_PAGE)
+            .into_iter()
+            .flatten()
+            .map(parse_error_page)
+            .collect::<Result<_>>()?;
+        let media_types = matches
+            .values_of(ARG_MIME)
+            .into_iter()
+            .flatten()
+            .map(parse_media_type)
+            .collect::<Result<_>>()?;
         Ok(ServeOpts {
             cache_opts,
+            compress,
+            cors_origins,
+            default_format,
+            error_pages,
             listen_address,
             listen_port,
+            max_connections,
+            media_types,
             follow_symlinks,
             format,
+            list_directories,
+            log_format,
             root_path,
+            strict_accept,
+            timeout,
+            watch,
         })
     }
 }
 
+fn parse_error_page(arg: &str) -> Result<(u16, PathBuf)> {
+    let mut parts = arg.splitn(2, '=');
+    let (code, path) = match (parts.next(), parts.next()) {
+        (Some(code), Some(path)) => (code, path),
+        _ => anyhow::bail!("Expected an error page as CODE=PATH, but got {:?}", arg),
+    };
+    let code = code
+        .parse::<u16>()
+        .ok()
+        .filter(|code| (400..600).contains(code))
+        .ok_or_else(|| anyhow::anyhow!("{:?} is not an HTTP error status", code))?;
+    let path = PathBuf::from(path)
+        .canonicalize()
+        .with_context(|| format!("Failed to find the error page {:?}", path))?;
+    Ok((code, path))
+}
+
+fn parse_media_type(arg: &str) -> Result<(String, Mime)> {
+    let mut parts = arg.splitn(2, '=');
+    let (ext, mime) = match (parts.next(), parts.next()) {
+        (Some(ext), Some(mime)) if !ext.is_empty() => (ext.trim_start_matches('.'), mime),
+        _ => anyhow::bail!("Expected a media type as EXT=TYPE, but got {:?}", arg),
+    };
+    let mime = mime
+        .parse::<Mime>()
+        .with_context(|| format!("{:?} is not a media type", mime))?;
+    Ok((ext.to_string(), mime))
+}
+
 #[derive(Debug)]
 pub struct CacheOpts {
     pub max_age: u64,
@@ -116,8 +292,22 @@ impl FromArgs for Option<CacheOpts> {
 
 const ARG_ADDRESS: &str = "ip";
 const ARG_PORT: &str = "port";
+const ARG_COMPRESS: &str = "compress";
+const ARG_CORS: &str = "cors";
+const ARG_DEFAULT_FORMAT: &str = "default-format";
+const ARG_ERROR_PAGE: &str = "error-page";
 const ARG_ROOT_PATH: &str = "root-path";
 const ARG_FOLLOW_SYMLINKS: &str = "follow-symlinks";
+const ARG_LIST: &str = "list";
+const ARG_LOG_FORMAT: &str = "log-format";
+const ARG_MAX_CONNECTIONS: &str = "max-connections";
+const ARG_MIME: &str = "mime";
+const ARG_STRICT_ACCEPT: &str = "strict-accept";
+const ARG_TIMEOUT: &str = "timeout";
+const ARG_WATCH: &str = "watch";
+
+const LOG_FORMAT_JSON: &str = "json";
+const LOG_FORMAT_PRETTY: &str = "pretty";
 
 const ARG_CACHE: &str = "cache";
 const ARG_CACHE_MAX_AGE: &str = "cache-max-age";
@@ -0,0 +1,118 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::ops::Range;
+
+use hyper::http::header::{self, HeaderValue};
+use hyper::http::response::Builder;
+use hyper::{Body, Request, Response, StatusCode};
+
+use super::http_error::range_not_satisfiable;
+
+/// Reads the `Range` header of a request for a file of `len` bytes.
+///
+/// Returns the range to send, after marking the response as partial, or `None` if the whole file
+/// should be sent. Only single ranges are supported; anything else is answered with the whole
+/// file, as HTTP allows.
+pub fn handle_range(
+    request: &Request<Body>,
+    builder: &mut Builder,
+    len: usize,
+) -> Result<Option<Range<usize>>, anyhow::Result<Response<Body>>> {
+    builder.header(header::ACCEPT_RANGES, "bytes");
+    let value = match request.headers().get(header::RANGE) {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    match parse_range(value.to_str().unwrap_or_default(), len) {
+        ByteRange::Full => Ok(None),
+        ByteRange::Partial(range) => {
+            let content_range = format!("bytes {}-{}/{}", range.start, range.end - 1, len);
+            builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_RANGE, content_range);
+            Ok(Some(range))
+        }
+        ByteRange::Unsatisfiable => {
+            let mut response = range_not_satisfiable().map_err(|e| Err(e.into()))?;
+            let content_range =
+                HeaderValue::from_str(&format!("bytes */{}", len)).map_err(|e| Err(e.into()))?;
+            response
+                .headers_mut()
+                .insert(header::CONTENT_RANGE, content_range);
+            Err(Ok(response))
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    Full,
+    Partial(Range<usize>),
+    Unsatisfiable,
+}
+
+fn parse_range(value: &str, len: usize) -> ByteRange {
+    let spec = match value.trim().strip_prefix("bytes=") {
+        Some(spec) if !spec.contains(',') => spec.trim(),
+        _ => return ByteRange::Full,
+    };
+    let (start, end) = match spec.find('-') {
+        Some(index) => (&spec[..index], &spec[index + 1..]),
+        None => return ByteRange::Full,
+    };
+    let parse = |s: &str| -> Option<usize> {
+        if s.bytes().all(|byte| byte.is_ascii_digit()) {
+            s.parse().ok()
+        } else {
+            None
+        }
+    };
+    let range = match (start, end) {
+        // `bytes=-N` asks for the last N bytes.
+        ("", suffix) => match parse(suffix) {
+            Some(0) => return ByteRange::Unsatisfiable,
+            Some(suffix) => len.saturating_sub(suffix)..len,
+            None => return ByteRange::Full,
+        },
+        (start, "") => match parse(start) {
+            Some(start) => start..len,
+            None => return ByteRange::Full,
+        },
+        (start, end) => match (parse(start), parse(end)) {
+            (Some(start), Some(end)) if start <= end => start..len.min(end.saturating_add(1)),
+            _ => return ByteRange::Full,
+        },
+    };
+    if range.start < len {
+        ByteRange::Partial(range)
+    } else {
+        ByteRange::Unsatisfiable
+    }
+}
+
+#[test]
+fn parse_ranges() {
+    assert_eq!(ByteRange::Partial(0..100), parse_range("bytes=0-99", 1000));
+    assert_eq!(
+        ByteRange::Partial(500..1000),
+        parse_range("bytes=500-", 1000)
+    );
+    assert_eq!(
+        ByteRange::Partial(900..1000),
+        parse_range("bytes=-100", 1000)
+    );
+    assert_eq!(ByteRange::Partial(0..10), parse_range("bytes=-100", 10));
+    assert_eq!(
+        ByteRange::Partial(990..1000),
+        parse_range("bytes=990-2000", 1000)
+    );
+    assert_eq!(ByteRange::Unsatisfiable, parse_range("bytes=1000-", 1000));
+    assert_eq!(ByteRange::Unsatisfiable, parse_range("bytes=-0", 1000));
+    assert_eq!(ByteRange::Full, parse_range("bytes=0-1,5-6", 1000));
+    assert_eq!(ByteRange::Full, parse_range("bytes=9-1", 1000));
+    assert_eq!(ByteRange::Full, parse_range("items=0-1", 1000));
+    assert_eq!(ByteRange::Full, parse_range("bytes=+1-2", 1000));
+}
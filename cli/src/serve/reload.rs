@@ -0,0 +1,140 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use hyper::body::{Body, Sender};
+use hyper::{header, Response};
+
+/// The path clients subscribe to for reload events.
+pub const RELOAD_PATH: &str = "/__prosidy/reload";
+
+/// Subscribes to reload events and reloads the page on each one. Injected into HTML responses.
+pub const RELOAD_SCRIPT: &str =
+    "<script>new EventSource(\"/__prosidy/reload\").onmessage = () => location.reload();</script>";
+
+/// Tracks the clients listening for reload events.
+#[derive(Default)]
+pub struct Reload {
+    clients: Mutex<Vec<Sender>>,
+}
+
+impl Reload {
+    /// Opens a server-sent event stream, which receives an event each time [`Reload::notify`] is
+    /// called.
+    pub fn subscribe(&self) -> hyper::http::Result<Response<Body>> {
+        let (sender, body) = Body::channel();
+        self.clients.lock().unwrap().push(sender);
+        Response::builder()
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .body(body)
+    }
+
+    /// Sends a reload event to every client, forgetting those which have disconnected.
+    pub fn notify(&self) {
+        let mut clients = self.clients.lock().unwrap();
+        log::debug!("sending a reload event to {} client(s)", clients.len());
+        clients.retain_mut(|sender| sender.try_send_data("data: reload\n\n".into()).is_ok());
+    }
+}
+
+/// Polls a directory tree for changes on a background thread, notifying a [`Reload`] whenever a
+/// file is added, removed, or modified. The thread is stopped when the watcher is dropped.
+pub struct Watcher {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watcher {
+    pub fn spawn(reload: Arc<Reload>, root: PathBuf, follow_symlinks: bool) -> Watcher {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                let mut last = fingerprint(&root, follow_symlinks);
+                while !stop.load(Ordering::Relaxed) {
+                    thread::sleep(POLL_INTERVAL);
+                    let current = fingerprint(&root, follow_symlinks);
+                    if current != last {
+                        log::info!("change detected under {:?}", root);
+                        reload.notify();
+                        last = current;
+                    }
+                }
+            })
+        };
+        Watcher {
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Hashes the path, size, and modification time of every file under `root`.
+fn fingerprint(root: &Path, follow_symlinks: bool) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries: Vec<_> = match fs::read_dir(&dir) {
+            Ok(entries) => entries.flatten().collect(),
+            Err(_) => continue,
+        };
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path = entry.path();
+            let metadata = match entry.file_type() {
+                Ok(file_type) if file_type.is_symlink() && !follow_symlinks => continue,
+                Ok(_) => fs::metadata(&path),
+                Err(err) => Err(err),
+            };
+            let metadata = match metadata {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            if metadata.is_dir() {
+                // Symlinks may lead back up the tree; only descend into real directories.
+                if !entry.file_type().map_or(true, |t| t.is_symlink()) {
+                    dirs.push(path);
+                }
+                continue;
+            }
+            path.hash(&mut hasher);
+            metadata.len().hash(&mut hasher);
+            metadata.modified().ok().hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[test]
+fn fingerprint_changes() {
+    let root = std::env::temp_dir().join(format!("prosidy-reload-{}", std::process::id()));
+    fs::create_dir_all(root.join("sub")).unwrap();
+    let before = fingerprint(&root, false);
+    assert_eq!(before, fingerprint(&root, false));
+    fs::write(root.join("sub").join("doc.pro"), "---\nHello\n").unwrap();
+    let after = fingerprint(&root, false);
+    fs::remove_dir_all(&root).unwrap();
+    assert_ne!(before, after);
+}
@@ -5,10 +5,12 @@
 
 use std::borrow::Cow;
 use std::cmp::Ordering;
+use std::fmt::Write as _;
+use std::io::SeekFrom;
 use std::path::{Path, PathBuf};
 use std::result::Result as StdResult;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use hyper::http::{response::Builder, Error as HttpError};
@@ -16,54 +18,71 @@ use hyper::server::conn::AddrStream;
 use hyper::service::{make_service_fn, service_fn};
 use hyper::{header, Body, Method, Request, Response, Server};
 use mime::Mime;
+use prosidy::{PropSet, Text};
+use serde::Serialize;
+use tokio::codec::{BytesCodec, FramedRead};
+use tokio::future::FutureExt;
 use tokio::prelude::*;
 use tokio::runtime::Runtime;
+use tokio_executor::blocking;
 
+use super::access::Access;
 use super::cache::handle_caching;
+use super::cors;
+use super::gzip;
 use super::http_error::*;
+use super::limit::Limit;
+use super::listing::Listing;
 use super::opts::ServeOpts;
+use super::range::handle_range;
+use super::reload::{Reload, Watcher, RELOAD_PATH, RELOAD_SCRIPT};
 use crate::fmt::FormatKind;
 use crate::manifest::Manifest;
-use crate::mediatype::{infer_media_type, CBOR};
+use crate::mediatype::{infer_media_type, CBOR, LD, MARKDOWN};
 
 pub fn serve(opts: Arc<ServeOpts>) -> Result<()> {
     let addr = opts.address();
+    let reload = if opts.watch {
+        Some(Arc::new(Reload::default()))
+    } else {
+        None
+    };
+    let limit = opts.max_connections.map(|max| Arc::new(Limit::new(max)));
+    // Dropped after the runtime below, which stops the watcher's thread once the server exits.
+    let _watcher = reload.as_ref().map(|reload| {
+        Watcher::spawn(
+            Arc::clone(reload),
+            opts.root_path.clone(),
+            opts.follow_symlinks,
+        )
+    });
     let make_service = make_service_fn(|stream: &AddrStream| {
         let addr = stream.remote_addr();
         let opts = opts.clone();
+        let reload = reload.clone();
+        let limit = limit.clone();
         async move {
             let service = service_fn(move |req: Request<Body>| {
                 let start = Instant::now();
                 let method = req.method().clone();
                 let uri = req.uri().clone();
                 let opts = opts.clone();
-                handle(opts, req).map(move |result| {
+                let reload = reload.clone();
+                let limit = limit.clone();
+                async move {
+                    let result = handle(opts.clone(), reload, limit, req).await;
                     let dur = Instant::now() - start;
-                    match result {
-                        Ok(response) => {
-                            log::info!(
-                                "addr={addr:?}\nmethod={method:?}\nuri={uri:?}\nduration={dur:?}\nstatus={status:}",
-                                addr=addr,
-                                method=method,
-                                uri=uri,
-                                dur=dur,
-                                status=response.status(),
-                            );
-                            Ok(response)
-                        }
+                    let (response, error) = match result {
+                        Ok(response) => (response, None),
                         Err(error) => {
-                            log::error!(
-                                "addr={addr:?}\nmethod={method:?}\nuri={uri:?}\nduration={dur:?}\nerror={err:}",
-                                addr=addr,
-                                method=method,
-                                uri=uri,
-                                dur=dur,
-                                err=error,
-                            );
-                            internal_server_error()
+                            let response = internal_server_error()?;
+                            (with_error_page(&opts, response).await, Some(error))
                         }
-                    }
-                })
+                    };
+                    Access::new(addr, &method, &uri, dur, &response, error.as_ref())
+                        .log(opts.log_format);
+                    Ok::<_, HttpError>(response)
+                }
             });
             Ok::<_, HttpError>(service)
         }
@@ -83,8 +102,83 @@ macro_rules! handle {
     };
 }
 
-async fn handle(opts: Arc<ServeOpts>, request: Request<Body>) -> Result<Response<Body>> {
-    handle! { check_method(&request) };
+async fn handle(
+    opts: Arc<ServeOpts>,
+    reload: Option<Arc<Reload>>,
+    limit: Option<Arc<Limit>>,
+    request: Request<Body>,
+) -> Result<Response<Body>> {
+    let head = request.method() == Method::HEAD;
+    let response = handle_request(opts.clone(), reload, limit, request).await?;
+    let mut response = with_error_page(&opts, response).await;
+    // HEAD is answered just like GET, headers and all, but without the body.
+    if head {
+        *response.body_mut() = Body::empty();
+    }
+    Ok(response)
+}
+
+async fn handle_request(
+    opts: Arc<ServeOpts>,
+    reload: Option<Arc<Reload>>,
+    limit: Option<Arc<Limit>>,
+    request: Request<Body>,
+) -> Result<Response<Body>> {
+    // The slot travels with the request and is freed along with it: once the handler is done
+    // with it, or, for a document rendered on a blocking thread, once the render finishes, even
+    // if the response timed out first. Streamed bodies may still be sending after that, but the
+    // work of handling the request is done.
+    let mut request = request;
+    if let Some(ref limit) = limit {
+        match limit.try_acquire() {
+            Some(slot) => request.extensions_mut().insert(slot),
+            None => return service_unavailable().err_into(),
+        };
+    }
+    handle! { check_method(&opts, &request) };
+    let origin = cors::allowed_origin(&opts.cors_origins, &request);
+    if request.method() == Method::OPTIONS {
+        return cors::preflight(&request, origin).err_into();
+    }
+    let routed = route(opts.clone(), reload, request);
+    let mut response = within(opts.timeout, routed).await?;
+    if let Some(origin) = origin {
+        cors::allow(&mut response, origin);
+    }
+    Ok(response)
+}
+
+/// Waits for a response for at most `timeout`, answering 504 Gateway Timeout otherwise.
+///
+/// Rendering happens on a blocking thread, so the timeout can still fire while it runs. The
+/// thread finishes its work regardless, but the connection is answered right away.
+async fn within<F>(timeout: Option<Duration>, response: F) -> Result<Response<Body>>
+where
+    F: Future<Output = Result<Response<Body>>>,
+{
+    match timeout {
+        Some(timeout) => match response.timeout(timeout).await {
+            Ok(response) => response,
+            Err(_) => gateway_timeout().err_into(),
+        },
+        None => response.await,
+    }
+}
+
+async fn route(
+    opts: Arc<ServeOpts>,
+    reload: Option<Arc<Reload>>,
+    request: Request<Body>,
+) -> Result<Response<Body>> {
+    if let Some(reload) = reload {
+        if request.uri().path() == RELOAD_PATH {
+            return reload.subscribe().err_into();
+        }
+    }
+    if request.uri().path() == MANIFEST_PATH {
+        let root = opts.root_path.clone();
+        return handle_manifest(opts, request, root).await;
+    }
     let path = handle! {
         normalize_path(
             opts.follow_symlinks,
@@ -94,74 +188,408 @@ async fn handle(opts: Arc<ServeOpts>, request: Request<Body>) -> Result<Response
     };
     if path.is_file() {
         handle_file(opts, request, path).await
+    } else if let Some(index) = find_index(opts.follow_symlinks, &opts.root_path, &path) {
+        handle_file(opts, request, index).await
+    } else if !path.is_dir() || !path.starts_with(&opts.root_path) {
+        not_found().err_into()
+    } else if opts.list_directories {
+        handle_listing(opts, request, path).await
     } else {
         handle_manifest(opts, request, path).await
     }
 }
 
+/// Looks for an index file in a directory, in order of [`INDEX_FILES`]. Symlinks are only
+/// followed out of the root if `follow` is set, just as in `normalize_path`.
+fn find_index(follow: bool, root: &Path, dir: &Path) -> Option<PathBuf> {
+    INDEX_FILES.iter().find_map(|name| {
+        let canon = dir.join(name).canonicalize().ok()?;
+        if (follow || canon.starts_with(root)) && canon.is_file() {
+            Some(canon)
+        } else {
+            None
+        }
+    })
+}
+
+const INDEX_FILES: &[&str] = &["index.pro", "index.html"];
+
+/// Serves the manifest of the root directory, even when directories are listed instead.
+const MANIFEST_PATH: &str = "/__prosidy/manifest";
+
 async fn handle_file(
     opts: Arc<ServeOpts>,
     request: Request<Body>,
     path: PathBuf,
 ) -> Result<Response<Body>> {
-    let bytes = tokio::fs::read(&path).await?;
     let mut builder = Response::builder();
-    // add caching metadata, if caching is enabled
+    // Documents are rendered in memory anyway, so their source is read all at once.
+    if path.extension() == Some("pro".as_ref()) {
+        let bytes = tokio::fs::read(&path).await?;
+        let meta = wants_meta(&request);
+        if let Some(ref opts) = opts.cache_opts {
+            // The rendered document is derived from its source, so it's as old as the source.
+            let modified = tokio::fs::metadata(&path).await?.modified().ok();
+            // The header alone is a different resource, so it can't share the document's ETag.
+            let validator = if meta {
+                Cow::Owned([&b"meta:"[..], &bytes].concat())
+            } else {
+                Cow::Borrowed(&bytes[..])
+            };
+            handle!(handle_caching(
+                &request,
+                opts,
+                &mut builder,
+                &validator,
+                modified
+            ));
+        }
+        if meta {
+            return handle_meta(&request, builder, &opts, bytes);
+        }
+        let render = move || handle_prosidy(&request, builder, opts, bytes);
+        return blocking::run(render).await;
+    }
+    let metadata = tokio::fs::metadata(&path).await?;
+    let len = metadata.len() as usize;
     if let Some(ref opts) = opts.cache_opts {
-        handle!(handle_caching(&request, opts, &mut builder, &bytes));
+        // Hashing the content would mean reading all of it up front, so other files are
+        // validated by their size and modification time instead.
+        let modified = metadata.modified()?;
+        let validator = format!("{}:{:?}", len, modified);
+        handle!(handle_caching(
+            &request,
+            opts,
+            &mut builder,
+            validator.as_bytes(),
+            Some(modified)
+        ));
     }
-    // check the extension for how to respond
-    if path.extension() == Some("pro".as_ref()) {
-        handle_prosidy(&request, builder, opts, bytes)
+    let mime = infer_media_type(&opts.media_types, &path);
+    // Ranges index into the file as stored, so they're never compressed or rewritten.
+    let range = match handle!(handle_range(&request, &mut builder, len)) {
+        Some(range) => range,
+        None if is_rewritten(&opts, mime) => {
+            let bytes = tokio::fs::read(&path).await?;
+            return respond(&request, &opts, builder, mime, bytes);
+        }
+        None => 0..len,
+    };
+    let body = if request.method() == Method::HEAD {
+        Body::empty()
     } else {
-        let mime = infer_media_type(&path);
-        builder
-            .header(header::CONTENT_TYPE, mime.as_ref())
-            .body(bytes.into())
-            .map_err(anyhow::Error::from)
+        stream_file(&path, range.start as u64, range.len() as u64).await?
+    };
+    builder
+        .header(header::CONTENT_TYPE, mime.as_ref())
+        .header(header::CONTENT_LENGTH, range.len())
+        .body(body)
+        .err_into()
+}
+
+/// Streams part of a file in chunks, so memory use doesn't grow with the size of the file.
+async fn stream_file(path: &Path, start: u64, len: u64) -> Result<Body> {
+    let mut file = tokio::fs::File::open(path).await?;
+    if start > 0 {
+        file.seek(SeekFrom::Start(start)).await?;
     }
+    let mut frames = FramedRead::new(file.take(len), BytesCodec::new());
+    let (mut sender, body) = Body::channel();
+    let path = path.to_path_buf();
+    tokio::spawn(async move {
+        while let Some(frame) = frames.next().await {
+            let sent = match frame {
+                Ok(chunk) => sender.send_data(chunk.freeze().into()).await.err_into(),
+                Err(err) => Err(err).err_into(),
+            };
+            if let Err(err) = sent {
+                log::warn!("stopped streaming {:?}: {}", path, err);
+                sender.abort();
+                return;
+            }
+        }
+    });
+    Ok(body)
 }
 
-async fn handle_manifest(
+async fn handle_listing(
     opts: Arc<ServeOpts>,
     request: Request<Body>,
     path: PathBuf,
 ) -> Result<Response<Body>> {
-    let manifest = Manifest::read_async(path, opts.follow_symlinks).await?;
+    let listing = Listing::read(&path, opts.follow_symlinks).await?;
+    let mut output = Vec::with_capacity(8192);
+    let format = match determine_value_format(&request, opts.default_format, opts.strict_accept) {
+        Some(format) => format,
+        None => return not_acceptable().err_into(),
+    };
+    format.write(&opts.format, &mut output, &listing)?;
+    respond(&request, &opts, Response::builder(), format.media_type(), output)
+}
+
+async fn handle_manifest(
+    opts: Arc<ServeOpts>,
+    request: Request<Body>,
+    dir: PathBuf,
+) -> Result<Response<Body>> {
+    let format = match determine_value_format(&request, opts.default_format, opts.strict_accept) {
+        Some(format) => format,
+        None => return not_acceptable().err_into(),
+    };
+    let manifest = Manifest::read_async(&dir, opts.follow_symlinks).await?;
+    let mut builder = Response::builder();
+    for (path, reason) in manifest.warnings() {
+        builder.header(header::WARNING, skipped_warning(path, reason));
+    }
+    if let Some(ref cache_opts) = opts.cache_opts {
+        // Headers only change along with their file's modification time, and removing a file
+        // changes the directory's, so those times stand in for the content.
+        let mut entries: Vec<_> = manifest.iter().collect();
+        entries.sort_by_key(|(path, _)| *path);
+        let mut validator = String::new();
+        for (path, entry) in entries.iter() {
+            writeln!(validator, "{:?}:{:?}", path, entry.modified())?;
+        }
+        // A file can become readable without being modified, so what was skipped counts too.
+        for (path, reason) in manifest.warnings() {
+            writeln!(validator, "!{:?}:{}", path, reason)?;
+        }
+        let dir_modified = tokio::fs::metadata(&dir).await?.modified().ok();
+        let modified = entries
+            .iter()
+            .map(|(_, entry)| entry.modified())
+            .chain(Some(dir_modified))
+            .collect::<Option<Vec<_>>>()
+            .and_then(|times| times.into_iter().max());
+        handle!(handle_caching(
+            &request,
+            cache_opts,
+            &mut builder,
+            validator.as_bytes(),
+            modified
+        ));
+    }
     let mut output = Vec::with_capacity(8192);
-    let format = determine_format(&request);
     format.write(&opts.format, &mut output, &manifest)?;
-    Response::builder()
-        .header(header::CONTENT_TYPE, format.media_type().as_ref())
-        .body(output.into())
-        .err_into()
+    respond(&request, &opts, builder, format.media_type(), output)
+}
+
+/// A `Warning` header for a file skipped while reading a manifest, with the miscellaneous warning
+/// code 199. The text is escaped so that the header is always valid ASCII.
+fn skipped_warning(path: &Path, reason: &str) -> String {
+    let text = format!("Skipped {}: {}", path.display(), reason);
+    format!("199 - \"{}\"", text.escape_default())
 }
 
 fn handle_prosidy(
     request: &Request<Body>,
-    mut builder: Builder,
+    builder: Builder,
     opts: Arc<ServeOpts>,
     bytes: Vec<u8>,
 ) -> Result<Response<Body>> {
+    let format = match determine_format(request, opts.default_format, opts.strict_accept) {
+        Some(format) => format,
+        None => return not_acceptable().err_into(),
+    };
     let source = String::from_utf8(bytes)?;
-    let doc = prosidy::parse::parse_document(&source)?;
+    let mut output = Vec::with_capacity(output_capacity(format, source.len()));
+    {
+        let doc = prosidy::parse::parse_document(&source)?;
+        format.write_document(&opts.format, &mut output, &doc)?;
+    }
+    // Compression makes another copy of the output, so don't keep the source around for it too.
+    drop(source);
+    respond(request, &opts, builder, format.media_type(), output)
+}
 
-    let mut output = Vec::with_capacity(8192);
-    let format = determine_format(request);
-    format.write(&opts.format, &mut output, &doc)?;
+/// Guesses how large a document of `source_len` bytes renders to, from how much markup each
+/// format adds. Growing a buffer copies it into one twice its size while both are alive, so a
+/// guess a little high costs much less than one a little low.
+fn output_capacity(format: FormatKind, source_len: usize) -> usize {
+    match format {
+        FormatKind::JsonLd => 8192,
+        FormatKind::Markdown => source_len,
+        FormatKind::HTML => source_len + source_len / 2,
+        FormatKind::XML => source_len * 2,
+        FormatKind::CBOR | FormatKind::JSON => source_len * 4,
+    }
+}
+
+/// Answers a request for a document's header alone, as JSON or CBOR. Only the header is parsed,
+/// so this stays cheap however long the document is.
+fn handle_meta(
+    request: &Request<Body>,
+    builder: Builder,
+    opts: &ServeOpts,
+    bytes: Vec<u8>,
+) -> Result<Response<Body>> {
+    let format = match determine_value_format(request, FormatKind::JSON, opts.strict_accept) {
+        Some(FormatKind::CBOR) => FormatKind::CBOR,
+        Some(FormatKind::JSON) => FormatKind::JSON,
+        // A header isn't a page, so it's written as JSON unless the client insists otherwise.
+        Some(_) if !opts.strict_accept => FormatKind::JSON,
+        _ => return not_acceptable().err_into(),
+    };
+    let source = String::from_utf8(bytes)?;
+    let props = prosidy::parse::parse_meta(&source)?;
+    let meta = Meta {
+        title: props.title(),
+        props: &props,
+    };
+    let mut output = Vec::with_capacity(1024);
+    match format {
+        FormatKind::CBOR => opts.format.write_cbor(&mut output, &meta)?,
+        _ => opts.format.write_json(&mut output, &meta)?,
+    }
+    respond(request, opts, builder, format.media_type(), output)
+}
+
+/// A document's header, with its title pulled out for convenience.
+#[derive(Serialize)]
+struct Meta<'r, 'a> {
+    title: Option<Text<'a>>,
+    #[serde(flatten)]
+    props: &'r PropSet<'a>,
+}
+
+/// Checks for the `meta` query parameter, which asks for a document's header alone.
+fn wants_meta(request: &Request<Body>) -> bool {
+    let query = request.uri().query().unwrap_or_default();
+    query
+        .split('&')
+        .any(|param| param.eq_ignore_ascii_case("meta"))
+}
+
+/// Finishes a response, compressing the body if that's enabled and the client accepts it.
+///
+/// Any ETag has already been computed from the uncompressed content, so each encoding of a
+/// resource shares one cache entry.
+fn respond(
+    request: &Request<Body>,
+    opts: &ServeOpts,
+    mut builder: Builder,
+    mime: &Mime,
+    body: Vec<u8>,
+) -> Result<Response<Body>> {
+    builder.header(header::CONTENT_TYPE, mime.as_ref());
+    let body = if opts.watch && is_html(mime) {
+        inject_reload_script(body)
+    } else {
+        body
+    };
+    let body = if opts.compress && is_compressible(mime) {
+        builder.header(header::VARY, "Accept-Encoding");
+        if accepts_gzip(request) {
+            builder.header(header::CONTENT_ENCODING, "gzip");
+            gzip::compress(&body)
+        } else {
+            body
+        }
+    } else {
+        body
+    };
     builder
-        .header(header::CONTENT_TYPE, format.media_type().as_ref())
-        .body(output.into())
+        .header(header::CONTENT_LENGTH, body.len())
+        .body(body.into())
         .err_into()
 }
 
-fn determine_format(request: &Request<Body>) -> FormatKind {
-    determine_format_from_params(request)
-        .or_else(|| determine_format_from_headers(request))
-        .unwrap_or(FormatKind::XML)
+/// Inserts the reload script at the end of the page's body, or at the very end if it has none.
+fn inject_reload_script(mut html: Vec<u8>) -> Vec<u8> {
+    let end_body = html
+        .windows(b"</body>".len())
+        .rposition(|window| window.eq_ignore_ascii_case(b"</body>"))
+        .unwrap_or(html.len());
+    html.splice(end_body..end_body, RELOAD_SCRIPT.bytes());
+    html
+}
+
+/// Checks whether [`respond`] may change the body of a response with this media type, in which
+/// case it needs to be read in full.
+fn is_rewritten(opts: &ServeOpts, mime: &Mime) -> bool {
+    (opts.watch && is_html(mime)) || (opts.compress && is_compressible(mime))
+}
+
+fn is_html(mime: &Mime) -> bool {
+    mime.type_() == mime::TEXT && mime.subtype() == mime::HTML
+}
+
+fn is_compressible(mime: &Mime) -> bool {
+    mime.type_() == mime::TEXT
+        || mime.subtype() == mime::JSON
+        || mime.suffix() == Some(mime::JSON)
+        || mime.subtype() == mime::XML
+        || mime.suffix() == Some(mime::XML)
+        || mime.subtype() == mime::JAVASCRIPT
+}
+
+fn accepts_gzip(request: &Request<Body>) -> bool {
+    let accept = match request.headers().get(header::ACCEPT_ENCODING) {
+        Some(accept) => accept.to_str().unwrap_or_default(),
+        None => return false,
+    };
+    accept.split(',').any(|raw| {
+        let mut parts = raw.split(';').map(str::trim);
+        let coding = parts.next().unwrap_or_default();
+        let rejected = parts.any(|param| {
+            param
+                .strip_prefix("q=")
+                .and_then(|q| q.parse::<f64>().ok())
+                == Some(0.0)
+        });
+        (coding.eq_ignore_ascii_case("gzip") || coding == "*") && !rejected
+    })
+}
+
+/// Picks the format to respond with. Without a query parameter or an acceptable type in the
+/// `Accept` header this is `default`, unless `strict` is set and the request has an `Accept`
+/// header, in which case there is none.
+fn determine_format(
+    request: &Request<Body>,
+    default: FormatKind,
+    strict: bool,
+) -> Option<FormatKind> {
+    negotiate_format(request, default, strict, true)
+}
+
+/// Like [`determine_format`], for values other than documents, which can't be written as
+/// JSON-LD. A query parameter asking for JSON-LD has no acceptable format, while
+/// `application/ld+json` in the `Accept` header is passed over for the other types.
+fn determine_value_format(
+    request: &Request<Body>,
+    default: FormatKind,
+    strict: bool,
+) -> Option<FormatKind> {
+    negotiate_format(request, default, strict, false)
+}
+
+fn negotiate_format(
+    request: &Request<Body>,
+    default: FormatKind,
+    strict: bool,
+    document: bool,
+) -> Option<FormatKind> {
+    let format = match determine_format_from_params(request) {
+        Some(FormatKind::JsonLd) if !document => return None,
+        Some(format) => Some(format),
+        None => determine_format_from_headers(request, default, document),
+    };
+    if format.is_none() && strict && request.headers().contains_key(header::ACCEPT) {
+        return None;
+    }
+    Some(format.unwrap_or(default))
 }
 
-fn determine_format_from_headers(request: &Request<Body>) -> Option<FormatKind> {
+/// Finds the supported format with the highest quality in the `Accept` header.
+///
+/// The wildcard range `*/*` stands for the `default` format, while `application/*` stands for
+/// JSON and `text/*` for HTML. As they're less specific, a type named exactly is preferred to
+/// any range whatever their qualities. `application/ld+json` is only supported for a `document`.
+fn determine_format_from_headers(
+    request: &Request<Body>,
+    default: FormatKind,
+    document: bool,
+) -> Option<FormatKind> {
     let accept = request.headers().get(header::ACCEPT)?.to_str().ok()?;
     log::debug!("Reading format types from ACCEPT header: {:?}", accept);
     accept
@@ -169,30 +597,43 @@ fn determine_format_from_headers(request: &Request<Body>) -> Option<FormatKind>
         .split(',')
         .flat_map(|raw| raw.trim_start().parse::<Mime>().ok())
         // Then, try to match the mime type and subtype against supported formats,
-        // attaching the quality if one is set.
+        // attaching whether the match was exact and the quality if one is set.
         .flat_map(|mime| {
-            let kind = match mime.type_() {
-                mime::APPLICATION => match mime.subtype() {
-                    mime::JSON => Some(FormatKind::JSON),
-                    mime::XML => Some(FormatKind::XML),
-                    other if other == *CBOR => Some(FormatKind::CBOR),
-                    _ => None,
-                },
-                mime::TEXT => match mime.subtype() {
-                    mime::XML => Some(FormatKind::XML),
-                    _ => None,
-                },
-                _ => None,
-            }?;
+            let (kind, exact) = match (mime.type_(), mime.subtype()) {
+                (mime::STAR, mime::STAR) => (default, false),
+                (mime::APPLICATION, mime::STAR) => (FormatKind::JSON, false),
+                (mime::TEXT, mime::STAR) => (FormatKind::HTML, false),
+                (mime::APPLICATION, mime::JSON) => (FormatKind::JSON, true),
+                (mime::APPLICATION, mime::XML) => (FormatKind::XML, true),
+                (mime::APPLICATION, other) if other == *CBOR => (FormatKind::CBOR, true),
+                (mime::APPLICATION, other)
+                    if document && other == *LD && mime.suffix() == Some(mime::JSON) =>
+                {
+                    (FormatKind::JsonLd, true)
+                }
+                (mime::TEXT, mime::HTML) => (FormatKind::HTML, true),
+                (mime::TEXT, other) if other == *MARKDOWN => (FormatKind::Markdown, true),
+                (mime::TEXT, mime::XML) => (FormatKind::XML, true),
+                _ => return None,
+            };
             // https://developer.mozilla.org/en-US/docs/Glossary/Quality_values
             let quality = mime
                 .get_param("q")
                 .and_then(|q| q.as_ref().parse::<f64>().ok())
                 .unwrap_or(1.0);
-            Some((kind, quality))
+            // A quality of zero marks the type as not acceptable at all.
+            if quality > 0.0 {
+                Some((kind, exact, quality))
+            } else {
+                None
+            }
         })
-        .max_by(|(_, q1), (_, q2)| q1.partial_cmp(q2).unwrap_or(Ordering::Equal))
-        .map(|(kind, _)| kind)
+        .max_by(|(_, exact1, q1), (_, exact2, q2)| {
+            exact1
+                .cmp(exact2)
+                .then_with(|| q1.partial_cmp(q2).unwrap_or(Ordering::Equal))
+        })
+        .map(|(kind, _, _)| kind)
 }
 
 fn determine_format_from_params(request: &Request<Body>) -> Option<FormatKind> {
@@ -209,21 +650,32 @@ fn determine_format_from_params(request: &Request<Body>) -> Option<FormatKind> {
         })
         .flat_map(|s| match s.as_ref() {
             "cbor" => Some(FormatKind::CBOR),
+            "html" => Some(FormatKind::HTML),
             "json" => Some(FormatKind::JSON),
+            "jsonld" => Some(FormatKind::JsonLd),
+            "markdown" => Some(FormatKind::Markdown),
             "xml" => Some(FormatKind::XML),
             _ => None,
         })
         .next()
 }
 
-fn check_method(request: &Request<Body>) -> Handle<()> {
-    if request.method() == Method::GET {
+fn check_method(opts: &ServeOpts, request: &Request<Body>) -> Handle<()> {
+    let method = request.method();
+    // Preflight requests are only answered when cross-origin requests are allowed at all.
+    if method == Method::GET
+        || method == Method::HEAD
+        || (method == Method::OPTIONS && !opts.cors_origins.is_empty())
+    {
         Ok(())
     } else {
         Err(menthod_not_allowed().err_into())
     }
 }
 
+/// Resolves a request path to a file or directory under `root`, which must be canonical.
+///
+/// Unless `follow` is set, symlinks are only followed as far as they stay within the root.
 fn normalize_path(follow: bool, root: &Path, path_str: &str) -> Handle<PathBuf> {
     use std::io::ErrorKind::*;
     use std::path::Component::*;
@@ -235,13 +687,12 @@ fn normalize_path(follow: bool, root: &Path, path_str: &str) -> Handle<PathBuf>
             _ => return Err(bad_request().err_into()),
         }
     }
-    let full = root.join(buf);
-    let canon = full.canonicalize().map_err(|e| match e.kind() {
+    let canon = root.join(buf).canonicalize().map_err(|e| match e.kind() {
         NotFound => not_found().err_into(),
         PermissionDenied => forbidden().err_into(),
         _ => Err(e).err_into(),
     })?;
-    if (follow || canon == full) && (canon.is_file() || canon.is_dir()) {
+    if (follow || canon.starts_with(root)) && (canon.is_file() || canon.is_dir()) {
         Ok(canon)
     } else {
         Err(not_found().err_into())
@@ -267,28 +718,85 @@ where
 fn auto_format_default() {
     let req = Request::new(Body::default());
     assert_eq!(
-        FormatKind::XML,
-        determine_format(&req),
+        Some(FormatKind::XML),
+        determine_format(&req, FormatKind::XML, false),
         "if no format is specified, default to XML",
     );
 }
 
+#[test]
+fn auto_format_configured_default() {
+    let req = Request::new(Body::default());
+    assert_eq!(
+        Some(FormatKind::JSON),
+        determine_format(&req, FormatKind::JSON, false),
+        "if no format is specified, use the configured default",
+    );
+    let req = Request::builder()
+        .header(header::ACCEPT, "image/png, */*;q=0.5")
+        .body(Body::default())
+        .unwrap();
+    assert_eq!(
+        Some(FormatKind::CBOR),
+        determine_format(&req, FormatKind::CBOR, true),
+    );
+}
+
 #[test]
 fn auto_format_accept() {
     let req = Request::builder()
         .header(
             header::ACCEPT,
-            "text/html;q=1, text/xml;q=0.5, application/json;q=0.9",
+            "text/html;q=0.8, text/xml;q=0.5, application/json;q=0.9",
         )
         .body(Body::default())
         .unwrap();
     assert_eq!(
-        FormatKind::JSON,
-        determine_format(&req),
+        Some(FormatKind::JSON),
+        determine_format(&req, FormatKind::XML, false),
         "if an ACCEPT header is provided, provide the supported format with the highest quality",
     );
 }
 
+#[test]
+fn auto_format_accept_html() {
+    let req = Request::builder()
+        .header(header::ACCEPT, "text/html, application/xml;q=0.9")
+        .body(Body::default())
+        .unwrap();
+    assert_eq!(
+        Some(FormatKind::HTML),
+        determine_format(&req, FormatKind::XML, false),
+        "browsers asking for HTML are given HTML",
+    );
+}
+
+#[test]
+fn auto_format_accept_json_ld() {
+    let req = Request::builder()
+        .header(
+            header::ACCEPT,
+            "application/ld+json, application/json;q=0.9",
+        )
+        .body(Body::default())
+        .unwrap();
+    assert_eq!(
+        Some(FormatKind::JsonLd),
+        determine_format(&req, FormatKind::XML, false),
+        "JSON-LD is told apart from plain JSON by the subtype",
+    );
+    assert_eq!(
+        Some(FormatKind::JSON),
+        determine_value_format(&req, FormatKind::XML, true),
+        "only documents can be written as JSON-LD",
+    );
+    let req = Request::builder()
+        .uri("/?jsonld")
+        .body(Body::default())
+        .unwrap();
+    assert_eq!(None, determine_value_format(&req, FormatKind::XML, false));
+}
+
 #[test]
 fn auto_format_params() {
     let req = Request::builder()
@@ -296,8 +804,8 @@ fn auto_format_params() {
         .body(Body::default())
         .unwrap();
     assert_eq!(
-        FormatKind::CBOR,
-        determine_format(&req),
+        Some(FormatKind::CBOR),
+        determine_format(&req, FormatKind::XML, false),
         "if query parameters are provided with a format, return the first supported format",
     );
 }
@@ -313,8 +821,745 @@ fn auto_format_accept_and_params() {
         .body(Body::default())
         .unwrap();
     assert_eq!(
-        FormatKind::CBOR,
-        determine_format(&req),
+        Some(FormatKind::CBOR),
+        determine_format(&req, FormatKind::XML, false),
         "query parameters take precedence over the ACCEPT header (browsers send ACCEPT by default)",
     );
 }
+
+#[test]
+fn auto_format_accept_rejected() {
+    let accept = |accept: &str| {
+        Request::builder()
+            .header(header::ACCEPT, accept)
+            .body(Body::default())
+            .unwrap()
+    };
+    let req = accept("application/json;q=0");
+    assert_eq!(
+        Some(FormatKind::XML),
+        determine_format(&req, FormatKind::XML, false),
+        "a quality of zero rules a type out, even if it's the only one",
+    );
+    assert_eq!(None, determine_format(&req, FormatKind::XML, true));
+    let req = accept("application/json;q=0, text/html;q=0.1");
+    assert_eq!(
+        Some(FormatKind::HTML),
+        determine_format(&req, FormatKind::XML, true)
+    );
+    let req = accept("image/png, application/pdf");
+    assert_eq!(
+        Some(FormatKind::XML),
+        determine_format(&req, FormatKind::XML, false)
+    );
+    assert_eq!(
+        None,
+        determine_format(&req, FormatKind::XML, true),
+        "in strict mode, an ACCEPT header with no supported types has no format",
+    );
+    assert_eq!(
+        Some(FormatKind::XML),
+        determine_format(&Request::new(Body::default()), FormatKind::XML, true),
+        "in strict mode, requests without an ACCEPT header still default to XML",
+    );
+}
+
+#[test]
+fn auto_format_accept_wildcards() {
+    let accept = |accept: &str| {
+        let req = Request::builder()
+            .header(header::ACCEPT, accept)
+            .body(Body::default())
+            .unwrap();
+        determine_format(&req, FormatKind::XML, true)
+    };
+    assert_eq!(Some(FormatKind::XML), accept("*/*"));
+    assert_eq!(
+        Some(FormatKind::JSON),
+        accept("image/png, application/*;q=0.2")
+    );
+    assert_eq!(
+        Some(FormatKind::JSON),
+        accept("*/*, application/json;q=0.1"),
+        "exact types are preferred to wildcards, even with a lower quality",
+    );
+    assert_eq!(
+        Some(FormatKind::HTML),
+        accept("text/html;q=0.5, application/*, application/json;q=0.4, */*;q=0.8"),
+        "the best exact type wins",
+    );
+    assert_eq!(None, accept("*/*;q=0, image/png"));
+}
+
+#[test]
+fn auto_format_accept_type_ranges() {
+    let accept = |accept: &str| {
+        let req = Request::builder()
+            .header(header::ACCEPT, accept)
+            .body(Body::default())
+            .unwrap();
+        determine_format(&req, FormatKind::Markdown, true)
+    };
+    assert_eq!(Some(FormatKind::Markdown), accept("*/*"));
+    assert_eq!(
+        Some(FormatKind::JSON),
+        accept("application/*"),
+        "application/* never stands for a text default",
+    );
+    assert_eq!(Some(FormatKind::HTML), accept("image/png, text/*;q=0.2"));
+    assert_eq!(
+        Some(FormatKind::JSON),
+        accept("text/*;q=0.5, application/*"),
+        "ranges are ordered by quality",
+    );
+}
+
+#[test]
+fn serve_not_acceptable() {
+    let root = TempRoot::new("406");
+    std::fs::write(
+        root.join("doc.pro"),
+        "---
+Hello
+",
+    )
+    .unwrap();
+    let mut opts = test_opts(&root, false);
+    opts.strict_accept = true;
+    let req = Request::builder()
+        .uri("/doc.pro")
+        .header(header::ACCEPT, "image/png")
+        .body(Body::default())
+        .unwrap();
+    let (status, _, _) = test_get(opts, req);
+    assert_eq!(406, status);
+}
+
+#[cfg(test)]
+use super::access::LogFormat;
+
+#[cfg(test)]
+fn test_opts(root: &Path, list_directories: bool) -> ServeOpts {
+    ServeOpts {
+        cache_opts: None,
+        listen_address: [127, 0, 0, 1].into(),
+        listen_port: 0,
+        follow_symlinks: false,
+        format: Default::default(),
+        list_directories,
+        log_format: LogFormat::Pretty,
+        compress: false,
+        cors_origins: Vec::new(),
+        default_format: FormatKind::XML,
+        error_pages: Default::default(),
+        max_connections: None,
+        media_types: Default::default(),
+        timeout: None,
+        watch: false,
+        root_path: root.canonicalize().unwrap(),
+        strict_accept: false,
+    }
+}
+
+/// A directory for a test to serve, which is removed again when dropped, even if the test fails.
+#[cfg(test)]
+struct TempRoot(PathBuf);
+
+#[cfg(test)]
+impl TempRoot {
+    fn new(name: &str) -> Self {
+        let path = format!("prosidy-serve-{}-{}", name, std::process::id());
+        let path = std::env::temp_dir().join(path);
+        std::fs::create_dir_all(&path).unwrap();
+        TempRoot(path)
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TempRoot {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempRoot {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+fn test_get(opts: ServeOpts, req: Request<Body>) -> (u16, header::HeaderMap, Vec<u8>) {
+    let rt = Runtime::new().unwrap();
+    let response = rt
+        .block_on(handle(Arc::new(opts), None, None, req))
+        .unwrap();
+    let status = response.status().as_u16();
+    let headers = response.headers().clone();
+    let body = rt.block_on(async {
+        let mut body = response.into_body();
+        let mut buf = Vec::new();
+        while let Some(chunk) = body.next().await {
+            buf.extend_from_slice(&chunk.unwrap());
+        }
+        buf
+    });
+    (status, headers, body)
+}
+
+#[cfg(test)]
+fn test_request(uri: &str) -> Request<Body> {
+    Request::builder().uri(uri).body(Body::default()).unwrap()
+}
+
+#[test]
+fn serve_directory_index() {
+    let root = TempRoot::new("index");
+    std::fs::write(root.join("index.pro"), "title: Home\n---\nWelcome!\n").unwrap();
+    std::fs::write(root.join("index.html"), "<p>Not me</p>").unwrap();
+    let (status, _, body) = test_get(test_opts(&root, false), test_request("/"));
+    let body = String::from_utf8(body).unwrap();
+    assert_eq!(200, status);
+    assert!(body.contains(r#"<prosidy:document title="Home""#));
+    assert!(body.contains("Welcome!"));
+}
+
+#[test]
+fn serve_directory_listing() {
+    let root = TempRoot::new("list");
+    std::fs::create_dir_all(root.join("sub")).unwrap();
+    std::fs::write(root.join("post.pro"), "title: A Post\n---\nHello\n").unwrap();
+    std::fs::write(root.join("style.css"), "p {}").unwrap();
+    let (unlisted, _, manifest) = test_get(test_opts(&root, false), test_request("/?json"));
+    let (status, headers, body) = test_get(test_opts(&root, true), test_request("/"));
+    let (missing, _, _) = test_get(test_opts(&root, true), test_request("/nowhere/"));
+    let body = String::from_utf8(body).unwrap();
+    assert_eq!(200, unlisted, "unlisted directories serve a manifest");
+    let manifest: serde_json::Value = serde_json::from_slice(&manifest).unwrap();
+    assert_eq!("A Post", manifest["post.pro"]["settings"]["title"]);
+    assert_eq!(404, missing);
+    assert_eq!(200, status);
+    assert_eq!("text/xml", headers[header::CONTENT_TYPE]);
+    assert!(body.contains(r#"<prosidy:item prosidy:path="post.pro" title="A Post"/>"#));
+    assert!(body.contains(r#"<prosidy:file prosidy:path="style.css"/>"#));
+    assert!(body.contains(r#"<prosidy:file prosidy:path="sub/"/>"#));
+}
+
+#[test]
+fn serve_manifest() {
+    use super::opts::CacheOpts;
+
+    let root = TempRoot::new("manifest");
+    std::fs::write(root.join("post.pro"), "title: A Post\n---\nHello\n").unwrap();
+    std::fs::write(root.join("style.css"), "p {}").unwrap();
+    let opts = || ServeOpts {
+        cache_opts: Some(CacheOpts {
+            max_age: 60,
+            validate: true,
+        }),
+        ..test_opts(&root, false)
+    };
+    let (status, headers, body) = test_get(opts(), test_request("/__prosidy/manifest?json"));
+    let req = Request::builder()
+        .uri("/__prosidy/manifest?json")
+        .header(header::IF_NONE_MATCH, headers[header::ETAG].clone())
+        .body(Body::default())
+        .unwrap();
+    let (revalidated, _, _) = test_get(opts(), req);
+    std::fs::write(root.join("draft.pro"), "title: A Draft\n---\n").unwrap();
+    let req = Request::builder()
+        .uri("/__prosidy/manifest?json")
+        .header(header::IF_NONE_MATCH, headers[header::ETAG].clone())
+        .body(Body::default())
+        .unwrap();
+    let (changed, _, _) = test_get(opts(), req);
+    let (json_ld, _, _) = test_get(opts(), test_request("/__prosidy/manifest?jsonld"));
+    assert_eq!(200, status);
+    assert_eq!("application/json", headers[header::CONTENT_TYPE]);
+    assert_eq!(body.len().to_string(), headers[header::CONTENT_LENGTH]);
+    let manifest: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        serde_json::json!({
+            "post.pro": { "properties": [], "settings": { "title": "A Post" } }
+        }),
+        manifest
+    );
+    assert_eq!(304, revalidated);
+    assert_eq!(200, changed);
+    assert_eq!(406, json_ld);
+}
+
+#[test]
+fn serve_manifest_warnings() {
+    let root = TempRoot::new("manifest-warnings");
+    std::fs::write(root.join("post.pro"), "title: A Post\n---\n").unwrap();
+    std::fs::write(root.join("bad \"é\".pro"), b"title: \xff\n---\n").unwrap();
+    std::fs::write(root.join("logo.png"), b"\x89PNG\r\n\x1a\n\xff").unwrap();
+    let (status, headers, body) = test_get(
+        test_opts(&root, false),
+        test_request("/__prosidy/manifest?json"),
+    );
+    assert_eq!(200, status);
+    let warnings: Vec<_> = headers.get_all(header::WARNING).iter().collect();
+    // Only the Prosidy file is reported; the image simply isn't a document.
+    assert_eq!(1, warnings.len());
+    let warning = warnings[0].to_str().unwrap();
+    assert!(
+        warning.starts_with(r#"199 - "Skipped bad \"\u{e9}\".pro: "#),
+        "{}",
+        warning
+    );
+    let manifest: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(manifest.get("post.pro").is_some());
+}
+
+#[test]
+fn serve_meta() {
+    use super::opts::CacheOpts;
+
+    let root = TempRoot::new("meta");
+    std::fs::write(root.join("doc.pro"), "title: Meta\ndraft\n---\nHello\n").unwrap();
+    // Only the header is parsed, so a broken body goes unnoticed.
+    std::fs::write(root.join("broken.pro"), "title: Broken\n---\n#oops{\n").unwrap();
+    let opts = || ServeOpts {
+        cache_opts: Some(CacheOpts {
+            max_age: 60,
+            validate: true,
+        }),
+        ..test_opts(&root, false)
+    };
+    let (status, headers, body) = test_get(opts(), test_request("/doc.pro?meta"));
+    let req = Request::builder()
+        .uri("/doc.pro?meta")
+        .header(header::IF_NONE_MATCH, headers[header::ETAG].clone())
+        .body(Body::default())
+        .unwrap();
+    let (revalidated, _, _) = test_get(opts(), req);
+    let (document, document_headers, _) = test_get(opts(), test_request("/doc.pro"));
+    let (_, cbor_headers, cbor) = test_get(opts(), test_request("/doc.pro?meta&cbor"));
+    let (broken, _, _) = test_get(opts(), test_request("/broken.pro?meta"));
+    assert_eq!(200, status);
+    assert_eq!("application/json", headers[header::CONTENT_TYPE]);
+    let meta: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        serde_json::json!({
+            "title": "Meta",
+            "properties": ["draft"],
+            "settings": { "title": "Meta" },
+        }),
+        meta
+    );
+    assert_eq!(304, revalidated);
+    assert_eq!(200, document);
+    assert_ne!(
+        headers.get(header::ETAG),
+        document_headers.get(header::ETAG)
+    );
+    assert_eq!("application/cbor", cbor_headers[header::CONTENT_TYPE]);
+    let meta: serde_json::Value = serde_cbor::from_slice(&cbor).unwrap();
+    assert_eq!("Meta", meta["title"]);
+    assert_eq!(200, broken);
+}
+
+#[test]
+fn serve_compressed() {
+    use super::opts::CacheOpts;
+
+    let root = TempRoot::new("gzip");
+    std::fs::write(root.join("doc.pro"), "title: Zip\n---\nSquash me.\n").unwrap();
+    let opts = || ServeOpts {
+        cache_opts: Some(CacheOpts {
+            max_age: 60,
+            validate: false,
+        }),
+        compress: true,
+        ..test_opts(&root, false)
+    };
+    let (_, plain_headers, plain) = test_get(opts(), test_request("/doc.pro"));
+    let req = Request::builder()
+        .uri("/doc.pro")
+        .header(header::ACCEPT_ENCODING, "deflate, gzip;q=0.5")
+        .body(Body::default())
+        .unwrap();
+    let (status, headers, gzipped) = test_get(opts(), req);
+    assert_eq!(200, status);
+    assert!(plain_headers.get(header::CONTENT_ENCODING).is_none());
+    assert_eq!("gzip", headers[header::CONTENT_ENCODING]);
+    assert_eq!("Accept-Encoding", headers[header::VARY]);
+    assert_eq!(gzipped.len().to_string(), headers[header::CONTENT_LENGTH]);
+    assert_eq!(plain_headers[header::ETAG], headers[header::ETAG]);
+    let size = &gzipped[gzipped.len() - 4..];
+    assert_eq!(&(plain.len() as u32).to_le_bytes()[..], size);
+}
+
+#[test]
+fn accept_encoding() {
+    let accepts = |value: &str| {
+        let req = Request::builder()
+            .header(header::ACCEPT_ENCODING, value)
+            .body(Body::default())
+            .unwrap();
+        accepts_gzip(&req)
+    };
+    assert!(accepts("gzip"));
+    assert!(accepts("br, GZIP;q=0.8"));
+    assert!(accepts("*"));
+    assert!(!accepts("deflate, br"));
+    assert!(!accepts("gzip;q=0"));
+}
+
+#[test]
+fn serve_reload_events() {
+    let root = TempRoot::new("reload");
+    std::fs::write(root.join("doc.pro"), "title: Live\n---\nReload me.\n").unwrap();
+    let opts = Arc::new(ServeOpts {
+        watch: true,
+        ..test_opts(&root, false)
+    });
+    let reload = Arc::new(Reload::default());
+    let rt = Runtime::new().unwrap();
+
+    let req = Request::builder()
+        .uri("/doc.pro?html")
+        .body(Body::default())
+        .unwrap();
+    let response = rt
+        .block_on(handle(opts.clone(), Some(reload.clone()), None, req))
+        .unwrap();
+    let page = rt.block_on(response.into_body().next()).unwrap().unwrap();
+    let page = String::from_utf8(page.to_vec()).unwrap();
+    assert!(page.ends_with(&format!("{}</body></html>\n", RELOAD_SCRIPT)));
+
+    let req = test_request(RELOAD_PATH);
+    let response = rt
+        .block_on(handle(opts, Some(reload.clone()), None, req))
+        .unwrap();
+    assert_eq!(
+        "text/event-stream",
+        response.headers()[header::CONTENT_TYPE]
+    );
+    reload.notify();
+    let event = rt.block_on(response.into_body().next()).unwrap().unwrap();
+    assert_eq!(&b"data: reload\n\n"[..], &event[..]);
+}
+
+#[test]
+fn serve_cors() {
+    let root = TempRoot::new("cors");
+    std::fs::write(root.join("doc.pro"), "title: Shared\n---\nHello\n").unwrap();
+    let opts = |origins: &[&str]| ServeOpts {
+        cors_origins: origins.iter().map(|origin| origin.to_string()).collect(),
+        ..test_opts(&root, false)
+    };
+    let request = |method: Method, origin: &str| {
+        Request::builder()
+            .method(method)
+            .uri("/doc.pro?json")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "x-requested-with")
+            .body(Body::default())
+            .unwrap()
+    };
+    let app = "https://app.example.com";
+    let (_, allowed, _) = test_get(opts(&[app]), request(Method::GET, app));
+    let (_, other, _) = test_get(opts(&[app]), request(Method::GET, "https://evil.example"));
+    let (_, any, _) = test_get(opts(&["*"]), request(Method::GET, app));
+    let (_, off, _) = test_get(opts(&[]), request(Method::GET, app));
+    let (preflight, preflight_headers, _) = test_get(opts(&[app]), request(Method::OPTIONS, app));
+    let (rejected, _, _) = test_get(opts(&[]), request(Method::OPTIONS, app));
+
+    assert_eq!(app, allowed[header::ACCESS_CONTROL_ALLOW_ORIGIN]);
+    assert_eq!("Origin", allowed[header::VARY]);
+    assert!(other.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    assert_eq!("*", any[header::ACCESS_CONTROL_ALLOW_ORIGIN]);
+    assert!(off.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    assert_eq!(204, preflight);
+    assert_eq!(app, preflight_headers[header::ACCESS_CONTROL_ALLOW_ORIGIN]);
+    assert_eq!(
+        "GET, HEAD, OPTIONS",
+        preflight_headers[header::ACCESS_CONTROL_ALLOW_METHODS]
+    );
+    assert_eq!(
+        "x-requested-with",
+        preflight_headers[header::ACCESS_CONTROL_ALLOW_HEADERS]
+    );
+    assert_eq!(405, rejected);
+}
+
+#[test]
+fn serve_range() {
+    let root = TempRoot::new("range");
+    std::fs::write(root.join("clip.txt"), "0123456789").unwrap();
+    std::fs::write(root.join("doc.pro"), "---\nWhole\n").unwrap();
+    let ranged = |uri: &str, range: &str| {
+        let req = Request::builder()
+            .uri(uri)
+            .header(header::RANGE, range)
+            .body(Body::default())
+            .unwrap();
+        test_get(test_opts(&root, false), req)
+    };
+    let (status, headers, body) = ranged("/clip.txt", "bytes=2-5");
+    let (unsatisfiable, unsatisfiable_headers, _) = ranged("/clip.txt", "bytes=10-");
+    let (prosidy, _, _) = ranged("/doc.pro", "bytes=0-1");
+    assert_eq!(206, status);
+    assert_eq!(&b"2345"[..], &body[..]);
+    assert_eq!("bytes 2-5/10", headers[header::CONTENT_RANGE]);
+    assert_eq!("bytes", headers[header::ACCEPT_RANGES]);
+    assert_eq!("4", headers[header::CONTENT_LENGTH]);
+    assert_eq!(416, unsatisfiable);
+    assert_eq!("bytes */10", unsatisfiable_headers[header::CONTENT_RANGE]);
+    assert_eq!(200, prosidy);
+    // A range covering the whole file is still sent as stored, even to a client taking gzip.
+    let req = Request::builder()
+        .uri("/clip.txt")
+        .header(header::RANGE, "bytes=0-")
+        .header(header::ACCEPT_ENCODING, "gzip")
+        .body(Body::default())
+        .unwrap();
+    let opts = ServeOpts {
+        compress: true,
+        ..test_opts(&root, false)
+    };
+    let (status, headers, body) = test_get(opts, req);
+    assert_eq!(206, status);
+    assert_eq!(&b"0123456789"[..], &body[..]);
+    assert_eq!("bytes 0-9/10", headers[header::CONTENT_RANGE]);
+    assert!(!headers.contains_key(header::CONTENT_ENCODING));
+}
+
+#[test]
+fn serve_streamed() {
+    use super::opts::CacheOpts;
+
+    let root = TempRoot::new("stream");
+    let data: Vec<u8> = (0..1 << 20).map(|i| (i % 251) as u8).collect();
+    std::fs::write(root.join("large.bin"), &data).unwrap();
+    let opts = || ServeOpts {
+        cache_opts: Some(CacheOpts {
+            max_age: 60,
+            validate: true,
+        }),
+        ..test_opts(&root, false)
+    };
+    let (status, headers, body) = test_get(opts(), test_request("/large.bin"));
+    let req = Request::builder()
+        .uri("/large.bin")
+        .header(header::IF_NONE_MATCH, headers[header::ETAG].clone())
+        .body(Body::default())
+        .unwrap();
+    let (revalidated, _, _) = test_get(opts(), req);
+    assert_eq!(200, status);
+    assert_eq!(data.len().to_string(), headers[header::CONTENT_LENGTH]);
+    assert!(body == data);
+    assert_eq!(304, revalidated);
+}
+
+#[test]
+fn serve_over_limit() {
+    let root = TempRoot::new("limit");
+    std::fs::write(root.join("doc.pro"), "---\nBusy\n").unwrap();
+    let opts = Arc::new(test_opts(&root, false));
+    let limit = Arc::new(Limit::new(1));
+    let rt = Runtime::new().unwrap();
+    let get = |limit: &Arc<Limit>| {
+        let response = handle(
+            opts.clone(),
+            None,
+            Some(limit.clone()),
+            test_request("/doc.pro"),
+        );
+        rt.block_on(response).unwrap().status().as_u16()
+    };
+    let ok = get(&limit);
+    let slot = limit.try_acquire();
+    let busy = get(&limit);
+    drop(slot);
+    let recovered = get(&limit);
+    assert_eq!((200, 503, 200), (ok, busy, recovered));
+}
+
+#[test]
+fn slot_outlives_timeout() {
+    use std::sync::mpsc;
+
+    let limit = Arc::new(Limit::new(1));
+    let mut request = test_request("/doc.pro");
+    request
+        .extensions_mut()
+        .insert(limit.try_acquire().unwrap());
+    let (release, released) = mpsc::channel::<()>();
+    // Like a document render, this owns the request, and so its slot, until it returns.
+    let render = blocking::run(move || {
+        let _request = request;
+        released.recv().unwrap();
+        Ok(Response::new(Body::empty()))
+    });
+    let rt = Runtime::new().unwrap();
+    let response = rt
+        .block_on(within(Some(Duration::from_millis(10)), render))
+        .unwrap();
+    assert_eq!(504, response.status().as_u16());
+    assert!(
+        limit.try_acquire().is_none(),
+        "the slot is held while the render is still running"
+    );
+    release.send(()).unwrap();
+    let start = Instant::now();
+    while limit.try_acquire().is_none() {
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "the slot was never freed"
+        );
+        std::thread::yield_now();
+    }
+}
+
+#[test]
+fn serve_timeout() {
+    let root = TempRoot::new("timeout");
+    std::fs::write(root.join("fast.pro"), "---\nQuick\n").unwrap();
+    let opts = ServeOpts {
+        timeout: Some(Duration::from_secs(10)),
+        ..test_opts(&root, false)
+    };
+    let (fast, _, _) = test_get(opts, test_request("/fast.pro"));
+    // Sleeps far past the budget, so the timeout fires first however slow the machine is.
+    let slow = blocking::run(|| {
+        std::thread::sleep(Duration::from_millis(200));
+        Ok(Response::new(Body::empty()))
+    });
+    let rt = Runtime::new().unwrap();
+    let timed_out = rt
+        .block_on(within(Some(Duration::from_millis(1)), slow))
+        .unwrap();
+    assert_eq!(200, fast);
+    assert_eq!(504, timed_out.status().as_u16());
+}
+
+#[cfg(unix)]
+#[test]
+fn serve_symlinks() {
+    use std::os::unix::fs::symlink;
+
+    let base = TempRoot::new("links");
+    let (root, outside) = (base.join("root"), base.join("outside"));
+    std::fs::create_dir_all(root.join("docs")).unwrap();
+    std::fs::create_dir_all(&outside).unwrap();
+    std::fs::write(root.join("docs").join("inner.txt"), "inside").unwrap();
+    std::fs::write(outside.join("secret.txt"), "outside").unwrap();
+    symlink(&outside, root.join("escape")).unwrap();
+    symlink(root.join("docs"), root.join("alias")).unwrap();
+    symlink(&root, base.join("linked-root")).unwrap();
+
+    let (escaped, _, _) = test_get(test_opts(&root, false), test_request("/escape/secret.txt"));
+    let (followed, _, _) = test_get(
+        ServeOpts {
+            follow_symlinks: true,
+            ..test_opts(&root, false)
+        },
+        test_request("/escape/secret.txt"),
+    );
+    let (aliased, _, _) = test_get(test_opts(&root, false), test_request("/alias/inner.txt"));
+    let linked_root = test_opts(&base.join("linked-root"), false);
+    let (via_linked_root, _, body) = test_get(linked_root, test_request("/docs/inner.txt"));
+
+    assert_eq!(404, escaped);
+    assert_eq!(200, followed);
+    assert_eq!(200, aliased);
+    assert_eq!(200, via_linked_root);
+    assert_eq!(&b"inside"[..], &body[..]);
+}
+
+#[test]
+fn serve_error_page() {
+    let root = TempRoot::new("errors");
+    std::fs::write(root.join("missing.html"), "<h1>Lost?</h1>").unwrap();
+    let opts = || ServeOpts {
+        error_pages: vec![(404, root.join("missing.html"))].into_iter().collect(),
+        ..test_opts(&root, false)
+    };
+    let (status, headers, body) = test_get(opts(), test_request("/nowhere.pro"));
+    let post = Request::builder()
+        .method(Method::POST)
+        .uri("/")
+        .body(Body::default())
+        .unwrap();
+    let (not_allowed, not_allowed_headers, _) = test_get(opts(), post);
+    assert_eq!(404, status);
+    assert_eq!("text/html", headers[header::CONTENT_TYPE]);
+    assert_eq!(&b"<h1>Lost?</h1>"[..], &body[..]);
+    assert_eq!(405, not_allowed);
+    assert_eq!("text/xml", not_allowed_headers[header::CONTENT_TYPE]);
+}
+
+#[test]
+fn serve_last_modified() {
+    use super::opts::CacheOpts;
+
+    let root = TempRoot::new("mtime");
+    std::fs::write(root.join("doc.pro"), "---\nDated\n").unwrap();
+    let opts = || ServeOpts {
+        cache_opts: Some(CacheOpts {
+            max_age: 60,
+            validate: true,
+        }),
+        ..test_opts(&root, false)
+    };
+    let since = |date: &str, etag: Option<&str>| {
+        let mut req = Request::builder();
+        req.uri("/doc.pro").header(header::IF_MODIFIED_SINCE, date);
+        if let Some(etag) = etag {
+            req.header(header::IF_NONE_MATCH, etag);
+        }
+        test_get(opts(), req.body(Body::default()).unwrap()).0
+    };
+    let (status, headers, _) = test_get(opts(), test_request("/doc.pro"));
+    let last_modified = headers[header::LAST_MODIFIED].to_str().unwrap();
+    let unchanged = since(last_modified, None);
+    let stale = since("Thu, 01 Jan 1970 00:00:00 GMT", None);
+    let mismatched = since(last_modified, Some("elsewhere"));
+    assert_eq!(200, status);
+    assert_eq!(304, unchanged);
+    assert_eq!(200, stale);
+    assert_eq!(200, mismatched);
+}
+
+#[test]
+fn serve_head() {
+    use super::opts::CacheOpts;
+
+    let root = TempRoot::new("head");
+    std::fs::write(root.join("doc.pro"), "title: Head\n---\nHello\n").unwrap();
+    let opts = || ServeOpts {
+        cache_opts: Some(CacheOpts {
+            max_age: 60,
+            validate: true,
+        }),
+        ..test_opts(&root, false)
+    };
+    let head = |uri: &str| {
+        let req = Request::builder()
+            .method(Method::HEAD)
+            .uri(uri)
+            .body(Body::default())
+            .unwrap();
+        test_get(opts(), req)
+    };
+    let (get_status, get_headers, get_body) = test_get(opts(), test_request("/doc.pro"));
+    let (status, headers, body) = head("/doc.pro");
+    let (missing, missing_headers, missing_body) = head("/nowhere.pro");
+    assert_eq!(200, get_status);
+    assert_eq!(200, status);
+    assert!(body.is_empty());
+    assert_eq!(get_body.len().to_string(), headers[header::CONTENT_LENGTH]);
+    for name in &[header::CONTENT_TYPE, header::ETAG, header::LAST_MODIFIED] {
+        assert_eq!(get_headers[name], headers[name]);
+    }
+    assert_eq!(404, missing);
+    assert!(missing_body.is_empty());
+    assert_ne!("0", missing_headers[header::CONTENT_LENGTH]);
+}
@@ -0,0 +1,131 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::io::Write;
+
+use anyhow::Result;
+use prosidy::xml::quick_xml::events::{BytesDecl, BytesEnd, BytesStart, Event};
+use prosidy::xml::quick_xml::{Result as XMLResult, Writer};
+use prosidy::xml::{XMLConfig, XML};
+use prosidy::Key;
+
+use crate::atom::{page_url, text_element};
+use crate::manifest::Manifest;
+use crate::timestamp::{format_rfc3339, parse_rfc3339};
+
+/// A sitemap listing the compiled page of every document in a [`Manifest`].
+///
+/// Each document is linked to its page, as [`page_url`] describes. Its last modification time is
+/// taken from its `date` setting, falling back to the file's modification time.
+#[derive(Debug)]
+pub struct Sitemap {
+    urls: Vec<SitemapUrl>,
+}
+
+#[derive(Debug)]
+struct SitemapUrl {
+    loc: String,
+    lastmod: Option<String>,
+}
+
+impl Sitemap {
+    const SITEMAP_URI: &'static str = "http://www.sitemaps.org/schemas/sitemap/0.9";
+
+    pub fn new(manifest: &Manifest, base_url: &str) -> Result<Sitemap> {
+        let base_url = base_url.trim_end_matches('/');
+        let date = Key::new("date");
+        let mut urls = Vec::new();
+        for (path, entry) in manifest.iter() {
+            let lastmod = match entry.props().lookup(&date) {
+                Some(date) => {
+                    let date = date.trim();
+                    anyhow::ensure!(
+                        parse_rfc3339(date.as_str()).is_some(),
+                        "{:?} has a malformed date {:?}; expected an RFC 3339 timestamp such as \
+                         2020-01-31T09:30:00Z",
+                        path,
+                        date.as_str(),
+                    );
+                    Some(date.as_str().to_string())
+                }
+                None => entry.modified().map(format_rfc3339),
+            };
+            urls.push(SitemapUrl {
+                loc: page_url(base_url, path),
+                lastmod,
+            });
+        }
+        urls.sort_by(|a, b| a.loc.cmp(&b.loc));
+        Ok(Sitemap { urls })
+    }
+}
+
+impl XML for Sitemap {
+    fn to_events<F>(&self, _config: &XMLConfig, emit: &mut F) -> XMLResult<()>
+    where
+        F: for<'a> FnMut(Event<'a>) -> XMLResult<()>,
+    {
+        let mut start = BytesStart::borrowed_name(b"urlset");
+        start.push_attribute(("xmlns", Sitemap::SITEMAP_URI));
+        emit(Event::Start(start))?;
+        for url in self.urls.iter() {
+            emit(Event::Start(BytesStart::borrowed_name(b"url")))?;
+            text_element(emit, "loc", &url.loc)?;
+            if let Some(ref lastmod) = url.lastmod {
+                text_element(emit, "lastmod", lastmod)?;
+            }
+            emit(Event::End(BytesEnd::borrowed(b"url")))?;
+        }
+        emit(Event::End(BytesEnd::borrowed(b"urlset")))
+    }
+}
+
+/// Writes a sitemap as an indented XML document.
+pub fn write_sitemap<W: Write>(writer: W, sitemap: &Sitemap) -> Result<()> {
+    let mut writer = Writer::new_with_indent(writer, b' ', 2);
+    let decl = BytesDecl::new(b"1.0", Some(b"UTF-8"), None);
+    writer.write_event(Event::Decl(decl))?;
+    sitemap.to_events(&XMLConfig::default(), &mut |event| {
+        writer.write_event(event).map(|_| ())
+    })?;
+    writer.into_inner().write_all(b"\n")?;
+    Ok(())
+}
+
+#[test]
+fn sitemap_from_manifest() {
+    let root = std::env::temp_dir().join(format!("prosidy-sitemap-{}", std::process::id()));
+    std::fs::create_dir_all(root.join("posts")).unwrap();
+    let write_sitemap_of = |root: &std::path::Path| {
+        let manifest = Manifest::read(root, false, true, &[]).unwrap();
+        let mut out = Vec::new();
+        write_sitemap(
+            &mut out,
+            &Sitemap::new(&manifest, "https://example.com").unwrap(),
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    let empty = write_sitemap_of(&root);
+    std::fs::write(root.join("index.pro"), "title: Home\n---\n").unwrap();
+    let dated = "date: 2020-01-01T12:00:00+02:00\n---\n";
+    std::fs::write(root.join("posts").join("first.pro"), dated).unwrap();
+    let modified = std::fs::metadata(root.join("index.pro"))
+        .and_then(|meta| meta.modified())
+        .unwrap();
+    let full = write_sitemap_of(&root);
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert!(empty.contains("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">"));
+    assert!(!empty.contains("<url>"));
+    let expected = format!(
+        "\n  <url>\n    <loc>https://example.com/index.html</loc>\n    \
+         <lastmod>{}</lastmod>\n  </url>\
+         \n  <url>\n    <loc>https://example.com/posts/first.html</loc>\n    \
+         <lastmod>2020-01-01T12:00:00+02:00</lastmod>\n  </url>\n",
+        format_rfc3339(modified),
+    );
+    assert!(full.contains(&expected), "{}", full);
+}
@@ -0,0 +1,226 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use prosidy::xml::quick_xml::events::{BytesEnd, BytesStart, Event};
+use prosidy::xml::{quick_xml::Result as XMLResult, XMLConfig, XML};
+use prosidy::{Block, BlockTag, Document, Inline, Key, Node};
+use serde::Serialize;
+
+use crate::fmt::Format;
+use crate::io::Output;
+
+/// A document divided into sections, each starting at a top-level occurrence of a tag.
+///
+/// Blocks preceding the first occurrence of the tag are kept in a leading `preamble` section.
+/// Every section keeps the header of the original document.
+#[derive(Debug)]
+pub struct Split<'a> {
+    contents: Contents,
+    sections: Vec<Document<'a>>,
+}
+
+impl<'a> Split<'a> {
+    const PREAMBLE: &'static str = "preamble";
+    const CONTENTS: &'static str = "contents";
+
+    pub fn new(mut doc: Document<'a>, tag_name: &str, extension: &str) -> Self {
+        let tag_name = Key::new(tag_name);
+        let mut names = Names::default();
+        names.reserve(Split::CONTENTS);
+        let mut contents = Contents::default();
+        let mut sections = Vec::new();
+        let mut current: Option<(PathBuf, Option<String>, Vec<Block<'a>>)> = None;
+        for block in std::mem::take(doc.content_mut()) {
+            match block {
+                Block::Tag(ref tag) if tag.name_is(&tag_name) => {
+                    let (name, title) = section_name(tag);
+                    let path = names.unique(name.as_deref(), extension);
+                    let next = (path, title, Vec::new());
+                    if let Some(section) = current.replace(next) {
+                        sections.push(section);
+                    }
+                }
+                _ if current.is_none() => {
+                    let path = names.unique(Some(Split::PREAMBLE), extension);
+                    let title = doc.props().title().map(|title| title.to_string());
+                    current = Some((path, title, Vec::new()));
+                }
+                _ => {}
+            }
+            if let Some((_, _, ref mut blocks)) = current {
+                blocks.push(block);
+            }
+        }
+        sections.extend(current);
+        let sections = sections
+            .into_iter()
+            .map(|(path, title, blocks)| {
+                contents.0.push(ContentsEntry { path, title });
+                Document::new(doc.props().clone().into_inner(), blocks)
+            })
+            .collect();
+        Split { contents, sections }
+    }
+
+    /// Writes each section, and a table of contents linking them, into a directory.
+    pub fn write(&self, dir: &Path, format: &Format) -> Result<()> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create output directory {:?}", dir))?;
+        for (entry, section) in self.contents.0.iter().zip(self.sections.iter()) {
+            let path = dir.join(&entry.path);
+            log::debug!("writing section to {:?}", path);
+            format.write_document(Output::open(&path)?, section)?;
+        }
+        let path = dir.join(Split::CONTENTS).with_extension(format.extension());
+        log::debug!("writing table of contents to {:?}", path);
+        format.write(Output::open(&path)?, &self.contents)
+    }
+}
+
+/// The table of contents of a [`Split`] document, listing each section's file and title.
+#[derive(Debug, Default, Serialize)]
+pub struct Contents(Vec<ContentsEntry>);
+
+impl Contents {
+    const TAG_CONTENTS: &'static str = "contents";
+    const TAG_SECTION: &'static str = "section";
+    const ATTR_PATH: &'static str = "path";
+    const ATTR_TITLE: &'static str = "title";
+}
+
+impl XML for Contents {
+    fn to_events<F>(&self, config: &XMLConfig, emit: &mut F) -> XMLResult<()>
+    where
+        F: for<'a> FnMut(Event<'a>) -> XMLResult<()>,
+    {
+        let start = BytesStart::owned_name(config.name(Contents::TAG_CONTENTS));
+        emit(Event::Start(start))?;
+        for entry in self.0.iter() {
+            let mut start = BytesStart::owned_name(config.name(Contents::TAG_SECTION));
+            let path_str = entry.path.to_string_lossy();
+            start.push_attribute((config.name(Contents::ATTR_PATH).as_str(), path_str.as_ref()));
+            if let Some(ref title) = entry.title {
+                start.push_attribute((config.name(Contents::ATTR_TITLE).as_str(), title.as_str()));
+            }
+            emit(Event::Empty(start))?;
+        }
+        let end = BytesEnd::owned(config.name(Contents::TAG_CONTENTS).into_bytes());
+        emit(Event::End(end))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ContentsEntry {
+    path: PathBuf,
+    title: Option<String>,
+}
+
+/// Tracks the file names already assigned to sections so that each is unique.
+#[derive(Default)]
+struct Names {
+    used: HashSet<String>,
+    sections: usize,
+}
+
+impl Names {
+    fn reserve(&mut self, name: &str) {
+        self.used.insert(name.to_owned());
+    }
+
+    fn unique(&mut self, name: Option<&str>, extension: &str) -> PathBuf {
+        self.sections += 1;
+        let base = name
+            .map(slugify)
+            .filter(|slug| !slug.is_empty())
+            .unwrap_or_else(|| format!("section-{}", self.sections));
+        let mut name = base.clone();
+        let mut count = 1;
+        while self.used.contains(&name) {
+            count += 1;
+            name = format!("{}-{}", base, count);
+        }
+        self.used.insert(name.clone());
+        Path::new(&name).with_extension(extension)
+    }
+}
+
+/// Picks a file name and title for a section from its `id` and `title` settings, falling back to
+/// the text content of the tag.
+fn section_name(tag: &BlockTag) -> (Option<String>, Option<String>) {
+    let id = tag.props().lookup_str(Key::new("id")).map(str::to_string);
+    let title = tag
+        .props()
+        .lookup_str(Key::new("title"))
+        .map(str::to_string)
+        .or_else(|| Some(tag_text(tag)).filter(|text| !text.is_empty()));
+    (id.or_else(|| title.clone()), title)
+}
+
+fn tag_text(tag: &BlockTag) -> String {
+    let mut text = String::new();
+    let mut stack: Vec<Node> = Vec::new();
+    for block in tag.content().iter().rev() {
+        stack.push(block.into());
+    }
+    while let Some(node) = stack.pop() {
+        match node {
+            Node::Inline(Inline::Text(t)) => text.push_str(t),
+            Node::Inline(Inline::SoftBreak) => text.push(' '),
+            _ => node.push_children(|child| stack.push(child)),
+        }
+    }
+    text
+}
+
+fn slugify(s: &str) -> String {
+    let mut slug = String::with_capacity(s.len());
+    for ch in s.chars() {
+        if ch.is_alphanumeric() {
+            slug.extend(ch.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_owned()
+}
+
+#[test]
+fn split_sections() {
+    let source = "title: Book\n---\n\
+                  Front matter.\n\n\
+                  #-h1{Intro}\n\n\
+                  Hello.\n\n\
+                  #-h1[id='next']{Next}\n\n\
+                  #-h1{Intro}\n";
+    let doc = prosidy::parse::parse_document(source).unwrap();
+    let split = Split::new(doc, "h1", "xml");
+    let paths: Vec<_> = split.contents.0.iter().map(|e| e.path.clone()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            PathBuf::from("preamble.xml"),
+            PathBuf::from("intro.xml"),
+            PathBuf::from("next.xml"),
+            PathBuf::from("intro-2.xml"),
+        ],
+    );
+    let titles: Vec<_> = split.contents.0.iter().map(|e| e.title.clone()).collect();
+    assert_eq!(
+        titles,
+        vec![
+            Some("Book".into()),
+            Some("Intro".into()),
+            Some("Next".into()),
+            Some("Intro".into()),
+        ],
+    );
+    let lengths: Vec<_> = split.sections.iter().map(|s| s.content().len()).collect();
+    assert_eq!(lengths, vec![1, 2, 1, 1]);
+}
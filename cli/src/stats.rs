@@ -0,0 +1,68 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use prosidy::xml::quick_xml::events::{BytesStart, Event};
+use prosidy::xml::{quick_xml::Result as XMLResult, XMLConfig, XML};
+use prosidy::{Block, Document, Node};
+use serde::Serialize;
+
+/// Summary counts describing the length of a document.
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    words: usize,
+    paragraphs: usize,
+}
+
+impl Stats {
+    const TAG_STATS: &'static str = "stats";
+    const ATTR_WORDS: &'static str = "words";
+    const ATTR_PARAGRAPHS: &'static str = "paragraphs";
+
+    /// Counts the words and paragraphs in a document. Words in literal blocks are only counted
+    /// if `literals` is set.
+    pub fn new(doc: &Document, literals: bool) -> Self {
+        let words = if literals {
+            doc.word_count_with_literals()
+        } else {
+            doc.word_count()
+        };
+        let paragraphs = Node::from(doc)
+            .descendants()
+            .filter(
+                |node| matches!(node, Node::Block(Block::Content(content)) if !content.is_empty()),
+            )
+            .count();
+        Stats { words, paragraphs }
+    }
+}
+
+impl XML for Stats {
+    fn to_events<F>(&self, config: &XMLConfig, emit: &mut F) -> XMLResult<()>
+    where
+        F: for<'a> FnMut(Event<'a>) -> XMLResult<()>,
+    {
+        let mut start = BytesStart::owned_name(config.name(Stats::TAG_STATS));
+        start.push_attribute((
+            config.name(Stats::ATTR_WORDS).as_str(),
+            self.words.to_string().as_str(),
+        ));
+        start.push_attribute((
+            config.name(Stats::ATTR_PARAGRAPHS).as_str(),
+            self.paragraphs.to_string().as_str(),
+        ));
+        emit(Event::Empty(start))
+    }
+}
+
+#[test]
+fn count_document() {
+    let source =
+        "title: Stats\n---\nOne #em{two} three.\n\nFour\nfive.\n\n#=code:\nfn main() {}\n#:\n";
+    let doc = prosidy::parse::parse_document(source).unwrap();
+    let stats = Stats::new(&doc, false);
+    assert_eq!((5, 2), (stats.words, stats.paragraphs));
+    let stats = Stats::new(&doc, true);
+    assert_eq!((8, 2), (stats.words, stats.paragraphs));
+}
@@ -0,0 +1,265 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Parses an RFC 3339 timestamp, such as `2020-01-31T09:30:00.5+01:00`, into seconds and
+/// nanoseconds since the Unix epoch.
+pub fn parse_rfc3339(s: &str) -> Option<(i64, u32)> {
+    let bytes = s.as_bytes();
+    let number = |start: usize, len: usize| -> Option<i64> {
+        let digits = bytes.get(start..start + len)?;
+        if !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        Some(
+            digits
+                .iter()
+                .fold(0, |acc, digit| acc * 10 + i64::from(digit - b'0')),
+        )
+    };
+    let separator = |index: usize, allowed: &[u8]| match bytes.get(index) {
+        Some(byte) => allowed.contains(byte),
+        None => false,
+    };
+    let (year, month, day) = (number(0, 4)?, number(5, 2)?, number(8, 2)?);
+    let (hour, minute, second) = (number(11, 2)?, number(14, 2)?, number(17, 2)?);
+    let separated = separator(4, b"-")
+        && separator(7, b"-")
+        && separator(10, b"Tt ")
+        && separator(13, b":")
+        && separator(16, b":");
+    if !separated
+        || !(1..=12).contains(&month)
+        || day < 1
+        || day > days_in_month(year, month)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+    let mut index = 19;
+    let mut nanos = 0u32;
+    if separator(index, b".") {
+        index += 1;
+        let start = index;
+        while matches!(bytes.get(index), Some(byte) if byte.is_ascii_digit()) {
+            if index - start < 9 {
+                nanos = nanos * 10 + u32::from(bytes[index] - b'0');
+            }
+            index += 1;
+        }
+        if index == start {
+            return None;
+        }
+        nanos *= 10u32.pow(9u32.saturating_sub((index - start) as u32));
+    }
+    let offset = match bytes.get(index)? {
+        b'Z' | b'z' if index + 1 == bytes.len() => 0,
+        sign @ b'+' | sign @ b'-' if index + 6 == bytes.len() && separator(index + 3, b":") => {
+            let (hours, minutes) = (number(index + 1, 2)?, number(index + 4, 2)?);
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let offset = hours * 3600 + minutes * 60;
+            if *sign == b'-' {
+                -offset
+            } else {
+                offset
+            }
+        }
+        _ => return None,
+    };
+    let days = days_from_civil(year, month, day);
+    let seconds = days * 86400 + hour * 3600 + minute * 60 + second - offset;
+    Some((seconds, nanos))
+}
+
+/// Formats a time as an RFC 3339 timestamp in UTC, to the second.
+pub fn format_rfc3339(time: SystemTime) -> String {
+    let (days, seconds) = split_days(unix_seconds(time));
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+    )
+}
+
+#[cfg(feature = "server")]
+/// Parses an HTTP date in the preferred format of RFC 7231, such as
+/// `Sun, 06 Nov 1994 08:49:37 GMT`, into seconds since the Unix epoch.
+///
+/// The obsolete RFC 850 and asctime formats aren't supported.
+pub fn parse_http_date(s: &str) -> Option<i64> {
+    let mut fields = s.split_whitespace();
+    let weekday = fields.next()?;
+    if weekday.len() != 4 || !weekday.ends_with(',') {
+        return None;
+    }
+    let number = |field: &str, len: usize| -> Option<i64> {
+        if field.len() == len && field.bytes().all(|byte| byte.is_ascii_digit()) {
+            field.parse().ok()
+        } else {
+            None
+        }
+    };
+    let day = number(fields.next()?, 2)?;
+    let month = fields.next()?;
+    let month = MONTHS.iter().position(|name| *name == month)? as i64 + 1;
+    let year = number(fields.next()?, 4)?;
+    let mut time = fields.next()?.split(':');
+    let (hour, minute, second) = (
+        number(time.next()?, 2)?,
+        number(time.next()?, 2)?,
+        number(time.next()?, 2)?,
+    );
+    if time.next().is_some()
+        || fields.next() != Some("GMT")
+        || fields.next().is_some()
+        || day < 1
+        || day > days_in_month(year, month)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+#[cfg(feature = "server")]
+/// Formats a time as an HTTP date, such as `Sun, 06 Nov 1994 08:49:37 GMT`, to the second.
+pub fn format_http_date(time: SystemTime) -> String {
+    let (days, seconds) = split_days(unix_seconds(time));
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[(days + 4).rem_euclid(7) as usize],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+    )
+}
+
+/// Counts the whole seconds from the Unix epoch to a time, rounding towards the past.
+pub fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(err) => {
+            let before = err.duration();
+            -(before.as_secs() as i64) - i64::from(before.subsec_nanos() > 0)
+        }
+    }
+}
+
+fn split_days(seconds: i64) -> (i64, i64) {
+    (seconds.div_euclid(86400), seconds.rem_euclid(86400))
+}
+
+#[cfg(feature = "server")]
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+#[cfg(feature = "server")]
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Counts the days from the Unix epoch to a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The inverse of [`days_from_civil`].
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+#[test]
+fn parse_timestamps() {
+    assert_eq!(Some((0, 0)), parse_rfc3339("1970-01-01T00:00:00Z"));
+    assert_eq!(
+        parse_rfc3339("2020-02-29T09:30:00Z"),
+        parse_rfc3339("2020-02-29T10:30:00+01:00"),
+    );
+    assert_eq!(
+        Some((1_582_968_600, 250_000_000)),
+        parse_rfc3339("2020-02-29t09:30:00.25z")
+    );
+    for malformed in &[
+        "2020-02-29",
+        "2019-02-29T09:30:00Z",
+        "2020-02-29T09:30:00",
+        "2020-02-29T09:30:00+0100",
+        "2020-02-29T09:30:00.Z",
+        "2020-02-29T24:00:00Z",
+        "January 1st",
+    ] {
+        assert_eq!(None, parse_rfc3339(malformed), "{}", malformed);
+    }
+    let time = UNIX_EPOCH + std::time::Duration::from_secs(1_582_968_600);
+    assert_eq!("2020-02-29T09:30:00Z", format_rfc3339(time));
+}
+
+#[cfg(feature = "server")]
+#[test]
+fn http_dates() {
+    let time = UNIX_EPOCH + std::time::Duration::from_secs(784_111_777);
+    assert_eq!("Sun, 06 Nov 1994 08:49:37 GMT", format_http_date(time));
+    assert_eq!(
+        Some(784_111_777),
+        parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT")
+    );
+    assert_eq!(
+        "Thu, 01 Jan 1970 00:00:00 GMT",
+        format_http_date(UNIX_EPOCH)
+    );
+    for malformed in &[
+        "Sunday, 06-Nov-94 08:49:37 GMT",
+        "Sun Nov  6 08:49:37 1994",
+        "Sun, 06 Nov 1994 08:49:37 UTC",
+        "Sun, 31 Nov 1994 08:49:37 GMT",
+        "Sun, 6 Nov 1994 08:49:37 GMT",
+    ] {
+        assert_eq!(None, parse_http_date(malformed), "{}", malformed);
+    }
+}
@@ -0,0 +1,202 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use prosidy::xml::quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+use prosidy::xml::{quick_xml::Result as XMLResult, XMLConfig, XML};
+use prosidy::{Block, Document, Inline, Key, Node, PropSet};
+use serde::Serialize;
+
+/// The lints to check documents against. Only duplicate ids are checked by default.
+#[derive(Debug)]
+pub struct Rules {
+    /// The only tag names documents may use. Any name is allowed when this is `None`.
+    pub allowed_tags: Option<HashSet<String>>,
+    /// Header properties every document must have.
+    pub required_props: Vec<String>,
+    /// Tags which must not be empty.
+    pub require_content: Vec<String>,
+    /// Whether each `id` setting must be unique within its document.
+    pub unique_ids: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            allowed_tags: None,
+            required_props: Vec::new(),
+            require_content: Vec::new(),
+            unique_ids: true,
+        }
+    }
+}
+
+/// The problems found in one or more documents.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    findings: Vec<Finding>,
+}
+
+#[derive(Debug, Serialize)]
+struct Finding {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<PathBuf>,
+    lint: Lint,
+    message: String,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Lint {
+    DuplicateId,
+    EmptyTag,
+    MissingProp,
+    SyntaxError,
+    UnknownTag,
+}
+
+impl Lint {
+    fn name(self) -> &'static str {
+        match self {
+            Lint::DuplicateId => "duplicate-id",
+            Lint::EmptyTag => "empty-tag",
+            Lint::MissingProp => "missing-prop",
+            Lint::SyntaxError => "syntax-error",
+            Lint::UnknownTag => "unknown-tag",
+        }
+    }
+}
+
+impl Report {
+    const TAG_REPORT: &'static str = "report";
+    const TAG_FINDING: &'static str = "finding";
+    const ATTR_LINT: &'static str = "lint";
+    const ATTR_PATH: &'static str = "path";
+
+    pub fn len(&self) -> usize {
+        self.findings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// Parses `source` and checks the document against `rules`. A document which doesn't parse
+    /// is reported as a syntax error.
+    pub fn check_source(&mut self, path: Option<&Path>, source: &str, rules: &Rules) {
+        match prosidy::parse::parse_document(source) {
+            Ok(doc) => self.check(path, &doc, rules),
+            Err(error) => self.push(path, Lint::SyntaxError, error.report(source)),
+        }
+    }
+
+    pub fn check(&mut self, path: Option<&Path>, doc: &Document, rules: &Rules) {
+        for prop in rules.required_props.iter() {
+            if !has_prop(doc.props(), &Key::new(prop)) {
+                let message = format!("missing header property {:?}", prop);
+                self.push(path, Lint::MissingProp, message);
+            }
+        }
+        let id = Key::new("id");
+        let mut ids = HashSet::new();
+        for node in Node::from(doc).descendants() {
+            let (name, props, empty) = match node {
+                Node::Block(Block::Tag(tag)) => (tag.name(), tag.props(), tag.content().is_empty()),
+                Node::Inline(Inline::Tag(tag)) => {
+                    (tag.name(), tag.props(), tag.content().is_empty())
+                }
+                _ => continue,
+            };
+            if let Some(ref allowed) = rules.allowed_tags {
+                if !allowed.contains(name.as_str()) {
+                    let message = format!("unknown tag {:?}", name.as_str());
+                    self.push(path, Lint::UnknownTag, message);
+                }
+            }
+            if empty && rules.require_content.iter().any(|tag| tag == name.as_str()) {
+                let message = format!("tag {:?} has no content", name.as_str());
+                self.push(path, Lint::EmptyTag, message);
+            }
+            match props.lookup(&id) {
+                Some(value) if rules.unique_ids && !ids.insert(value.as_str().to_string()) => {
+                    let message = format!("id {:?} is used more than once", value.as_str());
+                    self.push(path, Lint::DuplicateId, message);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn push(&mut self, path: Option<&Path>, lint: Lint, message: String) {
+        self.findings.push(Finding {
+            path: path.map(Path::to_path_buf),
+            lint,
+            message,
+        });
+    }
+}
+
+fn has_prop(props: &PropSet, key: &Key) -> bool {
+    props.is_set(key) || props.lookup(key).is_some()
+}
+
+impl XML for Report {
+    fn to_events<F>(&self, config: &XMLConfig, emit: &mut F) -> XMLResult<()>
+    where
+        F: for<'a> FnMut(Event<'a>) -> XMLResult<()>,
+    {
+        let report = config.name(Report::TAG_REPORT);
+        let finding_tag = config.name(Report::TAG_FINDING);
+        let (attr_lint, attr_path) = (
+            config.name(Report::ATTR_LINT),
+            config.name(Report::ATTR_PATH),
+        );
+        emit(Event::Start(BytesStart::borrowed_name(report.as_bytes())))?;
+        for finding in self.findings.iter() {
+            let mut start = BytesStart::borrowed_name(finding_tag.as_bytes());
+            start.push_attribute((attr_lint.as_str(), finding.lint.name()));
+            if let Some(ref path) = finding.path {
+                start.push_attribute((attr_path.as_str(), path.to_string_lossy().as_ref()));
+            }
+            emit(Event::Start(start))?;
+            emit(Event::Text(BytesText::from_plain_str(&finding.message)))?;
+            emit(Event::End(BytesEnd::borrowed(finding_tag.as_bytes())))?;
+        }
+        emit(Event::End(BytesEnd::borrowed(report.as_bytes())))
+    }
+}
+
+#[test]
+fn lint_document() {
+    let source = "title: Lints\n---\n#-section[id='a']:\n#em[id='a']{Hi} #b #x{y}\n#:\n";
+    let rules = Rules {
+        allowed_tags: Some(
+            ["section", "em", "b"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        ),
+        required_props: vec!["title".into(), "date".into()],
+        require_content: vec!["b".into()],
+        unique_ids: true,
+    };
+    let mut report = Report::default();
+    report.check_source(None, source, &rules);
+    let lints: Vec<_> = report.findings.iter().map(|finding| finding.lint).collect();
+    assert_eq!(
+        vec![
+            Lint::MissingProp,
+            Lint::DuplicateId,
+            Lint::EmptyTag,
+            Lint::UnknownTag
+        ],
+        lints
+    );
+    let mut report = Report::default();
+    report.check_source(None, "---\n#oops{\n", &Rules::default());
+    assert_eq!(Lint::SyntaxError, report.findings[0].lint);
+}
@@ -0,0 +1,54 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Blocks until the modification time of `path` differs from `last`, returning the new one.
+///
+/// Editors often write a file in several steps, so this waits until the file has gone unchanged
+/// for [`DEBOUNCE`] before returning. A missing file counts as having no modification time.
+pub fn wait_for_change(path: &Path, last: Option<SystemTime>) -> Option<SystemTime> {
+    let mut current = modified(path);
+    while current == last {
+        thread::sleep(POLL_INTERVAL);
+        current = modified(path);
+    }
+    loop {
+        thread::sleep(DEBOUNCE);
+        let settled = modified(path);
+        if settled == current {
+            return current;
+        }
+        current = settled;
+    }
+}
+
+pub fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+#[test]
+fn wait_for_write() {
+    let path = std::env::temp_dir().join(format!("prosidy-watch-{}.pro", std::process::id()));
+    fs::write(&path, "---\nBefore\n").unwrap();
+    let before = modified(&path);
+    let writer = {
+        let path = path.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            fs::write(&path, "---\nAfter\n").unwrap();
+        })
+    };
+    let after = wait_for_change(&path, before);
+    writer.join().unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_ne!(before, after);
+}
@@ -0,0 +1,93 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Counts the heap allocations made by the owned and arena-backed parsers on a large document.
+//! Run with `cargo bench -p prosidy-parse --features arena --bench allocations`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+use prosidy_parse::{arena, parse_document};
+
+const SECTIONS: usize = 500;
+
+const SECTION: &str = "\
+#-section[id='part']:
+#-h2{A #em{section} heading}
+
+Lorem ipsum #em{dolor} sit amet, consectetur adipiscing elit, sed do eiusmod
+incididunt ut labore et #strong[weight='bold']{dolore magna} aliqua. Ut enim
+nostrud exercitation \\{ullamco\\} laboris nisi ut aliquip ex ea commodo.
+
+Plain prose, with no markup at all, which takes the fast path through the
+parser and is split into lines directly.
+
+#=code[lang='rust']:
+fn main() {}
+#:
+#:
+";
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Relaxed);
+        BYTES.fetch_add(layout.size(), Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Relaxed);
+        BYTES.fetch_add(new_size.saturating_sub(layout.size()), Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Runs `f`, returning the number of allocations and bytes allocated while it ran.
+fn count<T>(f: impl FnOnce() -> T) -> (usize, usize) {
+    let (allocations, bytes) = (ALLOCATIONS.load(Relaxed), BYTES.load(Relaxed));
+    let value = f();
+    let counts = (
+        ALLOCATIONS.load(Relaxed) - allocations,
+        BYTES.load(Relaxed) - bytes,
+    );
+    drop(value);
+    counts
+}
+
+fn main() {
+    let mut source = String::from("title: Benchmark\n---\n");
+    for _ in 0..SECTIONS {
+        source.push('\n');
+        source.push_str(SECTION);
+    }
+    // Warm up the global key set, so that interning doesn't count against the owned parser.
+    parse_document(&source).unwrap();
+
+    let owned = count(|| parse_document(&source).unwrap());
+    let arena = count(|| {
+        let bump = arena::Bump::new();
+        arena::parse_document(&bump, &source).unwrap();
+        bump
+    });
+    println!("source: {} bytes", source.len());
+    for (name, (allocations, bytes)) in [("owned", owned), ("arena", arena)].iter() {
+        println!(
+            "{:>6}: {:>8} allocations, {:>10} bytes",
+            name, allocations, bytes
+        );
+    }
+}
@@ -0,0 +1,59 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use prosidy_parse::parse_document;
+
+const PARAGRAPHS: usize = 500;
+
+const INLINE_TAGS: usize = 5000;
+
+const PROSE: &str = "\
+Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor
+incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis
+nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat.
+";
+
+const MARKUP: &str = "\
+Lorem ipsum #em{dolor} sit amet, consectetur adipiscing elit, sed do eiusmod
+incididunt ut labore et #strong[weight='bold']{dolore magna} aliqua. Ut enim
+nostrud exercitation \\{ullamco\\} laboris nisi ut aliquip ex ea commodo.
+";
+
+fn document(paragraph: &str) -> String {
+    let mut doc = String::from("title: Benchmark\n---\n");
+    for _ in 0..PARAGRAPHS {
+        doc.push('\n');
+        doc.push_str(paragraph);
+    }
+    doc
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_document");
+    for (name, paragraph) in [("prose", PROSE), ("markup", MARKUP)].iter() {
+        let source = document(paragraph);
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_function(*name, |b| b.iter(|| parse_document(&source).unwrap()));
+    }
+    group.finish();
+}
+
+/// A single paragraph holding thousands of inline nodes, to measure the cost of building long
+/// vectors of siblings.
+fn bench_parse_inlines(c: &mut Criterion) {
+    let mut source = String::from("---\n");
+    for index in 0..INLINE_TAGS {
+        source.push_str(&format!("word #em{{{}}} ", index));
+    }
+    source.push('\n');
+    let mut group = c.benchmark_group("parse_inlines");
+    group.throughput(Throughput::Bytes(source.len() as u64));
+    group.bench_function("tags", |b| b.iter(|| parse_document(&source).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse, bench_parse_inlines);
+criterion_main!(benches);
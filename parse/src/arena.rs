@@ -0,0 +1,471 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Parsing into a document allocated from a bump arena.
+//!
+//! The types here mirror those in `prosidy_ast`, but every slice and every string which can't
+//! borrow from the source is allocated from a [`Bump`]. A parse then makes a few large
+//! allocations rather than several per node, and the whole document is freed at once when the
+//! arena is dropped. Use [`Document::to_ast`] where the owned AST is needed.
+//!
+//! ```rust
+//! # use prosidy_parse::arena::{self, Block, Inline};
+//! let bump = arena::Bump::new();
+//! let doc = arena::parse_document(&bump, "title: Hi\n---\nHello, #em{world}!\n").unwrap();
+//! assert_eq!(Some("Hi"), doc.prop("title"));
+//! match doc.content() {
+//!     [Block::Content([Inline::Text(text), ..])] => assert_eq!("Hello, ", *text),
+//!     content => panic!("unexpected content: {:?}", content),
+//! }
+//! ```
+
+use std::fmt;
+use std::slice;
+
+use bumpalo::collections::{String as BumpString, Vec as BumpVec};
+use pest::{Parser, Span};
+use prosidy_ast as ast;
+
+use crate::context::Context;
+use crate::error::{ErrorKind::*, Location, Result};
+use crate::parse::{code_point, is_prose, syntax_error, DocumentParser, Pairs, Rule};
+use crate::traits::*;
+use crate::warning::{Warning, WarningKind::*};
+
+pub use bumpalo::Bump;
+
+/// Parses a document, allocating it from `arena`.
+///
+/// Warnings are logged, but not returned.
+pub fn parse_document<'a>(arena: &'a Bump, src: &'a str) -> Result<Document<'a>> {
+    let mut ast =
+        DocumentParser::parse(Rule::Document, src).map_err(|err| syntax_error(src, err))?;
+    let mut session = Session {
+        arena,
+        ctx: Context::new(),
+    };
+    let doc = session.document(&mut ast)?;
+    ast.assert_empty()?;
+    Ok(doc)
+}
+
+/// A parsed document.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Document<'a> {
+    props: &'a [Prop<'a>],
+    content: &'a [Block<'a>],
+}
+
+impl<'a> Document<'a> {
+    /// The properties in the document's header, in source order.
+    #[inline]
+    pub fn props(&self) -> &'a [Prop<'a>] {
+        self.props
+    }
+
+    #[inline]
+    pub fn content(&self) -> &'a [Block<'a>] {
+        self.content
+    }
+
+    /// See [`Tag::prop`].
+    #[inline]
+    pub fn prop(&self, key: &str) -> Option<&'a str> {
+        lookup(self.props, key)
+    }
+
+    /// Copies the document into the owned AST, still borrowing from the source and the arena.
+    pub fn to_ast(&self) -> ast::Document<'a> {
+        let props = props_to_ast(self.props);
+        let content = self.content.iter().map(Block::to_ast).collect();
+        ast::Document::new(props, content)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Block<'a> {
+    Content(&'a [Inline<'a>]),
+    Literal(&'a str),
+    Tag(Tag<'a, Block<'a>>),
+}
+
+impl<'a> Block<'a> {
+    pub fn to_ast(&self) -> ast::Block<'a> {
+        match self {
+            Block::Content(content) => ast::Block::Content(inlines_to_ast(content)),
+            Block::Literal(literal) => ast::Block::Literal(ast::Text::from(*literal).into()),
+            Block::Tag(tag) => ast::Block::Tag(tag.to_ast(Block::to_ast)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Inline<'a> {
+    SoftBreak,
+    Tag(Tag<'a, Inline<'a>>),
+    Text(&'a str),
+}
+
+impl<'a> Inline<'a> {
+    pub fn to_ast(&self) -> ast::Inline<'a> {
+        match self {
+            Inline::SoftBreak => ast::Inline::SoftBreak,
+            Inline::Tag(tag) => ast::Inline::Tag(tag.to_ast(Inline::to_ast)),
+            Inline::Text(text) => ast::Inline::Text(ast::Text::from(*text)),
+        }
+    }
+}
+
+/// A block or inline tag.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Tag<'a, T> {
+    name: &'a str,
+    props: &'a [Prop<'a>],
+    content: &'a [T],
+}
+
+// Derived, these would require `T: Copy`, which can't be proven for the recursive node types.
+impl<'a, T> Clone for Tag<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for Tag<'a, T> {}
+
+impl<'a, T> Tag<'a, T> {
+    #[inline]
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// The tag's properties, in source order.
+    #[inline]
+    pub fn props(&self) -> &'a [Prop<'a>] {
+        self.props
+    }
+
+    #[inline]
+    pub fn content(&self) -> &'a [T] {
+        self.content
+    }
+
+    /// Looks up the value of a property. A key given more than once keeps every occurrence in
+    /// [`props`](Tag::props), but only the last value is returned here.
+    #[inline]
+    pub fn prop(&self, key: &str) -> Option<&'a str> {
+        lookup(self.props, key)
+    }
+
+    fn to_ast<U, F>(self, f: F) -> ast::Tag<'a, U>
+    where
+        F: FnMut(&T) -> U,
+    {
+        let content = self.content.iter().map(f).collect();
+        ast::Tag::new(self.name, props_to_ast(self.props), content)
+    }
+}
+
+/// A property of a tag or document. Those without values are settings.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct Prop<'a> {
+    key: &'a str,
+    value: Option<&'a str>,
+}
+
+impl<'a> Prop<'a> {
+    #[inline]
+    pub fn key(&self) -> &'a str {
+        self.key
+    }
+
+    #[inline]
+    pub fn value(&self) -> Option<&'a str> {
+        self.value
+    }
+}
+
+impl fmt::Debug for Prop<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.value {
+            Some(value) => write!(f, "{}={:?}", self.key, value),
+            None => write!(f, "{}", self.key),
+        }
+    }
+}
+
+fn lookup<'a>(props: &'a [Prop<'a>], key: &str) -> Option<&'a str> {
+    props
+        .iter()
+        .rev()
+        .find(|prop| prop.key == key && prop.value.is_some())
+        .and_then(|prop| prop.value)
+}
+
+fn props_to_ast<'a>(props: &[Prop<'a>]) -> ast::PropSet<'a> {
+    let mut set = ast::PropSet::new();
+    for prop in props {
+        match prop.value {
+            Some(value) => {
+                set.put(prop.key, value);
+            }
+            None => set.set(prop.key),
+        }
+    }
+    set
+}
+
+fn inlines_to_ast<'a>(inlines: &[Inline<'a>]) -> Vec<ast::Inline<'a>> {
+    inlines.iter().map(Inline::to_ast).collect()
+}
+
+/// The state of a single parse. Each method mirrors an implementation of `Parse` for the owned
+/// AST; see those for the details of the grammar.
+struct Session<'a> {
+    arena: &'a Bump,
+    ctx: Context,
+}
+
+impl<'a> Session<'a> {
+    fn document(&mut self, pairs: &mut Pairs<'a>) -> Result<Document<'a>> {
+        pairs.with_block(Rule::Document, |pairs| {
+            log::debug!("parsing document into arena");
+            let props = pairs.with_block(Rule::Header, |pairs| self.header(pairs))?;
+            let content = self.blocks(pairs)?;
+            pairs.rule(Rule::EOI)?;
+            Ok(Document { props, content })
+        })
+    }
+
+    fn blocks(&mut self, pairs: &mut Pairs<'a>) -> Result<&'a [Block<'a>]> {
+        let mut buf = BumpVec::new_in(self.arena);
+        while let Some(block) = self.block(pairs).recover()? {
+            buf.push(block);
+        }
+        Ok(buf.into_bump_slice())
+    }
+
+    fn block(&mut self, pairs: &mut Pairs<'a>) -> Result<Block<'a>> {
+        self.block_tag(pairs)
+            .map(Block::Tag)
+            .recover()
+            .transpose()
+            .unwrap_or_else(|| self.paragraph(pairs).map(Block::Content))
+    }
+
+    fn block_tag(&mut self, pairs: &mut Pairs<'a>) -> Result<Tag<'a, Block<'a>>> {
+        pairs
+            .with_block(Rule::BlockTag, |pairs| {
+                let name = self.key(pairs)?;
+                let props = self.props(pairs).recover_default()?;
+                let content = self.blocks(pairs)?;
+                Ok(Tag {
+                    name,
+                    props,
+                    content,
+                })
+            })
+            .recover()
+            .transpose()
+            .unwrap_or_else(|| {
+                pairs.with_block(Rule::LiteralTag, |pairs| {
+                    let name = self.key(pairs)?;
+                    let props = self.props(pairs).recover_default()?;
+                    let literal = pairs
+                        .with_atom(Rule::Literal, |s| Ok(self.normalize_newlines(s)))
+                        .recover_default()?;
+                    let content = slice::from_ref(self.arena.alloc(Block::Literal(literal)));
+                    Ok(Tag {
+                        name,
+                        props,
+                        content,
+                    })
+                })
+            })
+    }
+
+    fn inlines(&mut self, pairs: &mut Pairs<'a>) -> Result<&'a [Inline<'a>]> {
+        let mut buf = BumpVec::new_in(self.arena);
+        while let Some(inline) = self.inline(pairs).recover()? {
+            buf.push(inline);
+        }
+        Ok(buf.into_bump_slice())
+    }
+
+    fn inline(&mut self, pairs: &mut Pairs<'a>) -> Result<Inline<'a>> {
+        pairs
+            .with_block(Rule::SoftBreak, |_| Ok(Inline::SoftBreak))
+            .recover()
+            .transpose()
+            .unwrap_or_else(|| self.inline_tag(pairs).map(Inline::Tag))
+            .recover()
+            .transpose()
+            .unwrap_or_else(|| self.text(pairs).map(Inline::Text))
+    }
+
+    fn inline_tag(&mut self, pairs: &mut Pairs<'a>) -> Result<Tag<'a, Inline<'a>>> {
+        pairs.with_block(Rule::InlineTag, |pairs| {
+            let name = self.key(pairs)?;
+            let props = self.props(pairs).recover_default()?;
+            let content = self.paragraph(pairs).recover_default()?;
+            Ok(Tag {
+                name,
+                props,
+                content,
+            })
+        })
+    }
+
+    fn paragraph(&mut self, pairs: &mut Pairs<'a>) -> Result<&'a [Inline<'a>]> {
+        let is_prose = pairs
+            .peek()
+            .filter(|pair| pair.as_rule() == Rule::Paragraph && is_prose(pair.as_str()))
+            .is_some();
+        if is_prose {
+            pairs.with_atom(Rule::Paragraph, |s| Ok(self.prose(s)))
+        } else {
+            pairs.with_block(Rule::Paragraph, |pairs| self.inlines(pairs))
+        }
+    }
+
+    fn prose(&self, s: &'a str) -> &'a [Inline<'a>] {
+        let mut lines = s.split('\n');
+        let mut content = BumpVec::new_in(self.arena);
+        if let Some(first) = lines.next() {
+            content.push(Inline::Text(first));
+        }
+        for line in lines {
+            content.push(Inline::SoftBreak);
+            content.push(Inline::Text(line.trim_start_matches(char::is_whitespace)));
+        }
+        content.into_bump_slice()
+    }
+
+    fn header(&mut self, pairs: &mut Pairs<'a>) -> Result<&'a [Prop<'a>]> {
+        pairs.with_block(Rule::DocumentProps, |pairs| {
+            let mut props = BumpVec::new_in(self.arena);
+            while self
+                .prop(
+                    pairs,
+                    Rule::DocumentProp,
+                    Rule::DocumentPropValue,
+                    &mut props,
+                )
+                .recover()?
+                .is_some()
+            {}
+            Ok(props.into_bump_slice())
+        })
+    }
+
+    fn props(&mut self, pairs: &mut Pairs<'a>) -> Result<&'a [Prop<'a>]> {
+        pairs.with_block(Rule::Props, |pairs| {
+            let mut props = BumpVec::new_in(self.arena);
+            while self
+                .prop(pairs, Rule::Prop, Rule::QuotedText, &mut props)
+                .recover()?
+                .is_some()
+            {}
+            Ok(props.into_bump_slice())
+        })
+    }
+
+    fn prop(
+        &mut self,
+        pairs: &mut Pairs<'a>,
+        rule: Rule,
+        value_rule: Rule,
+        props: &mut BumpVec<'a, Prop<'a>>,
+    ) -> Result<()> {
+        pairs.with_block(rule, |pairs| {
+            let span = pairs.peek().map(|pair| pair.as_span());
+            let key = self.key(pairs)?;
+            let value = pairs
+                .with_block(value_rule, |pairs| self.text(pairs))
+                .recover()?;
+            if props.iter().any(|prop| prop.key == key) {
+                self.warn_duplicate(key, span);
+            }
+            props.push(Prop { key, value });
+            Ok(())
+        })
+    }
+
+    fn warn_duplicate(&mut self, key: &str, span: Option<Span<'a>>) {
+        if let Some(span) = span {
+            let location = Location::new(Rule::Key, span);
+            let warning = Warning::new(DuplicateKey(ast::Key::new(key)), location);
+            self.ctx.warn(warning);
+        }
+    }
+
+    fn key(&mut self, pairs: &mut Pairs<'a>) -> Result<&'a str> {
+        pairs.with_atom(Rule::Key, Ok)
+    }
+
+    /// Parses a run of text, which borrows from the source unless it contains escapes.
+    fn text(&mut self, pairs: &mut Pairs<'a>) -> Result<&'a str> {
+        let first = self.text_piece(pairs)?;
+        let second = match self.text_piece(pairs).recover()? {
+            Some(second) => second,
+            None => return Ok(first),
+        };
+        let mut buf = BumpString::from_str_in(first, self.arena);
+        buf.push_str(second);
+        while let Some(piece) = self.text_piece(pairs).recover()? {
+            buf.push_str(piece);
+        }
+        Ok(buf.into_bump_str())
+    }
+
+    fn text_piece(&mut self, pairs: &mut Pairs<'a>) -> Result<&'a str> {
+        pairs
+            .with_atom(Rule::PlainText, Ok)
+            .recover()
+            .transpose()
+            .or_else(|| {
+                pairs
+                    .with_atom(Rule::EscapedPlainText, |s| self.unescape(s))
+                    .recover()
+                    .transpose()
+            })
+            .or_else(|| {
+                pairs
+                    .with_atom(Rule::PlainQuotedText, |s| Ok(self.normalize_newlines(s)))
+                    .recover()
+                    .transpose()
+            })
+            .or_else(|| {
+                pairs
+                    .with_atom(Rule::EscapedQuotedText, |s| Ok(&s[1..]))
+                    .recover()
+                    .transpose()
+            })
+            .unwrap_or_else(|| Err(NoMatch.into()))
+    }
+
+    fn unescape(&self, s: &str) -> Result<&'a str> {
+        match s {
+            r#"\n"# => Ok("\n"),
+            r#"\t"# => Ok("\t"),
+            r#"\\"# => Ok("\\"),
+            r#"\#"# => Ok("#"),
+            r#"\{"# => Ok("{"),
+            r#"\}"# => Ok("}"),
+            _ => code_point(s)
+                .map(|c| &*self.arena.alloc_str(c.encode_utf8(&mut [0; 4])))
+                .ok_or_else(|| InvalidEscape(s.into()).into()),
+        }
+    }
+
+    fn normalize_newlines(&self, s: &'a str) -> &'a str {
+        if s.contains('\r') {
+            self.arena
+                .alloc_str(&s.replace("\r\n", "\n").replace('\r', "\n"))
+        } else {
+            s
+        }
+    }
+}
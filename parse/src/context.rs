@@ -0,0 +1,114 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::HashSet;
+
+use prosidy_ast::Key;
+
+use crate::error::Location;
+use crate::span::{Span, Spans};
+use crate::warning::{Warning, WarningKind};
+
+/// State shared across a single parse of a document.
+#[derive(Debug, Default)]
+pub struct Context {
+    warnings: Vec<Warning>,
+    /// The spans of the nodes parsed so far, in pre-order, if they're being recorded.
+    spans: Option<Vec<Span>>,
+    /// The tag names expected in the document, if any; see [`Context::with_vocabulary`].
+    vocabulary: Option<HashSet<Key>>,
+    /// Every `id` property seen so far.
+    ids: HashSet<String>,
+    /// Every `href='#...'` property seen so far, checked against `ids` once the document ends.
+    references: Vec<(String, Location)>,
+}
+
+impl Context {
+    #[inline]
+    pub fn new() -> Self {
+        Context::default()
+    }
+
+    /// Creates a context which records the span of each node parsed.
+    #[inline]
+    pub fn with_spans() -> Self {
+        Context {
+            spans: Some(Vec::new()),
+            ..Context::default()
+        }
+    }
+
+    /// Restricts the tag names expected in the document to `names`.
+    ///
+    /// Any other tag raises an [`UnknownTag`](WarningKind::UnknownTag) warning.
+    pub fn with_vocabulary<I>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = Key>,
+    {
+        self.vocabulary = Some(names.into_iter().collect());
+        self
+    }
+
+    /// Records a warning, logging it as well.
+    pub fn warn(&mut self, warning: Warning) {
+        log::warn!("{}", warning);
+        self.warnings.push(warning);
+    }
+
+    #[inline]
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    #[inline]
+    pub fn into_warnings(self) -> Vec<Warning> {
+        self.warnings
+    }
+
+    #[inline]
+    pub fn into_spans(self) -> Spans {
+        self.spans.unwrap_or_default().into()
+    }
+
+    #[inline]
+    pub(crate) fn spans_mut(&mut self) -> Option<&mut Vec<Span>> {
+        self.spans.as_mut()
+    }
+
+    /// Warns if `name` is outside the vocabulary, if there is one.
+    pub(crate) fn check_tag(&mut self, name: &Key, location: impl FnOnce() -> Location) {
+        match &self.vocabulary {
+            Some(vocabulary) if !vocabulary.contains(name) => {
+                self.warn(Warning::new(
+                    WarningKind::UnknownTag(name.clone()),
+                    location(),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    /// Records an element `id`, which references elsewhere in the document may target.
+    pub(crate) fn define_id(&mut self, id: &str) {
+        self.ids.insert(id.to_owned());
+    }
+
+    /// Records a reference to the element `id`, to be checked by [`Context::finish`].
+    pub(crate) fn reference_id(&mut self, id: &str, location: Location) {
+        self.references.push((id.to_owned(), location));
+    }
+
+    /// Warns about each reference whose target was never defined.
+    ///
+    /// Called once the whole document has been parsed, since references may point forward.
+    pub(crate) fn finish(&mut self) {
+        let references = std::mem::take(&mut self.references);
+        for (id, location) in references {
+            if !self.ids.contains(&id) {
+                self.warn(Warning::new(WarningKind::DanglingReference(id), location));
+            }
+        }
+    }
+}
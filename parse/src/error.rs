@@ -5,9 +5,10 @@
 
 use std::fmt::{self, Display, Formatter};
 use std::io::Error as IOError;
+use std::path::PathBuf;
 use std::result::Result as StdResult;
 
-use pest::error::Error as PestError;
+use pest::error::{Error as PestError, InputLocation};
 
 use crate::parse::Rule;
 use crate::traits::ResultExt;
@@ -16,18 +17,59 @@ use crate::traits::ResultExt;
 pub struct Error {
     spans: Vec<Location>,
     #[source]
-    kind: ErrorKind,
+    kind: Box<ErrorKind>,
 }
 
 impl Error {
     pub fn annotate(mut self, rule: Rule, span: pest::Span) -> Self {
-        let start = span.start();
-        let end = span.end();
-        let loc = Location { start, end, rule };
-        self.spans.push(loc);
+        self.spans.push(Location::new(rule, span));
         self
     }
 
+    /// Moves an error raised while parsing a slice of `src`, starting at `offset`, onto `src`
+    /// itself.
+    pub(crate) fn relocate(mut self, src: &str, offset: usize) -> Self {
+        for location in self.spans.iter_mut() {
+            location.relocate(src, offset);
+        }
+        self
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// Renders the error alongside the line of `source` where it occurred, with the innermost span
+    /// underlined. Spans covering several lines are underlined up to the end of their first line.
+    ///
+    /// `source` must be the string the error was produced from.
+    pub fn report(&self, source: &str) -> String {
+        let mut out = match &*self.kind {
+            ErrorKind::SyntaxError(err) => format!("Syntax error: {}", err.variant.message()),
+            kind => kind.to_string(),
+        };
+        out.push('\n');
+        if let Some((start, end)) = self.innermost() {
+            snippet(&mut out, source, start, end);
+        }
+        for span in self.spans.iter() {
+            out.push_str(&format!("    {}\n", span));
+        }
+        out
+    }
+
+    /// The byte range of the innermost span this error occurred in, if it has one.
+    fn innermost(&self) -> Option<(usize, usize)> {
+        match (&*self.kind, self.spans.first()) {
+            (_, Some(location)) => Some((location.start, location.end)),
+            (ErrorKind::SyntaxError(err), None) => match err.location {
+                InputLocation::Pos(pos) => Some((pos, pos)),
+                InputLocation::Span(span) => Some(span),
+            },
+            _ => None,
+        }
+    }
+
     pub fn trailing<I: Iterator<Item = Rule>>(trailing: I) -> Option<Self> {
         let trailing: Vec<_> = trailing.collect();
         if trailing.is_empty() {
@@ -38,67 +80,181 @@ impl Error {
     }
 }
 
+fn snippet(out: &mut String, source: &str, start: usize, end: usize) {
+    let start = start.min(source.len());
+    let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[start..]
+        .find('\n')
+        .map_or(source.len(), |i| start + i);
+    let line = source[line_start..line_end].trim_end();
+    let line_number = source[..line_start].matches('\n').count() + 1;
+    let prefix = &source[line_start..start];
+    let column = prefix.chars().count() + 1;
+    // Keep tabs in the padding so the carets line up with the source however tabs are rendered.
+    let padding: String = prefix
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let underline_end = end.max(start).min(line_start + line.len());
+    let carets = source
+        .get(start..underline_end)
+        .map_or(0, |s| s.chars().count())
+        .max(1);
+    let gutter = " ".repeat(line_number.to_string().len());
+    out.push_str(&format!(
+        "{gutter}--> line {line}, col {column}\n\
+         {gutter} |\n\
+         {line} | {text}\n\
+         {gutter} | {padding}{carets}\n",
+        gutter = gutter,
+        line = line_number,
+        column = column,
+        text = line,
+        padding = padding,
+        carets = "^".repeat(carets),
+    ));
+    if end > line_end {
+        out.push_str(&format!("{} = note: the span continues past this line\n", gutter));
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
         writeln!(fmt, "{}", self.kind)?;
         if !self.spans.is_empty() {
             writeln!(fmt, "trace:")?;
             for span in self.spans.iter() {
-                writeln!(
-                    fmt,
-                    "    in rule {rule:?}, {start}-{end}",
-                    rule = span.rule,
-                    start = span.start,
-                    end = span.end,
-                )?
+                writeln!(fmt, "    {}", span)?
             }
         }
         Ok(())
     }
 }
 
+/// Labels the innermost span of the error, followed by each enclosing rule in the trace. Errors
+/// carry byte offsets rather than their source, so attach it with `Report::with_source_code`.
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for Error {
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        use miette::LabeledSpan;
+        let (start, end) = self.innermost()?;
+        let label = match (&*self.kind, self.spans.first()) {
+            (ErrorKind::SyntaxError(err), None) => err.variant.message().into_owned(),
+            (kind, _) => kind.to_string(),
+        };
+        let primary = LabeledSpan::new_primary_with_span(Some(label), start..end);
+        let trace = self.spans.iter().skip(1).map(|location| {
+            let label = format!("in rule {:?}", location.rule);
+            LabeledSpan::new_with_span(Some(label), location.start..location.end)
+        });
+        Some(Box::new(std::iter::once(primary).chain(trace)))
+    }
+}
+
 impl From<ErrorKind> for Error {
     fn from(kind: ErrorKind) -> Self {
         Error {
             spans: Vec::new(),
-            kind,
+            kind: Box::new(kind),
         }
     }
 }
 
 impl<T> ResultExt<T, Error> for Result<T> {
     fn recover(self) -> Result<Option<T>> {
-        if let Some(ErrorKind::NoMatch) = self.as_ref().err().map(|e| &e.kind) {
+        if let Some(ErrorKind::NoMatch) = self.as_ref().err().map(|e| &*e.kind) {
             Ok(None)
         } else {
             self.map(Some)
         }
     }
-
-    fn unwrap_display(self) -> T {
-        self.unwrap_or_else(|e| panic!("{}", e))
-    }
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum ErrorKind {
+    #[error("Invalid block")]
+    InvalidBlock,
     #[error("Invalid escape sequence {0:?}")]
     InvalidEscape(String),
+    #[error("Failed to include {path:?}: {error}")]
+    Include {
+        path: PathBuf,
+        #[source]
+        error: Box<Error>,
+    },
+    #[error("Cannot include {0:?}, since it would include itself")]
+    IncludeCycle(PathBuf),
+    #[error("Cannot include {0:?}, since includes are nested too deeply")]
+    IncludeTooDeep(PathBuf),
+    #[error("Cannot include {0:?}, since it is outside the document's directory")]
+    IncludeOutsideRoot(PathBuf),
     #[error("IO Error: {0:}")]
     IOError(#[from] IOError),
     #[error("No match.")]
     NoMatch,
     #[error("Syntax error: {0:}")]
     SyntaxError(#[from] PestError<Rule>),
+    /// A literal tag which is never closed, or whose closing line names a different delimiter,
+    /// like `#:end` closing `#=code:`. `end` is the line expected to close it.
+    #[error(
+        "Unterminated literal opened at line {} col {}, expected {end:?} on a line of its own",
+        .opened_at.line,
+        .opened_at.column
+    )]
+    UnterminatedLiteral { opened_at: Location, end: String },
     #[error("Trailing rules: {0:?}")]
     Trailing(Vec<Rule>),
+    #[error("Cannot print as Prosidy: {0}")]
+    Unprintable(String),
 }
 
+/// Where in the source a rule was matched.
+///
+/// `start` and `end` are byte offsets; `line` and `column` are 1-based and point at `start`.
 #[derive(Debug)]
 pub struct Location {
     pub rule: Rule,
     pub start: usize,
     pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    pub(crate) fn new(rule: Rule, span: pest::Span) -> Self {
+        let start = span.start();
+        let end = span.end();
+        let (line, column) = span.start_pos().line_col();
+        Location {
+            rule,
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    fn relocate(&mut self, src: &str, offset: usize) {
+        self.start += offset;
+        self.end += offset;
+        if let Some(pos) = pest::Position::new(src, self.start) {
+            let (line, column) = pos.line_col();
+            self.line = line;
+            self.column = column;
+        }
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "in rule {rule:?}, line {line} col {column}",
+            rule = self.rule,
+            line = self.line,
+            column = self.column,
+        )
+    }
 }
 
 pub type Result<T> = StdResult<T, Error>;
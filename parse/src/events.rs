@@ -0,0 +1,247 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use pest::Parser;
+use prosidy_ast::{Key, Literal, Text};
+
+use crate::error::{Error, ErrorKind::*, Result};
+use crate::parse::{
+    normalize_newlines, relocate, syntax_error, unescape, DocumentParser, Pair, Pairs, Rule,
+};
+
+/// Parses a document into a stream of [`Event`]s, rather than building a
+/// [`Document`](prosidy_ast::Document).
+///
+/// The header is parsed up front, and the body only once an event past [`Event::EndHeader`] is
+/// asked for, so reading just the header costs no more than [`parse_meta`](crate::parse_meta).
+/// Syntax errors in the body are returned from the iterator, which then ends.
+///
+/// ```rust
+/// # use prosidy_parse::{parse_events, Event};
+/// let mut events = parse_events("title: Hi\n---\n#-h1{Hi}\n").unwrap();
+/// assert!(matches!(events.next(), Some(Ok(Event::Prop(..)))));
+/// assert!(matches!(events.next(), Some(Ok(Event::EndHeader))));
+/// ```
+pub fn parse_events(src: &str) -> Result<Events<'_>> {
+    let mut pairs = DocumentParser::parse(Rule::Header, src).map_err(SyntaxError)?;
+    let header = pairs.next().ok_or(NoMatch)?;
+    Ok(Events {
+        src,
+        offset: header.as_span().end(),
+        stack: vec![Frame::new(header.into_inner(), Some(Event::EndHeader))],
+        body: true,
+    })
+}
+
+/// Something found while parsing a document, in the order it appears in the source.
+///
+/// Every `StartTag` and `StartParagraph` is matched by an `EndTag` or `EndParagraph`. The content
+/// of an inline tag is a run of inline events directly, without a paragraph around it, matching
+/// the content of an [`InlineTag`](prosidy_ast::InlineTag).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Event<'p> {
+    /// A property of the document's header, before `EndHeader`, or else of the tag most recently
+    /// started. Properties without a value are `None`.
+    Prop(Key, Option<Text<'p>>),
+    /// The end of the header, which is always the first event after its properties.
+    EndHeader,
+    StartTag(TagKind, Key),
+    EndTag,
+    StartParagraph,
+    EndParagraph,
+    /// A run of text, with any escapes decoded.
+    Text(Text<'p>),
+    SoftBreak,
+    /// The content of a literal tag, which is its only content.
+    Literal(Literal<'p>),
+}
+
+/// The kinds of tag started by [`Event::StartTag`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TagKind {
+    /// A block tag, like `#-section:`.
+    Block,
+    /// An inline tag, like `#em{...}`.
+    Inline,
+    /// A literal tag, like `#=code:`.
+    Literal,
+}
+
+/// An iterator over the [`Event`]s of a document. See [`parse_events`].
+#[derive(Debug)]
+pub struct Events<'p> {
+    src: &'p str,
+    /// Where the body starts in `src`, just past the header.
+    offset: usize,
+    stack: Vec<Frame<'p>>,
+    /// Whether the body has yet to be parsed.
+    body: bool,
+}
+
+/// The remaining children of a node being walked.
+#[derive(Debug)]
+struct Frame<'p> {
+    pairs: Pairs<'p>,
+    /// The event which closes the node, if it has one.
+    end: Option<Event<'p>>,
+    /// Set on a literal tag until its content has been emitted, since an empty literal has none.
+    literal: bool,
+    /// Whether the node is an inline tag, whose paragraph isn't reported as one.
+    inline: bool,
+}
+
+impl<'p> Frame<'p> {
+    fn new(pairs: Pairs<'p>, end: Option<Event<'p>>) -> Self {
+        Frame {
+            pairs,
+            end,
+            literal: false,
+            inline: false,
+        }
+    }
+}
+
+impl<'p> Iterator for Events<'p> {
+    type Item = Result<Event<'p>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let frame = match self.stack.last_mut() {
+                Some(frame) => frame,
+                None if self.body => {
+                    self.body = false;
+                    match self.parse_body() {
+                        Ok(()) => continue,
+                        Err(error) => return Some(Err(error)),
+                    }
+                }
+                None => return None,
+            };
+            match frame.pairs.next() {
+                Some(pair) => match self.enter(pair) {
+                    Ok(Some(event)) => return Some(Ok(event)),
+                    Ok(None) => {}
+                    Err(error) => {
+                        self.stack.clear();
+                        return Some(Err(self.relocate(error)));
+                    }
+                },
+                None if frame.literal => {
+                    frame.literal = false;
+                    return Some(Ok(Event::Literal(Literal::default())));
+                }
+                None => {
+                    if let Some(end) = self.stack.pop().and_then(|frame| frame.end) {
+                        return Some(Ok(end));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'p> Events<'p> {
+    /// Parses the blocks after the header, without going over the header again, and queues them
+    /// up.
+    fn parse_body(&mut self) -> Result<()> {
+        log::debug!("parsing document body for events");
+        let (src, offset) = (self.src, self.offset);
+        let mut pairs = DocumentParser::parse(Rule::DocumentBody, &src[offset..])
+            .map_err(|err| syntax_error(src, relocate(err, src, offset)))?;
+        let blocks = pairs.next().ok_or(NoMatch)?.into_inner();
+        self.stack.push(Frame::new(blocks, None));
+        Ok(())
+    }
+
+    /// Moves an error found in the body, whose spans start at the body, onto the whole source.
+    fn relocate(&self, error: Error) -> Error {
+        if self.body {
+            error
+        } else {
+            error.relocate(self.src, self.offset)
+        }
+    }
+
+    /// Handles the next child of the innermost node, returning the event it starts if any.
+    fn enter(&mut self, pair: Pair<'p>) -> Result<Option<Event<'p>>> {
+        let event = match pair.as_rule() {
+            Rule::DocumentProps | Rule::Props => {
+                self.stack.push(Frame::new(pair.into_inner(), None));
+                return Ok(None);
+            }
+            Rule::DocumentProp | Rule::Prop => {
+                let mut inner = pair.into_inner();
+                let key = Key::new(inner.next().ok_or(NoMatch)?.as_str());
+                let value = match inner.next() {
+                    Some(value) => Some(text(&mut value.into_inner())?),
+                    None => None,
+                };
+                Event::Prop(key, value)
+            }
+            Rule::BlockTag => self.start_tag(TagKind::Block, pair)?,
+            Rule::InlineTag => self.start_tag(TagKind::Inline, pair)?,
+            Rule::LiteralTag => self.start_tag(TagKind::Literal, pair)?,
+            Rule::Literal => {
+                if let Some(frame) = self.stack.last_mut() {
+                    frame.literal = false;
+                }
+                Event::Literal(Literal::from(normalize_newlines(pair.as_str())))
+            }
+            Rule::Paragraph if self.stack.last().is_some_and(|frame| frame.inline) => {
+                self.stack.push(Frame::new(pair.into_inner(), None));
+                return Ok(None);
+            }
+            Rule::Paragraph => {
+                self.stack
+                    .push(Frame::new(pair.into_inner(), Some(Event::EndParagraph)));
+                Event::StartParagraph
+            }
+            Rule::SoftBreak => Event::SoftBreak,
+            Rule::PlainText | Rule::EscapedPlainText => {
+                let frame = self.stack.last_mut().ok_or(NoMatch)?;
+                let mut run = vec![text_piece(pair)?];
+                while let Some(next) = frame.pairs.peek().filter(|next| is_text(next.as_rule())) {
+                    frame.pairs.next();
+                    run.push(text_piece(next)?);
+                }
+                Event::Text(run.into_iter().collect())
+            }
+            Rule::EOI => return Ok(None),
+            rule => return Err(Error::from(NoMatch).annotate(rule, pair.as_span())),
+        };
+        Ok(Some(event))
+    }
+
+    fn start_tag(&mut self, kind: TagKind, pair: Pair<'p>) -> Result<Event<'p>> {
+        let mut inner = pair.into_inner();
+        let name = Key::new(inner.next().ok_or(NoMatch)?.as_str());
+        let mut frame = Frame::new(inner, Some(Event::EndTag));
+        frame.literal = kind == TagKind::Literal;
+        frame.inline = kind == TagKind::Inline;
+        self.stack.push(frame);
+        Ok(Event::StartTag(kind, name))
+    }
+}
+
+/// Concatenates the text pieces making up a property value.
+fn text<'p>(pairs: &mut Pairs<'p>) -> Result<Text<'p>> {
+    pairs.map(text_piece).collect()
+}
+
+fn text_piece(pair: Pair) -> Result<Text> {
+    let span = pair.as_span();
+    let s = pair.as_str();
+    match pair.as_rule() {
+        Rule::PlainText => Ok(Text::from(s)),
+        Rule::EscapedPlainText => unescape(s).map_err(|e| e.annotate(Rule::EscapedPlainText, span)),
+        Rule::PlainQuotedText => Ok(normalize_newlines(s)),
+        Rule::EscapedQuotedText => Ok(Text::from(&s[1..])),
+        _ => Err(NoMatch.into()),
+    }
+}
+
+fn is_text(rule: Rule) -> bool {
+    matches!(rule, Rule::PlainText | Rule::EscapedPlainText)
+}
@@ -0,0 +1,108 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use prosidy_ast::{Block, BlockTag, Document, Key};
+
+use crate::error::{Error, ErrorKind, Result};
+use crate::parse::parse_document;
+
+/// How deeply includes may be nested before giving up.
+pub const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Replaces each `#-include{PATH}` block tag in the document, at any depth, with the content of
+/// the Prosidy file at `PATH`. The included file's header is discarded.
+///
+/// Paths are relative to the directory of the file containing the include, starting from
+/// `base_dir` for the document itself. Included files may include others in turn, up to
+/// [`MAX_INCLUDE_DEPTH`] levels deep, but a file may not include itself, directly or otherwise.
+///
+/// Every included file must be within `base_dir`, once symlinks and `..` components are resolved,
+/// so a document can't pull in arbitrary files from elsewhere on the system.
+pub fn resolve_includes<'a>(mut doc: Document<'a>, base_dir: &Path) -> Result<Document<'a>> {
+    let base_dir = if base_dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        base_dir
+    };
+    let mut resolver = Resolver {
+        include: Key::new(TAG_INCLUDE),
+        root: base_dir
+            .canonicalize()
+            .map_err(|error| include_error(base_dir, ErrorKind::from(error)))?,
+        stack: Vec::new(),
+    };
+    let content = mem::take(doc.content_mut());
+    *doc.content_mut() = resolver.splice(content, base_dir)?;
+    Ok(doc)
+}
+
+struct Resolver {
+    include: Key,
+    /// The canonical directory every included file must be within.
+    root: PathBuf,
+    /// The canonical paths of the files currently being included, outermost first.
+    stack: Vec<PathBuf>,
+}
+
+impl Resolver {
+    fn splice<'a>(&mut self, blocks: Vec<Block<'a>>, dir: &Path) -> Result<Vec<Block<'a>>> {
+        let mut spliced = Vec::with_capacity(blocks.len());
+        for block in blocks {
+            match block {
+                Block::Tag(tag) if tag.name_is(&self.include) => {
+                    spliced.extend(self.include(&tag, dir)?);
+                }
+                Block::Tag(mut tag) => {
+                    let content = mem::take(tag.content_mut());
+                    *tag.content_mut() = self.splice(content, dir)?;
+                    spliced.push(tag.into());
+                }
+                block => spliced.push(block),
+            }
+        }
+        Ok(spliced)
+    }
+
+    fn include(&mut self, tag: &BlockTag, dir: &Path) -> Result<Vec<Block<'static>>> {
+        let path = dir.join(tag.plain_text().trim());
+        let canonical = path
+            .canonicalize()
+            .map_err(|error| include_error(&path, ErrorKind::from(error)))?;
+        if !canonical.starts_with(&self.root) {
+            return Err(ErrorKind::IncludeOutsideRoot(path).into());
+        }
+        if self.stack.contains(&canonical) {
+            return Err(ErrorKind::IncludeCycle(path).into());
+        }
+        if self.stack.len() >= MAX_INCLUDE_DEPTH {
+            return Err(ErrorKind::IncludeTooDeep(path).into());
+        }
+        log::debug!("including {:?}", canonical);
+        let source = fs::read_to_string(&canonical)
+            .map_err(|error| include_error(&path, ErrorKind::from(error)))?;
+        let mut doc = parse_document(&source)
+            .map_err(|error| include_error(&path, error))?
+            .into_owned();
+        let dir = canonical.parent().unwrap_or_else(|| Path::new(""));
+        self.stack.push(canonical.clone());
+        let content = self.splice(mem::take(doc.content_mut()), dir);
+        self.stack.pop();
+        content
+    }
+}
+
+fn include_error<E: Into<Error>>(path: &Path, error: E) -> Error {
+    ErrorKind::Include {
+        path: path.to_path_buf(),
+        error: Box::new(error.into()),
+    }
+    .into()
+}
+
+const TAG_INCLUDE: &str = "include";
@@ -3,9 +3,27 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-pub use error::{Error, Result};
-pub use parse::{parse_document, parse_meta, Parse};
+pub use context::Context;
+pub use error::{Error, ErrorKind, Location, Result};
+pub use events::{parse_events, Event, Events, TagKind};
+pub use include::{resolve_includes, MAX_INCLUDE_DEPTH};
+pub use parse::{
+    parse_block, parse_document, parse_document_in, parse_document_recovering,
+    parse_document_spanned, parse_document_with_warnings, parse_inline, parse_meta, Parse, Rule,
+};
+pub use print::{print_document, PrintOpts};
+pub use span::{Span, Spans};
+pub use warning::{Warning, WarningKind};
 
+#[cfg(feature = "arena")]
+pub mod arena;
+
+mod context;
 mod error;
+mod events;
+mod include;
 mod parse;
+mod print;
+mod span;
 mod traits;
+mod warning;
@@ -5,36 +5,254 @@
 
 use std::iter;
 
-use pest::Parser;
+use pest::error::{Error as PestError, InputLocation};
+use pest::{Parser, Position, Span};
 use prosidy_ast::*;
 
-use crate::error::{ErrorKind::*, Result};
+use crate::context::Context;
+use crate::error::{Error, ErrorKind::*, Location, Result};
+use crate::span::{Span as NodeSpan, Spans};
 use crate::traits::*;
+use crate::warning::{Warning, WarningKind::*};
 
-pub fn parse_meta<'p>(src: &'p str) -> Result<PropSet<'p>> {
+pub fn parse_meta<'p>(src: &'p str) -> Result<DocumentProps<'p>> {
     let mut ast = DocumentParser::parse(Rule::Header, src).map_err(SyntaxError)?;
-    ast.with_block(Rule::Header, |ast| PropSet::parse(ast))
+    let mut ctx = Context::new();
+    ast.with_block(Rule::Header, |pairs| {
+        DocumentProps::parse_with(pairs, &mut ctx)
+    })
 }
 
 pub fn parse_document<'p>(src: &'p str) -> Result<Document<'p>> {
-    let mut ast = DocumentParser::parse(Rule::Document, src).map_err(SyntaxError)?;
-    let doc = Document::parse(&mut ast)?;
+    parse_document_with_warnings(src).map(|(doc, _)| doc)
+}
+
+/// Parses a document, returning every [`Warning`] raised along the way.
+///
+/// Warnings describe source which parsed successfully but is likely a mistake, such as a property
+/// key repeated within a single tag. They are also logged as they occur.
+pub fn parse_document_with_warnings<'p>(src: &'p str) -> Result<(Document<'p>, Vec<Warning>)> {
+    let mut ctx = Context::new();
+    let doc = parse_document_in(src, &mut ctx)?;
+    Ok((doc, ctx.into_warnings()))
+}
+
+/// Parses a document within an existing [`Context`], which collects any warnings raised.
+///
+/// This is how checks which need configuring, such as [`Context::with_vocabulary`], are enabled.
+pub fn parse_document_in<'p>(src: &'p str, ctx: &mut Context) -> Result<Document<'p>> {
+    let mut ast =
+        DocumentParser::parse(Rule::Document, src).map_err(|err| syntax_error(src, err))?;
+    let doc = Document::parse_with(&mut ast, ctx)?;
     ast.assert_empty()?;
     Ok(doc)
 }
 
+/// Parses a document, along with the span of source each of its nodes was parsed from.
+///
+/// Recording spans has a cost, so [`parse_document`] doesn't. See [`Spans`] for how to find the
+/// span of a particular node.
+pub fn parse_document_spanned<'p>(src: &'p str) -> Result<(Document<'p>, Spans)> {
+    let mut ast =
+        DocumentParser::parse(Rule::Document, src).map_err(|err| syntax_error(src, err))?;
+    let mut ctx = Context::with_spans();
+    let doc = spanned(&mut ast, &mut ctx, Document::parse_with)?;
+    ast.assert_empty()?;
+    Ok((doc, ctx.into_spans()))
+}
+
+/// Parses a fragment of inline content, such as `Hello #em{world}`, without a document header.
+pub fn parse_inline<'p>(src: &'p str) -> Result<Vec<Inline<'p>>> {
+    let mut ast = DocumentParser::parse(Rule::Inlines, src).map_err(SyntaxError)?;
+    let mut ctx = Context::new();
+    let content = ast.with_block(Rule::Inlines, |pairs| {
+        log::debug!("parsing inline fragment");
+        let content = paragraph(pairs, &mut ctx).recover_default()?;
+        pairs.rule(Rule::EOI)?;
+        Ok(content)
+    })?;
+    ast.assert_empty()?;
+    Ok(content)
+}
+
+/// Parses a fragment of block content without a document header.
+pub fn parse_block<'p>(src: &'p str) -> Result<Vec<Block<'p>>> {
+    let mut ast = DocumentParser::parse(Rule::Blocks, src).map_err(|err| syntax_error(src, err))?;
+    let mut ctx = Context::new();
+    let content = ast.with_block(Rule::Blocks, |pairs| {
+        log::debug!("parsing block fragment");
+        let content = Vec::parse_with(pairs, &mut ctx)?;
+        pairs.rule(Rule::EOI)?;
+        Ok(content)
+    })?;
+    ast.assert_empty()?;
+    Ok(content)
+}
+
+/// Parses a document, continuing past blocks which fail to parse so that every error can be
+/// reported at once.
+///
+/// Recovery happens at the level of top-level blocks: a malformed block is skipped up to the next
+/// blank line, and an error describing it is collected. The document is returned with every
+/// block that did parse, or `None` if the header itself could not be parsed.
+pub fn parse_document_recovering<'p>(src: &'p str) -> (Option<Document<'p>>, Vec<Error>) {
+    let mut errors = Vec::new();
+    let mut ast = match DocumentParser::parse(Rule::RecoverDocument, src) {
+        Ok(ast) => ast,
+        Err(err) => return (None, vec![SyntaxError(err).into()]),
+    };
+    let mut ctx = Context::new();
+    let result = ast.with_block(Rule::RecoverDocument, |pairs| {
+        log::debug!("parsing document with recovery");
+        let props = pairs.with_block(Rule::Header, |pairs| {
+            DocumentProps::parse_with(pairs, &mut ctx)
+        })?;
+        let mut content = Vec::new();
+        while let Some(pair) = pairs.peek() {
+            let span = pair.as_span();
+            match pair.as_rule() {
+                Rule::EOI => {
+                    pairs.next();
+                }
+                Rule::Invalid => {
+                    pairs.next();
+                    errors.push(invalid_block(src, span));
+                }
+                _ => match Block::parse_with(pairs, &mut ctx) {
+                    Ok(block) => content.push(block),
+                    Err(err) => {
+                        if pairs.peek().map(|pair| pair.as_span()) == Some(span) {
+                            pairs.next();
+                        }
+                        errors.push(err);
+                    }
+                },
+            }
+        }
+        ctx.finish();
+        Ok(Document::new(props.into_inner(), content))
+    });
+    match result {
+        Ok(doc) => (Some(doc), errors),
+        Err(err) => {
+            errors.push(err);
+            (None, errors)
+        }
+    }
+}
+
+/// Finds the error within a region skipped while recovering, by re-parsing it in isolation.
+fn invalid_block(src: &str, span: Span) -> Error {
+    match DocumentParser::parse(Rule::Blocks, span.as_str()) {
+        Err(err) => SyntaxError(relocate(err, src, span.start())).into(),
+        Ok(_) => Error::from(InvalidBlock).annotate(Rule::Invalid, span),
+    }
+}
+
+/// Moves an error raised while parsing a slice of `src`, starting at `offset`, onto `src` itself.
+pub(crate) fn relocate(err: PestError<Rule>, src: &str, offset: usize) -> PestError<Rule> {
+    let relocated = match err.location {
+        InputLocation::Pos(pos) => Position::new(src, offset + pos)
+            .map(|pos| PestError::new_from_pos(err.variant.clone(), pos)),
+        InputLocation::Span((start, end)) => Span::new(src, offset + start, offset + end)
+            .map(|span| PestError::new_from_span(err.variant.clone(), span)),
+    };
+    relocated.unwrap_or(err)
+}
+
+/// Converts a failure to parse `src` into an [`Error`]. If a literal tag in `src` is left open,
+/// that's reported in place of pest's error, which points wherever the parser gave up rather than
+/// at the literal.
+pub(crate) fn syntax_error(src: &str, err: PestError<Rule>) -> Error {
+    match unterminated_literal(src) {
+        Some((span, end)) => Error::from(UnterminatedLiteral {
+            opened_at: Location::new(Rule::LiteralTag, span),
+            end,
+        })
+        .annotate(Rule::LiteralTag, span),
+        None => SyntaxError(err).into(),
+    }
+}
+
+/// Finds the first literal tag which isn't closed by a line of its own, returning the span of its
+/// opening line and the line which should have closed it.
+fn unterminated_literal(src: &str) -> Option<(Span<'_>, String)> {
+    let mut open: Option<(Span, String)> = None;
+    let mut offset = 0;
+    for line in src.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let line = line.trim_end_matches(['\n', '\r']);
+        match &open {
+            // The literal ends at the first line starting with its closer, but anything after the
+            // closer means it was meant to close something else.
+            Some((_, end)) => match line.trim_start().strip_prefix(end.as_str()) {
+                Some(rest) if rest.trim_end().is_empty() => open = None,
+                Some(_) => return open,
+                None => {}
+            },
+            None => {
+                if let Some(delimiter) = literal_delimiter(line) {
+                    let indent = line.len() - line.trim_start().len();
+                    let span = Span::new(src, start + indent, start + line.len())?;
+                    open = Some((span, format!("#:{}", delimiter)));
+                }
+            }
+        }
+    }
+    open
+}
+
+/// The delimiter of a literal tag opened on `line`, like `end` in `#=code[lang='rs']:end`.
+fn literal_delimiter(line: &str) -> Option<&str> {
+    let tag = line.trim_start().strip_prefix("#=")?;
+    let key_end = tag
+        .find(|c: char| c.is_whitespace() || "\\#{}[]:=\",".contains(c))
+        .unwrap_or(tag.len());
+    let mut rest = &tag[key_end..];
+    if rest.starts_with('[') {
+        let mut quote = None;
+        let mut escaped = false;
+        let close = rest.char_indices().skip(1).find(|&(_, c)| {
+            match quote {
+                _ if escaped => escaped = false,
+                Some(_) if c == '\\' => escaped = true,
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None if c == '"' || c == '\'' => quote = Some(c),
+                None => return c == ']',
+            }
+            false
+        })?;
+        rest = &rest[close.0 + 1..];
+    }
+    let delimiter = rest.strip_prefix(':')?;
+    if delimiter.contains(char::is_whitespace) {
+        None
+    } else {
+        Some(delimiter)
+    }
+}
+
 pub trait Parse<'p>: Sized {
-    fn parse(pairs: &mut Pairs<'p>) -> Result<Self>;
+    /// Parses a value from `pairs`, recording any warnings in `ctx`.
+    fn parse_with(pairs: &mut Pairs<'p>, ctx: &mut Context) -> Result<Self>;
+
+    /// Parses a value from `pairs`, discarding any warnings.
+    #[deprecated(note = "use `Parse::parse_with`, which reports warnings through a `Context`")]
+    fn parse(pairs: &mut Pairs<'p>) -> Result<Self> {
+        Self::parse_with(pairs, &mut Context::new())
+    }
 }
 
 impl<'p, T> Parse<'p> for Vec<T>
 where
     T: Parse<'p>,
 {
-    fn parse(pairs: &mut Pairs<'p>) -> Result<Self> {
+    fn parse_with(pairs: &mut Pairs<'p>, ctx: &mut Context) -> Result<Self> {
         log::debug!("parsing vector");
-        let mut buf = Vec::with_capacity(pairs.clone().count());
-        while let Some(item) = T::parse(pairs).recover()? {
+        let mut buf = Vec::with_capacity(pairs.len());
+        while let Some(item) = T::parse_with(pairs, ctx).recover()? {
             buf.push(item);
         }
         buf.shrink_to_fit();
@@ -43,43 +261,54 @@ where
 }
 
 impl<'p> Parse<'p> for Block<'p> {
-    fn parse(pairs: &mut Pairs<'p>) -> Result<Self> {
-        fn content<'p>(pairs: &mut Pairs<'p>) -> Result<Block<'p>> {
-            pairs.with_block(Rule::Paragraph, |pairs| {
-                log::debug!("parsing block content");
-                let content = Vec::parse(pairs)?;
-                Ok(Block::Content(content))
-            })
+    fn parse_with(pairs: &mut Pairs<'p>, ctx: &mut Context) -> Result<Self> {
+        fn content<'p>(pairs: &mut Pairs<'p>, ctx: &mut Context) -> Result<Block<'p>> {
+            log::debug!("parsing block content");
+            paragraph(pairs, ctx).map(Block::Content)
         }
 
-        fn tag<'p>(pairs: &mut Pairs<'p>) -> Result<Block<'p>> {
-            BlockTag::parse(pairs).map(Block::Tag)
+        fn tag<'p>(pairs: &mut Pairs<'p>, ctx: &mut Context) -> Result<Block<'p>> {
+            BlockTag::parse_with(pairs, ctx).map(Block::Tag)
         }
 
-        tag(pairs)
-            .recover()
-            .transpose()
-            .unwrap_or_else(|| content(pairs))
+        spanned(pairs, ctx, |pairs, ctx| {
+            tag(pairs, ctx)
+                .recover()
+                .transpose()
+                .unwrap_or_else(|| content(pairs, ctx))
+        })
     }
 }
 
 impl<'p> Parse<'p> for BlockTag<'p> {
-    fn parse(pairs: &mut Pairs<'p>) -> Result<Self> {
+    fn parse_with(pairs: &mut Pairs<'p>, ctx: &mut Context) -> Result<Self> {
         pairs
             .with_block(Rule::BlockTag, |pairs| {
                 log::debug!("parsing block tag");
-                let name = Key::parse(pairs)?;
-                let props = PropSet::parse(pairs).recover_default()?;
-                let content = Vec::parse(pairs)?;
+                let span = pairs.peek().map(|pair| pair.as_span());
+                let name = Key::parse_with(pairs, ctx)?;
+                let props = PropSet::parse_with(pairs, ctx).recover_default()?;
+                check_tag(ctx, span, &name, &props);
+                let content = Vec::parse_with(pairs, ctx)?;
                 Ok(BlockTag::new(name, props, content))
             })
             .recover()
             .transpose()
             .unwrap_or_else(|| {
+                let end = pairs.peek().map(|pair| pair.as_span().end());
                 pairs.with_block(Rule::LiteralTag, |pairs| {
-                    let name = Key::parse(pairs)?;
-                    let props = PropSet::parse(pairs).recover_default()?;
-                    let content = Literal::parse(pairs).recover_default()?;
+                    let span = pairs.peek().map(|pair| pair.as_span());
+                    let name = Key::parse_with(pairs, ctx)?;
+                    let props = PropSet::parse_with(pairs, ctx).recover_default()?;
+                    check_tag(ctx, span, &name, &props);
+                    let content = spanned(pairs, ctx, Literal::parse_with).recover()?;
+                    let content = content.unwrap_or_else(|| {
+                        // An empty literal has no pair, so it's given an empty span at the end.
+                        if let (Some(spans), Some(end)) = (ctx.spans_mut(), end) {
+                            spans.push(NodeSpan { start: end, end });
+                        }
+                        Literal::default()
+                    });
                     Ok(BlockTag::new(name, props, vec![Block::Literal(content)]))
                 })
             })
@@ -87,19 +316,21 @@ impl<'p> Parse<'p> for BlockTag<'p> {
 }
 
 impl<'p> Parse<'p> for Document<'p> {
-    fn parse(pairs: &mut Pairs<'p>) -> Result<Self> {
+    fn parse_with(pairs: &mut Pairs<'p>, ctx: &mut Context) -> Result<Self> {
         pairs.with_block(Rule::Document, |pairs| {
             log::debug!("parsing document");
-            let props = pairs.with_block(Rule::Header, |pairs| PropSet::parse(pairs))?;
-            let content = Vec::parse(pairs)?;
+            let props =
+                pairs.with_block(Rule::Header, |pairs| DocumentProps::parse_with(pairs, ctx))?;
+            let content = Vec::parse_with(pairs, ctx)?;
             pairs.rule(Rule::EOI)?;
-            Ok(Document::new(props, content))
+            ctx.finish();
+            Ok(Document::new(props.into_inner(), content))
         })
     }
 }
 
 impl<'p> Parse<'p> for Inline<'p> {
-    fn parse(pairs: &mut Pairs<'p>) -> Result<Self> {
+    fn parse_with(pairs: &mut Pairs<'p>, ctx: &mut Context) -> Result<Self> {
         fn softbreak<'p>(pairs: &mut Pairs<'p>) -> Result<Inline<'p>> {
             pairs.with_block(Rule::SoftBreak, |_| {
                 log::debug!("parsing soft break");
@@ -107,46 +338,48 @@ impl<'p> Parse<'p> for Inline<'p> {
             })
         }
 
-        fn text<'p>(pairs: &mut Pairs<'p>) -> Result<Inline<'p>> {
-            Text::parse(pairs).map(Inline::Text)
+        fn text<'p>(pairs: &mut Pairs<'p>, ctx: &mut Context) -> Result<Inline<'p>> {
+            Text::parse_with(pairs, ctx).map(Inline::Text)
         }
 
-        fn tag<'p>(pairs: &mut Pairs<'p>) -> Result<Inline<'p>> {
-            InlineTag::parse(pairs).map(Inline::Tag)
+        fn tag<'p>(pairs: &mut Pairs<'p>, ctx: &mut Context) -> Result<Inline<'p>> {
+            InlineTag::parse_with(pairs, ctx).map(Inline::Tag)
         }
 
-        softbreak(pairs)
-            .recover()
-            .transpose()
-            .unwrap_or_else(|| tag(pairs))
-            .recover()
-            .transpose()
-            .unwrap_or_else(|| text(pairs))
+        spanned(pairs, ctx, |pairs, ctx| {
+            softbreak(pairs)
+                .recover()
+                .transpose()
+                .unwrap_or_else(|| tag(pairs, ctx))
+                .recover()
+                .transpose()
+                .unwrap_or_else(|| text(pairs, ctx))
+        })
     }
 }
 
 impl<'p> Parse<'p> for InlineTag<'p> {
-    fn parse(pairs: &mut Pairs<'p>) -> Result<Self> {
+    fn parse_with(pairs: &mut Pairs<'p>, ctx: &mut Context) -> Result<Self> {
         pairs.with_block(Rule::InlineTag, |pairs| {
             log::debug!("parsing inline tag");
-            let name = Key::parse(pairs)?;
-            let props = PropSet::parse(pairs).recover_default()?;
-            let content = pairs
-                .with_block(Rule::Paragraph, |pairs| Vec::parse(pairs))
-                .recover_default()?;
+            let span = pairs.peek().map(|pair| pair.as_span());
+            let name = Key::parse_with(pairs, ctx)?;
+            let props = PropSet::parse_with(pairs, ctx).recover_default()?;
+            check_tag(ctx, span, &name, &props);
+            let content = paragraph(pairs, ctx).recover_default()?;
             Ok(InlineTag::new(name, props, content))
         })
     }
 }
 
 impl<'p> Parse<'p> for Literal<'p> {
-    fn parse(pairs: &mut Pairs<'p>) -> Result<Self> {
-        pairs.with_atom(Rule::Literal, |s| Ok(Literal::from(Text::new(s))))
+    fn parse_with(pairs: &mut Pairs<'p>, _ctx: &mut Context) -> Result<Self> {
+        pairs.with_atom(Rule::Literal, |s| Ok(Literal::from(normalize_newlines(s))))
     }
 }
 
 impl<'p> Parse<'p> for Key {
-    fn parse(pairs: &mut Pairs<'p>) -> Result<Self> {
+    fn parse_with(pairs: &mut Pairs<'p>, _ctx: &mut Context) -> Result<Self> {
         pairs.with_atom(Rule::Key, |s| {
             log::debug!("parsing key");
             Ok(Key::new(s))
@@ -154,67 +387,66 @@ impl<'p> Parse<'p> for Key {
     }
 }
 
-impl<'p> Parse<'p> for PropSet<'p> {
-    fn parse(pairs: &mut Pairs<'p>) -> Result<Self> {
-        fn prop<'p>(pairs: &mut Pairs<'p>, props: &mut PropSet<'p>) -> Result<()> {
-            pairs.with_block(Rule::Prop, |pairs| {
-                log::debug!("parsing prop key-value pair");
-                let key = Key::parse(pairs)?;
-                let opt_value = pairs
-                    .with_block(Rule::QuotedText, |pairs| Text::parse(pairs))
-                    .recover()?;
-                if let Some(value) = opt_value {
-                    props.put(key, value);
-                } else {
-                    props.set(key);
-                }
-                Ok(())
-            })
-        }
-
-        fn header_prop<'p>(pairs: &mut Pairs<'p>, props: &mut PropSet<'p>) -> Result<()> {
+impl<'p> Parse<'p> for DocumentProps<'p> {
+    fn parse_with(pairs: &mut Pairs<'p>, ctx: &mut Context) -> Result<Self> {
+        fn header_prop<'p>(
+            pairs: &mut Pairs<'p>,
+            ctx: &mut Context,
+            props: &mut PropSet<'p>,
+        ) -> Result<()> {
             pairs.with_block(Rule::DocumentProp, |pairs| {
                 log::debug!("parsing header prop key-value pair");
-                let key = Key::parse(pairs)?;
+                let span = pairs.peek().map(|pair| pair.as_span());
+                let key = Key::parse_with(pairs, ctx)?;
                 let opt_value = pairs
-                    .with_block(Rule::DocumentPropValue, |pairs| Text::parse(pairs))
+                    .with_block(Rule::DocumentPropValue, |pairs| {
+                        Text::parse_with(pairs, ctx)
+                    })
                     .recover()?;
-                if let Some(value) = opt_value {
-                    props.put(key, value);
-                } else {
-                    props.set(key);
-                }
+                insert_prop(ctx, props, span, key, opt_value);
                 Ok(())
             })
         }
 
-        fn props<'p>(pairs: &mut Pairs<'p>) -> Result<PropSet<'p>> {
-            pairs.with_block(Rule::Props, |pairs| {
-                log::debug!("parsing property set");
-                let mut props = PropSet::new();
-                while prop(pairs, &mut props).recover()?.is_some() {}
-                Ok(props)
-            })
-        }
+        pairs.with_block(Rule::DocumentProps, |pairs| {
+            log::debug!("parsing header property set");
+            let mut props = DocumentProps::new();
+            while header_prop(pairs, ctx, &mut props).recover()?.is_some() {}
+            Ok(props)
+        })
+    }
+}
 
-        fn headers<'p>(pairs: &mut Pairs<'p>) -> Result<PropSet<'p>> {
-            pairs.with_block(Rule::DocumentProps, |pairs| {
-                log::debug!("parsing header property set");
-                let mut props = PropSet::new();
-                while header_prop(pairs, &mut props).recover()?.is_some() {}
-                Ok(props)
+impl<'p> Parse<'p> for PropSet<'p> {
+    fn parse_with(pairs: &mut Pairs<'p>, ctx: &mut Context) -> Result<Self> {
+        fn prop<'p>(
+            pairs: &mut Pairs<'p>,
+            ctx: &mut Context,
+            props: &mut PropSet<'p>,
+        ) -> Result<()> {
+            pairs.with_block(Rule::Prop, |pairs| {
+                log::debug!("parsing prop key-value pair");
+                let span = pairs.peek().map(|pair| pair.as_span());
+                let key = Key::parse_with(pairs, ctx)?;
+                let opt_value = pairs
+                    .with_block(Rule::QuotedText, |pairs| Text::parse_with(pairs, ctx))
+                    .recover()?;
+                insert_prop(ctx, props, span, key, opt_value);
+                Ok(())
             })
         }
 
-        props(pairs)
-            .recover()
-            .transpose()
-            .unwrap_or_else(|| headers(pairs))
+        pairs.with_block(Rule::Props, |pairs| {
+            log::debug!("parsing property set");
+            let mut props = PropSet::new();
+            while prop(pairs, ctx, &mut props).recover()?.is_some() {}
+            Ok(props)
+        })
     }
 }
 
 impl<'p> Parse<'p> for Text<'p> {
-    fn parse(pairs: &mut Pairs<'p>) -> Result<Self> {
+    fn parse_with(pairs: &mut Pairs<'p>, _ctx: &mut Context) -> Result<Self> {
         fn plaintext<'p>(pairs: &mut Pairs<'p>) -> Option<Result<Text<'p>>> {
             pairs
                 .with_atom(Rule::PlainText, |s| {
@@ -229,7 +461,7 @@ impl<'p> Parse<'p> for Text<'p> {
             pairs
                 .with_atom(Rule::PlainQuotedText, |s| {
                     log::debug!("parsing quoted text");
-                    Ok(Text::from(s))
+                    Ok(normalize_newlines(s))
                 })
                 .recover()
                 .transpose()
@@ -239,15 +471,17 @@ impl<'p> Parse<'p> for Text<'p> {
             pairs
                 .with_atom(Rule::EscapedPlainText, |s| {
                     log::debug!("parsing plain text escape");
-                    match s {
-                        r#"\n"# => Ok(Text::Borrowed("\n")),
-                        r#"\t"# => Ok(Text::Borrowed("\t")),
-                        r#"\\"# => Ok(Text::Borrowed("\\")),
-                        r#"\#"# => Ok(Text::Borrowed("#")),
-                        r#"\{"# => Ok(Text::Borrowed("{")),
-                        r#"\}"# => Ok(Text::Borrowed("}")),
-                        _ => Err(InvalidEscape(s.into()).into()),
-                    }
+                    unescape(s)
+                })
+                .recover()
+                .transpose()
+        }
+
+        fn escaped_quote<'p>(pairs: &mut Pairs<'p>) -> Option<Result<Text<'p>>> {
+            pairs
+                .with_atom(Rule::EscapedQuotedText, |s| {
+                    log::debug!("parsing quoted text escape");
+                    Ok(Text::from(&s[1..]))
                 })
                 .recover()
                 .transpose()
@@ -257,6 +491,7 @@ impl<'p> Parse<'p> for Text<'p> {
             plaintext(pairs)
                 .or_else(|| escaped(pairs))
                 .or_else(|| quotetext(pairs))
+                .or_else(|| escaped_quote(pairs))
         })
         .peekable();
 
@@ -268,7 +503,164 @@ impl<'p> Parse<'p> for Text<'p> {
     }
 }
 
-type Pairs<'p> = pest::iterators::Pairs<'p, Rule>;
+/// Parses the inline content of a `Paragraph`.
+///
+/// Paragraphs of plain prose (containing no tags, escapes, or braces) are split on newlines
+/// directly rather than walking each inline pair; both paths produce identical content.
+fn paragraph<'p>(pairs: &mut Pairs<'p>, ctx: &mut Context) -> Result<Vec<Inline<'p>>> {
+    // Prose skips the inline pairs, which carry the spans of its nodes.
+    let is_prose = ctx.spans_mut().is_none()
+        && pairs
+            .peek()
+            .filter(|pair| pair.as_rule() == Rule::Paragraph && is_prose(pair.as_str()))
+            .is_some();
+    if is_prose {
+        pairs.with_atom(Rule::Paragraph, |s| {
+            log::debug!("parsing prose paragraph");
+            Ok(prose(s))
+        })
+    } else {
+        pairs.with_block(Rule::Paragraph, |pairs| Vec::parse_with(pairs, ctx))
+    }
+}
+
+/// Parses a node with `f`, recording its span if the context is collecting them.
+///
+/// A node's span is reserved before its children are parsed, so that spans are recorded in
+/// pre-order, and released again if it fails to parse.
+fn spanned<'p, T, F>(pairs: &mut Pairs<'p>, ctx: &mut Context, f: F) -> Result<T>
+where
+    F: FnOnce(&mut Pairs<'p>, &mut Context) -> Result<T>,
+{
+    let index = match ctx.spans_mut() {
+        Some(spans) => {
+            spans.push(NodeSpan::default());
+            spans.len() - 1
+        }
+        None => return f(pairs, ctx),
+    };
+    let before = pairs.clone();
+    let result = f(pairs, ctx);
+    let spans = ctx.spans_mut().unwrap();
+    if result.is_err() {
+        spans.truncate(index);
+        return result;
+    }
+    let consumed = before.len() - pairs.len();
+    let mut consumed = before.take(consumed);
+    if let Some(first) = consumed.next() {
+        let end = consumed
+            .last()
+            .unwrap_or_else(|| first.clone())
+            .as_span()
+            .end();
+        let start = match first.as_rule() {
+            // The grammar matches the `#-` or `#=` opening a block tag outside of its rule.
+            Rule::BlockTag | Rule::LiteralTag => first.as_span().start() - 2,
+            _ => first.as_span().start(),
+        };
+        spans[index] = NodeSpan { start, end };
+    }
+    result
+}
+
+/// Checks a tag's name against the vocabulary, and records any element `id` or `href='#...'`
+/// reference among its properties.
+fn check_tag<'p>(ctx: &mut Context, span: Option<Span<'p>>, name: &Key, props: &PropSet<'p>) {
+    if let Some(id) = props.lookup_str(Key::new("id")) {
+        ctx.define_id(id);
+    }
+    // The tag's name is always the first pair within it, so this is only `None` for a malformed
+    // tag, which will fail to parse anyway.
+    let span = match span {
+        Some(span) => span,
+        None => return,
+    };
+    ctx.check_tag(name, || Location::new(Rule::Key, span));
+    let href = props.lookup_str(Key::new("href"));
+    if let Some(target) = href.and_then(|href| href.strip_prefix('#')) {
+        ctx.reference_id(target, Location::new(Rule::Key, span));
+    }
+}
+
+/// Inserts a parsed property into a set, warning if its key was already present.
+fn insert_prop<'p>(
+    ctx: &mut Context,
+    props: &mut PropSet<'p>,
+    span: Option<Span<'p>>,
+    key: Key,
+    opt_value: Option<Text<'p>>,
+) {
+    if props.is_set(&key) || props.lookup(&key).is_some() {
+        if let Some(span) = span {
+            let location = Location::new(Rule::Key, span);
+            ctx.warn(Warning::new(DuplicateKey(key.clone()), location));
+        }
+    }
+    if let Some(value) = opt_value {
+        props.put(key, value);
+    } else {
+        props.set(key);
+    }
+}
+
+/// Converts `\r\n` and lone `\r` line endings to `\n`, borrowing the source if it has none.
+///
+/// The grammar already treats every line ending as a newline, so only text which is copied
+/// verbatim from the source (literals and quoted property values) needs normalizing.
+pub(crate) fn normalize_newlines(s: &str) -> Text<'_> {
+    if s.contains('\r') {
+        Text::from(s.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Text::from(s)
+    }
+}
+
+/// Decodes an escape sequence matched by `EscapedPlainText`, such as `\#` or `\u{1F600}`.
+pub(crate) fn unescape(s: &str) -> Result<Text<'_>> {
+    match s {
+        r#"\n"# => Ok(Text::Borrowed("\n")),
+        r#"\t"# => Ok(Text::Borrowed("\t")),
+        r#"\\"# => Ok(Text::Borrowed("\\")),
+        r#"\#"# => Ok(Text::Borrowed("#")),
+        r#"\{"# => Ok(Text::Borrowed("{")),
+        r#"\}"# => Ok(Text::Borrowed("}")),
+        _ => code_point(s)
+            .map(|c| Text::from(c.to_string()))
+            .ok_or_else(|| InvalidEscape(s.into()).into()),
+    }
+}
+
+/// Decodes a `\u{...}` escape sequence into the character it names.
+pub(crate) fn code_point(s: &str) -> Option<char> {
+    let hex = s.strip_prefix("\\u{")?.strip_suffix('}')?;
+    if hex.is_empty() || hex.len() > 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok().and_then(std::char::from_u32)
+}
+
+pub(crate) fn is_prose(s: &str) -> bool {
+    !s.bytes()
+        .any(|b| matches!(b, b'#' | b'{' | b'}' | b'\\' | b'\r'))
+}
+
+fn prose(s: &str) -> Vec<Inline<'_>> {
+    let mut lines = s.split('\n');
+    let mut content = Vec::new();
+    if let Some(first) = lines.next() {
+        content.push(Inline::Text(Text::from(first)));
+    }
+    for line in lines {
+        // A `SoftBreak` consumes the newline and any whitespace indenting the next line.
+        let line = line.trim_start_matches(char::is_whitespace);
+        content.push(Inline::SoftBreak);
+        content.push(Inline::Text(Text::from(line)));
+    }
+    content
+}
+
+pub(crate) type Pairs<'p> = pest::iterators::Pairs<'p, Rule>;
 
 impl<'p> PairsExt<'p> for Pairs<'p> {
     fn peek(&self) -> Option<Pair<'p>> {
@@ -276,8 +668,8 @@ impl<'p> PairsExt<'p> for Pairs<'p> {
     }
 }
 
-type Pair<'p> = pest::iterators::Pair<'p, Rule>;
+pub(crate) type Pair<'p> = pest::iterators::Pair<'p, Rule>;
 
 #[derive(pest_derive::Parser)]
 #[grammar = "document.pest"]
-struct DocumentParser;
+pub(crate) struct DocumentParser;
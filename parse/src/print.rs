@@ -0,0 +1,40 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use prosidy_ast::Document;
+pub use prosidy_ast::PrintOpts;
+
+use crate::error::{ErrorKind, Result};
+
+/// Writes a document back out as Prosidy source, in a canonical style.
+///
+/// - Header properties are written one per line, sorted by key.
+/// - Blocks are separated by blank lines, and the blocks inside a block tag are indented.
+/// - A block tag holding a single paragraph is written with braces, and one holding a single
+///   literal as `#=name:`, with the shortest label that doesn't appear in the literal.
+/// - Properties are written as `[key='value', flag]`, sorted by key. A value containing `'` is
+///   quoted with `"` instead, if that needs fewer escapes.
+/// - Reserved characters in text are escaped, as is whitespace that would otherwise be dropped,
+///   like at the start of a paragraph.
+///
+/// Parsing the output gives back the same document. There are two exceptions: comments are lost,
+/// since they aren't kept in the AST, and a property with an empty value is written as a flag,
+/// since Prosidy has no syntax for one. With [`PrintOpts::width`] set, the whitespace between
+/// words may also change as paragraphs are reflowed.
+///
+/// Documents which can't be written as Prosidy at all, like one with a literal outside of a block
+/// tag or a key containing whitespace, produce an error.
+///
+/// ```rust
+/// # use prosidy_parse::{parse_document, print_document, PrintOpts};
+/// let doc = parse_document("title:Hi\n---\n#-section[id=\"s\"]:\nSome   #em{text}\n#:\n").unwrap();
+/// let source = print_document(&doc, &PrintOpts::default()).unwrap();
+/// assert_eq!("title: Hi\n---\n\n#-section[id='s']{Some   #em{text}}\n", source);
+/// assert_eq!(doc, parse_document(&source).unwrap());
+/// ```
+pub fn print_document(doc: &Document, opts: &PrintOpts) -> Result<String> {
+    doc.print(opts)
+        .map_err(|err| ErrorKind::Unprintable(err.to_string()).into())
+}
@@ -0,0 +1,83 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::ops::Range;
+
+use prosidy_ast::{Document, Node};
+
+/// A range of bytes in the source a node was parsed from.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Returns the text of the source covered by the span.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the span is out of bounds, or doesn't fall on character boundaries, which can
+    /// only happen if `src` isn't the source the span came from.
+    #[inline]
+    pub fn slice<'s>(&self, src: &'s str) -> &'s str {
+        &src[self.range()]
+    }
+
+    #[inline]
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+/// The spans of every node in a document, as returned by
+/// [`parse_document_spanned`](crate::parse_document_spanned).
+///
+/// Spans are stored in the order [`Node::descendants`] visits the nodes, starting with the
+/// document itself, so the span of the `n`th node is at index `n`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Spans(Vec<Span>);
+
+impl Spans {
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<Span> {
+        self.0.get(index).copied()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl '_ + Iterator<Item = Span> {
+        self.0.iter().copied()
+    }
+
+    /// Pairs each node of `doc` with its span. `doc` must be the document these spans were
+    /// parsed alongside, unmodified.
+    pub fn nodes<'s, 'r, 'a>(
+        &'s self,
+        doc: &'r Document<'a>,
+    ) -> impl 's + Iterator<Item = (Node<'r, 'a>, Span)>
+    where
+        'r: 's,
+        'a: 'r,
+    {
+        Node::from(doc).descendants().zip(self.iter())
+    }
+}
+
+impl From<Vec<Span>> for Spans {
+    #[inline]
+    fn from(spans: Vec<Span>) -> Self {
+        Spans(spans)
+    }
+}
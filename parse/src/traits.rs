@@ -3,8 +3,6 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::fmt::Display;
-
 use pest::iterators::{Pair, Pairs};
 
 use crate::error::{Error, ErrorKind};
@@ -19,10 +17,6 @@ pub trait ResultExt<T, E>: Sized {
     {
         self.recover().map(Option::unwrap_or_default)
     }
-
-    fn unwrap_display(self) -> T
-    where
-        E: Display;
 }
 
 pub trait PairsExt<'p>: Iterator<Item = Pair<'p, Rule>> + Sized {
@@ -60,7 +54,7 @@ pub trait PairsExt<'p>: Iterator<Item = Pair<'p, Rule>> + Sized {
         let pair = self.rule(rule)?;
         let span = pair.as_span();
         let mut pairs = pair.into_inner();
-        let out = f(&mut pairs).map_err(|e| e.annotate(rule, span.clone()))?;
+        let out = f(&mut pairs).map_err(|e| e.annotate(rule, span))?;
         pairs.assert_empty().map_err(|e| e.annotate(rule, span))?;
         Ok(out)
     }
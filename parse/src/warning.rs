@@ -0,0 +1,49 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::fmt::{self, Display, Formatter};
+
+use prosidy_ast::Key;
+
+use crate::error::Location;
+
+/// A problem found in a document which did not prevent it from being parsed.
+#[derive(Debug)]
+pub struct Warning {
+    location: Location,
+    kind: WarningKind,
+}
+
+impl Warning {
+    pub fn new(kind: WarningKind, location: Location) -> Self {
+        Warning { location, kind }
+    }
+
+    pub fn kind(&self) -> &WarningKind {
+        &self.kind
+    }
+
+    pub fn location(&self) -> &Location {
+        &self.location
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        write!(fmt, "{} ({})", self.kind, self.location)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WarningKind {
+    #[error("Duplicate property key '{0}'")]
+    DuplicateKey(Key),
+    /// A tag outside the vocabulary given to [`Context::with_vocabulary`](crate::Context::with_vocabulary).
+    #[error("Unknown tag '{0}'")]
+    UnknownTag(Key),
+    /// An `href='#id'` property with no element carrying a matching `id` property.
+    #[error("Reference to undefined id '{0}'")]
+    DanglingReference(String),
+}
@@ -0,0 +1,49 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+#![cfg(feature = "arena")]
+
+use pretty_assertions::assert_eq;
+use prosidy_parse::{arena, parse_document, Result};
+
+const SOURCES: &[&str] = &[
+    include_str!("test01.pro"),
+    include_str!("test02.pro"),
+    include_str!("test03.pro"),
+    include_str!("test04.pro"),
+    include_str!("test05.pro"),
+    include_str!("test06.pro"),
+    include_str!("test07.pro"),
+    include_str!("test08.pro"),
+    include_str!("test09.pro"),
+    include_str!("test10.pro"),
+    include_str!("test11.pro"),
+];
+
+#[test]
+fn test_arena_matches_owned() {
+    for source in SOURCES {
+        let bump = arena::Bump::new();
+        match (arena::parse_document(&bump, source), parse_document(source)) {
+            (Ok(actual), Ok(expected)) => assert_eq!(actual.to_ast(), expected),
+            (Err(actual), Err(expected)) => assert_eq!(actual.to_string(), expected.to_string()),
+            (actual, expected) => panic!("arena: {:?}\nowned: {:?}", actual, expected),
+        }
+    }
+}
+
+#[test]
+fn test_arena_escapes() -> Result<()> {
+    let bump = arena::Bump::new();
+    let source = "---\n#a[href='x\r\ny']{\\{\\u{1F600}\\}}\n";
+    let doc = arena::parse_document(&bump, source)?;
+    let tag = match doc.content() {
+        [arena::Block::Content([arena::Inline::Tag(tag)])] => tag,
+        content => panic!("unexpected content: {:?}", content),
+    };
+    assert_eq!(Some("x\ny"), tag.prop("href"));
+    assert_eq!(&[arena::Inline::Text("{😀}")], tag.content());
+    Ok(())
+}
@@ -0,0 +1,205 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::iter::Peekable;
+use std::vec;
+
+use pretty_assertions::assert_eq;
+use prosidy_ast::*;
+use prosidy_parse::{parse_document, parse_events, Event, Result, TagKind};
+
+#[test]
+fn test_nested_events() -> Result<()> {
+    let source = "title: Events\ndraft\n---\n\
+                  #-section[id='a']:\n\
+                  One \\#1\ntwo #em[x]{three}.\n\
+                  #=code:\nfn main() {}\n#:\n\
+                  #:\n";
+    let events = parse_events(source)?.collect::<Result<Vec<_>>>()?;
+    let expected = vec![
+        Event::Prop(Key::new("title"), Some(Text::from("Events"))),
+        Event::Prop(Key::new("draft"), None),
+        Event::EndHeader,
+        Event::StartTag(TagKind::Block, Key::new("section")),
+        Event::Prop(Key::new("id"), Some(Text::from("a"))),
+        Event::StartParagraph,
+        Event::Text(Text::from("One #1")),
+        Event::SoftBreak,
+        Event::Text(Text::from("two ")),
+        Event::StartTag(TagKind::Inline, Key::new("em")),
+        Event::Prop(Key::new("x"), None),
+        Event::Text(Text::from("three")),
+        Event::EndTag,
+        Event::Text(Text::from(".")),
+        Event::EndParagraph,
+        Event::StartTag(TagKind::Literal, Key::new("code")),
+        Event::Literal(Literal::from(Text::from("fn main() {}\n"))),
+        Event::EndTag,
+        Event::EndTag,
+    ];
+    assert_eq!(expected, events);
+    Ok(())
+}
+
+#[test]
+fn test_header_only() -> Result<()> {
+    // The body isn't parsed until it's asked for, so its syntax error goes unnoticed.
+    let events = parse_events("title: Hi\n---\n#oops{\n")?;
+    let header: Vec<_> = events
+        .take_while(|event| !matches!(event, Ok(Event::EndHeader)))
+        .collect::<Result<_>>()?;
+    assert_eq!(
+        vec![Event::Prop(Key::new("title"), Some(Text::from("Hi")))],
+        header
+    );
+    let mut events = parse_events("title: Hi\n---\n#oops{\n")?.skip(2);
+    assert!(matches!(events.next(), Some(Err(_))));
+    assert!(events.next().is_none());
+    Ok(())
+}
+
+#[test]
+fn test_events_match_documents() -> Result<()> {
+    let sources = [
+        include_str!("test01.pro"),
+        include_str!("test02.pro"),
+        include_str!("test03.pro"),
+        include_str!("test04.pro"),
+        include_str!("test05.pro"),
+        include_str!("test06.pro"),
+        include_str!("test07.pro"),
+        include_str!("test08.pro"),
+        include_str!("test09.pro"),
+        include_str!("test10.pro"),
+        include_str!("test11.pro"),
+        include_str!("test12.pro"),
+    ];
+    for source in sources.iter() {
+        let events = parse_events(source).and_then(|events| events.collect());
+        match parse_document(source) {
+            Ok(doc) => assert_eq!(doc, build(events?)),
+            Err(_) => assert!(events.is_err()),
+        }
+    }
+    Ok(())
+}
+
+type Stream<'p> = Peekable<vec::IntoIter<Event<'p>>>;
+
+/// Assembles a document from its events, to check that they describe it fully.
+fn build(events: Vec<Event>) -> Document {
+    let mut events = events.into_iter().peekable();
+    let header = props(&mut events);
+    assert_eq!(Some(Event::EndHeader), events.next());
+    let content = blocks(&mut events);
+    assert_eq!(None, events.next());
+    Document::new(header, content)
+}
+
+fn props<'p>(events: &mut Stream<'p>) -> PropSet<'p> {
+    let mut props = PropSet::new();
+    while let Some(Event::Prop(..)) = events.peek() {
+        match events.next() {
+            Some(Event::Prop(key, Some(value))) => {
+                props.put(key, value);
+            }
+            Some(Event::Prop(key, None)) => props.set(key),
+            _ => unreachable!(),
+        }
+    }
+    props
+}
+
+fn blocks<'p>(events: &mut Stream<'p>) -> Vec<Block<'p>> {
+    let mut content = Vec::new();
+    loop {
+        match events.peek() {
+            Some(Event::StartParagraph) => {
+                events.next();
+                content.push(Block::Content(inlines(events)));
+                assert_eq!(Some(Event::EndParagraph), events.next());
+            }
+            Some(Event::StartTag(TagKind::Block, _)) => {
+                let (name, props) = start_tag(events);
+                let children = blocks(events);
+                assert_eq!(Some(Event::EndTag), events.next());
+                content.push(BlockTag::new(name, props, children).into());
+            }
+            Some(Event::StartTag(TagKind::Literal, _)) => {
+                let (name, props) = start_tag(events);
+                let literal = match events.next() {
+                    Some(Event::Literal(literal)) => literal,
+                    event => panic!("expected a literal, found {:?}", event),
+                };
+                assert_eq!(Some(Event::EndTag), events.next());
+                content.push(BlockTag::new(name, props, vec![Block::Literal(literal)]).into());
+            }
+            _ => return content,
+        }
+    }
+}
+
+fn inlines<'p>(events: &mut Stream<'p>) -> Vec<Inline<'p>> {
+    let mut content = Vec::new();
+    loop {
+        match events.peek() {
+            Some(Event::Text(_)) => match events.next() {
+                Some(Event::Text(text)) => content.push(text.into()),
+                _ => unreachable!(),
+            },
+            Some(Event::SoftBreak) => {
+                events.next();
+                content.push(Inline::SoftBreak);
+            }
+            Some(Event::StartTag(TagKind::Inline, _)) => {
+                let (name, props) = start_tag(events);
+                let children = inlines(events);
+                assert_eq!(Some(Event::EndTag), events.next());
+                content.push(InlineTag::new(name, props, children).into());
+            }
+            _ => return content,
+        }
+    }
+}
+
+fn start_tag<'p>(events: &mut Stream<'p>) -> (Key, PropSet<'p>) {
+    match events.next() {
+        Some(Event::StartTag(_, name)) => (name, props(events)),
+        event => panic!("expected a tag, found {:?}", event),
+    }
+}
+
+#[test]
+fn test_body_errors() {
+    // The body is parsed apart from the header, but it's held to the same rules as in a document,
+    // and its errors point into the whole source.
+    for source in [
+        "title: Hi\n---\nNo final newline",
+        "---\n## comment\n#-a{b}\n",
+    ]
+    .iter()
+    {
+        let events = parse_events(source).and_then(|events| events.collect::<Result<Vec<_>>>());
+        assert_eq!(
+            parse_document(source).is_ok(),
+            events.is_ok(),
+            "{:?}",
+            source
+        );
+    }
+    let invalid_escape = "title: Hi\n---\nFine.\n\nAn \\u{D800} surrogate.\n";
+    let syntax_error = "title: Hi\n---\nFine.\n\nAn #unclosed{tag.\n";
+    for source in [invalid_escape, syntax_error].iter() {
+        let error = parse_events(source)
+            .unwrap()
+            .find_map(|event| event.err())
+            .unwrap();
+        // Only the message and location are compared, as a document's error also lists the rules
+        // enclosing it.
+        let expected = parse_document(source).unwrap_err().report(source);
+        let first_lines = |report: &str| report.lines().take(2).collect::<Vec<_>>().join("\n");
+        assert_eq!(first_lines(&expected), first_lines(&error.report(source)));
+    }
+}
@@ -0,0 +1,52 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use pretty_assertions::assert_eq;
+use prosidy_ast::*;
+use prosidy_parse::{parse_block, parse_inline, Result};
+
+#[test]
+fn test_inline() -> Result<()> {
+    let actual = parse_inline("Hello #em{world}")?;
+    let expected: Vec<Inline> = vec![
+        Text::new("Hello ").into(),
+        InlineTag::new("em", props! {}, vec![Text::new("world").into()]).into(),
+    ];
+    assert_eq!(actual, expected);
+    assert_eq!(parse_inline("")?, vec![]);
+    Ok(())
+}
+
+#[test]
+fn test_block() -> Result<()> {
+    let actual = parse_block("#-h1{Title}\n\nFirst\nparagraph.\n\n#=code:\nfn main() {}\n#:")?;
+    let expected: Vec<Block> = vec![
+        BlockTag::new(
+            "h1",
+            props! {},
+            vec![Block::Content(vec![Text::new("Title").into()])],
+        )
+        .into(),
+        Block::Content(vec![
+            Text::new("First").into(),
+            Inline::SoftBreak,
+            Text::new("paragraph.").into(),
+        ]),
+        BlockTag::new(
+            "code",
+            props! {},
+            vec![Block::Literal(Literal::from(Text::new("fn main() {}\n")))],
+        )
+        .into(),
+    ];
+    assert_eq!(actual, expected);
+    Ok(())
+}
+
+#[test]
+fn test_trailing_input() {
+    assert!(parse_inline("Hello\n\nworld").is_err());
+    assert!(parse_block("Unopened\n#:\n").is_err());
+}
@@ -0,0 +1,92 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::fs;
+use std::path::PathBuf;
+
+use pretty_assertions::assert_eq;
+use prosidy_parse::{parse_document, resolve_includes, MAX_INCLUDE_DEPTH};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("prosidy-include-{}-{}", name, std::process::id()));
+    fs::create_dir_all(dir.join("parts")).unwrap();
+    dir
+}
+
+#[test]
+fn test_resolve_includes() {
+    let dir = temp_dir("nested");
+    fs::write(
+        dir.join("parts/one.pro"),
+        "title: Ignored\n---\nFirst part.\n\n#-include{two.pro}\n",
+    )
+    .unwrap();
+    fs::write(dir.join("parts/two.pro"), "---\nSecond part.\n").unwrap();
+    let source = "title: Book\n---\n#-section:\n  #-include{parts/one.pro}\n#:\n\nThe end.\n";
+    let doc = resolve_includes(parse_document(source).unwrap(), &dir);
+    fs::remove_dir_all(&dir).unwrap();
+    let expected =
+        "title: Book\n---\n#-section:\n  First part.\n\n  Second part.\n#:\n\nThe end.\n";
+    assert_eq!(parse_document(expected).unwrap(), doc.unwrap());
+}
+
+#[test]
+fn test_include_cycle() {
+    let dir = temp_dir("cycle");
+    fs::write(dir.join("a.pro"), "---\n#-include{parts/b.pro}\n").unwrap();
+    fs::write(dir.join("parts/b.pro"), "---\n#-include{../a.pro}\n").unwrap();
+    let doc = parse_document("---\n#-include{a.pro}\n").unwrap();
+    let result = resolve_includes(doc, &dir);
+    fs::remove_dir_all(&dir).unwrap();
+    let error = result.unwrap_err().to_string();
+    assert!(error.contains("would include itself"), "{}", error);
+}
+
+#[test]
+fn test_include_depth() {
+    let dir = temp_dir("depth");
+    for n in 0..=MAX_INCLUDE_DEPTH {
+        let source = format!("---\n#-include{{{}.pro}}\n", n + 1);
+        fs::write(dir.join(format!("{}.pro", n)), source).unwrap();
+    }
+    let doc = parse_document("---\n#-include{0.pro}\n").unwrap();
+    let result = resolve_includes(doc, &dir);
+    fs::remove_dir_all(&dir).unwrap();
+    let error = result.unwrap_err().to_string();
+    assert!(error.contains("nested too deeply"), "{}", error);
+}
+
+#[test]
+fn test_include_outside_root() {
+    let outer = temp_dir("outside");
+    let dir = outer.join("book");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(outer.join("secret.pro"), "---\nSecret.\n").unwrap();
+    fs::write(dir.join("sneaky.pro"), "---\n#-include{../secret.pro}\n").unwrap();
+    let direct = resolve_includes(
+        parse_document("---\n#-include{../secret.pro}\n").unwrap(),
+        &dir,
+    );
+    let nested = resolve_includes(
+        parse_document("---\n#-include{sneaky.pro}\n").unwrap(),
+        &dir,
+    );
+    #[cfg(unix)]
+    let linked = {
+        std::os::unix::fs::symlink(outer.join("secret.pro"), dir.join("link.pro")).unwrap();
+        resolve_includes(parse_document("---\n#-include{link.pro}\n").unwrap(), &dir)
+    };
+    fs::remove_dir_all(&outer).unwrap();
+    for result in [direct, nested] {
+        let error = result.unwrap_err().to_string();
+        assert!(
+            error.contains("outside the document's directory"),
+            "{}",
+            error
+        );
+    }
+    #[cfg(unix)]
+    assert!(linked.is_err(), "symlinks are resolved before checking");
+}
@@ -0,0 +1,22 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+#![cfg(feature = "miette")]
+
+use miette::Diagnostic;
+use prosidy_parse::parse_document;
+
+const SOURCE: &str = include_str!("test07.pro");
+
+#[test]
+fn test_diagnostic_labels() {
+    let error = parse_document(SOURCE).unwrap_err();
+    let labels: Vec<_> = error.labels().unwrap().collect();
+    assert_eq!(labels.len(), 1);
+    assert!(labels[0].primary());
+    assert_eq!(&SOURCE[labels[0].offset()..], "} brace.\n\nAnother paragraph.\n");
+    let report = miette::Report::new(error).with_source_code(SOURCE);
+    assert!(format!("{:?}", report).contains("stray } brace"));
+}
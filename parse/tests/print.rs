@@ -0,0 +1,281 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use pretty_assertions::assert_eq;
+use prosidy_ast::*;
+use prosidy_parse::{parse_block, parse_document, print_document, PrintOpts, Result};
+
+// test07 and test08 don't parse.
+const SOURCES: &[&str] = &[
+    include_str!("test01.pro"),
+    include_str!("test02.pro"),
+    include_str!("test03.pro"),
+    include_str!("test04.pro"),
+    include_str!("test05.pro"),
+    include_str!("test06.pro"),
+    include_str!("test09.pro"),
+    include_str!("test10.pro"),
+    include_str!("test11.pro"),
+];
+
+#[test]
+fn test_print_round_trip() -> Result<()> {
+    let opts = PrintOpts::default();
+    for source in SOURCES {
+        let doc = parse_document(source)?;
+        let printed = print_document(&doc, &opts)?;
+        assert_eq!(doc, parse_document(&printed)?, "printed as:\n{}", printed);
+        assert_eq!(printed, print_document(&parse_document(&printed)?, &opts)?);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_print_wrapped() -> Result<()> {
+    let opts = PrintOpts {
+        width: Some(40),
+        ..PrintOpts::default()
+    };
+    let words = |doc: &Document| -> Vec<String> {
+        doc.plain_text()
+            .split_whitespace()
+            .map(String::from)
+            .collect()
+    };
+    for source in SOURCES {
+        let doc = parse_document(source)?;
+        let printed = print_document(&doc, &opts)?;
+        let reparsed = parse_document(&printed)?;
+        assert_eq!(words(&doc), words(&reparsed));
+        assert_eq!(printed, print_document(&reparsed, &opts)?);
+    }
+    let doc = parse_document("---\nOne two three #em{four five} six seven\neight.\n")?;
+    let printed = print_document(
+        &doc,
+        &PrintOpts {
+            width: Some(16),
+            ..opts
+        },
+    )?;
+    assert_eq!(
+        "---\n\nOne two three\n#em{four five}\nsix seven eight.\n",
+        printed
+    );
+    Ok(())
+}
+
+#[test]
+fn test_print_escapes() -> Result<()> {
+    let mut props = PropSet::new();
+    props.put("single", "it's");
+    props.put("both", "'\"\\");
+    props.put("empty", "");
+    props.set("flag");
+    let doc = Document::new(
+        props! { title = "  #1 {draft}" },
+        vec![
+            Block::Content(vec![
+                Text::from(" \\#").into(),
+                InlineTag::new("br", PropSet::new(), vec![]).into(),
+                Text::from("x").into(),
+                Inline::SoftBreak,
+                Text::from("\u{A0}end").into(),
+            ]),
+            BlockTag::new(
+                "code",
+                props,
+                vec![Block::Literal(Literal::from(Text::from("a #: b\n")))],
+            )
+            .into(),
+        ],
+    );
+    let printed = print_document(&doc, &PrintOpts::default())?;
+    let expected = "title: \\u{20}\\u{20}\\#1 \\{draft\\}\n---\n\n\
+                    \\u{20}\\\\\\##br{}x\n\\u{A0}end\n\n\
+                    #=code[both='\\'\"\\\\', empty, flag, single=\"it's\"]:end\na #: b\n#:end\n";
+    assert_eq!(expected, printed);
+    let reparsed = parse_document(&printed)?;
+    assert_eq!(doc.content()[0], reparsed.content()[0]);
+    assert_eq!(doc.props(), reparsed.props());
+    Ok(())
+}
+
+#[test]
+fn test_print_unprintable() {
+    let opts = PrintOpts::default();
+    let literal = Block::Literal(Literal::from(Text::from("x\n")));
+    let doc = Document::new(PropSet::new(), vec![literal]);
+    assert!(print_document(&doc, &opts).is_err());
+    let tag = BlockTag::new("has space", PropSet::new(), vec![]);
+    let doc = Document::new(PropSet::new(), vec![tag.into()]);
+    assert!(print_document(&doc, &opts).is_err());
+    // The parser skips whitespace and comments at the start of a quoted value and after an
+    // escape, and reads carriage returns as line feeds.
+    for value in &[" x", "## x", "x\r", "'\" \\ "] {
+        let tag = BlockTag::new("tag", props! { key = *value }, vec![]);
+        let doc = Document::new(PropSet::new(), vec![tag.into()]);
+        assert!(
+            print_document(&doc, &opts).is_err(),
+            "{:?} was printed",
+            value
+        );
+    }
+    // Escaping the usual quote would leave a space after an escape, so the other one is used.
+    let tag = BlockTag::new("tag", props! { key = "'x'\" y" }, vec![]);
+    let doc = Document::new(PropSet::new(), vec![tag.clone().into()]);
+    let expected = "#-tag[key='\\'x\\'\" y']\n";
+    assert_eq!(
+        format!("---\n\n{}", expected),
+        print_document(&doc, &opts).unwrap()
+    );
+    assert_eq!(expected, tag.to_string());
+}
+
+#[test]
+fn test_display_unprintable() {
+    // Display can't fail, so nodes which can't be printed fall back to their Debug form.
+    let tag = BlockTag::new("has space", PropSet::new(), vec![]);
+    assert_eq!(format!("{:?}", tag), tag.to_string());
+    let block = Block::from(tag);
+    assert_eq!(format!("{:?}", block), block.to_string());
+    let tag = InlineTag::new("em", props! { key = " x" }, vec![]);
+    assert_eq!(format!("{:?}", tag), tag.to_string());
+    let inline = Inline::from(tag);
+    assert_eq!(format!("{:?}", inline), inline.to_string());
+    let literal = Block::Literal(Literal::from(Text::from("x\n")));
+    let doc = Document::new(PropSet::new(), vec![literal]);
+    assert_eq!(format!("{:?}", doc), doc.to_string());
+}
+
+/// A small xorshift generator, so the documents below are random but the same on every run.
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())]
+    }
+
+    fn key(&mut self) -> &'static str {
+        self.pick(&["a", "b", "em", "x-y", "_z", "café", "h1"])
+    }
+
+    /// Text which is never empty, drawn heavily from characters the printer has to escape.
+    fn text(&mut self) -> String {
+        let chars = [
+            'a', 'z', ' ', ' ', '\t', '#', '{', '}', '\\', '\n', '\r', '[', ']', ':', '=', '\'',
+            '"', ',', '-', '+', 'é', '❤', '\u{A0}',
+        ];
+        (0..1 + self.below(8)).map(|_| self.pick(&chars)).collect()
+    }
+
+    /// A property value, which can't start with whitespace or hold a carriage return, since the
+    /// parser would drop or rewrite them.
+    fn value(&mut self) -> String {
+        let text = self.text().replace('\r', "");
+        match text.trim_start() {
+            "" => String::from("v"),
+            value => value.to_string(),
+        }
+    }
+
+    fn props(&mut self) -> PropSet<'static> {
+        let mut props = PropSet::new();
+        for _ in 0..self.below(3) {
+            match self.below(2) {
+                0 => props.set(self.key()),
+                _ => {
+                    let value = self.value();
+                    props.put(self.key(), value);
+                }
+            }
+        }
+        props
+    }
+
+    fn inlines(&mut self, depth: usize) -> Vec<Inline<'static>> {
+        let mut inlines: Vec<Inline> = Vec::new();
+        for index in 0..1 + self.below(5) {
+            let last = inlines.last();
+            let inline = match self.below(4) {
+                // Breaks can't start or end a paragraph or follow one another, and adjacent text
+                // would be parsed back as one run.
+                0 if index > 0 && !matches!(last, Some(Inline::SoftBreak)) => Inline::SoftBreak,
+                1 if depth < 3 => {
+                    let content = if self.below(3) == 0 {
+                        Vec::new()
+                    } else {
+                        self.inlines(depth + 1)
+                    };
+                    InlineTag::new(self.key(), self.props(), content).into()
+                }
+                _ if matches!(last, Some(Inline::Text(_))) => continue,
+                _ => Text::from(self.text()).into(),
+            };
+            inlines.push(inline);
+        }
+        if let Some(Inline::SoftBreak) = inlines.last() {
+            inlines.pop();
+        }
+        inlines
+    }
+
+    fn blocks(&mut self, depth: usize) -> Vec<Block<'static>> {
+        (0..self.below(4))
+            .map(|_| match self.below(4) {
+                0 if depth < 3 => {
+                    let content = self.blocks(depth + 1);
+                    BlockTag::new(self.key(), self.props(), content).into()
+                }
+                1 => {
+                    let lines: String = (0..self.below(3))
+                        .map(|_| self.text().replace(['\n', '\r'], "") + "\n")
+                        .collect();
+                    let literal = Block::Literal(Literal::from(Text::from(lines)));
+                    BlockTag::new(self.key(), self.props(), vec![literal]).into()
+                }
+                _ => Block::Content(self.inlines(0)),
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_display_round_trip() -> Result<()> {
+    let mut rng = Rng(0x5EED_CAFE);
+    let mut printed = 0;
+    for _ in 0..500 {
+        let mut header = PropSet::new();
+        for _ in 0..rng.below(3) {
+            let value = rng.text();
+            header.put(rng.key(), value);
+        }
+        let doc = Document::new(header, rng.blocks(0));
+        // Some values can't be quoted as they are, which is checked in test_print_unprintable.
+        let source = match doc.print(&PrintOpts::default()) {
+            Ok(source) => source,
+            Err(_) => continue,
+        };
+        printed += 1;
+        assert_eq!(source, doc.to_string());
+        assert_eq!(doc, parse_document(&source)?, "printed as:\n{}", source);
+        for block in doc.content() {
+            assert_eq!(
+                vec![block.clone()],
+                parse_block(&block.to_string())?,
+                "printed as:\n{}",
+                block
+            );
+        }
+    }
+    assert!(printed > 250, "only {} documents could be printed", printed);
+    Ok(())
+}
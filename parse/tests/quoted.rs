@@ -0,0 +1,39 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use pretty_assertions::assert_eq;
+use prosidy_ast::*;
+use prosidy_parse::{parse_document, Result};
+
+const SOURCE: &str = "---\n#x[single='it\\'s', double=\"say \\\"hi\\\"\", slash='a\\\\b']{}\n";
+
+#[test]
+fn test_escaped_quotes() -> Result<()> {
+    let doc = parse_document(SOURCE)?;
+    let tag = match doc.content() {
+        [Block::Content(inlines)] => match inlines.as_slice() {
+            [Inline::Tag(tag)] => tag,
+            other => panic!("expected a tag, found {:?}", other),
+        },
+        other => panic!("expected a paragraph, found {:?}", other),
+    };
+    let props = tag.props();
+    assert_eq!(Some(Text::from("it's")), props.lookup(Key::new("single")));
+    assert_eq!(
+        Some(Text::from("say \"hi\"")),
+        props.lookup(Key::new("double"))
+    );
+    assert_eq!(Some(Text::from("a\\b")), props.lookup(Key::new("slash")));
+    Ok(())
+}
+
+#[cfg(feature = "arena")]
+#[test]
+fn test_arena_escaped_quotes() -> Result<()> {
+    let bump = prosidy_parse::arena::Bump::new();
+    let doc = prosidy_parse::arena::parse_document(&bump, SOURCE)?;
+    assert_eq!(parse_document(SOURCE)?, doc.to_ast());
+    Ok(())
+}
@@ -0,0 +1,65 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use pretty_assertions::assert_eq;
+use prosidy_ast::*;
+use prosidy_parse::{parse_document, parse_document_spanned, Result};
+
+const SOURCE: &str = "\
+title: Spans
+---
+Hello, #em[class='x']{big
+world}!
+
+#-section:
+#-h1{Title}
+
+#=code:
+fn main() {}
+#:
+#:
+";
+
+#[test]
+fn test_spans() -> Result<()> {
+    let (doc, spans) = parse_document_spanned(SOURCE)?;
+    assert_eq!(doc, parse_document(SOURCE)?);
+    assert_eq!(Node::from(&doc).descendants().count(), spans.len());
+    let slices: Vec<(&str, &str)> = spans
+        .nodes(&doc)
+        .map(|(node, span)| {
+            let kind = match node {
+                Node::Document(_) => "document",
+                Node::Block(Block::Content(_)) => "content",
+                Node::Block(Block::Literal(_)) => "literal",
+                Node::Block(Block::Tag(_)) => "block tag",
+                Node::Inline(Inline::SoftBreak) => "break",
+                Node::Inline(Inline::Tag(_)) => "inline tag",
+                Node::Inline(Inline::Text(_)) => "text",
+            };
+            (kind, span.slice(SOURCE))
+        })
+        .collect();
+    assert_eq!(("document", SOURCE), slices[0]);
+    assert_eq!(
+        vec![
+            ("content", "Hello, #em[class='x']{big\nworld}!"),
+            ("text", "Hello, "),
+            ("inline tag", "#em[class='x']{big\nworld}"),
+            ("text", "big"),
+            ("break", "\n"),
+            ("text", "world"),
+            ("text", "!"),
+        ],
+        &slices[1..8]
+    );
+    assert_eq!("block tag", slices[8].0);
+    assert!(slices[8].1.starts_with("#-section:\n") && slices[8].1.ends_with("#:"));
+    assert_eq!(("block tag", "#-h1{Title}"), slices[9]);
+    assert_eq!(("content", "Title"), slices[10]);
+    assert_eq!(("block tag", "#=code:\nfn main() {}\n#:"), slices[12]);
+    assert_eq!(("literal", "fn main() {}\n"), slices[13]);
+    Ok(())
+}
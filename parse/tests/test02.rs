@@ -16,6 +16,17 @@ fn test_escape() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_plain_text() -> Result<()> {
+    let actual = parse_document(SOURCE)?;
+    assert_eq!(
+        actual.plain_text(),
+        "This is a document that contains multiple escape sequences. \
+         Escape sequences appear as \\{, for instance."
+    );
+    Ok(())
+}
+
 fn expected() -> Document<'static> {
     Document::new(
         props! {
@@ -16,6 +16,49 @@ fn test_tags() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_find_all_tags() -> Result<()> {
+    let doc = parse_document(SOURCE)?;
+    let ems: Vec<_> = doc.tags("em").collect();
+    assert_eq!(2, ems.len());
+    assert!(ems.iter().all(|tag| tag.as_inline().is_some()));
+    assert_eq!(
+        Some(Text::from("еще")),
+        ems[0].props().lookup(Key::new("ru"))
+    );
+    assert_eq!(3, doc.tags("lit").count());
+    Ok(())
+}
+
+#[test]
+fn test_dedent_literals() -> Result<()> {
+    let literals = |doc: &Document<'static>| -> Vec<String> {
+        doc.tags("lit")
+            .filter_map(|tag| tag.as_block()?.content().first()?.as_literal())
+            .map(|literal| literal.to_string())
+            .collect()
+    };
+    let mut doc = parse_document(SOURCE)?.into_owned();
+    assert_eq!(
+        vec![
+            "#this{isn't} valid at all!\n#:\n#:\n#:\n",
+            "    this literal has properties!\n",
+            "        Literals can be nested!\n",
+        ],
+        literals(&doc)
+    );
+    assert_eq!(2, dedent_literals(&mut doc));
+    assert_eq!(
+        vec![
+            "#this{isn't} valid at all!\n#:\n#:\n#:\n",
+            "this literal has properties!\n",
+            "Literals can be nested!\n",
+        ],
+        literals(&doc)
+    );
+    Ok(())
+}
+
 fn expected() -> Document<'static> {
     Document::new(
         props! {
@@ -0,0 +1,14 @@
+## This Source Code Form is subject to the terms of the Mozilla Public
+## License, v. 2.0. If a copy of the MPL was not distributed with this
+## file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+title: Prose
+
+---
+
+This paragraph is plain prose,
+    spread across several lines   
+with uneven indentation.
+
+This paragraph has #em{some markup}
+  in the middle of it.
@@ -0,0 +1,40 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use pretty_assertions::assert_eq;
+use prosidy_ast::*;
+use prosidy_parse::{parse_document, Result};
+
+const SOURCE: &str = include_str!("test05.pro");
+
+#[test]
+fn test_prose() -> Result<()> {
+    let actual = parse_document(SOURCE)?;
+    assert_eq!(actual, expected());
+    Ok(())
+}
+
+fn expected() -> Document<'static> {
+    Document::new(
+        props! {
+            title = "Prose",
+        },
+        vec![
+            Block::Content(vec![
+                Text::new("This paragraph is plain prose,").into(),
+                Inline::SoftBreak,
+                Text::new("spread across several lines   ").into(),
+                Inline::SoftBreak,
+                Text::new("with uneven indentation.").into(),
+            ]),
+            Block::Content(vec![
+                Text::new("This paragraph has ").into(),
+                InlineTag::new("em", props! {}, vec![Text::new("some markup").into()]).into(),
+                Inline::SoftBreak,
+                Text::new("in the middle of it.").into(),
+            ]),
+        ],
+    )
+}
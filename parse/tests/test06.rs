@@ -0,0 +1,83 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use pretty_assertions::assert_eq;
+use prosidy_ast::*;
+use prosidy_parse::{
+    parse_document_in, parse_document_with_warnings, Context, Result, WarningKind,
+};
+
+const SOURCE: &str = include_str!("test06.pro");
+
+#[test]
+fn test_warnings() -> Result<()> {
+    let (actual, warnings) = parse_document_with_warnings(SOURCE)?;
+    assert_eq!(actual, expected());
+    let keys: Vec<&str> = warnings
+        .iter()
+        .filter_map(|warning| match warning.kind() {
+            WarningKind::DuplicateKey(key) => Some(key.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(keys.len(), warnings.len());
+    assert_eq!(keys, vec!["title", "foo"]);
+    let location = warnings[1].location();
+    assert_eq!(&SOURCE[location.start..location.end], "foo");
+    assert_eq!((location.line, location.column), (10, 12));
+    assert_eq!(
+        warnings[1].to_string(),
+        "Duplicate property key 'foo' (in rule Key, line 10 col 12)"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_unknown_tags() -> Result<()> {
+    let source = "---\n#-section:\n#em{Hi} #strong{there}\n#:\n";
+    let mut ctx = Context::new().with_vocabulary(vec![Key::new("section"), Key::new("em")]);
+    parse_document_in(source, &mut ctx)?;
+    let names: Vec<String> = ctx
+        .warnings()
+        .iter()
+        .map(|warning| match warning.kind() {
+            WarningKind::UnknownTag(name) => name.to_string(),
+            other => panic!("unexpected warning: {}", other),
+        })
+        .collect();
+    assert_eq!(names, vec!["strong"]);
+    // Without a vocabulary, every tag is accepted.
+    let (_, warnings) = parse_document_with_warnings(source)?;
+    assert!(warnings.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_dangling_references() -> Result<()> {
+    let source = "---\n#link[href='#later']{a} #link[href='#missing']{b} #link[href='https://x']{c}\n\n#note[id='later']{}\n";
+    let (_, warnings) = parse_document_with_warnings(source)?;
+    let ids: Vec<&str> = warnings
+        .iter()
+        .map(|warning| match warning.kind() {
+            WarningKind::DanglingReference(id) => id.as_str(),
+            other => panic!("unexpected warning: {}", other),
+        })
+        .collect();
+    assert_eq!(ids, vec!["missing"]);
+    assert_eq!(
+        warnings[0].to_string(),
+        "Reference to undefined id 'missing' (in rule Key, line 2 col 26)"
+    );
+    Ok(())
+}
+
+fn expected() -> Document<'static> {
+    Document::new(
+        props! {
+            title = "Duplicated title",
+        },
+        vec![BlockTag::new("tag", props! {foo, foo = "bar", baz = "qux"}, vec![]).into()],
+    )
+}
@@ -0,0 +1,22 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use pretty_assertions::assert_eq;
+use prosidy_parse::parse_document;
+
+const SOURCE: &str = include_str!("test07.pro");
+
+#[test]
+fn test_report() {
+    let error = parse_document(SOURCE).unwrap_err();
+    let expected = "\
+Syntax error: expected InlineTag, SoftBreak, PlainText, or EscapedPlainText
+  --> line 11, col 34
+   |
+11 | \tIndented #em{text} with a stray } brace.
+   | \t                                ^
+";
+    assert_eq!(error.report(SOURCE), expected);
+}
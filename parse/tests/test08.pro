@@ -0,0 +1,19 @@
+## This Source Code Form is subject to the terms of the Mozilla Public
+## License, v. 2.0. If a copy of the MPL was not distributed with this
+## file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+title: Recovery
+
+---
+
+First paragraph.
+
+A stray } brace.
+
+#-section:
+    Nested paragraph.
+#:
+
+Another #em{unclosed tag.
+
+Last paragraph.
@@ -0,0 +1,37 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use pretty_assertions::assert_eq;
+use prosidy_ast::*;
+use prosidy_parse::parse_document_recovering;
+
+const SOURCE: &str = include_str!("test08.pro");
+
+#[test]
+fn test_recovering() {
+    let (actual, errors) = parse_document_recovering(SOURCE);
+    assert_eq!(actual, Some(expected()));
+    assert_eq!(errors.len(), 2);
+    assert!(errors[0].report(SOURCE).contains("--> line 11, col 9"));
+    assert!(errors[1].report(SOURCE).contains("--> line 18, col 1"));
+}
+
+fn expected() -> Document<'static> {
+    Document::new(
+        props! {
+            title = "Recovery",
+        },
+        vec![
+            Block::Content(vec![Text::new("First paragraph.").into()]),
+            BlockTag::new(
+                "section",
+                props! {},
+                vec![Block::Content(vec![Text::new("Nested paragraph.").into()])],
+            )
+            .into(),
+            Block::Content(vec![Text::new("Last paragraph.").into()]),
+        ],
+    )
+}
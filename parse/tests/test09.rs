@@ -0,0 +1,44 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use pretty_assertions::assert_eq;
+use prosidy_ast::*;
+use prosidy_parse::{parse_document, Result};
+
+const SOURCE: &str = include_str!("test09.pro");
+
+#[test]
+fn test_code_points() -> Result<()> {
+    let actual = parse_document(SOURCE)?;
+    assert_eq!(actual, expected());
+    Ok(())
+}
+
+#[test]
+fn test_invalid_code_points() {
+    for escape in &[r"\u{}", r"\u{zz}", r"\u{+41}", r"\u{110000}", r"\u{D800}"] {
+        let source = format!("---\nBad {}\n", escape);
+        let error = parse_document(&source).unwrap_err();
+        assert!(
+            error.to_string().starts_with("Invalid escape sequence"),
+            "{:?} was accepted: {}",
+            escape,
+            error
+        );
+    }
+}
+
+fn expected() -> Document<'static> {
+    Document::new(
+        props! {
+            title = "Code points ❤",
+        },
+        vec![Block::Content(vec![
+            Text::new("I ❤ escapes, even ").into(),
+            InlineTag::new("em", props! {}, vec![Text::new("😀").into()]).into(),
+            Text::new(".").into(),
+        ])],
+    )
+}
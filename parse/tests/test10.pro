@@ -0,0 +1,22 @@
+## This Source Code Form is subject to the terms of the Mozilla Public
+## License, v. 2.0. If a copy of the MPL was not distributed with this
+## file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+title: Line endings
+
+---
+
+A paragraph which
+spans lines.
+
+#-note[caption='A caption
+over two lines']:
+    Nested
+    content.
+#:
+
+#=code:
+fn main() {
+    println!("hello");
+}
+#:
@@ -0,0 +1,40 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use pretty_assertions::assert_eq;
+use prosidy_ast::*;
+use prosidy_parse::{parse_document, Result};
+
+const SOURCE: &str = include_str!("test10.pro");
+
+#[test]
+fn test_line_endings() -> Result<()> {
+    let expected = parse_document(SOURCE)?;
+    for ending in &["\r\n", "\r"] {
+        let source = SOURCE.replace('\n', ending);
+        let actual = parse_document(&source)?;
+        assert_no_carriage_returns(&actual);
+        assert_eq!(actual, expected);
+    }
+    Ok(())
+}
+
+fn assert_no_carriage_returns(doc: &Document) {
+    let assert_props = |props: &PropSet| {
+        for (key, value) in props.settings() {
+            assert!(!value.contains('\r'), "setting {} contains \\r", key);
+        }
+    };
+    assert_props(doc.props());
+    for node in Node::from(doc).descendants() {
+        match node {
+            Node::Block(Block::Literal(literal)) => assert!(!literal.contains('\r')),
+            Node::Block(Block::Tag(tag)) => assert_props(tag.props()),
+            Node::Inline(Inline::Tag(tag)) => assert_props(tag.props()),
+            Node::Inline(Inline::Text(text)) => assert!(!text.contains('\r')),
+            _ => {}
+        }
+    }
+}
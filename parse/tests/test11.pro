@@ -0,0 +1,26 @@
+## This Source Code Form is subject to the terms of the Mozilla Public
+## License, v. 2.0. If a copy of the MPL was not distributed with this
+## file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+title: Comments
+## A comment between header properties.
+author: Someone
+
+---
+
+## A comment between blocks.
+
+A paragraph
+## with a comment line
+    ## or two
+which continues.
+
+#-section:
+    ## A comment inside a block tag.
+    Nested content.
+    ## Another, before the end of the tag.
+#:
+
+#=code:
+## Literals keep their comments.
+#:
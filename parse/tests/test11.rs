@@ -0,0 +1,35 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use pretty_assertions::assert_eq;
+use prosidy_parse::{parse_document, Result};
+
+const SOURCE: &str = include_str!("test11.pro");
+
+const UNCOMMENTED: &str = "\
+title: Comments
+author: Someone
+
+---
+
+A paragraph
+which continues.
+
+#-section:
+    Nested content.
+#:
+
+#=code:
+## Literals keep their comments.
+#:
+";
+
+#[test]
+fn test_comments() -> Result<()> {
+    let actual = parse_document(SOURCE)?;
+    let expected = parse_document(UNCOMMENTED)?;
+    assert_eq!(actual, expected);
+    Ok(())
+}
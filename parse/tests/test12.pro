@@ -0,0 +1,15 @@
+## This Source Code Form is subject to the terms of the Mozilla Public
+## License, v. 2.0. If a copy of the MPL was not distributed with this
+## file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+title: Unterminated literals
+
+---
+
+#-section:
+    #=code[lang='rust']:end
+        fn main() {}
+    #:
+#:
+
+This paragraph is swallowed by the literal.
@@ -0,0 +1,50 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use prosidy_parse::{parse_block, parse_document, parse_events, ErrorKind};
+
+const SOURCE: &str = include_str!("test12.pro");
+
+#[test]
+fn test_unterminated_literal() {
+    let error = parse_document(SOURCE).unwrap_err();
+    match error.kind() {
+        ErrorKind::UnterminatedLiteral { opened_at, end } => {
+            assert_eq!((opened_at.line, opened_at.column), (10, 5));
+            assert_eq!(
+                &SOURCE[opened_at.start..opened_at.end],
+                "#=code[lang='rust']:end"
+            );
+            assert_eq!(end, "#:end");
+        }
+        kind => panic!("expected an unterminated literal, found {:?}", kind),
+    }
+    let events = parse_events(SOURCE).unwrap().collect::<Result<Vec<_>, _>>();
+    assert!(matches!(
+        events.unwrap_err().kind(),
+        ErrorKind::UnterminatedLiteral { .. }
+    ));
+}
+
+#[test]
+fn test_mismatched_literal() {
+    let error = parse_block("#=code:\nfn main() {}\n#:end\n").unwrap_err();
+    match error.kind() {
+        ErrorKind::UnterminatedLiteral { opened_at, end } => {
+            assert_eq!((opened_at.line, opened_at.column), (1, 1));
+            assert_eq!(end, "#:");
+        }
+        kind => panic!("expected an unterminated literal, found {:?}", kind),
+    }
+    assert!(error
+        .report("#=code:\nfn main() {}\n#:end\n")
+        .contains("^^^^^^^"));
+}
+
+#[test]
+fn test_other_syntax_errors() {
+    let error = parse_block("#=code:\nfn main() {}\n#:\n#oops{\n").unwrap_err();
+    assert!(matches!(error.kind(), ErrorKind::SyntaxError(_)));
+}
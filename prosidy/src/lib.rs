@@ -5,9 +5,13 @@
 
 #[doc(inline)]
 pub use ast::{
-    self, Block, BlockTag, Document, Inline, InlineTag, Key, Literal, PropSet, Tag, Text,
+    self, collect_footnotes, dedent_literals, table_of_contents, Block, BlockTag, Document,
+    DocumentProps, Inline, InlineTag, Key, Literal, Node, ParseBoolError, PropSet, Tag, Text,
+    TocEntry,
 };
 #[doc(inline)]
 pub use parse;
 #[doc(inline)]
+pub use render;
+#[doc(inline)]
 pub use xml;
@@ -0,0 +1,178 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::io::Write;
+
+use prosidy_xml::quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+use prosidy_xml::quick_xml::{Result as XMLResult, Writer};
+use prosidy_xml::{self as xml, XML};
+
+/// Writes a value as HTML5, by translating the events of its XML encoding.
+///
+/// - The document becomes the `<html>` element, with its `title` setting in the `<head>`.
+/// - Paragraphs become `<p>`, and literals become `<pre><code>`. A literal tag is unwrapped, so
+///   `#=code[lang='rust']:` is written as `<pre><code lang="rust">`.
+/// - Soft breaks become a single space.
+/// - Every other tag is written as an element of the same name, with its properties as
+///   attributes.
+pub fn write_html<S: XML, W: Write>(writer: W, value: &S) -> XMLResult<()> {
+    let mut writer = Writer::new(writer);
+    writer.write(b"<!DOCTYPE html>\n")?;
+    let mut emit = |event: Event| writer.write_event(event).map(|_| ());
+    // A tag's start is held back until the event after it, since a tag wrapping a literal isn't
+    // written as an element of its own.
+    let mut pending: Option<BytesStart<'static>> = None;
+    // Whether each open tag was written, so that an unwrapped tag's end is skipped as well.
+    let mut written = Vec::new();
+    value.to_events(&xml::XMLConfig::default(), &mut |event| {
+        if let Some(tag) = pending.take() {
+            match event {
+                Event::Start(ref start) if start.name() == xml::TAG_LITERAL.as_bytes() => {
+                    written.push(false);
+                    emit(Event::Start(BytesStart::borrowed_name(b"pre")))?;
+                    let code = copy_attributes(BytesStart::borrowed_name(b"code"), &tag)?;
+                    return emit(Event::Start(code));
+                }
+                _ => {
+                    written.push(true);
+                    emit(Event::Start(without_empty_settings(tag)?))?;
+                }
+            }
+        }
+        match event {
+            Event::Start(start) if is_tag(start.name()) => {
+                pending = Some(start.into_owned());
+                Ok(())
+            }
+            Event::Start(start) => start_element(&mut emit, start),
+            Event::Empty(start) => {
+                if start.name() == xml::TAG_SOFTBREAK.as_bytes() {
+                    return emit(Event::Text(BytesText::from_plain_str(" ")));
+                }
+                let name = start.name().to_vec();
+                start_element(&mut emit, start)?;
+                // Only void elements may be self-closing in HTML; anything else needs an end tag.
+                if !VOID_ELEMENTS
+                    .iter()
+                    .any(|void| void.as_bytes() == name.as_slice())
+                {
+                    end_element(&mut emit, &name)?;
+                }
+                Ok(())
+            }
+            Event::End(end) if is_tag(end.name()) => match written.pop() {
+                Some(false) => Ok(()),
+                _ => end_element(&mut emit, end.name()),
+            },
+            Event::End(end) => end_element(&mut emit, end.name()),
+            event => emit(event),
+        }
+    })?;
+    writer.write(b"\n")?;
+    Ok(())
+}
+
+/// Whether an element is a tag from the document, rather than one of the structural elements.
+fn is_tag(name: &[u8]) -> bool {
+    ![xml::TAG_DOCUMENT, xml::TAG_PARAGRAPH, xml::TAG_LITERAL]
+        .iter()
+        .any(|tag| tag.as_bytes() == name)
+}
+
+fn start_element<F>(emit: &mut F, start: BytesStart) -> XMLResult<()>
+where
+    F: for<'a> FnMut(Event<'a>) -> XMLResult<()>,
+{
+    match start.name() {
+        name if name == xml::TAG_DOCUMENT.as_bytes() => {
+            emit(Event::Start(BytesStart::borrowed_name(b"html")))?;
+            emit(Event::Start(BytesStart::borrowed_name(b"head")))?;
+            let charset =
+                BytesStart::borrowed_name(b"meta").with_attributes(vec![("charset", "utf-8")]);
+            emit(Event::Start(charset))?;
+            for attr in start.attributes() {
+                let attr = attr?;
+                if attr.key == b"title" {
+                    emit(Event::Start(BytesStart::borrowed_name(b"title")))?;
+                    emit(Event::Text(BytesText::from_escaped(attr.value)))?;
+                    emit(Event::End(BytesEnd::borrowed(b"title")))?;
+                }
+            }
+            emit(Event::End(BytesEnd::borrowed(b"head")))?;
+            emit(Event::Start(BytesStart::borrowed_name(b"body")))
+        }
+        name if name == xml::TAG_PARAGRAPH.as_bytes() => {
+            emit(Event::Start(BytesStart::borrowed_name(b"p")))
+        }
+        name if name == xml::TAG_LITERAL.as_bytes() => {
+            emit(Event::Start(BytesStart::borrowed_name(b"pre")))?;
+            emit(Event::Start(BytesStart::borrowed_name(b"code")))
+        }
+        _ => emit(Event::Start(without_empty_settings(start)?)),
+    }
+}
+
+/// Drops the attribute listing settings with empty values, which only XML readers need.
+fn without_empty_settings(start: BytesStart) -> XMLResult<BytesStart> {
+    if !start
+        .attributes()
+        .any(|attr| attr.is_ok_and(|attr| is_marker(attr.key)))
+    {
+        return Ok(start);
+    }
+    copy_attributes(BytesStart::owned_name(start.name()), &start)
+}
+
+/// Copies the attributes of `from` onto `onto`, except for the empty settings marker.
+fn copy_attributes<'a>(mut onto: BytesStart<'a>, from: &BytesStart) -> XMLResult<BytesStart<'a>> {
+    for attr in from.attributes() {
+        let attr = attr?;
+        if !is_marker(attr.key) {
+            onto.push_attribute(attr);
+        }
+    }
+    Ok(onto)
+}
+
+fn is_marker(key: &[u8]) -> bool {
+    key == xml::ATTR_EMPTY_SETTINGS.as_bytes()
+}
+
+fn end_element<F>(emit: &mut F, name: &[u8]) -> XMLResult<()>
+where
+    F: for<'a> FnMut(Event<'a>) -> XMLResult<()>,
+{
+    match name {
+        name if name == xml::TAG_DOCUMENT.as_bytes() => {
+            emit(Event::End(BytesEnd::borrowed(b"body")))?;
+            emit(Event::End(BytesEnd::borrowed(b"html")))
+        }
+        name if name == xml::TAG_PARAGRAPH.as_bytes() => emit(Event::End(BytesEnd::borrowed(b"p"))),
+        name if name == xml::TAG_LITERAL.as_bytes() => {
+            emit(Event::End(BytesEnd::borrowed(b"code")))?;
+            emit(Event::End(BytesEnd::borrowed(b"pre")))
+        }
+        name => emit(Event::End(BytesEnd::borrowed(name))),
+    }
+}
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+#[test]
+fn render_document() {
+    let source = "title: A & B\n---\nSome #em{text}\nand #br a #span[class='x', alt=''] break.\n\n#=code[lang='html']:\n<b>\n#:\n";
+    let doc = prosidy_parse::parse_document(source).unwrap();
+    let mut out = Vec::new();
+    write_html(&mut out, &doc).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>A &amp; B</title></head>\
+         <body><p>Some <em>text</em> and <br> a <span class=\"x\" alt=\"\"></span> break.</p>\
+         <pre><code lang=\"html\">&lt;b&gt;\n</code></pre></body></html>\n",
+    );
+}
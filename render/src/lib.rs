@@ -0,0 +1,318 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::io::Write;
+
+use prosidy_xml::quick_xml::events::{BytesDecl, BytesText, Event};
+use prosidy_xml::{self as xml, XML};
+use serde::Serialize;
+
+pub use html::write_html;
+pub use markdown::write_markdown;
+
+mod html;
+mod markdown;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Renders a value, usually a parsed document, into a buffer.
+pub fn render<S: Serialize + XML>(
+    value: &S,
+    kind: FormatKind,
+    opts: &FormatOpts,
+) -> Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(8192);
+    kind.write(opts, &mut output, value)?;
+    Ok(output)
+}
+
+/// The formats a document can be rendered to.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FormatKind {
+    CBOR,
+    HTML,
+    JSON,
+    Markdown,
+    XML,
+}
+
+impl FormatKind {
+    pub fn write<S: Serialize + XML, W: Write>(
+        self,
+        opts: &FormatOpts,
+        writer: W,
+        value: &S,
+    ) -> Result<()> {
+        match self {
+            FormatKind::CBOR => opts.write_cbor(writer, value),
+            FormatKind::HTML => opts.write_html(writer, value),
+            FormatKind::JSON => opts.write_json(writer, value),
+            FormatKind::Markdown => opts.write_markdown(writer, value),
+            FormatKind::XML => opts.write_xml(writer, value),
+        }
+    }
+
+    /// The file extension conventionally used for this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            FormatKind::CBOR => "cbor",
+            FormatKind::HTML => "html",
+            FormatKind::JSON => "json",
+            FormatKind::Markdown => "md",
+            FormatKind::XML => "xml",
+        }
+    }
+}
+
+/// Settings which tweak the output of some formats. Each is ignored by the formats it doesn't
+/// apply to.
+#[derive(Clone, Debug, Default)]
+pub struct FormatOpts {
+    /// Writes canonical CBOR, as described in section 3.9 of RFC 7049: map keys are sorted
+    /// shortest first and then bytewise, and every length is definite. Encoding the same value
+    /// always produces the same bytes, so the output can be hashed or signed.
+    pub cbor_canonical: bool,
+    /// Pretty prints JSON.
+    pub json_pretty: bool,
+    /// Names Prosidy's own elements in XML.
+    pub xml_config: xml::XMLConfig,
+    /// Indents XML by this many spaces per level.
+    pub xml_indent: Option<usize>,
+    /// A namespace assigned to non-Prosidy tags in XML.
+    pub xml_namespace: Option<String>,
+    /// Stylesheets attached to XML, in the order they're given.
+    pub xml_stylesheets: Vec<Stylesheet>,
+}
+
+/// A stylesheet attached to XML by an `xml-stylesheet` processing instruction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Stylesheet {
+    /// The stylesheet's URL.
+    pub href: String,
+    /// The stylesheet's media type, like `text/xsl`.
+    pub media_type: String,
+}
+
+impl Stylesheet {
+    pub fn new(href: &str, media_type: &str) -> Self {
+        Stylesheet {
+            href: href.to_string(),
+            media_type: media_type.to_string(),
+        }
+    }
+
+    /// An XSLT stylesheet, which transforms the XML.
+    pub fn xslt(href: &str) -> Self {
+        Stylesheet::new(href, "text/xsl")
+    }
+
+    /// A CSS stylesheet, which styles the XML as it is.
+    pub fn css(href: &str) -> Self {
+        Stylesheet::new(href, "text/css")
+    }
+
+    /// The content of the processing instruction, with its pseudo-attributes escaped.
+    fn instruction(&self) -> String {
+        let escape = |value: &str| {
+            let text = BytesText::from_plain_str(value);
+            String::from_utf8_lossy(text.escaped()).into_owned()
+        };
+        format!(
+            r#"xml-stylesheet type="{}" href="{}""#,
+            escape(&self.media_type),
+            escape(&self.href)
+        )
+    }
+}
+
+impl FormatOpts {
+    pub fn write_cbor<S: Serialize, W: Write>(&self, writer: W, value: &S) -> Result<()> {
+        if self.cbor_canonical {
+            // Maps in a `Value` are ordered by the canonical ordering of their keys.
+            let value = serde_cbor::value::to_value(value)?;
+            serde_cbor::to_writer(writer, &value)?;
+        } else {
+            serde_cbor::to_writer(writer, value)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_html<S: XML, W: Write>(&self, writer: W, value: &S) -> Result<()> {
+        write_html(writer, value)?;
+        Ok(())
+    }
+
+    pub fn write_json<S: Serialize, W: Write>(&self, mut writer: W, value: &S) -> Result<()> {
+        if self.json_pretty {
+            serde_json::to_writer_pretty(&mut writer, value)?;
+        } else {
+            serde_json::to_writer(&mut writer, value)?;
+        }
+        writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    pub fn write_markdown<S: XML, W: Write>(&self, writer: W, value: &S) -> Result<()> {
+        write_markdown(writer, value)?;
+        Ok(())
+    }
+
+    pub fn write_xml<S: XML, W: Write>(&self, writer: W, value: &S) -> Result<()> {
+        let mut writer = match self.xml_indent {
+            Some(width) => xml::quick_xml::Writer::new_with_indent(writer, b' ', width),
+            None => xml::quick_xml::Writer::new(writer),
+        };
+        // first, write the XML declaration
+        let decl = BytesDecl::new(b"1.0", Some(b"UTF-8"), None);
+        writer.write_event(Event::Decl(decl))?;
+        // next, write all of the stylesheet instructions as pre-processor events
+        for stylesheet in self.xml_stylesheets.iter() {
+            let contents = stylesheet.instruction();
+            let event = BytesText::from_escaped_str(&contents);
+            writer.write_event(Event::PI(event))?;
+        }
+        // now, create a callback hook for writing events into the writer.
+        let mut first = true;
+        // Whitespace inside a paragraph or literal would become part of its text, so the writer
+        // mustn't break lines anywhere within one. This counts how deeply nested in one we are.
+        let mut verbatim_depth = 0usize;
+        let mut handle = |mut event: Event| {
+            if first {
+                first = false;
+                let start = match event {
+                    Event::Start(ref mut start) => start,
+                    Event::Empty(ref mut empty) => empty,
+                    _ => panic!("The first emitted XML event was not a tag"),
+                };
+                if let Some(ref ns) = self.xml_namespace {
+                    start.push_attribute(("xmlns", ns.as_str()));
+                }
+                let config = &self.xml_config;
+                start.push_attribute((config.xmlns().as_str(), config.namespace()));
+            }
+            let verbatim = verbatim_depth > 0;
+            match event {
+                Event::Start(_) if verbatim => verbatim_depth += 1,
+                Event::Start(ref start) if self.is_verbatim(start.name()) => verbatim_depth = 1,
+                Event::End(_) if verbatim => verbatim_depth -= 1,
+                _ => {}
+            }
+            if verbatim && !matches!(event, Event::Text(_)) {
+                // The writer never breaks the line after a text event, even an empty one.
+                writer.write_event(Event::Text(BytesText::from_plain_str("")))?;
+            }
+            writer.write_event(event).map(|_| ())
+        };
+        value.to_events(&self.xml_config, &mut handle)?;
+        writer.write_event(Event::Eof)?;
+        writer.into_inner().write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn is_verbatim(&self, name: &[u8]) -> bool {
+        name == self.xml_config.tag_paragraph().as_bytes()
+            || name == self.xml_config.tag_literal().as_bytes()
+    }
+}
+
+/// An error encountered while rendering.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    CBOR(#[from] serde_cbor::Error),
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+    #[error(transparent)]
+    JSON(#[from] serde_json::Error),
+    #[error(transparent)]
+    XML(#[from] xml::quick_xml::Error),
+}
+
+#[test]
+fn indent_xml_outside_of_text() {
+    let source = "---\n#em{Hi} there.\n\n#-section:\n#=code:\n  fn main() {}\n#:\n#:\n";
+    let doc = prosidy_parse::parse_document(source).unwrap();
+    let opts = FormatOpts {
+        xml_config: xml::XMLConfig::new("pro", xml::PROSIDY_NAMESPACE),
+        xml_indent: Some(2),
+        ..FormatOpts::default()
+    };
+    let out = render(&doc, FormatKind::XML, &opts).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("<pro:document xmlns:pro=\"https://prosidy.org/schema/prosidy.xsd\">"));
+    assert!(out.contains("\n  <pro:paragraph><em>Hi</em> there.</pro:paragraph>\n"));
+    let literal = "\n      <pro:literal>  fn main() {}\n</pro:literal>\n    </code>\n";
+    assert!(out.contains(literal));
+    let read = xml::from_reader_with(out.as_bytes(), &opts.xml_config).unwrap();
+    assert_eq!(doc, read);
+}
+
+#[test]
+fn canonical_cbor() {
+    let first = prosidy_parse::parse_document("b: 2\naa: 1\n---\n#em[z='1', y]{Hi}\n").unwrap();
+    let second = prosidy_parse::parse_document("aa: 1\nb: 2\n---\n#em[y, z='1']{Hi}\n").unwrap();
+    let opts = FormatOpts {
+        cbor_canonical: true,
+        ..FormatOpts::default()
+    };
+    let encoded = render(&first, FormatKind::CBOR, &opts).unwrap();
+    assert_eq!(encoded, render(&second, FormatKind::CBOR, &opts).unwrap());
+    let find = |needle: &[u8]| {
+        encoded
+            .windows(needle.len())
+            .position(|window| window == needle)
+            .unwrap()
+    };
+    // Shorter keys sort first: `b` before `aa`, and `settings` before `properties`.
+    assert!(find(b"\x61b") < find(b"\x62aa"));
+    assert!(find(b"settings") < find(b"properties"));
+    assert_eq!(first, serde_cbor::from_slice(&encoded).unwrap());
+}
+
+#[test]
+fn xml_stylesheets() {
+    let doc = prosidy_parse::parse_document("---\nHi\n").unwrap();
+    let opts = FormatOpts {
+        xml_stylesheets: vec![
+            Stylesheet::css("print.css"),
+            Stylesheet::xslt(r#"to "html".xsl?a=1&b=2"#),
+        ],
+        ..FormatOpts::default()
+    };
+    let out = String::from_utf8(render(&doc, FormatKind::XML, &opts).unwrap()).unwrap();
+    let css = r#"<?xml-stylesheet type="text/css" href="print.css"?>"#;
+    let xslt = r#"<?xml-stylesheet type="text/xsl" href="to &quot;html&quot;.xsl?a=1&amp;b=2"?>"#;
+    assert!(out.contains(&format!("{}{}", css, xslt)), "{}", out);
+}
+
+#[test]
+fn xml_escapes_namespaces() {
+    let doc = prosidy_parse::parse_document("---\nHi #em{there}\n").unwrap();
+    let opts = FormatOpts {
+        xml_config: xml::XMLConfig::new("p", "urn:example?a=1&b=<2>"),
+        xml_namespace: Some(r#"urn:tags?q="x"&y"#.to_string()),
+        xml_stylesheets: vec![Stylesheet::xslt(r#"a"b'c<d>.xsl"#)],
+        ..FormatOpts::default()
+    };
+    let out = String::from_utf8(render(&doc, FormatKind::XML, &opts).unwrap()).unwrap();
+    assert!(
+        out.contains(r#"href="a&quot;b&apos;c&lt;d&gt;.xsl""#),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains(r#"xmlns="urn:tags?q=&quot;x&quot;&amp;y""#),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains(r#"xmlns:p="urn:example?a=1&amp;b=&lt;2&gt;""#),
+        "{}",
+        out
+    );
+    let read = xml::from_reader_with(out.as_bytes(), &opts.xml_config).unwrap();
+    assert_eq!(doc, read);
+}
@@ -0,0 +1,202 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::io::Write;
+
+use prosidy_xml::quick_xml::events::{BytesStart, Event};
+use prosidy_xml::quick_xml::Result as XMLResult;
+use prosidy_xml::{self as xml, XML};
+
+/// Writes a value as Markdown, by translating the events of its XML encoding.
+///
+/// - The document's `title` setting becomes a level one heading.
+/// - Paragraphs are separated by blank lines, and soft breaks are kept as line breaks.
+/// - Literals become fenced code blocks. If the tag around a literal has a `lang` setting, it's
+///   used as the fence's info string.
+/// - The inline tags `em`, `strong`, and `code` are written as `*em*`, `**strong**`, and
+///   `` `code` ``. Any other tag is dropped, leaving only its content.
+pub fn write_markdown<S: XML, W: Write>(writer: W, value: &S) -> XMLResult<()> {
+    let mut markdown = Markdown {
+        out: writer,
+        langs: Vec::new(),
+        inlines: Vec::new(),
+        paragraph: false,
+        verbatim: None,
+        wrote_block: false,
+    };
+    value.to_events(&xml::XMLConfig::default(), &mut |event| {
+        markdown.event(event)
+    })
+}
+
+struct Markdown<W> {
+    out: W,
+    /// The `lang` setting of each enclosing block tag, innermost last.
+    langs: Vec<Option<String>>,
+    /// How to close each inline tag open in the current paragraph, innermost last.
+    inlines: Vec<Inline>,
+    paragraph: bool,
+    /// Text of the current literal or inline code, collected so that its delimiters can be
+    /// chosen to not clash with it.
+    verbatim: Option<String>,
+    wrote_block: bool,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Inline {
+    Code,
+    Delimited(&'static str),
+    Passthrough,
+}
+
+impl<W: Write> Markdown<W> {
+    fn event(&mut self, event: Event) -> XMLResult<()> {
+        match event {
+            Event::Start(start) => self.start(&start),
+            Event::Empty(start) => {
+                if start.name() == xml::TAG_SOFTBREAK.as_bytes() {
+                    self.text("\n")
+                } else {
+                    Ok(())
+                }
+            }
+            Event::End(_) => self.end(),
+            Event::Text(text) => {
+                let text = text.unescaped()?;
+                self.text(std::str::from_utf8(&text)?)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn start(&mut self, start: &BytesStart) -> XMLResult<()> {
+        let name = start.name();
+        if self.paragraph || self.verbatim.is_some() {
+            let inline = match name {
+                _ if self.verbatim.is_some() => Inline::Passthrough,
+                b"code" => {
+                    self.verbatim = Some(String::new());
+                    Inline::Code
+                }
+                b"em" => Inline::Delimited("*"),
+                b"strong" => Inline::Delimited("**"),
+                _ => Inline::Passthrough,
+            };
+            if let Inline::Delimited(delim) = inline {
+                self.out.write_all(delim.as_bytes())?;
+            }
+            self.inlines.push(inline);
+            return Ok(());
+        }
+        if name == xml::TAG_PARAGRAPH.as_bytes() {
+            self.begin_block()?;
+            self.paragraph = true;
+        } else if name == xml::TAG_LITERAL.as_bytes() {
+            self.begin_block()?;
+            self.verbatim = Some(String::new());
+        } else if name == xml::TAG_DOCUMENT.as_bytes() {
+            if let Some(title) = attribute(start, b"title")? {
+                self.begin_block()?;
+                self.out.write_all(b"# ")?;
+                self.text(&title)?;
+                self.out.write_all(b"\n")?;
+            }
+        } else {
+            let lang = attribute(start, b"lang")?;
+            self.langs.push(lang);
+        }
+        Ok(())
+    }
+
+    fn end(&mut self) -> XMLResult<()> {
+        if let Some(inline) = self.inlines.pop() {
+            match inline {
+                Inline::Code => {
+                    let code = self.verbatim.take().unwrap_or_default();
+                    let delim = "`".repeat(longest_backtick_run(&code) + 1);
+                    // Code starting or ending with a backtick needs space to separate it from
+                    // the delimiters, which Markdown strips back off.
+                    let pad = if code.starts_with('`') || code.ends_with('`') {
+                        " "
+                    } else {
+                        ""
+                    };
+                    write!(self.out, "{0}{1}{2}{1}{0}", delim, pad, code)?;
+                }
+                Inline::Delimited(delim) => self.out.write_all(delim.as_bytes())?,
+                Inline::Passthrough => {}
+            }
+        } else if self.paragraph {
+            self.paragraph = false;
+            self.out.write_all(b"\n")?;
+        } else if let Some(mut literal) = self.verbatim.take() {
+            let fence = "`".repeat(longest_backtick_run(&literal).max(2) + 1);
+            let lang = self.langs.last().cloned().flatten().unwrap_or_default();
+            if !literal.is_empty() && !literal.ends_with('\n') {
+                literal.push('\n');
+            }
+            write!(self.out, "{0}{1}\n{2}{0}\n", fence, lang, literal)?;
+        } else {
+            self.langs.pop();
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, text: &str) -> XMLResult<()> {
+        if let Some(ref mut verbatim) = self.verbatim {
+            verbatim.push_str(text);
+            return Ok(());
+        }
+        for c in text.chars() {
+            if MARKDOWN_RESERVED.contains(&c) {
+                self.out.write_all(b"\\")?;
+            }
+            write!(self.out, "{}", c)?;
+        }
+        Ok(())
+    }
+
+    fn begin_block(&mut self) -> XMLResult<()> {
+        if self.wrote_block {
+            self.out.write_all(b"\n")?;
+        }
+        self.wrote_block = true;
+        Ok(())
+    }
+}
+
+fn attribute(start: &BytesStart, key: &[u8]) -> XMLResult<Option<String>> {
+    for attr in start.attributes() {
+        let attr = attr?;
+        if attr.key == key {
+            let value = attr.unescaped_value()?;
+            return Ok(Some(std::str::from_utf8(&value)?.to_string()));
+        }
+    }
+    Ok(None)
+}
+
+fn longest_backtick_run(s: &str) -> usize {
+    s.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+const MARKDOWN_RESERVED: &[char] = &['\\', '`', '*', '_', '[', ']', '<', '>', '#'];
+
+#[test]
+fn render_document() {
+    let source = "title: Notes\n---\nSome #em{emphasized} and #strong{#span{strong}} text\n\
+                  with #code{a `tick`}, and 2 * 3.\n\n\
+                  #-section:\n#=code[lang='rust']:\nfn main() {}\n#:\n#:\n";
+    let doc = prosidy_parse::parse_document(source).unwrap();
+    let mut out = Vec::new();
+    write_markdown(&mut out, &doc).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "# Notes\n\n\
+         Some *emphasized* and **strong** text\n\
+         with `` a `tick` ``, and 2 \\* 3.\n\n\
+         ```rust\nfn main() {}\n```\n",
+    );
+}
@@ -0,0 +1,148 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/// How Prosidy's own elements and attributes are named when encoding to XML.
+///
+/// Each is qualified with a namespace prefix, `prosidy` by default, giving names like
+/// `prosidy:paragraph`. Choosing another prefix avoids clashing with tags in the document which
+/// already use it.
+///
+/// ```rust
+/// # use prosidy_xml::XMLConfig;
+/// let config = XMLConfig::new("p", "urn:example");
+/// assert_eq!("p:paragraph", config.tag_paragraph());
+/// assert_eq!("xmlns:p", config.xmlns());
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct XMLConfig {
+    prefix: String,
+    namespace: String,
+    tag_document: String,
+    tag_literal: String,
+    tag_paragraph: String,
+    tag_softbreak: String,
+    attr_empty_settings: String,
+    softbreak: SoftBreak,
+    invalid_chars: InvalidChars,
+}
+
+impl XMLConfig {
+    /// Names elements with `prefix`, bound to the URI `namespace`. The prefix must be a valid XML
+    /// name without a colon.
+    pub fn new(prefix: &str, namespace: &str) -> Self {
+        let name = |local| format!("{}:{}", prefix, local);
+        XMLConfig {
+            prefix: prefix.to_string(),
+            namespace: namespace.to_string(),
+            tag_document: name("document"),
+            tag_literal: name("literal"),
+            tag_paragraph: name("paragraph"),
+            tag_softbreak: name("softbreak"),
+            attr_empty_settings: name("empty-settings"),
+            softbreak: SoftBreak::Element,
+            invalid_chars: InvalidChars::Remove,
+        }
+    }
+
+    /// Sets how soft breaks are written.
+    pub fn with_softbreak(mut self, softbreak: SoftBreak) -> Self {
+        self.softbreak = softbreak;
+        self
+    }
+
+    /// Sets what happens to characters which XML doesn't allow.
+    pub fn with_invalid_chars(mut self, invalid_chars: InvalidChars) -> Self {
+        self.invalid_chars = invalid_chars;
+        self
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// Qualifies `local` with the prefix, for elements and attributes outside of a document's
+    /// AST, like those describing a manifest.
+    pub fn name(&self, local: &str) -> String {
+        format!("{}:{}", self.prefix, local)
+    }
+
+    /// The attribute which binds the prefix to the namespace, like `xmlns:prosidy`.
+    pub fn xmlns(&self) -> String {
+        format!("xmlns:{}", self.prefix)
+    }
+
+    pub fn tag_document(&self) -> &str {
+        &self.tag_document
+    }
+
+    pub fn tag_literal(&self) -> &str {
+        &self.tag_literal
+    }
+
+    pub fn tag_paragraph(&self) -> &str {
+        &self.tag_paragraph
+    }
+
+    pub fn tag_softbreak(&self) -> &str {
+        &self.tag_softbreak
+    }
+
+    /// The attribute listing the settings of a tag which have an empty value, which would
+    /// otherwise be written just like properties. See [`insert_props`](crate::insert_props).
+    pub fn attr_empty_settings(&self) -> &str {
+        &self.attr_empty_settings
+    }
+
+    pub fn softbreak(&self) -> SoftBreak {
+        self.softbreak
+    }
+
+    pub fn invalid_chars(&self) -> InvalidChars {
+        self.invalid_chars
+    }
+}
+
+/// How [`Inline::SoftBreak`](prosidy_ast::Inline::SoftBreak) is written.
+///
+/// Only [`SoftBreak::Element`] can be read back by [`from_reader`](crate::from_reader); the
+/// others become part of the surrounding text.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SoftBreak {
+    /// An empty element, like `<prosidy:softbreak/>`. This is the default.
+    Element,
+    /// A line feed.
+    Newline,
+    /// A single space.
+    Space,
+}
+
+/// What to do with characters in text or property values which XML 1.0 doesn't allow, like NUL
+/// and most other control characters. Left in, they'd make the output unreadable to any
+/// conforming parser.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum InvalidChars {
+    /// Fail with an error naming the character.
+    Error,
+    /// Drop the characters, logging a warning. This is the default.
+    Remove,
+    /// Replace each character with U+FFFD REPLACEMENT CHARACTER.
+    Replace,
+}
+
+impl Default for XMLConfig {
+    fn default() -> Self {
+        XMLConfig::new(DEFAULT_PREFIX, PROSIDY_NAMESPACE)
+    }
+}
+
+/// The prefix Prosidy's elements are named with, unless configured otherwise.
+pub const DEFAULT_PREFIX: &str = "prosidy";
+
+/// The namespace Prosidy's elements belong to.
+pub const PROSIDY_NAMESPACE: &str = "https://prosidy.org/schema/prosidy.xsd";
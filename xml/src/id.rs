@@ -0,0 +1,132 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::HashSet;
+
+use prosidy_ast::visit::{walk_block_mut, VisitMut};
+use prosidy_ast::*;
+
+/// The setting holding a tag's id.
+const SETTING_ID: &str = "id";
+
+/// Gives every block tag without an `id` setting one derived from its text, as long as it has
+/// any. Ids are unique within the document: when a slug is already taken, by an explicit id or an
+/// earlier generated one, it's suffixed with `-2`, `-3`, and so on.
+pub fn assign_ids(doc: &mut Document) {
+    let key = Key::new(SETTING_ID);
+    let taken = Node::from(&*doc)
+        .descendants()
+        .filter_map(|node| match node {
+            Node::Block(Block::Tag(tag)) => tag.props().lookup_str(&key).map(str::to_string),
+            _ => None,
+        })
+        .collect();
+    AssignIds { key, taken }.visit_document_mut(doc);
+}
+
+struct AssignIds {
+    key: Key,
+    taken: HashSet<String>,
+}
+
+impl<'a> VisitMut<'a> for AssignIds {
+    fn visit_block_mut(&mut self, block: &mut Block<'a>) {
+        if let Block::Tag(tag) = block {
+            if tag.props().lookup(&self.key).is_none() {
+                let slug = slugify(&tag.plain_text());
+                if !slug.is_empty() {
+                    let id = self.claim(slug);
+                    tag.props_mut().put(self.key.clone(), id);
+                }
+            }
+        }
+        walk_block_mut(self, block)
+    }
+}
+
+impl AssignIds {
+    fn claim(&mut self, slug: String) -> String {
+        let mut id = slug.clone();
+        let mut suffix = 1;
+        while self.taken.contains(&id) {
+            suffix += 1;
+            id = format!("{}-{}", slug, suffix);
+        }
+        self.taken.insert(id.clone());
+        id
+    }
+}
+
+/// Turns text into a slug: lowercase ASCII letters and digits, with each run of anything else
+/// replaced by a single `-`. Accented Latin letters are folded to their base letter first, so
+/// `Über Café` becomes `uber-cafe`. Words past [`MAX_SLUG_LEN`] bytes are dropped.
+pub fn slugify(text: &str) -> String {
+    let mut words = vec![String::new()];
+    for ch in text.chars().flat_map(char::to_lowercase) {
+        let word = words.last_mut().unwrap();
+        if ch.is_ascii_alphanumeric() {
+            word.push(ch);
+        } else if let Some(folded) = fold(ch) {
+            word.push_str(folded);
+        } else if !word.is_empty() {
+            words.push(String::new());
+        }
+    }
+    let mut slug = String::new();
+    for word in words.iter().filter(|word| !word.is_empty()) {
+        if !slug.is_empty() {
+            if slug.len() + 1 + word.len() > MAX_SLUG_LEN {
+                break;
+            }
+            slug.push('-');
+        }
+        slug.push_str(word);
+    }
+    slug
+}
+
+/// Folds a lowercase Latin letter with diacritics to ASCII.
+fn fold(ch: char) -> Option<&'static str> {
+    let folded = match ch {
+        'à'..='å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è'..='ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'ĥ' | 'ħ' => "h",
+        'ì'..='ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'ĵ' => "j",
+        'ķ' => "k",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'ò'..='ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'œ' => "oe",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'ś' | 'ŝ' | 'ş' | 'š' => "s",
+        'ß' => "ss",
+        'ţ' | 'ť' | 'ŧ' => "t",
+        'þ' => "th",
+        'ù'..='ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'ŵ' => "w",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    };
+    Some(folded)
+}
+
+/// The longest a generated slug may be, before any suffix added to keep it unique.
+const MAX_SLUG_LEN: usize = 64;
+
+#[test]
+fn slugs() {
+    assert_eq!("getting-started", slugify("Getting Started!"));
+    assert_eq!("uber-cafe-strasse", slugify("  Über café — Straße"));
+    assert_eq!("c-2-0", slugify("C++ 2.0"));
+    assert_eq!("", slugify("¿?"));
+    let long = slugify(&"word ".repeat(20));
+    assert!(long.len() <= MAX_SLUG_LEN && long.ends_with("word"));
+}
@@ -3,16 +3,29 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use std::borrow::Cow;
+use std::io;
+
 use prosidy_ast::*;
 use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
 use quick_xml::Result;
 
+pub use config::{InvalidChars, SoftBreak, XMLConfig, DEFAULT_PREFIX, PROSIDY_NAMESPACE};
+pub use id::{assign_ids, slugify};
 pub use quick_xml;
+pub use read::{from_reader, from_reader_with};
+pub use schema::schema;
+
+mod config;
+mod id;
+mod read;
+mod schema;
 
 /// A trait used to encode a structure into one or more [`Event`]s.
 pub trait XML {
-    /// Write zero or more XML events via the `emit` function.
-    fn to_events<F>(&self, emit: &mut F) -> Result<()>
+    /// Write zero or more XML events via the `emit` function, naming Prosidy's own elements as
+    /// `config` describes.
+    fn to_events<F>(&self, config: &XMLConfig, emit: &mut F) -> Result<()>
     where
         F: for<'a> FnMut(Event<'a>) -> Result<()>;
 }
@@ -21,30 +34,30 @@ impl<T> XML for [T]
 where
     T: XML,
 {
-    fn to_events<F>(&self, emit: &mut F) -> Result<()>
+    fn to_events<F>(&self, config: &XMLConfig, emit: &mut F) -> Result<()>
     where
         F: for<'a> FnMut(Event<'a>) -> Result<()>,
     {
         for item in self.iter() {
-            item.to_events(emit)?;
+            item.to_events(config, emit)?;
         }
         Ok(())
     }
 }
 
 impl<'p> XML for Block<'p> {
-    fn to_events<F>(&self, emit: &mut F) -> Result<()>
+    fn to_events<F>(&self, config: &XMLConfig, emit: &mut F) -> Result<()>
     where
         F: for<'a> FnMut(Event<'a>) -> Result<()>,
     {
         match self {
-            Block::Tag(tag) => tag.to_events(emit),
-            Block::Literal(lit) => lit.to_events(emit),
+            Block::Tag(tag) => tag.to_events(config, emit),
+            Block::Literal(lit) => lit.to_events(config, emit),
             Block::Content(ct) => {
-                let start = BytesStart::borrowed_name(TAG_PARAGRAPH.as_bytes());
+                let start = BytesStart::borrowed_name(config.tag_paragraph().as_bytes());
                 emit(Event::Start(start))?;
-                ct.as_slice().to_events(emit)?;
-                let end = BytesEnd::borrowed(TAG_PARAGRAPH.as_bytes());
+                ct.as_slice().to_events(config, emit)?;
+                let end = BytesEnd::borrowed(config.tag_paragraph().as_bytes());
                 emit(Event::End(end))
             }
         }
@@ -52,49 +65,67 @@ impl<'p> XML for Block<'p> {
 }
 
 impl<'p> XML for Document<'p> {
-    fn to_events<F>(&self, emit: &mut F) -> Result<()>
+    fn to_events<F>(&self, config: &XMLConfig, emit: &mut F) -> Result<()>
     where
         F: for<'a> FnMut(Event<'a>) -> Result<()>,
     {
-        let mut start = BytesStart::borrowed_name(TAG_DOCUMENT.as_bytes());
-        insert_props(&mut start, self.props());
-        emit(Event::Start(start))?;
-        self.content().to_events(emit)?;
-        let end = BytesEnd::borrowed(TAG_DOCUMENT.as_bytes());
-        emit(Event::End(end))?;
-        Ok(())
+        // Ids are generated on a copy, so that writing a document never changes it.
+        if self.props().is_set(Key::new(PROP_AUTO_ID)) {
+            let mut doc = self.clone();
+            assign_ids(&mut doc);
+            return write_document(&doc, config, emit);
+        }
+        write_document(self, config, emit)
     }
 }
 
+fn write_document<F>(doc: &Document, config: &XMLConfig, emit: &mut F) -> Result<()>
+where
+    F: for<'a> FnMut(Event<'a>) -> Result<()>,
+{
+    let mut start = BytesStart::borrowed_name(config.tag_document().as_bytes());
+    insert_props(&mut start, doc.props(), config)?;
+    emit(Event::Start(start))?;
+    doc.content().to_events(config, emit)?;
+    let end = BytesEnd::borrowed(config.tag_document().as_bytes());
+    emit(Event::End(end))
+}
+
 impl<'p> XML for Inline<'p> {
-    fn to_events<F>(&self, emit: &mut F) -> Result<()>
+    fn to_events<F>(&self, config: &XMLConfig, emit: &mut F) -> Result<()>
     where
         F: for<'a> FnMut(Event<'a>) -> Result<()>,
     {
         match self {
-            Inline::Tag(tag) => tag.to_events(emit),
+            Inline::Tag(tag) => tag.to_events(config, emit),
             Inline::Text(text) => {
+                let text = sanitize(text, config)?;
                 let text = BytesText::from_plain_str(&text);
                 emit(Event::Text(text))
             }
-            Inline::SoftBreak => {
-                let start = BytesStart::borrowed_name(TAG_SOFTBREAK.as_bytes());
-                emit(Event::Empty(start))
-            }
+            Inline::SoftBreak => match config.softbreak() {
+                SoftBreak::Element => {
+                    let start = BytesStart::borrowed_name(config.tag_softbreak().as_bytes());
+                    emit(Event::Empty(start))
+                }
+                SoftBreak::Newline => emit(Event::Text(BytesText::from_plain_str("\n"))),
+                SoftBreak::Space => emit(Event::Text(BytesText::from_plain_str(" "))),
+            },
         }
     }
 }
 
 impl<'p> XML for Literal<'p> {
-    fn to_events<F>(&self, emit: &mut F) -> Result<()>
+    fn to_events<F>(&self, config: &XMLConfig, emit: &mut F) -> Result<()>
     where
         F: for<'a> FnMut(Event<'a>) -> Result<()>,
     {
-        let start = BytesStart::borrowed_name(TAG_LITERAL.as_bytes());
+        let start = BytesStart::borrowed_name(config.tag_literal().as_bytes());
         emit(Event::Start(start))?;
-        let text = BytesText::from_plain_str(&self);
+        let text = sanitize(self, config)?;
+        let text = BytesText::from_plain_str(&text);
         emit(Event::Text(text))?;
-        let end = BytesEnd::borrowed(TAG_LITERAL.as_bytes());
+        let end = BytesEnd::borrowed(config.tag_literal().as_bytes());
         emit(Event::End(end))
     }
 }
@@ -103,30 +134,131 @@ impl<'p, T> XML for Tag<'p, T>
 where
     T: XML,
 {
-    fn to_events<F>(&self, emit: &mut F) -> Result<()>
+    fn to_events<F>(&self, config: &XMLConfig, emit: &mut F) -> Result<()>
     where
         F: for<'a> FnMut(Event<'a>) -> Result<()>,
     {
         let name: &[u8] = self.name().as_str().as_bytes();
         let mut start = BytesStart::borrowed_name(name);
-        insert_props(&mut start, self.props());
+        insert_props(&mut start, self.props(), config)?;
         if self.content().is_empty() {
             emit(Event::Empty(start))
         } else {
             emit(Event::Start(start))?;
-            self.content().to_events(emit)?;
+            self.content().to_events(config, emit)?;
             emit(Event::End(BytesEnd::borrowed(name)))
         }
     }
 }
 
-fn insert_props<'a>(start: &mut BytesStart<'a>, props: &PropSet<'a>) {
-    for (name, opt_value) in props.iter() {
-        start.push_attribute((name.as_str(), opt_value.unwrap_or(Text::EMPTY).as_str()));
+/// Adds props to an element as attributes: settings as `name="value"`, and properties as
+/// `name=""`.
+///
+/// So that a setting with an empty value can be told apart from a property, the names of any
+/// such settings are also listed, separated by spaces, in the
+/// [`attr_empty_settings`](XMLConfig::attr_empty_settings) attribute, like
+/// `prosidy:empty-settings="alt"`. It's left out when there are none.
+pub fn insert_props(start: &mut BytesStart, props: &PropSet, config: &XMLConfig) -> Result<()> {
+    let mut empty_settings = String::new();
+    for (name, value) in props.settings_ref() {
+        if value.is_empty() {
+            if !empty_settings.is_empty() {
+                empty_settings.push(' ');
+            }
+            empty_settings.push_str(name);
+        }
+        start.push_attribute((name.as_str(), sanitize(value, config)?.as_ref()));
+    }
+    for name in props.properties() {
+        start.push_attribute((name.as_str(), ""));
+    }
+    if !empty_settings.is_empty() {
+        start.push_attribute((config.attr_empty_settings(), empty_settings.as_str()));
     }
+    Ok(())
 }
 
+/// Applies the config's [`InvalidChars`] policy to text about to be written.
+fn sanitize<'t>(text: &'t str, config: &XMLConfig) -> Result<Cow<'t, str>> {
+    let invalid = match text.chars().find(|&ch| !is_xml_char(ch)) {
+        Some(invalid) => invalid,
+        None => return Ok(Cow::Borrowed(text)),
+    };
+    match config.invalid_chars() {
+        InvalidChars::Error => {
+            let message = format!("{:?} can't be written in XML", invalid);
+            Err(io::Error::new(io::ErrorKind::InvalidData, message).into())
+        }
+        InvalidChars::Remove => {
+            log::warn!("removing characters XML doesn't allow, like {:?}", invalid);
+            Ok(text.chars().filter(|&ch| is_xml_char(ch)).collect())
+        }
+        InvalidChars::Replace => {
+            let replace = |ch| if is_xml_char(ch) { ch } else { '\u{FFFD}' };
+            Ok(text.chars().map(replace).collect())
+        }
+    }
+}
+
+/// Whether XML 1.0 allows a character, as defined by its `Char` production.
+fn is_xml_char(ch: char) -> bool {
+    matches!(ch, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..)
+}
+
+/// The document property which gives block tags generated ids. See [`assign_ids`].
+pub const PROP_AUTO_ID: &str = "auto-id";
+
+// The names of Prosidy's elements under the default `XMLConfig`.
 pub const TAG_DOCUMENT: &str = "prosidy:document";
 pub const TAG_LITERAL: &str = "prosidy:literal";
-const TAG_PARAGRAPH: &str = "prosidy:paragraph";
-const TAG_SOFTBREAK: &str = "prosidy:softbreak";
+pub const TAG_PARAGRAPH: &str = "prosidy:paragraph";
+pub const TAG_SOFTBREAK: &str = "prosidy:softbreak";
+pub const ATTR_EMPTY_SETTINGS: &str = "prosidy:empty-settings";
+
+#[test]
+fn softbreak_modes() {
+    let doc = prosidy_parse::parse_document("---\nOne\ntwo\n").unwrap();
+    let write = |softbreak| {
+        let config = XMLConfig::default().with_softbreak(softbreak);
+        let mut writer = quick_xml::Writer::new(Vec::new());
+        doc.to_events(&config, &mut |event| writer.write_event(event).map(|_| ()))
+            .unwrap();
+        String::from_utf8(writer.into_inner()).unwrap()
+    };
+    let paragraph = |content| {
+        format!(
+            "<prosidy:document><prosidy:paragraph>{}</prosidy:paragraph></prosidy:document>",
+            content
+        )
+    };
+    assert_eq!(
+        paragraph("One<prosidy:softbreak/>two"),
+        write(SoftBreak::Element)
+    );
+    assert_eq!(paragraph("One\ntwo"), write(SoftBreak::Newline));
+    assert_eq!(paragraph("One two"), write(SoftBreak::Space));
+    assert_eq!(XMLConfig::default().softbreak(), SoftBreak::Element);
+}
+
+#[test]
+fn invalid_chars() {
+    let mut props = PropSet::new();
+    props.put("title", "a\u{1}b");
+    let doc = Document::new(props, vec![Block::Content(vec![Text::from("x\0y").into()])]);
+    let write = |invalid_chars| {
+        let config = XMLConfig::default().with_invalid_chars(invalid_chars);
+        let mut writer = quick_xml::Writer::new(Vec::new());
+        doc.to_events(&config, &mut |event| writer.write_event(event).map(|_| ()))
+            .map(|_| String::from_utf8(writer.into_inner()).unwrap())
+    };
+    let error = write(InvalidChars::Error).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("'\\u{1}' can't be written in XML"));
+    let removed = write(InvalidChars::Remove).unwrap();
+    assert!(removed.contains(r#"title="ab""#) && removed.contains(">xy<"));
+    let replaced = write(InvalidChars::Replace).unwrap();
+    assert!(replaced.contains("title=\"a\u{FFFD}b\"") && replaced.contains(">x\u{FFFD}y<"));
+    let read = from_reader(replaced.as_bytes()).unwrap();
+    assert_eq!(Some("a\u{FFFD}b"), read.title().as_deref());
+}
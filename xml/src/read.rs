@@ -0,0 +1,181 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::io::BufRead;
+use std::str;
+
+use prosidy_ast::*;
+use quick_xml::events::{BytesStart, BytesText, Event};
+use quick_xml::{Error, Reader, Result};
+
+use crate::XMLConfig;
+
+/// Reads a document back from the XML produced by its [`XML`](trait.XML.html) implementation.
+///
+/// Elements in block position become [`BlockTag`]s, and elements inside paragraphs become
+/// [`InlineTag`]s. Attributes with an empty value are read as properties, unless they're named in
+/// the [`attr_empty_settings`](XMLConfig::attr_empty_settings) attribute, and all others as
+/// settings. Namespace declarations (`xmlns` and `xmlns:*`) are dropped.
+///
+/// Whitespace between blocks is ignored, but any other text outside of a paragraph or literal is
+/// an error.
+pub fn from_reader<R: BufRead>(reader: R) -> Result<Document<'static>> {
+    from_reader_with(reader, &XMLConfig::default())
+}
+
+/// Reads a document back from XML whose Prosidy elements are named as `config` describes. See
+/// [`from_reader`].
+pub fn from_reader_with<R: BufRead>(reader: R, config: &XMLConfig) -> Result<Document<'static>> {
+    let mut reader = Reader::from_reader(reader);
+    reader.expand_empty_elements(true);
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(start) if start.name() == config.tag_document().as_bytes() => {
+                let props = read_props(&reader, &start, config)?;
+                let content = read_blocks(&mut reader, config)?;
+                return Ok(Document::new(props, content));
+            }
+            Event::Text(text) if is_blank(&text) => {}
+            Event::Decl(_) | Event::PI(_) | Event::Comment(_) | Event::DocType(_) => {}
+            Event::Eof => return Err(Error::UnexpectedEof(config.tag_document().to_string())),
+            event => return Err(unexpected(&event)),
+        }
+        buf.clear();
+    }
+}
+
+fn read_blocks<R: BufRead>(
+    reader: &mut Reader<R>,
+    config: &XMLConfig,
+) -> Result<Vec<Block<'static>>> {
+    let mut blocks = Vec::new();
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(start) => {
+                let block = match start.name() {
+                    name if name == config.tag_paragraph().as_bytes() => {
+                        Block::Content(read_inlines(reader, config)?)
+                    }
+                    name if name == config.tag_literal().as_bytes() => {
+                        Block::Literal(read_literal(reader, config)?)
+                    }
+                    name => {
+                        let name = str::from_utf8(name)?;
+                        let props = read_props(reader, &start, config)?;
+                        BlockTag::new(name, props, read_blocks(reader, config)?).into()
+                    }
+                };
+                blocks.push(block);
+            }
+            Event::End(_) => return Ok(blocks),
+            Event::Text(text) if is_blank(&text) => {}
+            Event::PI(_) | Event::Comment(_) => {}
+            Event::Eof => return Err(Error::UnexpectedEof("block content".to_string())),
+            event => return Err(unexpected(&event)),
+        }
+        buf.clear();
+    }
+}
+
+fn read_inlines<R: BufRead>(
+    reader: &mut Reader<R>,
+    config: &XMLConfig,
+) -> Result<Vec<Inline<'static>>> {
+    let mut inlines = Vec::new();
+    // Character references, CDATA sections, and comments split text into several events, which
+    // are joined back into a single `Inline::Text`.
+    let mut text = String::new();
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Text(t) => text.push_str(&t.unescape_and_decode(reader)?),
+            Event::CData(t) => text.push_str(str::from_utf8(&t)?),
+            Event::PI(_) | Event::Comment(_) => {}
+            event => {
+                if !text.is_empty() {
+                    inlines.push(Inline::Text(Text::from(std::mem::take(&mut text))));
+                }
+                match event {
+                    Event::Start(start) if start.name() == config.tag_softbreak().as_bytes() => {
+                        let mut skip = Vec::new();
+                        reader.read_to_end(config.tag_softbreak(), &mut skip)?;
+                        inlines.push(Inline::SoftBreak);
+                    }
+                    Event::Start(start) => {
+                        let name = str::from_utf8(start.name())?;
+                        let props = read_props(reader, &start, config)?;
+                        let tag = InlineTag::new(name, props, read_inlines(reader, config)?);
+                        inlines.push(tag.into());
+                    }
+                    Event::End(_) => return Ok(inlines),
+                    Event::Eof => return Err(Error::UnexpectedEof("inline content".to_string())),
+                    event => return Err(unexpected(&event)),
+                }
+            }
+        }
+        buf.clear();
+    }
+}
+
+fn read_literal<R: BufRead>(
+    reader: &mut Reader<R>,
+    config: &XMLConfig,
+) -> Result<Literal<'static>> {
+    let mut text = String::new();
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Text(t) => text.push_str(&t.unescape_and_decode(reader)?),
+            Event::CData(t) => text.push_str(str::from_utf8(&t)?),
+            Event::PI(_) | Event::Comment(_) => {}
+            Event::End(_) => return Ok(Literal::from(Text::from(text))),
+            Event::Eof => return Err(Error::UnexpectedEof(config.tag_literal().to_string())),
+            event => return Err(unexpected(&event)),
+        }
+        buf.clear();
+    }
+}
+
+fn read_props<R: BufRead>(
+    reader: &Reader<R>,
+    start: &BytesStart,
+    config: &XMLConfig,
+) -> Result<PropSet<'static>> {
+    let mut empty_settings = String::new();
+    for attr in start.attributes() {
+        let attr = attr?;
+        if attr.key == config.attr_empty_settings().as_bytes() {
+            empty_settings = attr.unescape_and_decode_value(reader)?;
+        }
+    }
+    let mut props = PropSet::new();
+    for attr in start.attributes() {
+        let attr = attr?;
+        if attr.key == b"xmlns"
+            || attr.key.starts_with(b"xmlns:")
+            || attr.key == config.attr_empty_settings().as_bytes()
+        {
+            continue;
+        }
+        let key = str::from_utf8(attr.key)?;
+        let value = attr.unescape_and_decode_value(reader)?;
+        if value.is_empty() && !empty_settings.split(' ').any(|name| name == key) {
+            props.set(key);
+        } else {
+            props.put(key, value);
+        }
+    }
+    Ok(props)
+}
+
+fn is_blank(text: &BytesText) -> bool {
+    text.iter().all(u8::is_ascii_whitespace)
+}
+
+fn unexpected(event: &Event) -> Error {
+    Error::UnexpectedToken(String::from_utf8_lossy(event).into_owned())
+}
@@ -0,0 +1,120 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use quick_xml::events::BytesText;
+
+use crate::config::{SoftBreak, XMLConfig};
+
+/// Generates an XML Schema (XSD) describing documents written with `config`.
+///
+/// Prosidy's own elements are declared in the config's namespace. A document's tags become
+/// elements named after them, and its properties become unqualified attributes; since their names
+/// can't be known ahead of time, tags are matched by a lax wildcard, in `tag_namespace` if one is
+/// given and otherwise in no namespace. Prosidy elements inside them are still validated.
+///
+/// The `empty-settings` attribute, which [`insert_props`](crate::insert_props) adds to tags with
+/// empty settings, is declared globally so that it's checked wherever it appears.
+///
+/// The soft break element is only declared when `config` writes soft breaks as elements.
+///
+/// ```rust
+/// # use prosidy_xml::{schema, XMLConfig};
+/// let xsd = schema(&XMLConfig::new("p", "urn:example"), None);
+/// assert!(xsd.contains(r#"targetNamespace="urn:example""#));
+/// assert!(xsd.contains(r#"<xs:element name="paragraph">"#));
+/// assert!(xsd.contains(r###"namespace="##local""###));
+/// ```
+pub fn schema(config: &XMLConfig, tag_namespace: Option<&str>) -> String {
+    let attr = |value: &str| {
+        let text = BytesText::from_plain_str(value);
+        String::from_utf8_lossy(text.escaped()).into_owned()
+    };
+    let prefix = config.prefix();
+    let tags = tag_namespace.map_or_else(|| "##local".to_string(), attr);
+    let softbreak = config.softbreak() == SoftBreak::Element;
+    let inline_softbreak = if softbreak {
+        format!("\n        <xs:element ref=\"{}:softbreak\"/>", prefix)
+    } else {
+        String::new()
+    };
+    let softbreak_element = if softbreak {
+        "\n  <xs:element name=\"softbreak\">\n    <xs:complexType/>\n  </xs:element>\n"
+    } else {
+        ""
+    };
+    format!(
+        r###"<?xml version="1.0" encoding="UTF-8"?>
+<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema" xmlns:{prefix}="{namespace}" targetNamespace="{namespace}" elementFormDefault="qualified">
+  <xs:annotation>
+    <xs:documentation>
+      Prosidy documents, as written by prosidy-xml {version}. Tags are elements named after them,
+      and their properties are attributes.
+    </xs:documentation>
+  </xs:annotation>
+
+  <xs:element name="document">
+    <xs:complexType>
+      <xs:group ref="{prefix}:blocks"/>
+      <xs:attribute ref="{prefix}:empty-settings"/>
+      <xs:anyAttribute namespace="##local" processContents="skip"/>
+    </xs:complexType>
+  </xs:element>
+
+  <xs:element name="paragraph">
+    <xs:complexType mixed="true">
+      <xs:choice minOccurs="0" maxOccurs="unbounded">{inline_softbreak}
+        <xs:any namespace="{tags}" processContents="lax"/>
+      </xs:choice>
+    </xs:complexType>
+  </xs:element>
+
+  <xs:element name="literal" type="xs:string"/>
+
+  <xs:attribute name="empty-settings" type="xs:string"/>
+{softbreak_element}
+  <xs:group name="blocks">
+    <xs:sequence>
+      <xs:choice minOccurs="0" maxOccurs="unbounded">
+        <xs:element ref="{prefix}:paragraph"/>
+        <xs:element ref="{prefix}:literal"/>
+        <xs:any namespace="{tags}" processContents="lax"/>
+      </xs:choice>
+    </xs:sequence>
+  </xs:group>
+</xs:schema>
+"###,
+        prefix = prefix,
+        namespace = attr(config.namespace()),
+        version = env!("CARGO_PKG_VERSION"),
+        tags = tags,
+        inline_softbreak = inline_softbreak,
+        softbreak_element = softbreak_element,
+    )
+}
+
+#[test]
+fn schema_follows_config() {
+    use quick_xml::events::Event;
+
+    let config = XMLConfig::new("p", "urn:example:a&b").with_softbreak(SoftBreak::Space);
+    let xsd = schema(&config, Some("urn:example:tags"));
+    let mut reader = quick_xml::Reader::from_str(&xsd);
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Eof) => break,
+            Ok(_) => buf.clear(),
+            Err(error) => panic!("invalid schema: {}\n{}", error, xsd),
+        }
+    }
+    assert!(xsd.contains(r#"xmlns:p="urn:example:a&amp;b""#), "{}", xsd);
+    assert!(
+        xsd.contains(r#"<xs:element ref="p:paragraph"/>"#),
+        "{}",
+        xsd
+    );
+    assert!(xsd.contains(r#"namespace="urn:example:tags""#), "{}", xsd);
+    assert!(!xsd.contains("softbreak"), "{}", xsd);
+}
@@ -0,0 +1,25 @@
+## This Source Code Form is subject to the terms of the Mozilla Public
+## License, v. 2.0. If a copy of the MPL was not distributed with this
+## file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+title: Round <trip> & "back"
+draft
+
+---
+
+A paragraph with #em{emphasis}, #link[href='https://example.com?a=1&b=2']{a link},
+and a soft break, plus escapes like \#, \{, and <angle brackets>.
+
+#-section[id='intro', hidden]:
+  Nested content with an empty #br tag.
+
+  #-note{A block tag with inline content.}
+
+  #=code[lang='rust']:
+  fn main() {
+      println!("<&>");
+  }
+  #:
+#:
+
+#-divider
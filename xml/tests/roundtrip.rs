@@ -0,0 +1,158 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use pretty_assertions::assert_eq;
+use prosidy_xml::quick_xml::Writer;
+use prosidy_xml::{from_reader, from_reader_with, XMLConfig, XML};
+
+const SOURCE: &str = include_str!("roundtrip.pro");
+
+#[test]
+fn test_roundtrip() {
+    let doc = prosidy_parse::parse_document(SOURCE).unwrap();
+    let mut writer = Writer::new(Vec::new());
+    doc.to_events(&XMLConfig::default(), &mut |event| {
+        writer.write_event(event).map(|_| ())
+    })
+    .unwrap();
+    let xml = writer.into_inner();
+    let read = from_reader(xml.as_slice()).unwrap();
+    assert_eq!(doc, read);
+}
+
+#[test]
+fn test_custom_prefix_roundtrip() {
+    let doc = prosidy_parse::parse_document(SOURCE).unwrap();
+    let config = XMLConfig::new("p", "urn:example:prosidy");
+    let mut writer = Writer::new(Vec::new());
+    doc.to_events(&config, &mut |event| writer.write_event(event).map(|_| ()))
+        .unwrap();
+    let xml = writer.into_inner();
+    let text = String::from_utf8_lossy(&xml);
+    assert!(text.starts_with("<p:document"), "{}", text);
+    assert!(!text.contains("prosidy:"), "{}", text);
+    assert_eq!(doc, from_reader_with(xml.as_slice(), &config).unwrap());
+    assert!(from_reader(xml.as_slice()).is_err());
+}
+
+#[test]
+fn test_ignores_namespaces_and_whitespace() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<prosidy:document xmlns:prosidy="https://prosidy.org/schema/prosidy.xsd" title="Hi">
+  <prosidy:paragraph>Hello, <b>world</b>!</prosidy:paragraph>
+</prosidy:document>
+"#;
+    let read = from_reader(xml.as_bytes()).unwrap();
+    let doc = prosidy_parse::parse_document("title: Hi\n---\nHello, #b{world}!\n").unwrap();
+    assert_eq!(doc, read);
+}
+
+#[test]
+fn test_rejects_stray_text() {
+    let xml = "<prosidy:document>stray</prosidy:document>";
+    assert!(from_reader(xml.as_bytes()).is_err());
+}
+
+#[test]
+fn test_title_roundtrip() {
+    let mut doc = prosidy_parse::parse_document("lang: en\n---\nHello\n").unwrap();
+    doc.set_title("Fish & Chips");
+    let mut writer = Writer::new(Vec::new());
+    doc.to_events(&XMLConfig::default(), &mut |event| {
+        writer.write_event(event).map(|_| ())
+    })
+    .unwrap();
+    let xml = String::from_utf8(writer.into_inner()).unwrap();
+    assert_eq!(1, xml.matches("title=").count(), "{}", xml);
+    assert!(xml.contains(r#"title="Fish &amp; Chips""#));
+    let read = from_reader(xml.as_bytes()).unwrap();
+    assert_eq!(Some("Fish & Chips"), read.title().as_deref());
+    assert_eq!(doc, read);
+}
+
+#[test]
+fn test_auto_ids() {
+    let source = "auto-id\n---\n#-h2{Café Menu}\n\n#-h2{Café menu!}\n\n#-h2[id='cafe-menu-3']{Drinks}\n\n#-h2{Café Menu}\n\n#-divider\n";
+    let doc = prosidy_parse::parse_document(source).unwrap();
+    let mut writer = Writer::new(Vec::new());
+    doc.to_events(&XMLConfig::default(), &mut |event| {
+        writer.write_event(event).map(|_| ())
+    })
+    .unwrap();
+    let xml = String::from_utf8(writer.into_inner()).unwrap();
+    assert!(xml.contains(r#"<h2 id="cafe-menu">"#), "{}", xml);
+    assert!(xml.contains(r#"<h2 id="cafe-menu-2">"#), "{}", xml);
+    assert!(xml.contains(r#"<h2 id="cafe-menu-3">"#), "{}", xml);
+    assert!(xml.contains(r#"<h2 id="cafe-menu-4">"#), "{}", xml);
+    assert!(xml.contains("<divider/>"), "{}", xml);
+
+    let plain = prosidy_parse::parse_document("---\n#-h2{Café Menu}\n").unwrap();
+    let mut writer = Writer::new(Vec::new());
+    plain
+        .to_events(&XMLConfig::default(), &mut |event| {
+            writer.write_event(event).map(|_| ())
+        })
+        .unwrap();
+    assert!(!String::from_utf8(writer.into_inner())
+        .unwrap()
+        .contains("id="));
+}
+
+#[test]
+fn test_empty_settings_roundtrip() {
+    use prosidy_ast::*;
+
+    let mut props = PropSet::new();
+    props.set("hidden");
+    props.put("alt", "");
+    props.put("caption", "");
+    let mut flag = PropSet::new();
+    flag.set("flag");
+    let mut header = PropSet::new();
+    header.set("draft");
+    header.put("subtitle", "");
+    let img = BlockTag::new("img", props, Vec::new()).into();
+    let em = InlineTag::new("em", flag, vec![Text::from("x").into()]).into();
+    let doc = Document::new(header, vec![img, Block::Content(vec![em])]);
+
+    let mut writer = Writer::new(Vec::new());
+    doc.to_events(&XMLConfig::default(), &mut |event| {
+        writer.write_event(event).map(|_| ())
+    })
+    .unwrap();
+    let xml = String::from_utf8(writer.into_inner()).unwrap();
+    assert!(
+        xml.contains(r#"<img alt="" caption="" hidden="" prosidy:empty-settings="alt caption"/>"#),
+        "{}",
+        xml
+    );
+    assert!(xml.contains(r#"<em flag="">"#), "{}", xml);
+    let read = from_reader(xml.as_bytes()).unwrap();
+    assert_eq!(doc, read);
+    assert!(read.props().is_set(Key::new("draft")));
+    assert_eq!(Some(""), read.props().lookup_str(Key::new("subtitle")));
+}
+
+#[test]
+fn test_literal_props() {
+    let source = "---\n#=lit[flag, withprops='true']:\n    this literal has properties!\n#:\n";
+    let doc = prosidy_parse::parse_document(source).unwrap();
+    let mut writer = Writer::new(Vec::new());
+    doc.to_events(&XMLConfig::default(), &mut |event| {
+        writer.write_event(event).map(|_| ())
+    })
+    .unwrap();
+    let xml = String::from_utf8(writer.into_inner()).unwrap();
+    // The props belong to the tag wrapping the literal, so they're its attributes.
+    assert!(
+        xml.contains(
+            "<lit withprops=\"true\" flag=\"\">\
+             <prosidy:literal>    this literal has properties!\n</prosidy:literal></lit>"
+        ),
+        "{}",
+        xml
+    );
+    assert_eq!(doc, from_reader(xml.as_bytes()).unwrap());
+}